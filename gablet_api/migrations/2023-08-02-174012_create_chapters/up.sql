@@ -0,0 +1,21 @@
+-- Your SQL goes here
+CREATE TABLE chapters(
+    id SERIAL PRIMARY KEY,
+    book_id INT NOT NULL REFERENCES books(id) ON DELETE CASCADE,
+    number INT NOT NULL,
+    title VARCHAR(255),
+    created TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+    UNIQUE(book_id, number)
+);
+
+CREATE TABLE pages(
+    id SERIAL PRIMARY KEY,
+    chapter_id INT NOT NULL REFERENCES chapters(id) ON DELETE CASCADE,
+    number INT NOT NULL,
+    image VARCHAR(255) NOT NULL,
+    width INT NOT NULL,
+    height INT NOT NULL,
+
+    UNIQUE(chapter_id, number)
+);
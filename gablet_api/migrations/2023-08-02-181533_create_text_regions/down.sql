@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE text_region_translations;
+DROP TABLE text_regions;
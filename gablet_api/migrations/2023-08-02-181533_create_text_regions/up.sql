@@ -0,0 +1,31 @@
+-- Your SQL goes here
+
+-- Region coordinates are stored as fractions of the page size so that
+-- clients can scale overlays to whatever resolution they render the page at.
+CREATE TABLE text_regions(
+    id SERIAL PRIMARY KEY,
+    page_id INT NOT NULL REFERENCES pages(id) ON DELETE CASCADE,
+    x REAL NOT NULL,
+    y REAL NOT NULL,
+    width REAL NOT NULL,
+    height REAL NOT NULL,
+    polygon REAL[],
+    source_text TEXT NOT NULL DEFAULT '',
+    source_lang VARCHAR(10) NOT NULL,
+    created_by INT NOT NULL REFERENCES users(id),
+    created TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX text_regions_page_id_idx ON text_regions(page_id);
+
+CREATE TABLE text_region_translations(
+    id SERIAL PRIMARY KEY,
+    region_id INT NOT NULL REFERENCES text_regions(id) ON DELETE CASCADE,
+    lang VARCHAR(10) NOT NULL,
+    text TEXT NOT NULL,
+    translator_id INT NOT NULL REFERENCES users(id),
+    updated TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+    UNIQUE(region_id, lang)
+);
@@ -0,0 +1,9 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE share_agreements;
+DROP TRIGGER ledger_entries_balanced ON ledger_entries;
+DROP FUNCTION ledger_check_balanced();
+DROP TABLE ledger_entries;
+DROP TABLE ledger_transactions;
+DROP TABLE ledger_accounts;
+DROP TYPE earning_kind;
+DROP TYPE ledger_account_kind;
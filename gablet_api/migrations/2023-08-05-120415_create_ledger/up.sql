@@ -0,0 +1,66 @@
+-- Your SQL goes here
+CREATE TYPE ledger_account_kind AS ENUM ('revenue', 'platform', 'contributor');
+CREATE TYPE earning_kind AS ENUM ('tip', 'subscription', 'ad_revenue');
+
+-- Every transaction posts entries that sum to zero. Money coming in is taken
+-- from the revenue account (so its balance is negative) and credited to the
+-- platform and contributor accounts.
+CREATE TABLE ledger_accounts(
+    id SERIAL PRIMARY KEY,
+    kind ledger_account_kind NOT NULL,
+    user_id INT UNIQUE REFERENCES users(id),
+    created TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+    CONSTRAINT contributor_has_user CHECK ((kind = 'contributor') = (user_id IS NOT NULL))
+);
+
+CREATE UNIQUE INDEX ledger_accounts_system_kind_idx ON ledger_accounts(kind) WHERE user_id IS NULL;
+
+INSERT INTO ledger_accounts (kind) VALUES ('revenue'), ('platform');
+
+CREATE TABLE ledger_transactions(
+    id SERIAL PRIMARY KEY,
+    kind earning_kind NOT NULL,
+    book_id INT NOT NULL REFERENCES books(id),
+    lang VARCHAR(10),
+    amount_cents BIGINT NOT NULL CHECK (amount_cents > 0),
+    description TEXT,
+    created TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE ledger_entries(
+    id SERIAL PRIMARY KEY,
+    transaction_id INT NOT NULL REFERENCES ledger_transactions(id) ON DELETE CASCADE,
+    account_id INT NOT NULL REFERENCES ledger_accounts(id),
+    amount_cents BIGINT NOT NULL
+);
+
+CREATE INDEX ledger_entries_account_id_idx ON ledger_entries(account_id, transaction_id);
+
+CREATE FUNCTION ledger_check_balanced() RETURNS trigger AS $$
+BEGIN
+    IF (SELECT COALESCE(SUM(amount_cents), 0) FROM ledger_entries WHERE transaction_id = NEW.transaction_id) <> 0 THEN
+        RAISE EXCEPTION 'Ledger transaction % is not balanced', NEW.transaction_id;
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE CONSTRAINT TRIGGER ledger_entries_balanced
+    AFTER INSERT OR UPDATE ON ledger_entries
+    DEFERRABLE INITIALLY DEFERRED
+    FOR EACH ROW EXECUTE PROCEDURE ledger_check_balanced();
+
+-- Shares are in basis points (1/100th of a percent). Agreements without a
+-- language apply to the book itself, agreements with a language apply to that
+-- translation of the book.
+CREATE TABLE share_agreements(
+    id SERIAL PRIMARY KEY,
+    book_id INT NOT NULL REFERENCES books(id) ON DELETE CASCADE,
+    lang VARCHAR(10),
+    user_id INT NOT NULL REFERENCES users(id),
+    share_bps INT NOT NULL CHECK (share_bps > 0 AND share_bps <= 10000),
+    created TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE UNIQUE INDEX share_agreements_book_lang_user_idx ON share_agreements(book_id, COALESCE(lang, ''), user_id);
@@ -0,0 +1,11 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE notifications;
+DROP TYPE notification_kind;
+DROP TABLE moderation_items;
+DROP TYPE moderation_status;
+DROP TYPE moderation_kind;
+
+ALTER TABLE chapters DROP COLUMN approved;
+
+ALTER TABLE books DROP COLUMN author_id;
+ALTER TABLE books ADD CONSTRAINT author_id FOREIGN KEY(id) REFERENCES users(id);
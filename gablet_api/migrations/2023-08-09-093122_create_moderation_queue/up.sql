@@ -0,0 +1,47 @@
+-- Your SQL goes here
+
+-- The old author_id constraint was placed on the book id by mistake. Books
+-- were created with the same id as their author, so use that as the author.
+ALTER TABLE books DROP CONSTRAINT author_id;
+ALTER TABLE books ADD COLUMN author_id INT;
+UPDATE books SET author_id = id;
+ALTER TABLE books
+    ALTER COLUMN author_id SET NOT NULL,
+    ADD CONSTRAINT books_author_id_fkey FOREIGN KEY(author_id) REFERENCES users(id);
+
+ALTER TABLE chapters ADD COLUMN approved BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE TYPE moderation_kind AS ENUM ('book', 'chapter');
+CREATE TYPE moderation_status AS ENUM ('pending', 'approved', 'rejected');
+
+CREATE TABLE moderation_items(
+    id SERIAL PRIMARY KEY,
+    kind moderation_kind NOT NULL,
+    book_id INT NOT NULL REFERENCES books(id) ON DELETE CASCADE,
+    chapter_id INT REFERENCES chapters(id) ON DELETE CASCADE,
+    submitted_by INT NOT NULL REFERENCES users(id),
+    status moderation_status NOT NULL DEFAULT 'pending',
+    reason TEXT,
+    reviewed_by INT REFERENCES users(id),
+    created TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    reviewed TIMESTAMP,
+
+    CONSTRAINT chapter_items_have_chapter CHECK ((kind = 'chapter') = (chapter_id IS NOT NULL))
+);
+
+CREATE INDEX moderation_items_pending_idx ON moderation_items(id) WHERE status = 'pending';
+
+CREATE TYPE notification_kind AS ENUM ('book_approved', 'book_rejected', 'chapter_approved', 'chapter_rejected');
+
+CREATE TABLE notifications(
+    id SERIAL PRIMARY KEY,
+    user_id INT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    kind notification_kind NOT NULL,
+    message TEXT NOT NULL,
+    book_id INT REFERENCES books(id) ON DELETE CASCADE,
+    chapter_id INT REFERENCES chapters(id) ON DELETE CASCADE,
+    read BOOLEAN NOT NULL DEFAULT FALSE,
+    created TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX notifications_user_id_idx ON notifications(user_id, id);
@@ -0,0 +1,18 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX users_name_trgm_idx;
+DROP INDEX books_name_trgm_idx;
+DROP INDEX books_search_vector_idx;
+
+DROP TRIGGER users_books_search_vector_trigger ON users;
+DROP FUNCTION users_books_search_vector_update();
+DROP TRIGGER books_search_vector_trigger ON books;
+DROP FUNCTION books_search_vector_update();
+
+ALTER TABLE books
+    DROP COLUMN search_vector,
+    DROP COLUMN content_rating,
+    DROP COLUMN status,
+    DROP COLUMN lang;
+
+DROP TYPE content_rating;
+DROP TYPE book_status;
@@ -0,0 +1,48 @@
+-- Your SQL goes here
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+CREATE TYPE book_status AS ENUM ('ongoing', 'completed', 'hiatus', 'cancelled');
+CREATE TYPE content_rating AS ENUM ('all_ages', 'teen', 'mature', 'explicit');
+
+ALTER TABLE books
+    ADD COLUMN lang VARCHAR(10) NOT NULL DEFAULT 'en',
+    ADD COLUMN status book_status NOT NULL DEFAULT 'ongoing',
+    ADD COLUMN content_rating content_rating NOT NULL DEFAULT 'all_ages',
+    ADD COLUMN search_vector TSVECTOR NOT NULL DEFAULT ''::TSVECTOR;
+
+-- The search vector is kept up to date by triggers because it includes the
+-- author's name, which a generated column can't reference.
+CREATE FUNCTION books_search_vector_update() RETURNS trigger AS $$
+BEGIN
+    NEW.search_vector :=
+        setweight(to_tsvector('simple', NEW.name), 'A') ||
+        setweight(to_tsvector('simple', COALESCE(
+            (SELECT name || ' ' || username FROM users WHERE id = NEW.author_id), ''
+        )), 'B') ||
+        setweight(to_tsvector('simple', COALESCE(NEW.description, '')), 'C');
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER books_search_vector_trigger
+    BEFORE INSERT OR UPDATE OF name, description, author_id ON books
+    FOR EACH ROW EXECUTE PROCEDURE books_search_vector_update();
+
+CREATE FUNCTION users_books_search_vector_update() RETURNS trigger AS $$
+BEGIN
+    UPDATE books SET name = name WHERE author_id = NEW.id;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER users_books_search_vector_trigger
+    AFTER UPDATE OF name, username ON users
+    FOR EACH ROW
+    WHEN (OLD.name IS DISTINCT FROM NEW.name OR OLD.username IS DISTINCT FROM NEW.username)
+    EXECUTE PROCEDURE users_books_search_vector_update();
+
+UPDATE books SET name = name;
+
+CREATE INDEX books_search_vector_idx ON books USING GIN (search_vector);
+CREATE INDEX books_name_trgm_idx ON books USING GIN (name gin_trgm_ops);
+CREATE INDEX users_name_trgm_idx ON users USING GIN (name gin_trgm_ops);
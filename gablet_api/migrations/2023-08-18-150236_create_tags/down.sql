@@ -0,0 +1,22 @@
+-- This file should undo anything in `up.sql`
+DROP TRIGGER book_tags_search_vector_trigger ON book_tags;
+DROP FUNCTION book_tags_search_vector_update();
+
+CREATE OR REPLACE FUNCTION books_search_vector_update() RETURNS trigger AS $$
+BEGIN
+    NEW.search_vector :=
+        setweight(to_tsvector('simple', NEW.name), 'A') ||
+        setweight(to_tsvector('simple', COALESCE(
+            (SELECT name || ' ' || username FROM users WHERE id = NEW.author_id), ''
+        )), 'B') ||
+        setweight(to_tsvector('simple', COALESCE(NEW.description, '')), 'C');
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+ALTER TABLE users DROP COLUMN max_content_rating;
+
+DROP TABLE book_tags;
+DROP TABLE tag_aliases;
+DROP TABLE tags;
+DROP TYPE tag_kind;
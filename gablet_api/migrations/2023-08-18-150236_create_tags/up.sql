@@ -0,0 +1,61 @@
+-- Your SQL goes here
+CREATE TYPE tag_kind AS ENUM ('genre', 'tag');
+
+CREATE TABLE tags(
+    id SERIAL PRIMARY KEY,
+    name VARCHAR(50) NOT NULL,
+    kind tag_kind NOT NULL DEFAULT 'tag',
+    created TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE UNIQUE INDEX tags_name_idx ON tags(LOWER(name));
+
+-- Aliases are stored in lower case and point at the canonical tag.
+CREATE TABLE tag_aliases(
+    id SERIAL PRIMARY KEY,
+    tag_id INT NOT NULL REFERENCES tags(id) ON DELETE CASCADE,
+    alias VARCHAR(50) UNIQUE NOT NULL
+);
+
+CREATE TABLE book_tags(
+    book_id INT NOT NULL REFERENCES books(id) ON DELETE CASCADE,
+    tag_id INT NOT NULL REFERENCES tags(id) ON DELETE CASCADE,
+
+    PRIMARY KEY(book_id, tag_id)
+);
+
+CREATE INDEX book_tags_tag_id_idx ON book_tags(tag_id);
+
+ALTER TABLE users ADD COLUMN max_content_rating content_rating NOT NULL DEFAULT 'teen';
+
+CREATE OR REPLACE FUNCTION books_search_vector_update() RETURNS trigger AS $$
+BEGIN
+    NEW.search_vector :=
+        setweight(to_tsvector('simple', NEW.name), 'A') ||
+        setweight(to_tsvector('simple', COALESCE(
+            (SELECT name || ' ' || username FROM users WHERE id = NEW.author_id), ''
+        )), 'B') ||
+        setweight(to_tsvector('simple', COALESCE(
+            (SELECT string_agg(tags.name, ' ') FROM book_tags
+                INNER JOIN tags ON tags.id = book_tags.tag_id
+                WHERE book_tags.book_id = NEW.id), ''
+        )), 'B') ||
+        setweight(to_tsvector('simple', COALESCE(NEW.description, '')), 'C');
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE FUNCTION book_tags_search_vector_update() RETURNS trigger AS $$
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        UPDATE books SET name = name WHERE id = OLD.book_id;
+    ELSE
+        UPDATE books SET name = name WHERE id = NEW.book_id;
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER book_tags_search_vector_trigger
+    AFTER INSERT OR DELETE ON book_tags
+    FOR EACH ROW EXECUTE PROCEDURE book_tags_search_vector_update();
@@ -0,0 +1,11 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE chapter_translations;
+DROP TABLE book_follows;
+
+ALTER TABLE chapters DROP COLUMN published;
+
+DELETE FROM notifications WHERE kind IN ('new_chapter', 'new_translation');
+ALTER TYPE notification_kind RENAME TO notification_kind_old;
+CREATE TYPE notification_kind AS ENUM ('book_approved', 'book_rejected', 'chapter_approved', 'chapter_rejected');
+ALTER TABLE notifications ALTER COLUMN kind TYPE notification_kind USING kind::TEXT::notification_kind;
+DROP TYPE notification_kind_old;
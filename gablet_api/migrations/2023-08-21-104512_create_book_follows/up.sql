@@ -0,0 +1,29 @@
+-- Your SQL goes here
+ALTER TYPE notification_kind ADD VALUE 'new_chapter';
+ALTER TYPE notification_kind ADD VALUE 'new_translation';
+
+ALTER TABLE chapters ADD COLUMN published TIMESTAMP;
+UPDATE chapters SET published = created WHERE approved;
+
+-- A follower with a language gets notified of translations into that
+-- language. Without one they get notified of chapters in the original language.
+CREATE TABLE book_follows(
+    user_id INT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    book_id INT NOT NULL REFERENCES books(id) ON DELETE CASCADE,
+    lang VARCHAR(10),
+    created TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+    PRIMARY KEY(user_id, book_id)
+);
+
+CREATE INDEX book_follows_book_id_idx ON book_follows(book_id);
+
+CREATE TABLE chapter_translations(
+    id SERIAL PRIMARY KEY,
+    chapter_id INT NOT NULL REFERENCES chapters(id) ON DELETE CASCADE,
+    lang VARCHAR(10) NOT NULL,
+    published_by INT NOT NULL REFERENCES users(id),
+    published TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+    UNIQUE(chapter_id, lang)
+);
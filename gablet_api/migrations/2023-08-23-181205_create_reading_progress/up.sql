@@ -0,0 +1,30 @@
+-- Your SQL goes here
+
+-- `updated` is when the reader was on this page according to their device.
+-- Writes with an older timestamp are ignored so devices can sync in any order.
+CREATE TABLE reading_progress(
+    user_id INT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    book_id INT NOT NULL REFERENCES books(id) ON DELETE CASCADE,
+    chapter_id INT NOT NULL REFERENCES chapters(id) ON DELETE CASCADE,
+    page_number INT NOT NULL CHECK (page_number > 0),
+    lang VARCHAR(10),
+    updated TIMESTAMP NOT NULL,
+
+    PRIMARY KEY(user_id, book_id)
+);
+
+CREATE INDEX reading_progress_updated_idx ON reading_progress(user_id, updated DESC);
+
+CREATE TABLE bookmarks(
+    id SERIAL PRIMARY KEY,
+    user_id INT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    book_id INT NOT NULL REFERENCES books(id) ON DELETE CASCADE,
+    chapter_id INT NOT NULL REFERENCES chapters(id) ON DELETE CASCADE,
+    page_number INT NOT NULL CHECK (page_number > 0),
+    lang VARCHAR(10),
+    name VARCHAR(100) NOT NULL,
+    created TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated TIMESTAMP NOT NULL
+);
+
+CREATE INDEX bookmarks_user_id_idx ON bookmarks(user_id, book_id);
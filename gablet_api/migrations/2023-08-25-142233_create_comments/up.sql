@@ -0,0 +1,32 @@
+-- Your SQL goes here
+
+-- Deleted comments are kept so replies to them stay in their thread.
+CREATE TABLE comments(
+    id SERIAL PRIMARY KEY,
+    chapter_id INT NOT NULL REFERENCES chapters(id) ON DELETE CASCADE,
+    user_id INT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    parent_id INT REFERENCES comments(id) ON DELETE CASCADE,
+    root_id INT REFERENCES comments(id) ON DELETE CASCADE,
+    body TEXT NOT NULL,
+    spoiler BOOLEAN NOT NULL DEFAULT FALSE,
+    score INT NOT NULL DEFAULT 0,
+    deleted BOOLEAN NOT NULL DEFAULT FALSE,
+    deleted_by INT REFERENCES users(id),
+    created TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    edited TIMESTAMP,
+
+    CONSTRAINT replies_have_root CHECK ((parent_id IS NULL) = (root_id IS NULL))
+);
+
+CREATE INDEX comments_chapter_id_idx ON comments(chapter_id, id) WHERE parent_id IS NULL;
+CREATE INDEX comments_chapter_score_idx ON comments(chapter_id, score, id) WHERE parent_id IS NULL;
+CREATE INDEX comments_root_id_idx ON comments(root_id);
+CREATE INDEX comments_user_id_idx ON comments(user_id, created);
+
+CREATE TABLE comment_votes(
+    comment_id INT NOT NULL REFERENCES comments(id) ON DELETE CASCADE,
+    user_id INT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    value SMALLINT NOT NULL CHECK (value IN (-1, 1)),
+
+    PRIMARY KEY(comment_id, user_id)
+);
@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+DROP TRIGGER book_ratings_insert_trigger ON books;
+DROP FUNCTION book_ratings_insert;
+DROP TABLE book_ratings;
+DROP TABLE reviews;
+DROP FUNCTION book_ratings_update;
@@ -0,0 +1,65 @@
+-- Your SQL goes here
+CREATE TABLE reviews(
+    id SERIAL PRIMARY KEY,
+    book_id INT NOT NULL REFERENCES books(id) ON DELETE CASCADE,
+    user_id INT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    rating SMALLINT NOT NULL CHECK (rating BETWEEN 1 AND 5),
+    body TEXT,
+    created TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+    UNIQUE(book_id, user_id)
+);
+
+CREATE INDEX reviews_user_id_idx ON reviews(user_id);
+
+-- Running totals of the reviews of each book. distribution[n] is the number of
+-- n star ratings. The score is a Bayesian average that starts every book off
+-- with 10 imaginary 3 star ratings, so books with few ratings stay near the
+-- middle until they have enough to say otherwise.
+CREATE TABLE book_ratings(
+    book_id INT PRIMARY KEY REFERENCES books(id) ON DELETE CASCADE,
+    rating_count INT NOT NULL DEFAULT 0,
+    rating_sum INT NOT NULL DEFAULT 0,
+    distribution INT[] NOT NULL DEFAULT '{0,0,0,0,0}',
+    score DOUBLE PRECISION GENERATED ALWAYS AS ((rating_sum + 30.0) / (rating_count + 10)) STORED
+);
+
+CREATE INDEX book_ratings_score_idx ON book_ratings(score DESC, book_id DESC);
+
+INSERT INTO book_ratings(book_id) SELECT id FROM books;
+
+CREATE FUNCTION book_ratings_insert() RETURNS trigger AS $$
+BEGIN
+    INSERT INTO book_ratings(book_id) VALUES (NEW.id);
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER book_ratings_insert_trigger
+    AFTER INSERT ON books
+    FOR EACH ROW EXECUTE PROCEDURE book_ratings_insert();
+
+CREATE FUNCTION book_ratings_update() RETURNS trigger AS $$
+BEGIN
+    IF TG_OP IN ('UPDATE', 'DELETE') THEN
+        UPDATE book_ratings SET
+            rating_count = rating_count - 1,
+            rating_sum = rating_sum - OLD.rating,
+            distribution[OLD.rating] = distribution[OLD.rating] - 1
+        WHERE book_id = OLD.book_id;
+    END IF;
+    IF TG_OP IN ('INSERT', 'UPDATE') THEN
+        UPDATE book_ratings SET
+            rating_count = rating_count + 1,
+            rating_sum = rating_sum + NEW.rating,
+            distribution[NEW.rating] = distribution[NEW.rating] + 1
+        WHERE book_id = NEW.book_id;
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER book_ratings_update_trigger
+    AFTER INSERT OR DELETE OR UPDATE OF rating ON reviews
+    FOR EACH ROW EXECUTE PROCEDURE book_ratings_update();
@@ -0,0 +1,10 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE user_profiles ADD COLUMN lang VARCHAR(10);
+
+UPDATE user_profiles SET lang = langs[1];
+
+ALTER TABLE user_profiles
+    DROP COLUMN langs,
+    DROP COLUMN links,
+    DROP CONSTRAINT user_profiles_user_id_fkey,
+    DROP CONSTRAINT user_profiles_user_id_key;
@@ -0,0 +1,16 @@
+-- Your SQL goes here
+
+-- Profiles are created on the first update for users who registered after
+-- user_profiles was added, so each user can only have one.
+DELETE FROM user_profiles WHERE user_id NOT IN (SELECT id FROM users);
+DELETE FROM user_profiles a USING user_profiles b WHERE a.user_id = b.user_id AND a.id > b.id;
+
+ALTER TABLE user_profiles
+    ADD CONSTRAINT user_profiles_user_id_key UNIQUE (user_id),
+    ADD CONSTRAINT user_profiles_user_id_fkey FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+    ADD COLUMN links TEXT[] NOT NULL DEFAULT '{}',
+    ADD COLUMN langs TEXT[] NOT NULL DEFAULT '{}';
+
+UPDATE user_profiles SET langs = ARRAY[lang] WHERE lang IS NOT NULL;
+
+ALTER TABLE user_profiles DROP COLUMN lang;
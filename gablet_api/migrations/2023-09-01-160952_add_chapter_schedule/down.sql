@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX chapters_publish_at_idx;
+
+ALTER TABLE chapters DROP COLUMN publish_at;
@@ -0,0 +1,8 @@
+-- Your SQL goes here
+
+-- Approved chapters with a publish_at in the future stay hidden until the
+-- release scheduler publishes them.
+ALTER TABLE chapters ADD COLUMN publish_at TIMESTAMP;
+
+CREATE INDEX chapters_publish_at_idx ON chapters(publish_at)
+    WHERE published IS NULL AND publish_at IS NOT NULL;
@@ -0,0 +1,16 @@
+-- Your SQL goes here
+
+-- Every version of a translation, including the current one. Reverting adds a
+-- new revision rather than removing the ones after it.
+CREATE TABLE translation_revisions(
+    id SERIAL PRIMARY KEY,
+    translation_id INT NOT NULL REFERENCES text_region_translations(id) ON DELETE CASCADE,
+    text TEXT NOT NULL,
+    author_id INT NOT NULL REFERENCES users(id),
+    created TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX translation_revisions_translation_id_idx ON translation_revisions(translation_id, id);
+
+INSERT INTO translation_revisions(translation_id, text, author_id, created)
+SELECT id, text, translator_id, updated FROM text_region_translations;
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE glossary_terms;
+DROP TYPE part_of_speech;
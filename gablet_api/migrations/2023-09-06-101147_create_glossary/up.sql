@@ -0,0 +1,22 @@
+-- Your SQL goes here
+CREATE TYPE part_of_speech AS ENUM (
+    'noun', 'proper_noun', 'pronoun', 'verb', 'adjective', 'adverb', 'phrase', 'other'
+);
+
+-- source_key is the source term in lower case, so each term can only be in a
+-- book's glossary for a language once.
+CREATE TABLE glossary_terms(
+    id SERIAL PRIMARY KEY,
+    book_id INT NOT NULL REFERENCES books(id) ON DELETE CASCADE,
+    lang VARCHAR(10) NOT NULL,
+    source_term VARCHAR(200) NOT NULL,
+    source_key VARCHAR(200) NOT NULL,
+    translation VARCHAR(200) NOT NULL,
+    notes TEXT,
+    part_of_speech part_of_speech,
+    updated_by INT NOT NULL REFERENCES users(id),
+    created TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+    UNIQUE(book_id, lang, source_key)
+);
@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE workflow_events;
+DROP TABLE chapter_workflows;
+DROP TABLE workflow_members;
+DROP TYPE workflow_action;
+DROP TYPE workflow_stage;
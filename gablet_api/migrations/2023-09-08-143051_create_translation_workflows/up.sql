@@ -0,0 +1,53 @@
+-- Your SQL goes here
+CREATE TYPE workflow_stage AS ENUM (
+    'translate', 'proofread', 'typeset', 'quality_check', 'complete'
+);
+
+CREATE TYPE workflow_action AS ENUM (
+    'started', 'claimed', 'released', 'submitted', 'returned', 'deadline_set'
+);
+
+-- The people who can work on each stage of a book's translation into a
+-- language. The author of the book can work on every stage.
+CREATE TABLE workflow_members(
+    book_id INT NOT NULL REFERENCES books(id) ON DELETE CASCADE,
+    lang VARCHAR(10) NOT NULL,
+    user_id INT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    stage workflow_stage NOT NULL CHECK (stage <> 'complete'),
+
+    PRIMARY KEY(book_id, lang, user_id, stage)
+);
+
+CREATE INDEX workflow_members_user_id_idx ON workflow_members(user_id);
+
+-- Where the translation of a chapter into a language is. due is the deadline
+-- for the current stage.
+CREATE TABLE chapter_workflows(
+    id SERIAL PRIMARY KEY,
+    chapter_id INT NOT NULL REFERENCES chapters(id) ON DELETE CASCADE,
+    lang VARCHAR(10) NOT NULL,
+    stage workflow_stage NOT NULL DEFAULT 'translate',
+    claimed_by INT REFERENCES users(id) ON DELETE SET NULL,
+    claimed TIMESTAMP,
+    due TIMESTAMP,
+    created TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+    UNIQUE(chapter_id, lang)
+);
+
+CREATE INDEX chapter_workflows_claimed_by_idx ON chapter_workflows(claimed_by)
+    WHERE claimed_by IS NOT NULL;
+
+CREATE TABLE workflow_events(
+    id SERIAL PRIMARY KEY,
+    workflow_id INT NOT NULL REFERENCES chapter_workflows(id) ON DELETE CASCADE,
+    user_id INT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    action workflow_action NOT NULL,
+    from_stage workflow_stage NOT NULL,
+    to_stage workflow_stage NOT NULL,
+    note TEXT,
+    created TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX workflow_events_workflow_id_idx ON workflow_events(workflow_id, id);
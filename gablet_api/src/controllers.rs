@@ -1 +1,16 @@
-pub mod profile;
\ No newline at end of file
+pub mod archives;
+pub mod books;
+pub mod comments;
+pub mod follows;
+pub mod glossary;
+pub mod ledger;
+pub mod moderation;
+pub mod notifications;
+pub mod profile;
+pub mod reading;
+pub mod reviews;
+pub mod search;
+pub mod tags;
+pub mod text_regions;
+pub mod trending;
+pub mod workflows;
\ No newline at end of file
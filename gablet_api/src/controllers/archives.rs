@@ -0,0 +1,560 @@
+use std::collections::{HashMap, HashSet};
+
+use axum::{
+    body::Bytes,
+    extract::{Path, Query},
+    headers::{authorization::Bearer, Authorization},
+    http::{header, StatusCode},
+    Json, TypedHeader,
+};
+use chrono::NaiveDateTime;
+use diesel::{
+    prelude::*,
+    result::{DatabaseErrorKind, Error as DbError},
+};
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use gablet_shared_api::errors::{
+    get_error, get_error_from_string, get_internal_error, ErrorResult,
+};
+use gablet_tokens::AuthToken;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    models::{
+        books::Book,
+        chapters::{Chapter, NewChapter, Page},
+    },
+    utils::{
+        archives::{
+            import_chapter_pages, read_archive_pages, write_cbz, write_epub, ArchiveError,
+            ExportChapter, ExportMetadata, ExportPage, PageOverlay,
+        },
+        books::{can_view_unapproved, find_book, get_max_content_rating},
+        images::read_image_info,
+        lang::validate_lang,
+        storage::load_page_image,
+        tracking::send_tracked_book,
+    },
+    PG_POOL, TOKEN_ISSUER,
+};
+
+/// Whole books are exported in memory, so they're limited to this many pages.
+const MAX_EXPORT_PAGES: usize = 3000;
+
+#[derive(Deserialize)]
+pub struct ImportChapterQuery {
+    pub number: i32,
+    pub title: Option<String>,
+    pub publish_at: Option<NaiveDateTime>,
+}
+
+#[derive(Deserialize, Default, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    #[default]
+    Cbz,
+    Epub,
+}
+
+impl ExportFormat {
+    fn content_type(self) -> &'static str {
+        match self {
+            ExportFormat::Cbz => "application/vnd.comicbook+zip",
+            ExportFormat::Epub => "application/epub+zip",
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Cbz => "cbz",
+            ExportFormat::Epub => "epub",
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ExportQuery {
+    pub format: Option<ExportFormat>,
+    /// Lay the translation into this language over the pages as text. The
+    /// page images are left as they are, so this needs an EPUB.
+    pub overlays: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct ImportResult {
+    #[serde(flatten)]
+    pub chapter: Chapter,
+    pub pages: Vec<Page>,
+}
+
+type ExportResponse = ([(header::HeaderName, String); 2], Vec<u8>);
+
+fn archive_error(err: ArchiveError) -> (StatusCode, Json<ErrorResult>) {
+    match err {
+        ArchiveError::Io(_) | ArchiveError::Xml(_) => get_internal_error(err).to_tuple(),
+        ArchiveError::Db(DbError::DatabaseError(DatabaseErrorKind::UniqueViolation, _)) => {
+            get_error_from_string(
+                StatusCode::CONFLICT,
+                "A chapter with that number already exists".into(),
+            )
+            .to_tuple()
+        }
+        ArchiveError::Db(err) => get_internal_error(err).to_tuple(),
+        err @ (ArchiveError::TooLarge | ArchiveError::PageTooLarge(_)) => {
+            get_error(err, StatusCode::PAYLOAD_TOO_LARGE).to_tuple()
+        }
+        err => get_error(err, StatusCode::BAD_REQUEST).to_tuple(),
+    }
+}
+
+/// Creates a chapter from a CBZ or ZIP archive sent as the request body. The
+/// images in the archive become the pages of the chapter in natural file name
+/// order, so `page2.png` comes before `page10.png`.
+pub async fn import_chapter(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Path(book_id): Path<i32>,
+    Query(query): Query<ImportChapterQuery>,
+    body: Bytes,
+) -> Result<(StatusCode, Json<ImportResult>), (StatusCode, Json<ErrorResult>)> {
+    let token = TOKEN_ISSUER
+        .validate_auth(auth.token())
+        .map_err(|err| get_error(err, StatusCode::UNAUTHORIZED).to_tuple())?;
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let book = find_book(book_id, connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?
+        .ok_or_else(|| {
+            get_error_from_string(StatusCode::NOT_FOUND, format!("No book {} exists", book_id))
+                .to_tuple()
+        })?;
+
+    if book.author_id != token.user_id() {
+        return Err(get_error_from_string(
+            StatusCode::FORBIDDEN,
+            format!("Only the author of {} can add chapters", book.name),
+        )
+        .to_tuple());
+    }
+
+    // Unzipping and checking the images is slow, so it's kept off the async
+    // workers.
+    let pages = tokio::task::spawn_blocking(move || read_archive_pages(body.to_vec()))
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?
+        .map_err(archive_error)?;
+
+    let (chapter, pages) = import_chapter_pages(
+        NewChapter {
+            book_id,
+            number: query.number,
+            title: query.title,
+            publish_at: query.publish_at,
+        },
+        token.user_id(),
+        pages,
+        connection,
+    )
+    .await
+    .map_err(archive_error)?;
+
+    send_tracked_book(book_id, connection).await;
+
+    Ok((StatusCode::CREATED, Json(ImportResult { chapter, pages })))
+}
+
+/// Checks that the owner of the token can read a book and returns whether
+/// they can also see its unapproved and unpublished parts.
+async fn check_export_access(
+    book: &Book,
+    token: Option<&AuthToken>,
+    connection: &mut AsyncPgConnection,
+) -> Result<bool, (StatusCode, Json<ErrorResult>)> {
+    let privileged = can_view_unapproved(book, token);
+
+    if !book.approved && !privileged {
+        return Err(get_error_from_string(
+            StatusCode::NOT_FOUND,
+            format!("No book {} exists", book.id),
+        )
+        .to_tuple());
+    }
+
+    let max_rating = get_max_content_rating(token, None, connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    if book.content_rating > max_rating && !privileged {
+        return Err(get_error_from_string(
+            StatusCode::FORBIDDEN,
+            format!("{} is above your content rating limit", book.name),
+        )
+        .to_tuple());
+    }
+
+    Ok(privileged)
+}
+
+/// Checks the export options. Overlays are positioned HTML text on top of
+/// the page images rather than part of them, so they need an EPUB. CBZ
+/// readers only show the images.
+fn validate_export_query(
+    query: &ExportQuery,
+) -> Result<ExportFormat, (StatusCode, Json<ErrorResult>)> {
+    let format = query.format.unwrap_or_default();
+
+    if let Some(lang) = &query.overlays {
+        validate_lang(lang).map_err(|err| err.to_tuple())?;
+
+        if format != ExportFormat::Epub {
+            return Err(get_error_from_string(
+                StatusCode::BAD_REQUEST,
+                "Translated overlays are only supported in EPUB exports".into(),
+            )
+            .to_tuple());
+        }
+    }
+
+    Ok(format)
+}
+
+/// Finds which of the chapters have a translation into the language that has
+/// been released to readers.
+async fn find_translated_chapters(
+    chapter_ids: &[i32],
+    lang: &str,
+    connection: &mut AsyncPgConnection,
+) -> Result<HashSet<i32>, (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::chapter_translations::dsl::{
+        chapter_id as db_chapter_id, chapter_translations as db_translations, lang as db_lang,
+    };
+
+    let translated = db_translations
+        .filter(db_chapter_id.eq_any(chapter_ids))
+        .filter(db_lang.eq(lang.to_string()))
+        .select(db_chapter_id)
+        .load::<i32>(connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    Ok(translated.into_iter().collect())
+}
+
+/// Loads the pages of the chapters along with their images, and the
+/// translated text for each region if `overlay_lang` is set.
+async fn load_export_chapters(
+    chapters: Vec<Chapter>,
+    overlay_lang: Option<&str>,
+    connection: &mut AsyncPgConnection,
+) -> Result<Vec<ExportChapter>, (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::pages::dsl::{
+        chapter_id as db_chapter_id, number as db_number, pages as db_pages,
+    };
+    use crate::schema::text_region_translations::dsl::{
+        lang as db_lang, text as db_text, text_region_translations as db_translations,
+    };
+    use crate::schema::text_regions::dsl::{
+        height as db_height, id as db_region_id, page_id as db_page_id, width as db_width,
+        x as db_x, y as db_y,
+    };
+
+    let chapter_ids: Vec<i32> = chapters.iter().map(|chapter| chapter.id).collect();
+
+    let pages: Vec<Page> = db_pages
+        .filter(db_chapter_id.eq_any(&chapter_ids))
+        .order((db_chapter_id.asc(), db_number.asc()))
+        .select(Page::as_select())
+        .load(connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    if pages.len() > MAX_EXPORT_PAGES {
+        return Err(get_error_from_string(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!(
+                "Exports can't have more than {} pages, so export the chapters one at a time",
+                MAX_EXPORT_PAGES
+            ),
+        )
+        .to_tuple());
+    }
+
+    let mut overlays: HashMap<i32, Vec<PageOverlay>> = HashMap::new();
+
+    if let Some(lang) = overlay_lang {
+        let page_ids: Vec<i32> = pages.iter().map(|page| page.id).collect();
+
+        let regions = db_translations
+            .inner_join(crate::schema::text_regions::table)
+            .filter(db_page_id.eq_any(page_ids))
+            .filter(db_lang.eq(lang.to_string()))
+            .order(db_region_id.asc())
+            .select((db_page_id, db_x, db_y, db_width, db_height, db_text))
+            .load::<(i32, f32, f32, f32, f32, String)>(connection)
+            .await
+            .map_err(|err| get_internal_error(err).to_tuple())?;
+
+        for (page_id, x, y, width, height, text) in regions {
+            overlays.entry(page_id).or_default().push(PageOverlay {
+                x,
+                y,
+                width,
+                height,
+                text,
+            });
+        }
+    }
+
+    let mut pages_by_chapter: HashMap<i32, Vec<ExportPage>> = HashMap::new();
+
+    for page in pages {
+        let image = load_page_image(&page.image)
+            .await
+            .map_err(|err| get_internal_error(err).to_tuple())?;
+
+        let info = read_image_info(&image).ok_or_else(|| {
+            get_error_from_string(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("The image of page {} is damaged", page.id),
+            )
+            .to_tuple()
+        })?;
+
+        pages_by_chapter
+            .entry(page.chapter_id)
+            .or_default()
+            .push(ExportPage {
+                info,
+                image,
+                overlays: overlays.remove(&page.id).unwrap_or_default(),
+            });
+    }
+
+    Ok(chapters
+        .into_iter()
+        .filter_map(|chapter| {
+            let pages = pages_by_chapter.remove(&chapter.id)?;
+
+            Some(ExportChapter {
+                number: chapter.number,
+                title: chapter.title,
+                pages,
+            })
+        })
+        .collect())
+}
+
+async fn find_author_name(
+    book: &Book,
+    connection: &mut AsyncPgConnection,
+) -> Result<String, (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::users::dsl::{name as db_name, users as db_users};
+
+    db_users
+        .find(book.author_id)
+        .select(db_name)
+        .first(connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())
+}
+
+/// Writes the archive on a blocking thread and wraps it in a download.
+async fn build_export(
+    format: ExportFormat,
+    metadata: ExportMetadata,
+    chapters: Vec<ExportChapter>,
+    file_name: String,
+) -> Result<ExportResponse, (StatusCode, Json<ErrorResult>)> {
+    if chapters.is_empty() {
+        return Err(get_error_from_string(
+            StatusCode::NOT_FOUND,
+            "There are no pages to export".into(),
+        )
+        .to_tuple());
+    }
+
+    let archive = tokio::task::spawn_blocking(move || match format {
+        ExportFormat::Cbz => write_cbz(&metadata, &chapters),
+        ExportFormat::Epub => write_epub(&metadata, &chapters),
+    })
+    .await
+    .map_err(|err| get_internal_error(err).to_tuple())?
+    .map_err(archive_error)?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, format.content_type().to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!(
+                    "attachment; filename=\"{}.{}\"",
+                    file_name,
+                    format.extension()
+                ),
+            ),
+        ],
+        archive,
+    ))
+}
+
+/// Exports a chapter as a CBZ or a fixed layout EPUB.
+pub async fn export_chapter(
+    bearer: Option<TypedHeader<Authorization<Bearer>>>,
+    Path(chapter_id): Path<i32>,
+    Query(query): Query<ExportQuery>,
+) -> Result<ExportResponse, (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::chapters::dsl::chapters as db_chapters;
+
+    let token = bearer.and_then(|TypedHeader(auth)| TOKEN_ISSUER.validate_auth(auth.token()).ok());
+
+    let format = validate_export_query(&query)?;
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let not_found = || {
+        get_error_from_string(
+            StatusCode::NOT_FOUND,
+            format!("No chapter {} exists", chapter_id),
+        )
+        .to_tuple()
+    };
+
+    let chapter: Chapter = db_chapters
+        .find(chapter_id)
+        .select(Chapter::as_select())
+        .first(connection)
+        .await
+        .optional()
+        .map_err(|err| get_internal_error(err).to_tuple())?
+        .ok_or_else(not_found)?;
+
+    let book = find_book(chapter.book_id, connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?
+        .ok_or_else(not_found)?;
+
+    let privileged = check_export_access(&book, token.as_ref(), connection).await?;
+
+    if !privileged && (!chapter.approved || chapter.published.is_none()) {
+        return Err(not_found());
+    }
+
+    if let Some(lang) = &query.overlays {
+        let translated = find_translated_chapters(&[chapter.id], lang, connection).await?;
+
+        if !privileged && translated.is_empty() {
+            return Err(get_error_from_string(
+                StatusCode::NOT_FOUND,
+                format!(
+                    "Chapter {} hasn't been translated into {} yet",
+                    chapter.number, lang
+                ),
+            )
+            .to_tuple());
+        }
+    }
+
+    let metadata = ExportMetadata {
+        book_id: book.id,
+        chapter_id: Some(chapter.id),
+        title: match &chapter.title {
+            Some(title) => format!("{} - Chapter {}: {}", book.name, chapter.number, title),
+            None => format!("{} - Chapter {}", book.name, chapter.number),
+        },
+        series: book.name.clone(),
+        author: find_author_name(&book, connection).await?,
+        description: book.description.clone(),
+        lang: query.overlays.clone().unwrap_or_else(|| book.lang.clone()),
+    };
+
+    let file_name = format!("book-{}-chapter-{}", book.id, chapter.number);
+    let chapters =
+        load_export_chapters(vec![chapter], query.overlays.as_deref(), connection).await?;
+
+    build_export(format, metadata, chapters, file_name).await
+}
+
+/// Exports every chapter of a book that readers can see as a CBZ or a fixed
+/// layout EPUB. With overlays, only the chapters that have been translated
+/// into the language are included.
+pub async fn export_book(
+    bearer: Option<TypedHeader<Authorization<Bearer>>>,
+    Path(book_id): Path<i32>,
+    Query(query): Query<ExportQuery>,
+) -> Result<ExportResponse, (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::chapters::dsl::{
+        approved as db_approved, book_id as db_book_id, chapters as db_chapters,
+        number as db_number, published as db_published,
+    };
+
+    let token = bearer.and_then(|TypedHeader(auth)| TOKEN_ISSUER.validate_auth(auth.token()).ok());
+
+    let format = validate_export_query(&query)?;
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let book = find_book(book_id, connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?
+        .ok_or_else(|| {
+            get_error_from_string(StatusCode::NOT_FOUND, format!("No book {} exists", book_id))
+                .to_tuple()
+        })?;
+
+    let privileged = check_export_access(&book, token.as_ref(), connection).await?;
+
+    let mut chapters_query = db_chapters.filter(db_book_id.eq(book_id)).into_boxed();
+
+    if !privileged {
+        chapters_query = chapters_query
+            .filter(db_approved.eq(true))
+            .filter(db_published.is_not_null());
+    }
+
+    let mut chapters: Vec<Chapter> = chapters_query
+        .order(db_number.asc())
+        .select(Chapter::as_select())
+        .load(connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    if let Some(lang) = &query.overlays {
+        let chapter_ids: Vec<i32> = chapters.iter().map(|chapter| chapter.id).collect();
+        let translated = find_translated_chapters(&chapter_ids, lang, connection).await?;
+
+        if !privileged {
+            chapters.retain(|chapter| translated.contains(&chapter.id));
+        }
+    }
+
+    let metadata = ExportMetadata {
+        book_id: book.id,
+        chapter_id: None,
+        title: book.name.clone(),
+        series: book.name.clone(),
+        author: find_author_name(&book, connection).await?,
+        description: book.description.clone(),
+        lang: query.overlays.clone().unwrap_or_else(|| book.lang.clone()),
+    };
+
+    let file_name = format!("book-{}", book.id);
+    let chapters = load_export_chapters(chapters, query.overlays.as_deref(), connection).await?;
+
+    build_export(format, metadata, chapters, file_name).await
+}
@@ -0,0 +1,829 @@
+use axum::{
+    extract::{Path, Query},
+    headers::{authorization::Bearer, Authorization},
+    http::StatusCode,
+    Json, TypedHeader,
+};
+use chrono::NaiveDateTime;
+use diesel::{
+    dsl::{exists, sql},
+    insert_into,
+    prelude::*,
+    result::Error as DbError,
+    sql_types::{Nullable, Timestamp},
+    update,
+};
+use diesel_async::{
+    scoped_futures::ScopedFutureExt, AsyncConnection, AsyncPgConnection, RunQueryDsl,
+};
+use gablet_shared_api::{
+    errors::{get_error, get_error_from_string, get_internal_error, ErrorResult},
+    kafka::kafka_events::{BOOK_TOPIC, CHAPTER_PUBLISHED_EVENT},
+    keyset_page,
+    pagination::{clamp_limit, fetch_page, Paged, SortDirection, SortWhitelist},
+};
+use gablet_tokens::AuthToken;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    models::{
+        books::{Book, BookChanges, ContentRating, NewBook},
+        chapters::{
+            Chapter, ChapterPublished, ChapterTranslation, NewChapter, NewChapterTranslation,
+        },
+        moderation::{ModerationItem, ModerationKind, NewModerationItem},
+        reviews::{BookRating, RatingSummary},
+    },
+    utils::{
+        books::{can_view_unapproved, find_book, get_max_content_rating, insert_chapter},
+        errors::map_insert_error,
+        kafka::send_event,
+        lang::validate_lang,
+        moderation::{resubmit_item, submit_for_review},
+        tags::{parse_tag_list, resolve_tags},
+        tracking::send_tracked_book,
+    },
+    CURSOR_SIGNER, PG_POOL, TOKEN_ISSUER,
+};
+
+const DEFAULT_BOOK_LIMIT: i64 = 25;
+const MAX_BOOK_LIMIT: i64 = 100;
+
+#[derive(Deserialize)]
+pub struct CreateBookRequest {
+    pub name: String,
+    pub description: Option<String>,
+    pub lang: String,
+    pub content_rating: Option<ContentRating>,
+}
+
+#[derive(Deserialize)]
+pub struct CreateChapterRequest {
+    pub number: i32,
+    pub title: Option<String>,
+    /// When to publish the chapter. Leave empty to publish it as soon as it's
+    /// approved.
+    pub publish_at: Option<NaiveDateTime>,
+}
+
+#[derive(Deserialize)]
+pub struct ScheduleRequest {
+    pub publish_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BookSort {
+    Newest,
+    /// Highest Bayesian average rating first.
+    Rating,
+}
+
+const BOOK_SORTS: SortWhitelist<BookSort> = SortWhitelist {
+    fields: &[("newest", BookSort::Newest), ("rating", BookSort::Rating)],
+    default: BookSort::Newest,
+};
+
+#[derive(Deserialize)]
+pub struct BookListQuery {
+    pub sort: Option<String>,
+    pub cursor: Option<String>,
+    pub limit: Option<i64>,
+    /// A comma separated list of tags. Books must have all of them.
+    pub tags: Option<String>,
+    pub max_rating: Option<ContentRating>,
+}
+
+#[derive(Serialize)]
+pub struct BookDetails {
+    #[serde(flatten)]
+    pub book: Book,
+    pub rating: RatingSummary,
+}
+
+/// Submits a new book. Books stay hidden until a moderator approves them.
+pub async fn create_book(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Json(request): Json<CreateBookRequest>,
+) -> Result<(StatusCode, Json<Book>), (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::books::dsl::books as db_books;
+
+    let token = TOKEN_ISSUER
+        .validate_auth(auth.token())
+        .map_err(|err| get_error(err, StatusCode::UNAUTHORIZED).to_tuple())?;
+
+    if request.name.trim().is_empty() {
+        return Err(
+            get_error_from_string(StatusCode::BAD_REQUEST, "Book name is required".into())
+                .to_tuple(),
+        );
+    }
+
+    validate_lang(&request.lang).map_err(|err| err.to_tuple())?;
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let author_id = token.user_id();
+    let name = request.name.clone();
+
+    let book = connection
+        .transaction::<_, DbError, _>(|connection| {
+            async move {
+                let book: Book = insert_into(db_books)
+                    .values(NewBook {
+                        name: request.name,
+                        description: request.description,
+                        author_id,
+                        lang: request.lang,
+                        content_rating: request.content_rating.unwrap_or(ContentRating::AllAges),
+                    })
+                    .returning(Book::as_returning())
+                    .get_result(connection)
+                    .await?;
+
+                submit_for_review(
+                    NewModerationItem {
+                        kind: ModerationKind::Book,
+                        book_id: book.id,
+                        chapter_id: None,
+                        submitted_by: author_id,
+                    },
+                    connection,
+                )
+                .await?;
+
+                Ok(book)
+            }
+            .scope_boxed()
+        })
+        .await
+        .map_err(|err| map_insert_error(err, format!("A book named {} already exists", name)))?;
+
+    send_tracked_book(book.id, connection).await;
+
+    Ok((StatusCode::CREATED, Json(book)))
+}
+
+/// Updates the details of a book. Only the author of the book can change it.
+pub async fn update_book(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Path(book_id): Path<i32>,
+    Json(changes): Json<BookChanges>,
+) -> Result<Json<Book>, (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::books::dsl::{author_id as db_author_id, books as db_books, id as db_id};
+
+    let token = TOKEN_ISSUER
+        .validate_auth(auth.token())
+        .map_err(|err| get_error(err, StatusCode::UNAUTHORIZED).to_tuple())?;
+
+    if changes.description.is_none()
+        && changes.lang.is_none()
+        && changes.status.is_none()
+        && changes.content_rating.is_none()
+    {
+        return Err(
+            get_error_from_string(StatusCode::BAD_REQUEST, "No changes given".into()).to_tuple(),
+        );
+    }
+
+    if let Some(lang) = &changes.lang {
+        validate_lang(lang).map_err(|err| err.to_tuple())?;
+    }
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let book = update(db_books)
+        .filter(db_id.eq(book_id))
+        .filter(db_author_id.eq(token.user_id()))
+        .set(&changes)
+        .returning(Book::as_returning())
+        .get_result(connection)
+        .await
+        .optional()
+        .map_err(|err| get_internal_error(err).to_tuple())?
+        .ok_or_else(|| {
+            get_error_from_string(
+                StatusCode::NOT_FOUND,
+                format!("No book {} written by {}", book_id, token.username()),
+            )
+            .to_tuple()
+        })?;
+
+    if changes.lang.is_some() {
+        send_tracked_book(book_id, connection).await;
+    }
+
+    Ok(Json(book))
+}
+
+/// Lists approved books, either newest or best rated first. Books rated above
+/// what the current user allows are left out.
+///
+/// Cursors hold the id of the last book on a page when sorting by newest, and
+/// its `(score, id)` when sorting by rating.
+pub async fn list_books(
+    bearer: Option<TypedHeader<Authorization<Bearer>>>,
+    Query(query): Query<BookListQuery>,
+) -> Result<Json<Paged<Book>>, (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::book_ratings::dsl::{book_ratings as db_book_ratings, score as db_score};
+    use crate::schema::book_tags::dsl::{
+        book_id as db_book_tag_book_id, book_tags as db_book_tags, tag_id as db_tag_id,
+    };
+    use crate::schema::books::dsl::{
+        approved as db_approved, books as db_books, content_rating as db_content_rating,
+        id as db_id,
+    };
+
+    let token = bearer.and_then(|TypedHeader(auth)| TOKEN_ISSUER.validate_auth(auth.token()).ok());
+
+    let sort = BOOK_SORTS
+        .parse(query.sort.as_deref())
+        .map_err(|err| err.to_tuple())?;
+    let sort_name = BOOK_SORTS.name(sort);
+
+    let after: Option<(f64, i32)> = match (sort, query.cursor.as_deref()) {
+        (_, None) => Ok(None),
+        (BookSort::Newest, Some(cursor)) => CURSOR_SIGNER
+            .decode::<i32>(sort_name, cursor)
+            .map(|id| Some((0.0, id))),
+        (BookSort::Rating, Some(cursor)) => CURSOR_SIGNER.decode(sort_name, cursor).map(Some),
+    }
+    .map_err(|err| ErrorResult::from(err).to_tuple())?;
+
+    let limit = clamp_limit(query.limit, DEFAULT_BOOK_LIMIT, MAX_BOOK_LIMIT);
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let max_rating = get_max_content_rating(token.as_ref(), query.max_rating, connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let mut books_query = db_books
+        .inner_join(db_book_ratings)
+        .filter(db_approved.eq(true))
+        .filter(db_content_rating.le(max_rating))
+        .select((Book::as_select(), db_score))
+        .into_boxed();
+
+    if let Some(tags) = &query.tags {
+        let (tags, unknown) = resolve_tags(&parse_tag_list(tags), connection)
+            .await
+            .map_err(|err| get_internal_error(err).to_tuple())?;
+
+        // No book can have a tag that doesn't exist.
+        if !unknown.is_empty() {
+            return Ok(Json(Paged {
+                items: vec![],
+                next_cursor: None,
+            }));
+        }
+
+        for tag in tags {
+            books_query = books_query.filter(
+                db_id.eq_any(
+                    db_book_tags
+                        .filter(db_tag_id.eq(tag.id))
+                        .select(db_book_tag_book_id),
+                ),
+            );
+        }
+    }
+
+    books_query = match sort {
+        BookSort::Newest => match after {
+            Some((_, after_id)) => books_query.filter(db_id.lt(after_id)),
+            None => books_query,
+        }
+        .order(db_id.desc()),
+        BookSort::Rating => keyset_page!(books_query, db_score, db_id, SortDirection::Desc, after),
+    };
+
+    let books: Vec<(Book, f64)> = fetch_page(books_query, limit)
+        .load(connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let page = Paged::from_rows(books, limit, |(last, score)| match sort {
+        BookSort::Newest => CURSOR_SIGNER.encode(sort_name, &last.id),
+        BookSort::Rating => CURSOR_SIGNER.encode(sort_name, &(score, last.id)),
+    });
+
+    Ok(Json(page.map(|(book, _)| book)))
+}
+
+/// Gets a book along with its rating aggregates.
+pub async fn get_book(
+    bearer: Option<TypedHeader<Authorization<Bearer>>>,
+    Path(book_id): Path<i32>,
+) -> Result<Json<BookDetails>, (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::book_ratings::dsl::book_ratings as db_book_ratings;
+
+    let token = bearer.and_then(|TypedHeader(auth)| TOKEN_ISSUER.validate_auth(auth.token()).ok());
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let book = find_book(book_id, connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?
+        .filter(|book| book.approved || can_view_unapproved(book, token.as_ref()))
+        .ok_or_else(|| {
+            get_error_from_string(StatusCode::NOT_FOUND, format!("No book {} exists", book_id))
+                .to_tuple()
+        })?;
+
+    let rating = db_book_ratings
+        .find(book_id)
+        .select(BookRating::as_select())
+        .first(connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    Ok(Json(BookDetails {
+        book,
+        rating: rating.into(),
+    }))
+}
+
+/// Submits a new chapter for a book. Only the author of the book can add
+/// chapters, and they stay hidden until a moderator approves them.
+pub async fn create_chapter(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Path(book_id): Path<i32>,
+    Json(request): Json<CreateChapterRequest>,
+) -> Result<(StatusCode, Json<Chapter>), (StatusCode, Json<ErrorResult>)> {
+    let token = TOKEN_ISSUER
+        .validate_auth(auth.token())
+        .map_err(|err| get_error(err, StatusCode::UNAUTHORIZED).to_tuple())?;
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let book = find_book(book_id, connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?
+        .ok_or_else(|| {
+            get_error_from_string(StatusCode::NOT_FOUND, format!("No book {} exists", book_id))
+                .to_tuple()
+        })?;
+
+    if book.author_id != token.user_id() {
+        return Err(get_error_from_string(
+            StatusCode::FORBIDDEN,
+            format!("Only the author of {} can add chapters", book.name),
+        )
+        .to_tuple());
+    }
+
+    let author_id = token.user_id();
+    let number = request.number;
+
+    let chapter = connection
+        .transaction::<_, DbError, _>(|connection| {
+            async move {
+                insert_chapter(
+                    NewChapter {
+                        book_id,
+                        number: request.number,
+                        title: request.title,
+                        publish_at: request.publish_at,
+                    },
+                    author_id,
+                    connection,
+                )
+                .await
+            }
+            .scope_boxed()
+        })
+        .await
+        .map_err(|err| map_insert_error(err, format!("Chapter {} already exists", number)))?;
+
+    send_tracked_book(book_id, connection).await;
+
+    Ok((StatusCode::CREATED, Json(chapter)))
+}
+
+pub async fn list_chapters(
+    bearer: Option<TypedHeader<Authorization<Bearer>>>,
+    Path(book_id): Path<i32>,
+) -> Result<Json<Vec<Chapter>>, (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::chapters::dsl::{
+        approved as db_approved, book_id as db_book_id, chapters as db_chapters,
+        number as db_number, published as db_published,
+    };
+
+    let token = bearer.and_then(|TypedHeader(auth)| TOKEN_ISSUER.validate_auth(auth.token()).ok());
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let book = find_book(book_id, connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let show_unapproved = match &book {
+        Some(book) => can_view_unapproved(book, token.as_ref()),
+        None => false,
+    };
+
+    if !book
+        .map(|book| book.approved || show_unapproved)
+        .unwrap_or(false)
+    {
+        return Err(get_error_from_string(
+            StatusCode::NOT_FOUND,
+            format!("No book {} exists", book_id),
+        )
+        .to_tuple());
+    }
+
+    let mut chapters_query = db_chapters.filter(db_book_id.eq(book_id)).into_boxed();
+
+    if !show_unapproved {
+        chapters_query = chapters_query
+            .filter(db_approved.eq(true))
+            .filter(db_published.is_not_null());
+    }
+
+    let chapters = chapters_query
+        .order(db_number.asc())
+        .select(Chapter::as_select())
+        .load(connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    Ok(Json(chapters))
+}
+
+/// Releases the translation of a chapter into a language and lets followers
+/// reading in that language know. The author of the book and anyone who
+/// translated part of the chapter can publish it.
+pub async fn publish_chapter_translation(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Path((chapter_id, lang)): Path<(i32, String)>,
+) -> Result<(StatusCode, Json<ChapterTranslation>), (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::chapter_translations::dsl::chapter_translations as db_chapter_translations;
+    use crate::schema::chapters::dsl::chapters as db_chapters;
+    use crate::schema::pages::dsl::{chapter_id as db_page_chapter_id, pages as db_pages};
+    use crate::schema::text_region_translations::dsl::{
+        lang as db_lang, text_region_translations as db_translations,
+        translator_id as db_translator_id,
+    };
+    use crate::schema::text_regions::dsl::text_regions as db_regions;
+
+    let token = TOKEN_ISSUER
+        .validate_auth(auth.token())
+        .map_err(|err| get_error(err, StatusCode::UNAUTHORIZED).to_tuple())?;
+
+    validate_lang(&lang).map_err(|err| err.to_tuple())?;
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let chapter = db_chapters
+        .find(chapter_id)
+        .select(Chapter::as_select())
+        .first(connection)
+        .await
+        .optional()
+        .map_err(|err| get_internal_error(err).to_tuple())?
+        .filter(|chapter| chapter.approved && chapter.published.is_some())
+        .ok_or_else(|| {
+            get_error_from_string(
+                StatusCode::NOT_FOUND,
+                format!("No chapter {} exists", chapter_id),
+            )
+            .to_tuple()
+        })?;
+
+    let book = find_book(chapter.book_id, connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?
+        .filter(|book| book.approved)
+        .ok_or_else(|| {
+            get_error_from_string(
+                StatusCode::NOT_FOUND,
+                format!("No book {} exists", chapter.book_id),
+            )
+            .to_tuple()
+        })?;
+
+    if book.lang == lang {
+        return Err(get_error_from_string(
+            StatusCode::BAD_REQUEST,
+            format!("{} is already written in {}", book.name, lang),
+        )
+        .to_tuple());
+    }
+
+    if book.author_id != token.user_id() {
+        let translated = diesel::select(exists(
+            db_translations
+                .inner_join(db_regions.inner_join(db_pages))
+                .filter(db_page_chapter_id.eq(chapter_id))
+                .filter(db_lang.eq(&lang))
+                .filter(db_translator_id.eq(token.user_id())),
+        ))
+        .get_result::<bool>(connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+        if !translated {
+            return Err(get_error_from_string(
+                StatusCode::FORBIDDEN,
+                format!(
+                    "Only the author of {} or a translator of the chapter can publish it",
+                    book.name
+                ),
+            )
+            .to_tuple());
+        }
+    }
+
+    let translation = insert_into(db_chapter_translations)
+        .values(NewChapterTranslation {
+            chapter_id,
+            lang: lang.clone(),
+            published_by: token.user_id(),
+        })
+        .returning(ChapterTranslation::as_returning())
+        .get_result(connection)
+        .await
+        .map_err(|err| {
+            map_insert_error(
+                err,
+                format!(
+                    "Chapter {} is already published in {}",
+                    chapter.number, lang
+                ),
+            )
+        })?;
+
+    send_event(
+        BOOK_TOPIC,
+        CHAPTER_PUBLISHED_EVENT,
+        &ChapterPublished {
+            book_id: book.id,
+            chapter_id,
+            lang: Some(lang),
+        },
+    );
+
+    send_tracked_book(book.id, connection).await;
+
+    Ok((StatusCode::CREATED, Json(translation)))
+}
+
+/// Finds a chapter of a book written by the owner of the token.
+async fn find_authored_chapter(
+    chapter_id: i32,
+    token: &AuthToken,
+    connection: &mut AsyncPgConnection,
+) -> Result<Chapter, (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::books::dsl::author_id as db_author_id;
+    use crate::schema::chapters::dsl::chapters as db_chapters;
+
+    db_chapters
+        .find(chapter_id)
+        .inner_join(crate::schema::books::table)
+        .filter(db_author_id.eq(token.user_id()))
+        .select(Chapter::as_select())
+        .first(connection)
+        .await
+        .optional()
+        .map_err(|err| get_internal_error(err).to_tuple())?
+        .ok_or_else(|| {
+            get_error_from_string(
+                StatusCode::NOT_FOUND,
+                format!("No chapter {} written by {}", chapter_id, token.username()),
+            )
+            .to_tuple()
+        })
+}
+
+/// Schedules a chapter to be published at a later time, or moves an existing
+/// schedule. The chapter still has to be approved before it's released, and a
+/// time in the past releases it as soon as it is.
+pub async fn schedule_chapter(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Path(chapter_id): Path<i32>,
+    Json(request): Json<ScheduleRequest>,
+) -> Result<Json<Chapter>, (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::chapters::dsl::{
+        chapters as db_chapters, publish_at as db_publish_at, published as db_published,
+    };
+
+    let token = TOKEN_ISSUER
+        .validate_auth(auth.token())
+        .map_err(|err| get_error(err, StatusCode::UNAUTHORIZED).to_tuple())?;
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    find_authored_chapter(chapter_id, &token, connection).await?;
+
+    // Checking published in the update keeps the scheduler from releasing the
+    // chapter while it's being rescheduled.
+    let chapter = update(db_chapters.find(chapter_id))
+        .filter(db_published.is_null())
+        .set(db_publish_at.eq(request.publish_at))
+        .returning(Chapter::as_returning())
+        .get_result(connection)
+        .await
+        .optional()
+        .map_err(|err| get_internal_error(err).to_tuple())?
+        .ok_or_else(|| {
+            get_error_from_string(
+                StatusCode::CONFLICT,
+                format!("Chapter {} has already been published", chapter_id),
+            )
+            .to_tuple()
+        })?;
+
+    Ok(Json(chapter))
+}
+
+/// Cancels the scheduled release of a chapter. Approved chapters were only
+/// waiting on their schedule, so they're published straight away, while
+/// chapters that are still waiting on review are published once approved.
+pub async fn cancel_chapter_schedule(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Path(chapter_id): Path<i32>,
+) -> Result<Json<Chapter>, (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::chapters::dsl::{
+        chapters as db_chapters, publish_at as db_publish_at, published as db_published,
+    };
+
+    let token = TOKEN_ISSUER
+        .validate_auth(auth.token())
+        .map_err(|err| get_error(err, StatusCode::UNAUTHORIZED).to_tuple())?;
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let chapter = find_authored_chapter(chapter_id, &token, connection).await?;
+
+    if chapter.publish_at.is_none() {
+        return Err(get_error_from_string(
+            StatusCode::NOT_FOUND,
+            format!("Chapter {} is not scheduled", chapter_id),
+        )
+        .to_tuple());
+    }
+
+    // Checking published keeps the scheduler and this from both releasing the
+    // chapter, since only one of the updates can find it unpublished.
+    let chapter = update(db_chapters.find(chapter_id))
+        .filter(db_published.is_null())
+        .set((
+            db_publish_at.eq(None::<NaiveDateTime>),
+            db_published.eq(sql::<Nullable<Timestamp>>(
+                "CASE WHEN approved THEN NOW() END",
+            )),
+        ))
+        .returning(Chapter::as_returning())
+        .get_result(connection)
+        .await
+        .optional()
+        .map_err(|err| get_internal_error(err).to_tuple())?
+        .ok_or_else(|| {
+            get_error_from_string(
+                StatusCode::CONFLICT,
+                format!("Chapter {} has already been published", chapter_id),
+            )
+            .to_tuple()
+        })?;
+
+    if chapter.published.is_some() {
+        send_event(
+            BOOK_TOPIC,
+            CHAPTER_PUBLISHED_EVENT,
+            &ChapterPublished {
+                book_id: chapter.book_id,
+                chapter_id,
+                lang: None,
+            },
+        );
+    }
+
+    Ok(Json(chapter))
+}
+
+/// Sends a rejected book back to the moderation queue, once its author has
+/// dealt with the reason it was rejected.
+pub async fn resubmit_book(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Path(book_id): Path<i32>,
+) -> Result<Json<ModerationItem>, (StatusCode, Json<ErrorResult>)> {
+    let token = TOKEN_ISSUER
+        .validate_auth(auth.token())
+        .map_err(|err| get_error(err, StatusCode::UNAUTHORIZED).to_tuple())?;
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    find_book(book_id, connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?
+        .filter(|book| book.author_id == token.user_id())
+        .ok_or_else(|| {
+            get_error_from_string(
+                StatusCode::NOT_FOUND,
+                format!("No book {} written by {}", book_id, token.username()),
+            )
+            .to_tuple()
+        })?;
+
+    let item = resubmit_item(book_id, None, token.user_id(), connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?
+        .ok_or_else(|| {
+            get_error_from_string(
+                StatusCode::CONFLICT,
+                format!("Book {} hasn't been rejected", book_id),
+            )
+            .to_tuple()
+        })?;
+
+    Ok(Json(item))
+}
+
+/// Sends a rejected chapter back to the moderation queue, the same as
+/// `resubmit_book`.
+pub async fn resubmit_chapter(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Path(chapter_id): Path<i32>,
+) -> Result<Json<ModerationItem>, (StatusCode, Json<ErrorResult>)> {
+    let token = TOKEN_ISSUER
+        .validate_auth(auth.token())
+        .map_err(|err| get_error(err, StatusCode::UNAUTHORIZED).to_tuple())?;
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let chapter = find_authored_chapter(chapter_id, &token, connection).await?;
+
+    let item = resubmit_item(
+        chapter.book_id,
+        Some(chapter_id),
+        token.user_id(),
+        connection,
+    )
+    .await
+    .map_err(|err| get_internal_error(err).to_tuple())?
+    .ok_or_else(|| {
+        get_error_from_string(
+            StatusCode::CONFLICT,
+            format!("Chapter {} hasn't been rejected", chapter_id),
+        )
+        .to_tuple()
+    })?;
+
+    Ok(Json(item))
+}
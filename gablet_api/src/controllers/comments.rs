@@ -0,0 +1,625 @@
+use std::collections::HashMap;
+
+use axum::{
+    extract::{Path, Query},
+    headers::{authorization::Bearer, Authorization},
+    http::StatusCode,
+    Json, TypedHeader,
+};
+use chrono::NaiveDateTime;
+use diesel::{
+    delete,
+    dsl::{now, IntervalDsl},
+    insert_into,
+    pg::upsert::excluded,
+    prelude::*,
+    result::Error as DbError,
+    update,
+};
+use diesel_async::{scoped_futures::ScopedFutureExt, AsyncConnection, RunQueryDsl};
+use gablet_shared_api::{
+    errors::{get_error, get_error_from_string, get_internal_error, ErrorResult},
+    keyset_page,
+    pagination::{fetch_page, PageQuery, Paged, SortDirection, SortWhitelist},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    models::{
+        comments::{Comment, NewComment, NewCommentVote},
+        users::UserLevel,
+    },
+    utils::{auth::require_user_level, books::find_published_chapter},
+    CURSOR_SIGNER, PG_POOL, TOKEN_ISSUER,
+};
+
+const DEFAULT_COMMENT_LIMIT: i64 = 25;
+const MAX_COMMENT_LIMIT: i64 = 100;
+const MAX_COMMENT_LENGTH: usize = 5000;
+
+/// How many comments a user can post within `COMMENT_RATE_WINDOW_SECONDS`.
+const MAX_COMMENTS_PER_WINDOW: i64 = 5;
+const COMMENT_RATE_WINDOW_SECONDS: i64 = 60;
+
+/// Shown in place of the body and author of a deleted comment that still has
+/// replies.
+const DELETED_PLACEHOLDER: &str = "[deleted]";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CommentSort {
+    Newest,
+    Score,
+}
+
+const COMMENT_SORTS: SortWhitelist<CommentSort> = SortWhitelist {
+    fields: &[
+        ("newest", CommentSort::Newest),
+        ("score", CommentSort::Score),
+    ],
+    default: CommentSort::Newest,
+};
+
+#[derive(Deserialize)]
+pub struct CommentRequest {
+    pub body: String,
+    #[serde(default)]
+    pub spoiler: bool,
+    /// The comment being replied to.
+    pub parent_id: Option<i32>,
+}
+
+#[derive(Deserialize)]
+pub struct CommentChanges {
+    pub body: Option<String>,
+    pub spoiler: Option<bool>,
+}
+
+#[derive(Deserialize)]
+pub struct VoteRequest {
+    /// 1 to upvote, -1 to downvote and 0 to take back a vote.
+    pub value: i16,
+}
+
+#[derive(Serialize)]
+pub struct VoteResult {
+    pub comment_id: i32,
+    pub score: i32,
+    pub value: i16,
+}
+
+#[derive(Serialize)]
+pub struct CommentResult {
+    pub id: i32,
+    pub chapter_id: i32,
+    pub parent_id: Option<i32>,
+    pub user_id: Option<i32>,
+    pub username: Option<String>,
+    pub body: String,
+    pub spoiler: bool,
+    pub score: i32,
+    pub deleted: bool,
+    pub created: NaiveDateTime,
+    pub edited: Option<NaiveDateTime>,
+    pub replies: Vec<CommentResult>,
+}
+
+impl CommentResult {
+    fn new(comment: Comment, username: String) -> CommentResult {
+        let (user_id, username, body) = if comment.deleted {
+            (None, None, DELETED_PLACEHOLDER.to_string())
+        } else {
+            (Some(comment.user_id), Some(username), comment.body)
+        };
+
+        CommentResult {
+            id: comment.id,
+            chapter_id: comment.chapter_id,
+            parent_id: comment.parent_id,
+            user_id,
+            username,
+            body,
+            spoiler: comment.spoiler,
+            score: comment.score,
+            deleted: comment.deleted,
+            created: comment.created,
+            edited: comment.edited,
+            replies: Vec::new(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct CommentPage {
+    pub comments: Vec<CommentResult>,
+    pub next_cursor: Option<String>,
+}
+
+/// Trims a comment body and checks that it isn't empty or too long.
+fn validate_body(body: &str) -> Result<String, (StatusCode, Json<ErrorResult>)> {
+    let body = body.trim();
+
+    if body.is_empty() {
+        return Err(
+            get_error_from_string(StatusCode::BAD_REQUEST, "Comment is empty".into()).to_tuple(),
+        );
+    }
+
+    if body.chars().count() > MAX_COMMENT_LENGTH {
+        return Err(get_error_from_string(
+            StatusCode::BAD_REQUEST,
+            format!("Comment is longer than {} characters", MAX_COMMENT_LENGTH),
+        )
+        .to_tuple());
+    }
+
+    Ok(body.to_string())
+}
+
+/// Attaches replies to the comment they answer. Deleted comments are only kept
+/// while they still have replies to show.
+fn build_thread(
+    mut comment: CommentResult,
+    children: &mut HashMap<i32, Vec<CommentResult>>,
+) -> Option<CommentResult> {
+    comment.replies = children
+        .remove(&comment.id)
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|reply| build_thread(reply, children))
+        .collect();
+
+    if comment.deleted && comment.replies.is_empty() {
+        None
+    } else {
+        Some(comment)
+    }
+}
+
+/// Lists the comment threads on a chapter. Threads are paged by their top level
+/// comment, and replies are returned oldest first under the comment they
+/// answer.
+///
+/// Cursors hold the id of the last thread on a page when sorting by newest,
+/// and its `(score, id)` when sorting by score.
+pub async fn list_comments(
+    Path(chapter_id): Path<i32>,
+    Query(query): Query<PageQuery>,
+) -> Result<Json<CommentPage>, (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::comments::dsl::{
+        chapter_id as db_chapter_id, comments as db_comments, id as db_id,
+        parent_id as db_parent_id, root_id as db_root_id, score as db_score,
+    };
+    use crate::schema::users::dsl::{id as db_user_id, username as db_username, users as db_users};
+
+    let sort = COMMENT_SORTS
+        .parse(query.sort.as_deref())
+        .map_err(|err| err.to_tuple())?;
+    let sort_name = COMMENT_SORTS.name(sort);
+
+    let after: Option<(i32, i32)> = match sort {
+        CommentSort::Newest => CURSOR_SIGNER
+            .decode_query::<i32>(sort_name, &query)
+            .map_err(|err| err.to_tuple())?
+            .map(|id| (id, id)),
+        CommentSort::Score => CURSOR_SIGNER
+            .decode_query(sort_name, &query)
+            .map_err(|err| err.to_tuple())?,
+    };
+
+    let limit = query.limit(DEFAULT_COMMENT_LIMIT, MAX_COMMENT_LIMIT);
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    find_published_chapter(chapter_id, connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?
+        .ok_or_else(|| {
+            get_error_from_string(
+                StatusCode::NOT_FOUND,
+                format!("No chapter {} exists", chapter_id),
+            )
+            .to_tuple()
+        })?;
+
+    let mut roots_query = db_comments
+        .inner_join(db_users.on(db_user_id.eq(crate::schema::comments::user_id)))
+        .filter(db_chapter_id.eq(chapter_id))
+        .filter(db_parent_id.is_null())
+        .select((Comment::as_select(), db_username))
+        .into_boxed();
+
+    roots_query = match sort {
+        CommentSort::Newest => match after {
+            Some((_, after_id)) => roots_query.filter(db_id.lt(after_id)),
+            None => roots_query,
+        }
+        .order(db_id.desc()),
+        CommentSort::Score => {
+            keyset_page!(roots_query, db_score, db_id, SortDirection::Desc, after)
+        }
+    };
+
+    let roots: Vec<(Comment, String)> = fetch_page(roots_query, limit)
+        .load(connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    // The cursor comes from the last thread fetched, even if it ends up being
+    // hidden, so pages don't overlap.
+    let Paged {
+        items: roots,
+        next_cursor,
+    } = Paged::from_rows(roots, limit, |(last, _)| match sort {
+        CommentSort::Newest => CURSOR_SIGNER.encode(sort_name, &last.id),
+        CommentSort::Score => CURSOR_SIGNER.encode(sort_name, &(last.score, last.id)),
+    });
+
+    let root_ids: Vec<i32> = roots.iter().map(|(root, _)| root.id).collect();
+
+    let replies: Vec<(Comment, String)> = db_comments
+        .inner_join(db_users.on(db_user_id.eq(crate::schema::comments::user_id)))
+        .filter(db_root_id.eq_any(&root_ids))
+        .order(db_id.asc())
+        .select((Comment::as_select(), db_username))
+        .load(connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let mut children: HashMap<i32, Vec<CommentResult>> = HashMap::new();
+
+    for (reply, username) in replies {
+        if let Some(parent_id) = reply.parent_id {
+            children
+                .entry(parent_id)
+                .or_default()
+                .push(CommentResult::new(reply, username));
+        }
+    }
+
+    let comments = roots
+        .into_iter()
+        .filter_map(|(root, username)| {
+            build_thread(CommentResult::new(root, username), &mut children)
+        })
+        .collect();
+
+    Ok(Json(CommentPage {
+        comments,
+        next_cursor,
+    }))
+}
+
+/// Posts a comment on a chapter, or a reply to another comment.
+pub async fn create_comment(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Path(chapter_id): Path<i32>,
+    Json(request): Json<CommentRequest>,
+) -> Result<(StatusCode, Json<CommentResult>), (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::comments::dsl::{
+        comments as db_comments, created as db_created, user_id as db_user_id,
+    };
+
+    let token = TOKEN_ISSUER
+        .validate_auth(auth.token())
+        .map_err(|err| get_error(err, StatusCode::UNAUTHORIZED).to_tuple())?;
+
+    let body = validate_body(&request.body)?;
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    find_published_chapter(chapter_id, connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?
+        .ok_or_else(|| {
+            get_error_from_string(
+                StatusCode::NOT_FOUND,
+                format!("No chapter {} exists", chapter_id),
+            )
+            .to_tuple()
+        })?;
+
+    let recent: i64 = db_comments
+        .filter(db_user_id.eq(token.user_id()))
+        .filter(db_created.gt(now - COMMENT_RATE_WINDOW_SECONDS.seconds()))
+        .count()
+        .get_result(connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    if recent >= MAX_COMMENTS_PER_WINDOW {
+        return Err(get_error_from_string(
+            StatusCode::TOO_MANY_REQUESTS,
+            format!(
+                "Only {} comments can be posted every {} seconds",
+                MAX_COMMENTS_PER_WINDOW, COMMENT_RATE_WINDOW_SECONDS
+            ),
+        )
+        .to_tuple());
+    }
+
+    let root_id = match request.parent_id {
+        Some(parent_id) => {
+            let parent: Comment = db_comments
+                .find(parent_id)
+                .select(Comment::as_select())
+                .first(connection)
+                .await
+                .optional()
+                .map_err(|err| get_internal_error(err).to_tuple())?
+                .filter(|parent| parent.chapter_id == chapter_id)
+                .ok_or_else(|| {
+                    get_error_from_string(
+                        StatusCode::NOT_FOUND,
+                        format!("Chapter {} has no comment {}", chapter_id, parent_id),
+                    )
+                    .to_tuple()
+                })?;
+
+            if parent.deleted {
+                return Err(get_error_from_string(
+                    StatusCode::BAD_REQUEST,
+                    format!("Comment {} has been deleted", parent_id),
+                )
+                .to_tuple());
+            }
+
+            Some(parent.root_id.unwrap_or(parent.id))
+        }
+        None => None,
+    };
+
+    let comment = insert_into(db_comments)
+        .values(NewComment {
+            chapter_id,
+            user_id: token.user_id(),
+            parent_id: request.parent_id,
+            root_id,
+            body,
+            spoiler: request.spoiler,
+        })
+        .returning(Comment::as_returning())
+        .get_result(connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(CommentResult::new(comment, token.username().to_string())),
+    ))
+}
+
+/// Edits the body or spoiler flag of one of the current user's comments.
+pub async fn update_comment(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Path(comment_id): Path<i32>,
+    Json(changes): Json<CommentChanges>,
+) -> Result<Json<CommentResult>, (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::comments::dsl::{
+        body as db_body, comments as db_comments, edited as db_edited, spoiler as db_spoiler,
+    };
+
+    let token = TOKEN_ISSUER
+        .validate_auth(auth.token())
+        .map_err(|err| get_error(err, StatusCode::UNAUTHORIZED).to_tuple())?;
+
+    let body = changes.body.as_deref().map(validate_body).transpose()?;
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let comment: Comment = db_comments
+        .find(comment_id)
+        .select(Comment::as_select())
+        .first(connection)
+        .await
+        .optional()
+        .map_err(|err| get_internal_error(err).to_tuple())?
+        .filter(|comment| !comment.deleted)
+        .ok_or_else(|| {
+            get_error_from_string(
+                StatusCode::NOT_FOUND,
+                format!("No comment {} exists", comment_id),
+            )
+            .to_tuple()
+        })?;
+
+    if comment.user_id != token.user_id() {
+        return Err(get_error_from_string(
+            StatusCode::FORBIDDEN,
+            format!("Comment {} belongs to another user", comment_id),
+        )
+        .to_tuple());
+    }
+
+    let comment = update(db_comments.find(comment_id))
+        .set((
+            db_body.eq(body.unwrap_or(comment.body)),
+            db_spoiler.eq(changes.spoiler.unwrap_or(comment.spoiler)),
+            db_edited.eq(now),
+        ))
+        .returning(Comment::as_returning())
+        .get_result(connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    Ok(Json(CommentResult::new(
+        comment,
+        token.username().to_string(),
+    )))
+}
+
+/// Deletes a comment. Authors can delete their own comments and moderators can
+/// delete anyone's. Deleted comments stay in their thread as a placeholder so
+/// their replies can still be read.
+pub async fn delete_comment(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Path(comment_id): Path<i32>,
+) -> Result<StatusCode, (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::comments::dsl::{
+        comments as db_comments, deleted as db_deleted, deleted_by as db_deleted_by,
+    };
+
+    let token = TOKEN_ISSUER
+        .validate_auth(auth.token())
+        .map_err(|err| get_error(err, StatusCode::UNAUTHORIZED).to_tuple())?;
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let comment: Comment = db_comments
+        .find(comment_id)
+        .select(Comment::as_select())
+        .first(connection)
+        .await
+        .optional()
+        .map_err(|err| get_internal_error(err).to_tuple())?
+        .filter(|comment| !comment.deleted)
+        .ok_or_else(|| {
+            get_error_from_string(
+                StatusCode::NOT_FOUND,
+                format!("No comment {} exists", comment_id),
+            )
+            .to_tuple()
+        })?;
+
+    if comment.user_id != token.user_id() {
+        require_user_level(&token, UserLevel::Mod).map_err(|err| err.to_tuple())?;
+    }
+
+    update(db_comments.find(comment_id))
+        .set((db_deleted.eq(true), db_deleted_by.eq(Some(token.user_id()))))
+        .execute(connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    Ok(StatusCode::OK)
+}
+
+/// Votes on a comment, replacing any earlier vote by the same user. The score
+/// of the comment is adjusted by the difference rather than recounted.
+pub async fn vote_comment(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Path(comment_id): Path<i32>,
+    Json(request): Json<VoteRequest>,
+) -> Result<Json<VoteResult>, (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::comment_votes::dsl::{
+        comment_id as db_vote_comment_id, comment_votes as db_votes, user_id as db_vote_user_id,
+        value as db_value,
+    };
+    use crate::schema::comments::dsl::{
+        comments as db_comments, deleted as db_deleted, score as db_score,
+    };
+
+    let token = TOKEN_ISSUER
+        .validate_auth(auth.token())
+        .map_err(|err| get_error(err, StatusCode::UNAUTHORIZED).to_tuple())?;
+
+    if !(-1..=1).contains(&request.value) {
+        return Err(get_error_from_string(
+            StatusCode::BAD_REQUEST,
+            "Votes must be -1, 0 or 1".into(),
+        )
+        .to_tuple());
+    }
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let user_id = token.user_id();
+    let value = request.value;
+
+    let score = connection
+        .transaction::<_, DbError, _>(|connection| {
+            async move {
+                // Locking the comment serializes votes on it, so the score
+                // can't drift from the votes.
+                let locked: Option<i32> = db_comments
+                    .find(comment_id)
+                    .filter(db_deleted.eq(false))
+                    .select(db_score)
+                    .for_update()
+                    .first(connection)
+                    .await
+                    .optional()?;
+
+                if locked.is_none() {
+                    return Ok(None);
+                }
+
+                let previous: i16 = db_votes
+                    .filter(db_vote_comment_id.eq(comment_id))
+                    .filter(db_vote_user_id.eq(user_id))
+                    .select(db_value)
+                    .first(connection)
+                    .await
+                    .optional()?
+                    .unwrap_or(0);
+
+                if value == 0 {
+                    delete(db_votes)
+                        .filter(db_vote_comment_id.eq(comment_id))
+                        .filter(db_vote_user_id.eq(user_id))
+                        .execute(connection)
+                        .await?;
+                } else {
+                    insert_into(db_votes)
+                        .values(NewCommentVote {
+                            comment_id,
+                            user_id,
+                            value,
+                        })
+                        .on_conflict((db_vote_comment_id, db_vote_user_id))
+                        .do_update()
+                        .set(db_value.eq(excluded(db_value)))
+                        .execute(connection)
+                        .await?;
+                }
+
+                let score = update(db_comments.find(comment_id))
+                    .set(db_score.eq(db_score + i32::from(value - previous)))
+                    .returning(db_score)
+                    .get_result(connection)
+                    .await?;
+
+                Ok(Some(score))
+            }
+            .scope_boxed()
+        })
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?
+        .ok_or_else(|| {
+            get_error_from_string(
+                StatusCode::NOT_FOUND,
+                format!("No comment {} exists", comment_id),
+            )
+            .to_tuple()
+        })?;
+
+    Ok(Json(VoteResult {
+        comment_id,
+        score,
+        value,
+    }))
+}
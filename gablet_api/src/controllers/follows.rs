@@ -0,0 +1,245 @@
+use axum::{
+    extract::{Path, Query},
+    headers::{authorization::Bearer, Authorization},
+    http::StatusCode,
+    Json, TypedHeader,
+};
+use chrono::NaiveDateTime;
+use diesel::{
+    delete, insert_into,
+    pg::upsert::excluded,
+    prelude::*,
+    sql_query,
+    sql_types::{Int4, Int8, Nullable, Timestamp, Varchar},
+};
+use diesel_async::RunQueryDsl;
+use gablet_shared_api::{
+    errors::{get_error, get_error_from_string, get_internal_error, ErrorResult},
+    pagination::{PageQuery, Paged},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    models::follows::{BookFollow, NewBookFollow},
+    utils::{books::find_book, lang::validate_lang},
+    CURSOR_SIGNER, PG_POOL, TOKEN_ISSUER,
+};
+
+const DEFAULT_FEED_LIMIT: i64 = 25;
+const MAX_FEED_LIMIT: i64 = 100;
+
+/// The only order the feed comes in. Cursors hold the `(published,
+/// chapter_id)` of the last item on a page.
+const FEED_SORT: &str = "newest";
+
+/// Chapters of followed books in the original language, along with
+/// translations into the language each book is followed in, newest first.
+const FOLLOWED_FEED_SQL: &str = "
+SELECT * FROM (
+    SELECT chapters.id AS chapter_id, books.id AS book_id, books.name AS book_name,
+        chapters.number, chapters.title, NULL::VARCHAR AS lang, chapters.published AS published
+    FROM book_follows
+    INNER JOIN books ON books.id = book_follows.book_id
+    INNER JOIN chapters ON chapters.book_id = books.id
+    WHERE book_follows.user_id = $1
+        AND books.approved
+        AND chapters.approved
+        AND chapters.published IS NOT NULL
+        AND (book_follows.lang IS NULL OR book_follows.lang = books.lang)
+    UNION ALL
+    SELECT chapters.id, books.id, books.name, chapters.number, chapters.title,
+        chapter_translations.lang, chapter_translations.published
+    FROM book_follows
+    INNER JOIN books ON books.id = book_follows.book_id
+    INNER JOIN chapters ON chapters.book_id = books.id
+    INNER JOIN chapter_translations ON chapter_translations.chapter_id = chapters.id
+        AND chapter_translations.lang = book_follows.lang
+    WHERE book_follows.user_id = $1
+        AND books.approved
+        AND chapters.approved
+        AND chapters.published IS NOT NULL
+) feed
+WHERE $2::TIMESTAMP IS NULL OR (feed.published, feed.chapter_id) < ($2, $3)
+ORDER BY feed.published DESC, feed.chapter_id DESC
+LIMIT $4";
+
+#[derive(Deserialize)]
+pub struct FollowRequest {
+    /// The language to follow translations in. Leave empty to follow the
+    /// book in its original language.
+    pub lang: Option<String>,
+}
+
+#[derive(QueryableByName, Serialize)]
+pub struct FeedItem {
+    #[diesel(sql_type = Int4)]
+    pub chapter_id: i32,
+    #[diesel(sql_type = Int4)]
+    pub book_id: i32,
+    #[diesel(sql_type = Varchar)]
+    pub book_name: String,
+    #[diesel(sql_type = Int4)]
+    pub number: i32,
+    #[diesel(sql_type = Nullable<Varchar>)]
+    pub title: Option<String>,
+    #[diesel(sql_type = Nullable<Varchar>)]
+    pub lang: Option<String>,
+    #[diesel(sql_type = Timestamp)]
+    pub published: NaiveDateTime,
+}
+
+/// Follows a book, or changes the language it's followed in.
+pub async fn follow_book(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Path(book_id): Path<i32>,
+    Json(request): Json<FollowRequest>,
+) -> Result<Json<BookFollow>, (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::book_follows::dsl::{
+        book_follows as db_follows, book_id as db_book_id, lang as db_lang, user_id as db_user_id,
+    };
+
+    let token = TOKEN_ISSUER
+        .validate_auth(auth.token())
+        .map_err(|err| get_error(err, StatusCode::UNAUTHORIZED).to_tuple())?;
+
+    if let Some(lang) = &request.lang {
+        validate_lang(lang).map_err(|err| err.to_tuple())?;
+    }
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    find_book(book_id, connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?
+        .filter(|book| book.approved)
+        .ok_or_else(|| {
+            get_error_from_string(StatusCode::NOT_FOUND, format!("No book {} exists", book_id))
+                .to_tuple()
+        })?;
+
+    let follow = insert_into(db_follows)
+        .values(NewBookFollow {
+            user_id: token.user_id(),
+            book_id,
+            lang: request.lang,
+        })
+        .on_conflict((db_user_id, db_book_id))
+        .do_update()
+        .set(db_lang.eq(excluded(db_lang)))
+        .returning(BookFollow::as_returning())
+        .get_result(connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    Ok(Json(follow))
+}
+
+pub async fn unfollow_book(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Path(book_id): Path<i32>,
+) -> Result<StatusCode, (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::book_follows::dsl::{
+        book_follows as db_follows, book_id as db_book_id, user_id as db_user_id,
+    };
+
+    let token = TOKEN_ISSUER
+        .validate_auth(auth.token())
+        .map_err(|err| get_error(err, StatusCode::UNAUTHORIZED).to_tuple())?;
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let deleted = delete(db_follows)
+        .filter(db_user_id.eq(token.user_id()))
+        .filter(db_book_id.eq(book_id))
+        .execute(connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    if deleted == 0 {
+        return Err(get_error_from_string(
+            StatusCode::NOT_FOUND,
+            format!("Book {} is not followed", book_id),
+        )
+        .to_tuple());
+    }
+
+    Ok(StatusCode::OK)
+}
+
+/// Lists the books the current user follows, most recently followed first.
+pub async fn list_follows(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+) -> Result<Json<Vec<BookFollow>>, (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::book_follows::dsl::{
+        book_follows as db_follows, created as db_created, user_id as db_user_id,
+    };
+
+    let token = TOKEN_ISSUER
+        .validate_auth(auth.token())
+        .map_err(|err| get_error(err, StatusCode::UNAUTHORIZED).to_tuple())?;
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let follows = db_follows
+        .filter(db_user_id.eq(token.user_id()))
+        .order(db_created.desc())
+        .select(BookFollow::as_select())
+        .load(connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    Ok(Json(follows))
+}
+
+/// Lists the latest chapters and translations of the books the current user
+/// follows, newest first.
+pub async fn get_followed_feed(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Query(query): Query<PageQuery>,
+) -> Result<Json<Paged<FeedItem>>, (StatusCode, Json<ErrorResult>)> {
+    let token = TOKEN_ISSUER
+        .validate_auth(auth.token())
+        .map_err(|err| get_error(err, StatusCode::UNAUTHORIZED).to_tuple())?;
+
+    let after: Option<(NaiveDateTime, i32)> = CURSOR_SIGNER
+        .decode_query(FEED_SORT, &query)
+        .map_err(|err| err.to_tuple())?;
+
+    let limit = query.limit(DEFAULT_FEED_LIMIT, MAX_FEED_LIMIT);
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    // Fetch one extra row to find out whether there is another page.
+    let items: Vec<FeedItem> = sql_query(FOLLOWED_FEED_SQL)
+        .bind::<Int4, _>(token.user_id())
+        .bind::<Nullable<Timestamp>, _>(after.map(|(published, _)| published))
+        .bind::<Nullable<Int4>, _>(after.map(|(_, chapter_id)| chapter_id))
+        .bind::<Int8, _>(limit + 1)
+        .load(connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    Ok(Json(Paged::from_rows(items, limit, |last| {
+        CURSOR_SIGNER.encode(FEED_SORT, &(last.published, last.chapter_id))
+    })))
+}
@@ -0,0 +1,547 @@
+use std::collections::{hash_map::Entry, HashMap};
+
+use axum::{
+    extract::{Path, Query},
+    headers::{authorization::Bearer, Authorization},
+    http::{header, StatusCode},
+    Json, TypedHeader,
+};
+use diesel::{delete, dsl::now, insert_into, pg::upsert::excluded, prelude::*, update};
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use gablet_shared_api::errors::{
+    get_error, get_error_from_string, get_internal_error, ErrorResult,
+};
+use gablet_tokens::AuthToken;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    models::{
+        books::Book,
+        glossary::{GlossaryTerm, NewGlossaryTerm, PartOfSpeech},
+    },
+    utils::{
+        books::{can_view_unapproved, find_book},
+        errors::map_insert_error,
+        glossary::{
+            can_edit_glossary, glossary_key, load_glossary, parse_delimited, write_delimited,
+            DelimitedRow, MAX_TERM_LENGTH,
+        },
+        lang::validate_lang,
+    },
+    PG_POOL, TOKEN_ISSUER,
+};
+
+const MAX_IMPORT_TERMS: usize = 5000;
+const GLOSSARY_COLUMNS: [&str; 4] = ["source_term", "translation", "notes", "part_of_speech"];
+
+#[derive(Deserialize, Default, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum GlossaryFormat {
+    #[default]
+    Csv,
+    Tsv,
+}
+
+impl GlossaryFormat {
+    fn delimiter(self) -> char {
+        match self {
+            GlossaryFormat::Csv => ',',
+            GlossaryFormat::Tsv => '\t',
+        }
+    }
+
+    fn content_type(self) -> &'static str {
+        match self {
+            GlossaryFormat::Csv => "text/csv; charset=utf-8",
+            GlossaryFormat::Tsv => "text/tab-separated-values; charset=utf-8",
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            GlossaryFormat::Csv => "csv",
+            GlossaryFormat::Tsv => "tsv",
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct FormatQuery {
+    pub format: Option<GlossaryFormat>,
+}
+
+#[derive(Deserialize)]
+pub struct GlossaryRequest {
+    pub source_term: String,
+    pub translation: String,
+    pub notes: Option<String>,
+    pub part_of_speech: Option<PartOfSpeech>,
+}
+
+#[derive(Serialize)]
+pub struct ImportResult {
+    pub imported: usize,
+}
+
+/// Checks a glossary entry and turns it into a term for the given book and
+/// language.
+fn validate_term(
+    request: GlossaryRequest,
+    book_id: i32,
+    lang: &str,
+    user_id: i32,
+) -> Result<NewGlossaryTerm, String> {
+    let source_term = request
+        .source_term
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    let translation = request.translation.trim().to_string();
+
+    if source_term.is_empty() || translation.is_empty() {
+        return Err("Glossary terms need a source term and a translation".into());
+    }
+
+    if source_term.chars().count() > MAX_TERM_LENGTH
+        || translation.chars().count() > MAX_TERM_LENGTH
+    {
+        return Err(format!(
+            "Glossary terms and translations can't be longer than {} characters",
+            MAX_TERM_LENGTH
+        ));
+    }
+
+    Ok(NewGlossaryTerm {
+        book_id,
+        lang: lang.to_string(),
+        source_key: glossary_key(&source_term),
+        source_term,
+        translation,
+        notes: request
+            .notes
+            .map(|notes| notes.trim().to_string())
+            .filter(|notes| !notes.is_empty()),
+        part_of_speech: request.part_of_speech,
+        updated_by: user_id,
+    })
+}
+
+/// Finds a book whose glossary the owner of the token can see.
+async fn find_glossary_book(
+    book_id: i32,
+    token: Option<&AuthToken>,
+    connection: &mut AsyncPgConnection,
+) -> Result<Book, (StatusCode, Json<ErrorResult>)> {
+    find_book(book_id, connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?
+        .filter(|book| book.approved || can_view_unapproved(book, token))
+        .ok_or_else(|| {
+            get_error_from_string(StatusCode::NOT_FOUND, format!("No book {} exists", book_id))
+                .to_tuple()
+        })
+}
+
+/// Returns an error unless the owner of the token can change the glossary of
+/// the book for the language.
+async fn check_glossary_editor(
+    book: &Book,
+    lang: &str,
+    token: &AuthToken,
+    connection: &mut AsyncPgConnection,
+) -> Result<(), (StatusCode, Json<ErrorResult>)> {
+    let allowed = can_edit_glossary(book.author_id, book.id, lang, token.user_id(), connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    if !allowed {
+        return Err(get_error_from_string(
+            StatusCode::FORBIDDEN,
+            format!(
+                "Only the author of {} or its {} translators can change its glossary",
+                book.name, lang
+            ),
+        )
+        .to_tuple());
+    }
+
+    Ok(())
+}
+
+/// Finds a glossary term and the book it belongs to.
+async fn find_term(
+    term_id: i32,
+    connection: &mut AsyncPgConnection,
+) -> Result<(GlossaryTerm, Book), (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::glossary_terms::dsl::glossary_terms as db_glossary_terms;
+
+    let not_found = || {
+        get_error_from_string(
+            StatusCode::NOT_FOUND,
+            format!("No glossary term {} exists", term_id),
+        )
+        .to_tuple()
+    };
+
+    let term: GlossaryTerm = db_glossary_terms
+        .find(term_id)
+        .select(GlossaryTerm::as_select())
+        .first(connection)
+        .await
+        .optional()
+        .map_err(|err| get_internal_error(err).to_tuple())?
+        .ok_or_else(not_found)?;
+
+    let book = find_book(term.book_id, connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?
+        .ok_or_else(not_found)?;
+
+    Ok((term, book))
+}
+
+/// Lists the glossary of a book for a language, sorted by source term.
+pub async fn list_glossary(
+    bearer: Option<TypedHeader<Authorization<Bearer>>>,
+    Path((book_id, lang)): Path<(i32, String)>,
+) -> Result<Json<Vec<GlossaryTerm>>, (StatusCode, Json<ErrorResult>)> {
+    let token = bearer.and_then(|TypedHeader(auth)| TOKEN_ISSUER.validate_auth(auth.token()).ok());
+
+    validate_lang(&lang).map_err(|err| err.to_tuple())?;
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    find_glossary_book(book_id, token.as_ref(), connection).await?;
+
+    let terms = load_glossary(book_id, Some(&lang), connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    Ok(Json(terms))
+}
+
+pub async fn create_glossary_term(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Path((book_id, lang)): Path<(i32, String)>,
+    Json(request): Json<GlossaryRequest>,
+) -> Result<(StatusCode, Json<GlossaryTerm>), (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::glossary_terms::dsl::glossary_terms as db_glossary_terms;
+
+    let token = TOKEN_ISSUER
+        .validate_auth(auth.token())
+        .map_err(|err| get_error(err, StatusCode::UNAUTHORIZED).to_tuple())?;
+
+    validate_lang(&lang).map_err(|err| err.to_tuple())?;
+
+    let term = validate_term(request, book_id, &lang, token.user_id())
+        .map_err(|err| get_error_from_string(StatusCode::BAD_REQUEST, err).to_tuple())?;
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let book = find_glossary_book(book_id, Some(&token), connection).await?;
+    check_glossary_editor(&book, &lang, &token, connection).await?;
+
+    let source_term = term.source_term.clone();
+
+    let term = insert_into(db_glossary_terms)
+        .values(term)
+        .returning(GlossaryTerm::as_returning())
+        .get_result(connection)
+        .await
+        .map_err(|err| {
+            map_insert_error(
+                err,
+                format!("{} is already in the {} glossary", source_term, lang),
+            )
+        })?;
+
+    Ok((StatusCode::CREATED, Json(term)))
+}
+
+pub async fn update_glossary_term(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Path(term_id): Path<i32>,
+    Json(request): Json<GlossaryRequest>,
+) -> Result<Json<GlossaryTerm>, (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::glossary_terms::dsl::{
+        glossary_terms as db_glossary_terms, notes as db_notes,
+        part_of_speech as db_part_of_speech, source_key as db_source_key,
+        source_term as db_source_term, translation as db_translation, updated as db_updated,
+        updated_by as db_updated_by,
+    };
+
+    let token = TOKEN_ISSUER
+        .validate_auth(auth.token())
+        .map_err(|err| get_error(err, StatusCode::UNAUTHORIZED).to_tuple())?;
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let (term, book) = find_term(term_id, connection).await?;
+    check_glossary_editor(&book, &term.lang, &token, connection).await?;
+
+    let changes = validate_term(request, book.id, &term.lang, token.user_id())
+        .map_err(|err| get_error_from_string(StatusCode::BAD_REQUEST, err).to_tuple())?;
+
+    let term = update(db_glossary_terms.find(term_id))
+        .set((
+            db_source_term.eq(&changes.source_term),
+            db_source_key.eq(&changes.source_key),
+            db_translation.eq(&changes.translation),
+            db_notes.eq(&changes.notes),
+            db_part_of_speech.eq(changes.part_of_speech),
+            db_updated_by.eq(changes.updated_by),
+            db_updated.eq(now),
+        ))
+        .returning(GlossaryTerm::as_returning())
+        .get_result(connection)
+        .await
+        .map_err(|err| {
+            map_insert_error(
+                err,
+                format!(
+                    "{} is already in the {} glossary",
+                    changes.source_term, changes.lang
+                ),
+            )
+        })?;
+
+    Ok(Json(term))
+}
+
+pub async fn delete_glossary_term(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Path(term_id): Path<i32>,
+) -> Result<StatusCode, (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::glossary_terms::dsl::glossary_terms as db_glossary_terms;
+
+    let token = TOKEN_ISSUER
+        .validate_auth(auth.token())
+        .map_err(|err| get_error(err, StatusCode::UNAUTHORIZED).to_tuple())?;
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let (term, book) = find_term(term_id, connection).await?;
+    check_glossary_editor(&book, &term.lang, &token, connection).await?;
+
+    delete(db_glossary_terms.find(term_id))
+        .execute(connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    Ok(StatusCode::OK)
+}
+
+/// Imports glossary terms from CSV or TSV. The columns are the source term,
+/// translation, notes and part of speech, and a header row naming them is
+/// optional. Terms already in the glossary are replaced. Nothing is imported
+/// if any row is invalid.
+pub async fn import_glossary(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Path((book_id, lang)): Path<(i32, String)>,
+    Query(query): Query<FormatQuery>,
+    body: String,
+) -> Result<Json<ImportResult>, (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::glossary_terms::dsl::{
+        book_id as db_book_id, glossary_terms as db_glossary_terms, lang as db_lang,
+        notes as db_notes, part_of_speech as db_part_of_speech, source_key as db_source_key,
+        source_term as db_source_term, translation as db_translation, updated as db_updated,
+        updated_by as db_updated_by,
+    };
+
+    let token = TOKEN_ISSUER
+        .validate_auth(auth.token())
+        .map_err(|err| get_error(err, StatusCode::UNAUTHORIZED).to_tuple())?;
+
+    validate_lang(&lang).map_err(|err| err.to_tuple())?;
+
+    let bad_request = |message: String| get_error_from_string(StatusCode::BAD_REQUEST, message);
+
+    // One more row than there can be terms leaves room for the header.
+    let rows = parse_delimited(
+        &body,
+        query.format.unwrap_or_default().delimiter(),
+        MAX_IMPORT_TERMS + 1,
+    )
+    .map_err(|err| bad_request(err).to_tuple())?;
+
+    let mut terms: Vec<NewGlossaryTerm> = Vec::new();
+    let mut term_indexes: HashMap<String, usize> = HashMap::new();
+
+    for (index, DelimitedRow { line, fields: row }) in rows.into_iter().enumerate() {
+        if row.iter().all(|field| field.trim().is_empty()) {
+            continue;
+        }
+
+        if index == 0 && row[0].trim().eq_ignore_ascii_case(GLOSSARY_COLUMNS[0]) {
+            continue;
+        }
+
+        if row.len() < 2 || row.len() > GLOSSARY_COLUMNS.len() {
+            return Err(bad_request(format!(
+                "Line {} should have the columns {}",
+                line,
+                GLOSSARY_COLUMNS.join(", ")
+            ))
+            .to_tuple());
+        }
+
+        let mut fields = row.into_iter();
+        let source_term = fields.next().unwrap_or_default();
+        let translation = fields.next().unwrap_or_default();
+        let notes = fields.next();
+        let part_of_speech = match fields.next().map(|field| field.trim().to_lowercase()) {
+            Some(field) if !field.is_empty() => Some(field.parse().map_err(|_| {
+                bad_request(format!(
+                    "Line {} has an unknown part of speech {}",
+                    line, field
+                ))
+                .to_tuple()
+            })?),
+            _ => None,
+        };
+
+        let term = validate_term(
+            GlossaryRequest {
+                source_term,
+                translation,
+                notes,
+                part_of_speech,
+            },
+            book_id,
+            &lang,
+            token.user_id(),
+        )
+        .map_err(|err| bad_request(format!("Line {}: {}", line, err)).to_tuple())?;
+
+        // A later row for the same term wins, since one statement can't
+        // update the same row twice.
+        match term_indexes.entry(term.source_key.clone()) {
+            Entry::Occupied(entry) => terms[*entry.get()] = term,
+            Entry::Vacant(entry) => {
+                entry.insert(terms.len());
+                terms.push(term);
+            }
+        }
+    }
+
+    if terms.len() > MAX_IMPORT_TERMS {
+        return Err(bad_request(format!(
+            "At most {} terms can be imported at once",
+            MAX_IMPORT_TERMS
+        ))
+        .to_tuple());
+    }
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let book = find_glossary_book(book_id, Some(&token), connection).await?;
+    check_glossary_editor(&book, &lang, &token, connection).await?;
+
+    let imported = terms.len();
+
+    if !terms.is_empty() {
+        insert_into(db_glossary_terms)
+            .values(terms)
+            .on_conflict((db_book_id, db_lang, db_source_key))
+            .do_update()
+            .set((
+                db_source_term.eq(excluded(db_source_term)),
+                db_translation.eq(excluded(db_translation)),
+                db_notes.eq(excluded(db_notes)),
+                db_part_of_speech.eq(excluded(db_part_of_speech)),
+                db_updated_by.eq(excluded(db_updated_by)),
+                db_updated.eq(now),
+            ))
+            .execute(connection)
+            .await
+            .map_err(|err| get_internal_error(err).to_tuple())?;
+    }
+
+    Ok(Json(ImportResult { imported }))
+}
+
+/// Exports the glossary of a book for a language as CSV or TSV, in the same
+/// format `import_glossary` reads.
+pub async fn export_glossary(
+    bearer: Option<TypedHeader<Authorization<Bearer>>>,
+    Path((book_id, lang)): Path<(i32, String)>,
+    Query(query): Query<FormatQuery>,
+) -> Result<([(header::HeaderName, String); 2], String), (StatusCode, Json<ErrorResult>)> {
+    let token = bearer.and_then(|TypedHeader(auth)| TOKEN_ISSUER.validate_auth(auth.token()).ok());
+
+    validate_lang(&lang).map_err(|err| err.to_tuple())?;
+
+    let format = query.format.unwrap_or_default();
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    find_glossary_book(book_id, token.as_ref(), connection).await?;
+
+    let terms = load_glossary(book_id, Some(&lang), connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let rows: Vec<Vec<String>> = std::iter::once(
+        GLOSSARY_COLUMNS
+            .iter()
+            .map(|column| column.to_string())
+            .collect(),
+    )
+    .chain(terms.into_iter().map(|term| {
+        vec![
+            term.source_term,
+            term.translation,
+            term.notes.unwrap_or_default(),
+            term.part_of_speech
+                .map(|part| part.to_string())
+                .unwrap_or_default(),
+        ]
+    }))
+    .collect();
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, format.content_type().to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!(
+                    "attachment; filename=\"glossary-{}-{}.{}\"",
+                    book_id,
+                    lang,
+                    format.extension()
+                ),
+            ),
+        ],
+        write_delimited(&rows, format.delimiter()),
+    ))
+}
@@ -0,0 +1,438 @@
+use std::collections::HashSet;
+
+use axum::{
+    extract::{Path, Query},
+    headers::{authorization::Bearer, Authorization},
+    http::StatusCode,
+    Json, TypedHeader,
+};
+use chrono::NaiveDateTime;
+use diesel::{
+    delete,
+    dsl::{exists, sql},
+    insert_into,
+    prelude::*,
+    select,
+    sql_types::BigInt,
+};
+use diesel_async::{scoped_futures::ScopedFutureExt, AsyncConnection, RunQueryDsl};
+use gablet_shared_api::{
+    errors::{get_error, get_error_from_string, get_internal_error, ErrorResult},
+    pagination::{fetch_page, PageQuery, Paged},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    models::{
+        ledger::{
+            EarningKind, LedgerEntry, LedgerTransaction, NewLedgerTransaction, NewShareAgreement,
+            ShareAgreement,
+        },
+        users::UserLevel,
+    },
+    utils::{
+        auth::{get_user_level, require_user_level},
+        books::find_book,
+        lang::validate_lang,
+        ledger::{find_contributor_account, get_share_agreements, record_earning, LedgerError},
+        revenue_split::TOTAL_BPS,
+        tracking::send_tracked_book,
+    },
+    CURSOR_SIGNER, PG_POOL, TOKEN_ISSUER,
+};
+
+const DEFAULT_HISTORY_LIMIT: i64 = 25;
+const MAX_HISTORY_LIMIT: i64 = 100;
+
+#[derive(Deserialize)]
+pub struct EarningRequest {
+    pub kind: EarningKind,
+    pub book_id: i32,
+    pub lang: Option<String>,
+    pub amount_cents: i64,
+    pub description: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct EarningResult {
+    pub transaction: LedgerTransaction,
+    pub entries: Vec<LedgerEntry>,
+}
+
+#[derive(Serialize)]
+pub struct BalanceResult {
+    pub user_id: i32,
+    pub balance_cents: i64,
+}
+
+#[derive(Serialize, Queryable)]
+pub struct HistoryEntryResult {
+    pub entry_id: i32,
+    pub amount_cents: i64,
+    pub transaction_id: i32,
+    pub kind: EarningKind,
+    pub book_id: i32,
+    pub lang: Option<String>,
+    pub description: Option<String>,
+    pub created: NaiveDateTime,
+}
+
+#[derive(Deserialize)]
+pub struct ShareQuery {
+    pub lang: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ShareRequest {
+    pub user_id: i32,
+    pub share_bps: i32,
+}
+
+#[derive(Deserialize)]
+pub struct SetSharesRequest {
+    pub lang: Option<String>,
+    pub shares: Vec<ShareRequest>,
+}
+
+pub async fn create_earning(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Json(request): Json<EarningRequest>,
+) -> Result<(StatusCode, Json<EarningResult>), (StatusCode, Json<ErrorResult>)> {
+    let token = TOKEN_ISSUER
+        .validate_auth(auth.token())
+        .map_err(|err| get_error(err, StatusCode::UNAUTHORIZED).to_tuple())?;
+
+    require_user_level(&token, UserLevel::Admin).map_err(|err| err.to_tuple())?;
+
+    if request.amount_cents <= 0 {
+        return Err(get_error_from_string(
+            StatusCode::BAD_REQUEST,
+            "Earnings must be a positive number of cents".into(),
+        )
+        .to_tuple());
+    }
+
+    if let Some(lang) = &request.lang {
+        validate_lang(lang).map_err(|err| err.to_tuple())?;
+    }
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    if find_book(request.book_id, connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?
+        .is_none()
+    {
+        return Err(get_error_from_string(
+            StatusCode::NOT_FOUND,
+            format!("No book {} exists", request.book_id),
+        )
+        .to_tuple());
+    }
+
+    let earning = NewLedgerTransaction {
+        kind: request.kind,
+        book_id: request.book_id,
+        lang: request.lang,
+        amount_cents: request.amount_cents,
+        description: request.description,
+    };
+
+    let (transaction, entries) =
+        record_earning(earning, connection)
+            .await
+            .map_err(|err| match err {
+                LedgerError::Split(err) => get_error(err, StatusCode::CONFLICT).to_tuple(),
+                err => get_internal_error(err).to_tuple(),
+            })?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(EarningResult {
+            transaction,
+            entries,
+        }),
+    ))
+}
+
+pub async fn get_balance(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+) -> Result<Json<BalanceResult>, (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::ledger_entries::dsl::{
+        account_id as db_account_id, ledger_entries as db_entries,
+    };
+
+    let token = TOKEN_ISSUER
+        .validate_auth(auth.token())
+        .map_err(|err| get_error(err, StatusCode::UNAUTHORIZED).to_tuple())?;
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    // Users who have never earned anything don't have an account yet.
+    let Some(account_id) = find_contributor_account(token.user_id(), connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?
+    else {
+        return Ok(Json(BalanceResult {
+            user_id: token.user_id(),
+            balance_cents: 0,
+        }));
+    };
+
+    let balance_cents = db_entries
+        .filter(db_account_id.eq(account_id))
+        .select(sql::<BigInt>("COALESCE(SUM(amount_cents), 0)::BIGINT"))
+        .get_result(connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    Ok(Json(BalanceResult {
+        user_id: token.user_id(),
+        balance_cents,
+    }))
+}
+
+/// The only order the history comes in. Cursors hold the id of the last
+/// entry on a page.
+const HISTORY_SORT: &str = "newest";
+
+/// Lists the entries in the current user's account, newest first.
+pub async fn get_history(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Query(query): Query<PageQuery>,
+) -> Result<Json<Paged<HistoryEntryResult>>, (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::{ledger_entries, ledger_transactions};
+
+    let token = TOKEN_ISSUER
+        .validate_auth(auth.token())
+        .map_err(|err| get_error(err, StatusCode::UNAUTHORIZED).to_tuple())?;
+
+    let before: Option<i32> = CURSOR_SIGNER
+        .decode_query(HISTORY_SORT, &query)
+        .map_err(|err| err.to_tuple())?;
+
+    let limit = query.limit(DEFAULT_HISTORY_LIMIT, MAX_HISTORY_LIMIT);
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let Some(account_id) = find_contributor_account(token.user_id(), connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?
+    else {
+        return Ok(Json(Paged {
+            items: Vec::new(),
+            next_cursor: None,
+        }));
+    };
+
+    let mut history_query = ledger_entries::table
+        .inner_join(ledger_transactions::table)
+        .filter(ledger_entries::account_id.eq(account_id))
+        .into_boxed();
+
+    if let Some(before) = before {
+        history_query = history_query.filter(ledger_entries::id.lt(before));
+    }
+
+    let history_query = history_query.order(ledger_entries::id.desc()).select((
+        ledger_entries::id,
+        ledger_entries::amount_cents,
+        ledger_transactions::id,
+        ledger_transactions::kind,
+        ledger_transactions::book_id,
+        ledger_transactions::lang,
+        ledger_transactions::description,
+        ledger_transactions::created,
+    ));
+
+    let history: Vec<HistoryEntryResult> = fetch_page(history_query, limit)
+        .load(connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    Ok(Json(Paged::from_rows(history, limit, |last| {
+        CURSOR_SIGNER.encode(HISTORY_SORT, &last.entry_id)
+    })))
+}
+
+/// Lists the share agreements for a book or one of its translations. Only
+/// admins, the book's author and the users with a share of the book can see
+/// them.
+pub async fn get_book_shares(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Path(book_id): Path<i32>,
+    Query(query): Query<ShareQuery>,
+) -> Result<Json<Vec<ShareAgreement>>, (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::share_agreements::dsl::{
+        book_id as db_book_id, share_agreements as db_agreements, user_id as db_user_id,
+    };
+
+    let token = TOKEN_ISSUER
+        .validate_auth(auth.token())
+        .map_err(|err| get_error(err, StatusCode::UNAUTHORIZED).to_tuple())?;
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let book = find_book(book_id, connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?
+        .ok_or_else(|| {
+            get_error_from_string(StatusCode::NOT_FOUND, format!("No book {} exists", book_id))
+                .to_tuple()
+        })?;
+
+    let admin = get_user_level(&token)
+        .map(|level| level >= UserLevel::Admin)
+        .unwrap_or(false);
+
+    let allowed = admin
+        || book.author_id == token.user_id()
+        || select(exists(
+            db_agreements
+                .filter(db_book_id.eq(book_id))
+                .filter(db_user_id.eq(token.user_id())),
+        ))
+        .get_result(connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    if !allowed {
+        return Err(get_error_from_string(
+            StatusCode::FORBIDDEN,
+            format!(
+                "Only admins and the people paid for {} can see its shares",
+                book.name
+            ),
+        )
+        .to_tuple());
+    }
+
+    let agreements = get_share_agreements(book_id, query.lang.as_deref(), connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    Ok(Json(agreements))
+}
+
+/// Replaces the share agreements for a book or one of its translations.
+pub async fn set_book_shares(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Path(book_id): Path<i32>,
+    Json(request): Json<SetSharesRequest>,
+) -> Result<Json<Vec<ShareAgreement>>, (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::share_agreements::dsl::{
+        book_id as db_book_id, lang as db_lang, share_agreements as db_agreements,
+    };
+
+    let token = TOKEN_ISSUER
+        .validate_auth(auth.token())
+        .map_err(|err| get_error(err, StatusCode::UNAUTHORIZED).to_tuple())?;
+
+    require_user_level(&token, UserLevel::Admin).map_err(|err| err.to_tuple())?;
+
+    if request.shares.iter().any(|share| share.share_bps <= 0) {
+        return Err(get_error_from_string(
+            StatusCode::BAD_REQUEST,
+            "Shares must be a positive number of basis points".into(),
+        )
+        .to_tuple());
+    }
+
+    let mut seen = HashSet::new();
+    if let Some(share) = request
+        .shares
+        .iter()
+        .find(|share| !seen.insert(share.user_id))
+    {
+        return Err(get_error_from_string(
+            StatusCode::BAD_REQUEST,
+            format!("User {} is given more than one share", share.user_id),
+        )
+        .to_tuple());
+    }
+
+    let allocated: i64 = request
+        .shares
+        .iter()
+        .map(|share| share.share_bps as i64)
+        .sum();
+    if allocated > TOTAL_BPS as i64 {
+        return Err(get_error_from_string(
+            StatusCode::BAD_REQUEST,
+            format!(
+                "Shares add up to {} basis points, which is more than {}",
+                allocated, TOTAL_BPS
+            ),
+        )
+        .to_tuple());
+    }
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let agreements: Vec<NewShareAgreement> = request
+        .shares
+        .iter()
+        .map(|share| NewShareAgreement {
+            book_id,
+            lang: request.lang.clone(),
+            user_id: share.user_id,
+            share_bps: share.share_bps,
+        })
+        .collect();
+
+    let lang = request.lang;
+
+    let agreements = connection
+        .transaction::<_, diesel::result::Error, _>(|connection| {
+            async move {
+                let existing = delete(db_agreements)
+                    .filter(db_book_id.eq(book_id))
+                    .into_boxed();
+
+                let existing = match lang {
+                    Some(lang) => existing.filter(db_lang.eq(lang)),
+                    None => existing.filter(db_lang.is_null()),
+                };
+
+                existing.execute(connection).await?;
+
+                insert_into(db_agreements)
+                    .values(agreements)
+                    .returning(ShareAgreement::as_returning())
+                    .get_results(connection)
+                    .await
+            }
+            .scope_boxed()
+        })
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    send_tracked_book(book_id, connection).await;
+
+    Ok(Json(agreements))
+}
@@ -0,0 +1,224 @@
+use axum::{
+    extract::{Path, Query},
+    headers::{authorization::Bearer, Authorization},
+    http::StatusCode,
+    Json, TypedHeader,
+};
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use gablet_shared_api::{
+    errors::{get_error, get_error_from_string, get_internal_error, ErrorResult},
+    kafka::kafka_events::{BOOK_TOPIC, CHAPTER_PUBLISHED_EVENT},
+    pagination::{clamp_limit, fetch_page, Paged},
+};
+use serde::Deserialize;
+
+use crate::{
+    models::{
+        chapters::ChapterPublished,
+        moderation::{ModerationItem, ModerationKind, ModerationStatus},
+        users::UserLevel,
+    },
+    utils::{
+        auth::require_user_level, kafka::send_event, moderation::review_item,
+        tracking::send_tracked_book,
+    },
+    CURSOR_SIGNER, PG_POOL, TOKEN_ISSUER,
+};
+
+const DEFAULT_QUEUE_LIMIT: i64 = 25;
+const MAX_QUEUE_LIMIT: i64 = 100;
+
+#[derive(Deserialize)]
+pub struct QueueQuery {
+    pub kind: Option<ModerationKind>,
+    pub cursor: Option<String>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Deserialize)]
+pub struct RejectRequest {
+    pub reason: String,
+}
+
+/// The only order the queue comes in. Cursors hold the id of the last item on
+/// a page.
+const QUEUE_SORT: &str = "oldest";
+
+/// Lists pending items, oldest first.
+pub async fn list_pending(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Query(query): Query<QueueQuery>,
+) -> Result<Json<Paged<ModerationItem>>, (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::moderation_items::dsl::{
+        id as db_id, kind as db_kind, moderation_items as db_items, status as db_status,
+    };
+
+    let token = TOKEN_ISSUER
+        .validate_auth(auth.token())
+        .map_err(|err| get_error(err, StatusCode::UNAUTHORIZED).to_tuple())?;
+
+    require_user_level(&token, UserLevel::Mod).map_err(|err| err.to_tuple())?;
+
+    let after: Option<i32> = query
+        .cursor
+        .as_deref()
+        .map(|cursor| CURSOR_SIGNER.decode(QUEUE_SORT, cursor))
+        .transpose()
+        .map_err(|err| ErrorResult::from(err).to_tuple())?;
+
+    let limit = clamp_limit(query.limit, DEFAULT_QUEUE_LIMIT, MAX_QUEUE_LIMIT);
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let mut items_query = db_items
+        .filter(db_status.eq(ModerationStatus::Pending))
+        .into_boxed();
+
+    if let Some(kind) = query.kind {
+        items_query = items_query.filter(db_kind.eq(kind));
+    }
+
+    if let Some(after) = after {
+        items_query = items_query.filter(db_id.gt(after));
+    }
+
+    let items: Vec<ModerationItem> = fetch_page(
+        items_query
+            .order(db_id.asc())
+            .select(ModerationItem::as_select()),
+        limit,
+    )
+    .load(connection)
+    .await
+    .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    Ok(Json(Paged::from_rows(items, limit, |last| {
+        CURSOR_SIGNER.encode(QUEUE_SORT, &last.id)
+    })))
+}
+
+/// Approves a pending item. Approving a chapter publishes it to the followers
+/// of its book.
+pub async fn approve_item(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Path(item_id): Path<i32>,
+) -> Result<Json<ModerationItem>, (StatusCode, Json<ErrorResult>)> {
+    let token = TOKEN_ISSUER
+        .validate_auth(auth.token())
+        .map_err(|err| get_error(err, StatusCode::UNAUTHORIZED).to_tuple())?;
+
+    require_user_level(&token, UserLevel::Mod).map_err(|err| err.to_tuple())?;
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let item = review_item(
+        item_id,
+        token.user_id(),
+        ModerationStatus::Approved,
+        None,
+        connection,
+    )
+    .await
+    .map_err(|err| get_internal_error(err).to_tuple())?
+    .ok_or_else(|| {
+        get_error_from_string(
+            StatusCode::NOT_FOUND,
+            format!("No pending moderation item {} exists", item_id),
+        )
+        .to_tuple()
+    })?;
+
+    if item.kind == ModerationKind::Book {
+        send_tracked_book(item.book_id, connection).await;
+    }
+
+    if let (ModerationKind::Chapter, Some(chapter_id)) = (item.kind, item.chapter_id) {
+        use crate::schema::chapters::dsl::{chapters as db_chapters, published as db_published};
+
+        // Chapters scheduled for later are announced by the release scheduler.
+        let published: Option<NaiveDateTime> = db_chapters
+            .find(chapter_id)
+            .select(db_published)
+            .first(connection)
+            .await
+            .map_err(|err| get_internal_error(err).to_tuple())?;
+
+        if published.is_none() {
+            return Ok(Json(item));
+        }
+
+        send_event(
+            BOOK_TOPIC,
+            CHAPTER_PUBLISHED_EVENT,
+            &ChapterPublished {
+                book_id: item.book_id,
+                chapter_id,
+                lang: None,
+            },
+        );
+    }
+
+    Ok(Json(item))
+}
+
+pub async fn reject_item(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Path(item_id): Path<i32>,
+    Json(request): Json<RejectRequest>,
+) -> Result<Json<ModerationItem>, (StatusCode, Json<ErrorResult>)> {
+    let token = TOKEN_ISSUER
+        .validate_auth(auth.token())
+        .map_err(|err| get_error(err, StatusCode::UNAUTHORIZED).to_tuple())?;
+
+    require_user_level(&token, UserLevel::Mod).map_err(|err| err.to_tuple())?;
+
+    if request.reason.trim().is_empty() {
+        return Err(get_error_from_string(
+            StatusCode::BAD_REQUEST,
+            "A reason is required to reject an item".into(),
+        )
+        .to_tuple());
+    }
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let item = review_item(
+        item_id,
+        token.user_id(),
+        ModerationStatus::Rejected,
+        Some(request.reason),
+        connection,
+    )
+    .await
+    .map_err(|err| get_internal_error(err).to_tuple())?
+    .ok_or_else(|| {
+        get_error_from_string(
+            StatusCode::NOT_FOUND,
+            format!("No pending moderation item {} exists", item_id),
+        )
+        .to_tuple()
+    })?;
+
+    if item.kind == ModerationKind::Book {
+        send_tracked_book(item.book_id, connection).await;
+    }
+
+    Ok(Json(item))
+}
@@ -0,0 +1,111 @@
+use axum::{
+    extract::{Path, Query},
+    headers::{authorization::Bearer, Authorization},
+    http::StatusCode,
+    Json, TypedHeader,
+};
+use diesel::{prelude::*, update};
+use diesel_async::RunQueryDsl;
+use gablet_shared_api::errors::{
+    get_error, get_error_from_string, get_internal_error, ErrorResult,
+};
+use serde::Deserialize;
+
+use crate::{models::notifications::Notification, PG_POOL, TOKEN_ISSUER};
+
+const DEFAULT_NOTIFICATION_LIMIT: i64 = 25;
+const MAX_NOTIFICATION_LIMIT: i64 = 100;
+
+#[derive(Deserialize)]
+pub struct NotificationQuery {
+    pub before: Option<i32>,
+    pub limit: Option<i64>,
+    #[serde(default)]
+    pub unread: bool,
+}
+
+/// Lists the notifications of the current user, newest first.
+pub async fn list_notifications(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Query(query): Query<NotificationQuery>,
+) -> Result<Json<Vec<Notification>>, (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::notifications::dsl::{
+        id as db_id, notifications as db_notifications, read as db_read, user_id as db_user_id,
+    };
+
+    let token = TOKEN_ISSUER
+        .validate_auth(auth.token())
+        .map_err(|err| get_error(err, StatusCode::UNAUTHORIZED).to_tuple())?;
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let mut notifications_query = db_notifications
+        .filter(db_user_id.eq(token.user_id()))
+        .into_boxed();
+
+    if query.unread {
+        notifications_query = notifications_query.filter(db_read.eq(false));
+    }
+
+    if let Some(before) = query.before {
+        notifications_query = notifications_query.filter(db_id.lt(before));
+    }
+
+    let notifications = notifications_query
+        .order(db_id.desc())
+        .limit(
+            query
+                .limit
+                .unwrap_or(DEFAULT_NOTIFICATION_LIMIT)
+                .clamp(1, MAX_NOTIFICATION_LIMIT),
+        )
+        .select(Notification::as_select())
+        .load(connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    Ok(Json(notifications))
+}
+
+pub async fn mark_notification_read(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Path(notification_id): Path<i32>,
+) -> Result<StatusCode, (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::notifications::dsl::{
+        id as db_id, notifications as db_notifications, read as db_read, user_id as db_user_id,
+    };
+
+    let token = TOKEN_ISSUER
+        .validate_auth(auth.token())
+        .map_err(|err| get_error(err, StatusCode::UNAUTHORIZED).to_tuple())?;
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let updated = update(db_notifications)
+        .filter(db_id.eq(notification_id))
+        .filter(db_user_id.eq(token.user_id()))
+        .set(db_read.eq(true))
+        .execute(connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    if updated == 0 {
+        return Err(get_error_from_string(
+            StatusCode::NOT_FOUND,
+            format!("No notification {} exists", notification_id),
+        )
+        .to_tuple());
+    }
+
+    Ok(StatusCode::OK)
+}
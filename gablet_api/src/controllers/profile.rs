@@ -1,31 +1,505 @@
 use axum::{
+    extract::Path,
     headers::{authorization::Bearer, Authorization},
     http::StatusCode,
     Json, TypedHeader,
 };
-use gablet_shared_api::{
-    auth::UserRequest,
-    errors::{get_internal_error, ErrorResult},
+use diesel::{
+    insert_into,
+    prelude::*,
+    result::Error as DbError,
+    sql_query,
+    sql_types::{Array, Int4, Nullable, Varchar},
+    update,
+};
+use diesel_async::{
+    scoped_futures::ScopedFutureExt, AsyncConnection, AsyncPgConnection, RunQueryDsl,
+};
+use gablet_shared_api::errors::{
+    get_error, get_error_from_string, get_internal_error, ErrorResult,
 };
 use serde::{Deserialize, Serialize};
 
-use crate::TOKEN_ISSUER;
+use crate::{
+    models::{
+        books::ContentRating,
+        users::{NewUserProfile, UserProfile},
+    },
+    utils::lang::validate_lang,
+    PG_POOL, TOKEN_ISSUER,
+};
+
+const MAX_NAME_LENGTH: usize = 128;
+const MAX_ABOUT_LENGTH: usize = 2000;
+const MAX_IMAGE_LENGTH: usize = 255;
+const MAX_GENDER_LENGTH: usize = 30;
+const MAX_COUNTRY_LENGTH: usize = 50;
+const MAX_LINKS: usize = 5;
+const MAX_LINK_LENGTH: usize = 255;
+const MAX_LANGS: usize = 10;
+
+/// Books with at least one text region translated by the user, along with the
+/// languages they translated into.
+const TRANSLATED_BOOKS_SQL: &str = "
+SELECT books.id, books.name, books.small_thumbnail,
+    array_agg(DISTINCT text_region_translations.lang ORDER BY text_region_translations.lang) AS langs
+FROM text_region_translations
+INNER JOIN text_regions ON text_regions.id = text_region_translations.region_id
+INNER JOIN pages ON pages.id = text_regions.page_id
+INNER JOIN chapters ON chapters.id = pages.chapter_id
+INNER JOIN books ON books.id = chapters.book_id
+WHERE text_region_translations.translator_id = $1
+    AND (books.approved OR $2)
+GROUP BY books.id
+ORDER BY books.name";
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ProfileBook {
+    pub id: i32,
+    pub name: String,
+    pub small_thumbnail: Option<String>,
+}
+
+#[derive(QueryableByName, Serialize, Deserialize, Clone)]
+pub struct TranslatedBook {
+    #[diesel(sql_type = Int4)]
+    pub id: i32,
+    #[diesel(sql_type = Varchar)]
+    pub name: String,
+    #[diesel(sql_type = Nullable<Varchar>)]
+    pub small_thumbnail: Option<String>,
+    #[diesel(sql_type = Array<Varchar>)]
+    pub langs: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ProfileResult {
+    pub id: i32,
+    pub username: String,
+    /// The display name, or `None` if the user hasn't set one.
+    pub name: Option<String>,
+    pub about: Option<String>,
+    pub avatar: Option<String>,
+    pub banner: Option<String>,
+    pub gender: Option<String>,
+    pub country: Option<String>,
+    pub links: Vec<String>,
+    /// The languages the user prefers to read in, most preferred first.
+    pub langs: Vec<String>,
+    pub books: Vec<ProfileBook>,
+    pub translations: Vec<TranslatedBook>,
+}
+
+/// Changes to the current user's profile. Missing fields are left as is, and
+/// empty strings clear a field.
+#[derive(Deserialize)]
+pub struct ProfileRequest {
+    pub name: Option<String>,
+    pub about: Option<String>,
+    pub avatar: Option<String>,
+    pub banner: Option<String>,
+    pub gender: Option<String>,
+    pub country: Option<String>,
+    pub links: Option<Vec<String>>,
+    pub langs: Option<Vec<String>>,
+}
 
+/// Settings that control what content is shown to a user.
 #[derive(Serialize, Deserialize, Clone)]
-pub struct CurrentUserResult {
-    username: String,
+pub struct ContentSettings {
+    pub max_content_rating: ContentRating,
+}
+
+/// Trims a changed profile field, turning empty strings into `None`. Returns
+/// the current value if the field wasn't changed.
+fn clean_field(
+    value: Option<String>,
+    current: Option<String>,
+    max_length: usize,
+    field: &str,
+) -> Result<Option<String>, (StatusCode, Json<ErrorResult>)> {
+    let Some(value) = value else {
+        return Ok(current);
+    };
+
+    let value = value.trim();
+
+    if value.chars().count() > max_length {
+        return Err(get_error_from_string(
+            StatusCode::BAD_REQUEST,
+            format!("{} can't be longer than {} characters", field, max_length),
+        )
+        .to_tuple());
+    }
+
+    Ok((!value.is_empty()).then(|| value.to_string()))
+}
+
+fn validate_links(links: &[String]) -> Result<(), (StatusCode, Json<ErrorResult>)> {
+    if links.len() > MAX_LINKS {
+        return Err(get_error_from_string(
+            StatusCode::BAD_REQUEST,
+            format!("Profiles can have at most {} links", MAX_LINKS),
+        )
+        .to_tuple());
+    }
+
+    for link in links {
+        if link.chars().count() > MAX_LINK_LENGTH
+            || !(link.starts_with("https://") || link.starts_with("http://"))
+        {
+            return Err(get_error_from_string(
+                StatusCode::BAD_REQUEST,
+                format!("{} is not a valid link", link),
+            )
+            .to_tuple());
+        }
+    }
+
+    Ok(())
 }
 
-#[axum::debug_handler]
+fn validate_langs(langs: &[String]) -> Result<(), (StatusCode, Json<ErrorResult>)> {
+    if langs.len() > MAX_LANGS {
+        return Err(get_error_from_string(
+            StatusCode::BAD_REQUEST,
+            format!("Profiles can have at most {} languages", MAX_LANGS),
+        )
+        .to_tuple());
+    }
+
+    for (index, lang) in langs.iter().enumerate() {
+        validate_lang(lang).map_err(|err| err.to_tuple())?;
+
+        if langs[..index].contains(lang) {
+            return Err(get_error_from_string(
+                StatusCode::BAD_REQUEST,
+                format!("{} is listed more than once", lang),
+            )
+            .to_tuple());
+        }
+    }
+
+    Ok(())
+}
+
+/// Loads the full profile of a user. Books that haven't been approved are only
+/// included when the user is looking at their own profile.
+async fn load_profile(
+    user_id: i32,
+    include_unapproved: bool,
+    connection: &mut AsyncPgConnection,
+) -> Result<Option<ProfileResult>, DbError> {
+    use crate::schema::books::dsl::{
+        approved as db_approved, author_id as db_author_id, books as db_books, id as db_book_id,
+        name as db_book_name, small_thumbnail as db_small_thumbnail,
+    };
+    use crate::schema::user_profiles::dsl::{
+        user_id as db_profile_user_id, user_profiles as db_user_profiles,
+    };
+    use crate::schema::users::dsl::{name as db_name, username as db_username, users as db_users};
+
+    let Some((username, name)) = db_users
+        .find(user_id)
+        .select((db_username, db_name))
+        .first::<(String, String)>(connection)
+        .await
+        .optional()?
+    else {
+        return Ok(None);
+    };
+
+    // Users who registered after profiles were added don't have one until
+    // they first update it.
+    let profile = db_user_profiles
+        .filter(db_profile_user_id.eq(user_id))
+        .select(UserProfile::as_select())
+        .first(connection)
+        .await
+        .optional()?
+        .unwrap_or_default();
+
+    let mut books_query = db_books.filter(db_author_id.eq(user_id)).into_boxed();
+
+    if !include_unapproved {
+        books_query = books_query.filter(db_approved.eq(true));
+    }
+
+    let books = books_query
+        .order(db_book_name.asc())
+        .select((db_book_id, db_book_name, db_small_thumbnail))
+        .load::<(i32, String, Option<String>)>(connection)
+        .await?
+        .into_iter()
+        .map(|(id, name, small_thumbnail)| ProfileBook {
+            id,
+            name,
+            small_thumbnail,
+        })
+        .collect();
+
+    let translations = sql_query(TRANSLATED_BOOKS_SQL)
+        .bind::<Int4, _>(user_id)
+        .bind::<diesel::sql_types::Bool, _>(include_unapproved)
+        .load(connection)
+        .await?;
+
+    Ok(Some(ProfileResult {
+        id: user_id,
+        username,
+        name: (!name.is_empty()).then_some(name),
+        about: profile.about,
+        avatar: profile.avatar,
+        banner: profile.banner,
+        gender: profile.gender,
+        country: profile.country,
+        links: profile.links.into_iter().flatten().collect(),
+        langs: profile.langs.into_iter().flatten().collect(),
+        books,
+        translations,
+    }))
+}
+
+/// Gets the profile of the current user.
 pub async fn current_user(
     TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
-    Json(user): Json<UserRequest>,
-) -> Result<Json<CurrentUserResult>, (StatusCode, Json<ErrorResult>)> {
-    let result = TOKEN_ISSUER
+) -> Result<Json<ProfileResult>, (StatusCode, Json<ErrorResult>)> {
+    let token = TOKEN_ISSUER
         .validate_auth(auth.token())
+        .map_err(|err| get_error(err, StatusCode::UNAUTHORIZED).to_tuple())?;
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
         .map_err(|err| get_internal_error(err).to_tuple())?;
 
-    Ok(Json(CurrentUserResult {
-        username: result.username(),
-    }))
-}
\ No newline at end of file
+    let profile = load_profile(token.user_id(), true, connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?
+        .ok_or_else(|| {
+            get_error_from_string(
+                StatusCode::NOT_FOUND,
+                format!("User {} does not exist", token.username()),
+            )
+            .to_tuple()
+        })?;
+
+    Ok(Json(profile))
+}
+
+/// Gets the public profile of any user.
+pub async fn get_user_profile(
+    Path(username): Path<String>,
+) -> Result<Json<ProfileResult>, (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::users::dsl::{id as db_id, username as db_username, users as db_users};
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let not_found = || {
+        get_error_from_string(
+            StatusCode::NOT_FOUND,
+            format!("User {} does not exist", username),
+        )
+        .to_tuple()
+    };
+
+    let user_id: i32 = db_users
+        .filter(db_username.eq(&username))
+        .select(db_id)
+        .first(connection)
+        .await
+        .optional()
+        .map_err(|err| get_internal_error(err).to_tuple())?
+        .ok_or_else(not_found)?;
+
+    let profile = load_profile(user_id, false, connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?
+        .ok_or_else(not_found)?;
+
+    Ok(Json(profile))
+}
+
+/// Updates the profile of the current user.
+pub async fn update_profile(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Json(request): Json<ProfileRequest>,
+) -> Result<Json<ProfileResult>, (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::user_profiles::dsl::{
+        user_id as db_profile_user_id, user_profiles as db_user_profiles,
+    };
+    use crate::schema::users::dsl::{name as db_name, users as db_users};
+
+    let token = TOKEN_ISSUER
+        .validate_auth(auth.token())
+        .map_err(|err| get_error(err, StatusCode::UNAUTHORIZED).to_tuple())?;
+
+    if let Some(links) = &request.links {
+        validate_links(links)?;
+    }
+
+    if let Some(langs) = &request.langs {
+        validate_langs(langs)?;
+    }
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let current = load_profile(token.user_id(), true, connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?
+        .ok_or_else(|| {
+            get_error_from_string(
+                StatusCode::NOT_FOUND,
+                format!("User {} does not exist", token.username()),
+            )
+            .to_tuple()
+        })?;
+
+    let name = clean_field(request.name, current.name, MAX_NAME_LENGTH, "Name")?;
+
+    let profile = NewUserProfile {
+        user_id: token.user_id(),
+        about: clean_field(request.about, current.about, MAX_ABOUT_LENGTH, "About")?,
+        avatar: clean_field(request.avatar, current.avatar, MAX_IMAGE_LENGTH, "Avatar")?,
+        banner: clean_field(request.banner, current.banner, MAX_IMAGE_LENGTH, "Banner")?,
+        gender: clean_field(request.gender, current.gender, MAX_GENDER_LENGTH, "Gender")?,
+        country: clean_field(
+            request.country,
+            current.country,
+            MAX_COUNTRY_LENGTH,
+            "Country",
+        )?,
+        links: request
+            .links
+            .unwrap_or(current.links)
+            .into_iter()
+            .map(Some)
+            .collect(),
+        langs: request
+            .langs
+            .unwrap_or(current.langs)
+            .into_iter()
+            .map(Some)
+            .collect(),
+    };
+
+    connection
+        .transaction::<_, DbError, _>(|connection| {
+            async move {
+                update(db_users.find(profile.user_id))
+                    .set(db_name.eq(name.unwrap_or_default()))
+                    .execute(connection)
+                    .await?;
+
+                insert_into(db_user_profiles)
+                    .values(&profile)
+                    .on_conflict(db_profile_user_id)
+                    .do_update()
+                    .set(&profile)
+                    .execute(connection)
+                    .await?;
+
+                Ok(())
+            }
+            .scope_boxed()
+        })
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let profile = load_profile(token.user_id(), true, connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?
+        .ok_or_else(|| {
+            get_error_from_string(
+                StatusCode::NOT_FOUND,
+                format!("User {} does not exist", token.username()),
+            )
+            .to_tuple()
+        })?;
+
+    Ok(Json(profile))
+}
+
+pub async fn get_content_settings(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+) -> Result<Json<ContentSettings>, (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::users::dsl::{
+        max_content_rating as db_max_content_rating, users as db_users,
+    };
+
+    let token = TOKEN_ISSUER
+        .validate_auth(auth.token())
+        .map_err(|err| get_error(err, StatusCode::UNAUTHORIZED).to_tuple())?;
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let max_content_rating = db_users
+        .find(token.user_id())
+        .select(db_max_content_rating)
+        .first(connection)
+        .await
+        .optional()
+        .map_err(|err| get_internal_error(err).to_tuple())?
+        .ok_or_else(|| {
+            get_error_from_string(
+                StatusCode::NOT_FOUND,
+                format!("User {} does not exist", token.username()),
+            )
+            .to_tuple()
+        })?;
+
+    Ok(Json(ContentSettings { max_content_rating }))
+}
+
+pub async fn update_content_settings(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Json(settings): Json<ContentSettings>,
+) -> Result<Json<ContentSettings>, (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::users::dsl::{
+        max_content_rating as db_max_content_rating, users as db_users,
+    };
+
+    let token = TOKEN_ISSUER
+        .validate_auth(auth.token())
+        .map_err(|err| get_error(err, StatusCode::UNAUTHORIZED).to_tuple())?;
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let updated = update(db_users.find(token.user_id()))
+        .set(db_max_content_rating.eq(settings.max_content_rating))
+        .execute(connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    if updated == 0 {
+        return Err(get_error_from_string(
+            StatusCode::NOT_FOUND,
+            format!("User {} does not exist", token.username()),
+        )
+        .to_tuple());
+    }
+
+    Ok(Json(settings))
+}
@@ -0,0 +1,463 @@
+use axum::{
+    extract::{Path, Query},
+    headers::{authorization::Bearer, Authorization},
+    http::StatusCode,
+    Json, TypedHeader,
+};
+use chrono::{NaiveDateTime, Utc};
+use diesel::{delete, insert_into, pg::upsert::excluded, prelude::*, query_dsl::methods, update};
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use gablet_shared_api::errors::{
+    get_error, get_error_from_string, get_internal_error, ErrorResult,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    models::{
+        books::Book,
+        reading::{Bookmark, BookmarkChanges, NewBookmark, NewReadingProgress, ReadingProgress},
+    },
+    utils::{books::find_book, lang::validate_lang},
+    PG_POOL, TOKEN_ISSUER,
+};
+
+const DEFAULT_CONTINUE_LIMIT: i64 = 10;
+const MAX_CONTINUE_LIMIT: i64 = 50;
+const MAX_BOOKMARK_NAME_LENGTH: usize = 100;
+
+#[derive(Deserialize)]
+pub struct ProgressRequest {
+    pub chapter_id: i32,
+    pub page_number: i32,
+    pub lang: Option<String>,
+    /// When the page was read on the device. Defaults to now.
+    pub updated: Option<NaiveDateTime>,
+}
+
+#[derive(Deserialize)]
+pub struct BookmarkRequest {
+    pub chapter_id: i32,
+    pub page_number: i32,
+    pub lang: Option<String>,
+    pub name: String,
+    /// When the bookmark was changed on the device. Defaults to now.
+    pub updated: Option<NaiveDateTime>,
+}
+
+#[derive(Deserialize)]
+pub struct ContinueReadingQuery {
+    pub limit: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct ContinueReadingItem {
+    #[serde(flatten)]
+    pub progress: ReadingProgress,
+    pub chapter_number: i32,
+    pub book: Book,
+}
+
+/// Returns the time a change was made on a device, limited to the current
+/// time so a device with a clock in the future can't lock in its changes.
+fn sync_timestamp(updated: Option<NaiveDateTime>) -> NaiveDateTime {
+    let now = Utc::now().naive_utc();
+    updated.map(|updated| updated.min(now)).unwrap_or(now)
+}
+
+fn validate_position(page_number: i32, lang: Option<&str>) -> Result<(), ErrorResult> {
+    if page_number < 1 {
+        return Err(get_error_from_string(
+            StatusCode::BAD_REQUEST,
+            "Page numbers start at 1".into(),
+        ));
+    }
+
+    match lang {
+        Some(lang) => validate_lang(lang),
+        None => Ok(()),
+    }
+}
+
+/// Validates a bookmark request and returns the trimmed bookmark name.
+fn validate_bookmark(request: &BookmarkRequest) -> Result<String, ErrorResult> {
+    validate_position(request.page_number, request.lang.as_deref())?;
+
+    let name = request.name.trim();
+
+    if name.is_empty() || name.chars().count() > MAX_BOOKMARK_NAME_LENGTH {
+        return Err(get_error_from_string(
+            StatusCode::BAD_REQUEST,
+            format!(
+                "Bookmark names must be between 1 and {} characters",
+                MAX_BOOKMARK_NAME_LENGTH
+            ),
+        ));
+    }
+
+    Ok(name.to_owned())
+}
+
+/// Makes sure a published chapter of a published book is being read.
+async fn check_readable_chapter(
+    book_id: i32,
+    chapter_id: i32,
+    connection: &mut AsyncPgConnection,
+) -> Result<(), (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::chapters::dsl::{
+        approved as db_approved, book_id as db_book_id, chapters as db_chapters, id as db_id,
+        published as db_published,
+    };
+
+    find_book(book_id, connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?
+        .filter(|book| book.approved)
+        .ok_or_else(|| {
+            get_error_from_string(StatusCode::NOT_FOUND, format!("No book {} exists", book_id))
+                .to_tuple()
+        })?;
+
+    db_chapters
+        .filter(db_id.eq(chapter_id))
+        .filter(db_book_id.eq(book_id))
+        .filter(db_approved.eq(true))
+        .filter(db_published.is_not_null())
+        .select(db_id)
+        .first::<i32>(connection)
+        .await
+        .optional()
+        .map_err(|err| get_internal_error(err).to_tuple())?
+        .ok_or_else(|| {
+            get_error_from_string(
+                StatusCode::NOT_FOUND,
+                format!("Book {} has no chapter {}", book_id, chapter_id),
+            )
+            .to_tuple()
+        })?;
+
+    Ok(())
+}
+
+pub async fn get_progress(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Path(book_id): Path<i32>,
+) -> Result<Json<ReadingProgress>, (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::reading_progress::dsl::reading_progress as db_progress;
+
+    let token = TOKEN_ISSUER
+        .validate_auth(auth.token())
+        .map_err(|err| get_error(err, StatusCode::UNAUTHORIZED).to_tuple())?;
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let progress = db_progress
+        .find((token.user_id(), book_id))
+        .select(ReadingProgress::as_select())
+        .first(connection)
+        .await
+        .optional()
+        .map_err(|err| get_internal_error(err).to_tuple())?
+        .ok_or_else(|| {
+            get_error_from_string(
+                StatusCode::NOT_FOUND,
+                format!("No progress saved for book {}", book_id),
+            )
+            .to_tuple()
+        })?;
+
+    Ok(Json(progress))
+}
+
+/// Saves the page a user is on. If another device already saved progress
+/// that is more recent, that progress is kept and returned instead.
+pub async fn update_progress(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Path(book_id): Path<i32>,
+    Json(request): Json<ProgressRequest>,
+) -> Result<Json<ReadingProgress>, (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::reading_progress::dsl::{
+        book_id as db_book_id, reading_progress as db_progress, updated as db_updated,
+        user_id as db_user_id,
+    };
+
+    let token = TOKEN_ISSUER
+        .validate_auth(auth.token())
+        .map_err(|err| get_error(err, StatusCode::UNAUTHORIZED).to_tuple())?;
+
+    validate_position(request.page_number, request.lang.as_deref())
+        .map_err(|err| err.to_tuple())?;
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    check_readable_chapter(book_id, request.chapter_id, connection).await?;
+
+    let progress = NewReadingProgress {
+        user_id: token.user_id(),
+        book_id,
+        chapter_id: request.chapter_id,
+        page_number: request.page_number,
+        lang: request.lang,
+        updated: sync_timestamp(request.updated),
+    };
+
+    let upsert = insert_into(db_progress)
+        .values(&progress)
+        .on_conflict((db_user_id, db_book_id))
+        .do_update()
+        .set(&progress);
+
+    // Only overwrite progress that is older than this change.
+    let saved = methods::FilterDsl::filter(upsert, db_updated.lt(excluded(db_updated)))
+        .returning(ReadingProgress::as_returning())
+        .get_result(connection)
+        .await
+        .optional()
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let progress = match saved {
+        Some(progress) => progress,
+        None => db_progress
+            .find((token.user_id(), book_id))
+            .select(ReadingProgress::as_select())
+            .first(connection)
+            .await
+            .map_err(|err| get_internal_error(err).to_tuple())?,
+    };
+
+    Ok(Json(progress))
+}
+
+/// Lists the books the current user has been reading, most recently read
+/// first.
+pub async fn continue_reading(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Query(query): Query<ContinueReadingQuery>,
+) -> Result<Json<Vec<ContinueReadingItem>>, (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::books::dsl::{approved as db_approved, books as db_books};
+    use crate::schema::chapters::dsl::{chapters as db_chapters, number as db_number};
+    use crate::schema::reading_progress::dsl::{
+        reading_progress as db_progress, updated as db_updated, user_id as db_user_id,
+    };
+
+    let token = TOKEN_ISSUER
+        .validate_auth(auth.token())
+        .map_err(|err| get_error(err, StatusCode::UNAUTHORIZED).to_tuple())?;
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let items = db_progress
+        .inner_join(db_books)
+        .inner_join(db_chapters)
+        .filter(db_user_id.eq(token.user_id()))
+        .filter(db_approved.eq(true))
+        .order(db_updated.desc())
+        .limit(
+            query
+                .limit
+                .unwrap_or(DEFAULT_CONTINUE_LIMIT)
+                .clamp(1, MAX_CONTINUE_LIMIT),
+        )
+        .select((ReadingProgress::as_select(), db_number, Book::as_select()))
+        .load::<(ReadingProgress, i32, Book)>(connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?
+        .into_iter()
+        .map(|(progress, chapter_number, book)| ContinueReadingItem {
+            progress,
+            chapter_number,
+            book,
+        })
+        .collect();
+
+    Ok(Json(items))
+}
+
+/// Lists the bookmarks the current user has in a book, in reading order.
+pub async fn list_bookmarks(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Path(book_id): Path<i32>,
+) -> Result<Json<Vec<Bookmark>>, (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::bookmarks::dsl::{
+        book_id as db_book_id, bookmarks as db_bookmarks, id as db_id,
+        page_number as db_page_number, user_id as db_user_id,
+    };
+    use crate::schema::chapters::dsl::{chapters as db_chapters, number as db_number};
+
+    let token = TOKEN_ISSUER
+        .validate_auth(auth.token())
+        .map_err(|err| get_error(err, StatusCode::UNAUTHORIZED).to_tuple())?;
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let bookmarks = db_bookmarks
+        .inner_join(db_chapters)
+        .filter(db_user_id.eq(token.user_id()))
+        .filter(db_book_id.eq(book_id))
+        .order((db_number.asc(), db_page_number.asc(), db_id.asc()))
+        .select(Bookmark::as_select())
+        .load(connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    Ok(Json(bookmarks))
+}
+
+pub async fn create_bookmark(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Path(book_id): Path<i32>,
+    Json(request): Json<BookmarkRequest>,
+) -> Result<(StatusCode, Json<Bookmark>), (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::bookmarks::dsl::bookmarks as db_bookmarks;
+
+    let token = TOKEN_ISSUER
+        .validate_auth(auth.token())
+        .map_err(|err| get_error(err, StatusCode::UNAUTHORIZED).to_tuple())?;
+
+    let name = validate_bookmark(&request).map_err(|err| err.to_tuple())?;
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    check_readable_chapter(book_id, request.chapter_id, connection).await?;
+
+    let bookmark = insert_into(db_bookmarks)
+        .values(NewBookmark {
+            user_id: token.user_id(),
+            book_id,
+            chapter_id: request.chapter_id,
+            page_number: request.page_number,
+            lang: request.lang,
+            name,
+            updated: sync_timestamp(request.updated),
+        })
+        .returning(Bookmark::as_returning())
+        .get_result(connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    Ok((StatusCode::CREATED, Json(bookmark)))
+}
+
+/// Moves or renames a bookmark. Changes older than the last saved change are
+/// ignored and the saved bookmark is returned instead.
+pub async fn update_bookmark(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Path(bookmark_id): Path<i32>,
+    Json(request): Json<BookmarkRequest>,
+) -> Result<Json<Bookmark>, (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::bookmarks::dsl::{
+        bookmarks as db_bookmarks, id as db_id, updated as db_updated, user_id as db_user_id,
+    };
+
+    let token = TOKEN_ISSUER
+        .validate_auth(auth.token())
+        .map_err(|err| get_error(err, StatusCode::UNAUTHORIZED).to_tuple())?;
+
+    let name = validate_bookmark(&request).map_err(|err| err.to_tuple())?;
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let bookmark: Bookmark = db_bookmarks
+        .filter(db_id.eq(bookmark_id))
+        .filter(db_user_id.eq(token.user_id()))
+        .select(Bookmark::as_select())
+        .first(connection)
+        .await
+        .optional()
+        .map_err(|err| get_internal_error(err).to_tuple())?
+        .ok_or_else(|| {
+            get_error_from_string(
+                StatusCode::NOT_FOUND,
+                format!("No bookmark {} exists", bookmark_id),
+            )
+            .to_tuple()
+        })?;
+
+    check_readable_chapter(bookmark.book_id, request.chapter_id, connection).await?;
+
+    let updated = sync_timestamp(request.updated);
+
+    let saved = update(db_bookmarks)
+        .filter(db_id.eq(bookmark_id))
+        .filter(db_updated.lt(updated))
+        .set(BookmarkChanges {
+            chapter_id: request.chapter_id,
+            page_number: request.page_number,
+            lang: request.lang,
+            name,
+            updated,
+        })
+        .returning(Bookmark::as_returning())
+        .get_result(connection)
+        .await
+        .optional()
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    Ok(Json(saved.unwrap_or(bookmark)))
+}
+
+pub async fn delete_bookmark(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Path(bookmark_id): Path<i32>,
+) -> Result<StatusCode, (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::bookmarks::dsl::{
+        bookmarks as db_bookmarks, id as db_id, user_id as db_user_id,
+    };
+
+    let token = TOKEN_ISSUER
+        .validate_auth(auth.token())
+        .map_err(|err| get_error(err, StatusCode::UNAUTHORIZED).to_tuple())?;
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let deleted = delete(db_bookmarks)
+        .filter(db_id.eq(bookmark_id))
+        .filter(db_user_id.eq(token.user_id()))
+        .execute(connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    if deleted == 0 {
+        return Err(get_error_from_string(
+            StatusCode::NOT_FOUND,
+            format!("No bookmark {} exists", bookmark_id),
+        )
+        .to_tuple());
+    }
+
+    Ok(StatusCode::OK)
+}
@@ -0,0 +1,226 @@
+use axum::{
+    extract::{Path, Query},
+    headers::{authorization::Bearer, Authorization},
+    http::StatusCode,
+    Json, TypedHeader,
+};
+use diesel::{delete, dsl::now, insert_into, pg::upsert::excluded, prelude::*};
+use diesel_async::RunQueryDsl;
+use gablet_shared_api::{
+    errors::{get_error, get_error_from_string, get_internal_error, ErrorResult},
+    pagination::{fetch_page, PageQuery, Paged},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    models::reviews::{NewReview, Review},
+    utils::books::find_book,
+    CURSOR_SIGNER, PG_POOL, TOKEN_ISSUER,
+};
+
+const DEFAULT_REVIEW_LIMIT: i64 = 25;
+const MAX_REVIEW_LIMIT: i64 = 100;
+const MAX_REVIEW_LENGTH: usize = 10000;
+
+#[derive(Deserialize)]
+pub struct ReviewRequest {
+    /// From 1 to 5 stars.
+    pub rating: i16,
+    pub body: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct ReviewResult {
+    #[serde(flatten)]
+    pub review: Review,
+    pub username: String,
+}
+
+/// The only order reviews come in. Cursors hold the id of the last review on
+/// a page.
+const REVIEW_SORT: &str = "newest";
+
+/// Lists the reviews of a book, newest first.
+pub async fn list_reviews(
+    Path(book_id): Path<i32>,
+    Query(query): Query<PageQuery>,
+) -> Result<Json<Paged<ReviewResult>>, (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::reviews::dsl::{book_id as db_book_id, id as db_id, reviews as db_reviews};
+    use crate::schema::users::dsl::{username as db_username, users as db_users};
+
+    let before: Option<i32> = CURSOR_SIGNER
+        .decode_query(REVIEW_SORT, &query)
+        .map_err(|err| err.to_tuple())?;
+
+    let limit = query.limit(DEFAULT_REVIEW_LIMIT, MAX_REVIEW_LIMIT);
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    find_book(book_id, connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?
+        .filter(|book| book.approved)
+        .ok_or_else(|| {
+            get_error_from_string(StatusCode::NOT_FOUND, format!("No book {} exists", book_id))
+                .to_tuple()
+        })?;
+
+    let mut reviews_query = db_reviews
+        .inner_join(db_users)
+        .filter(db_book_id.eq(book_id))
+        .into_boxed();
+
+    if let Some(before) = before {
+        reviews_query = reviews_query.filter(db_id.lt(before));
+    }
+
+    let reviews: Vec<(Review, String)> = fetch_page(
+        reviews_query
+            .order(db_id.desc())
+            .select((Review::as_select(), db_username)),
+        limit,
+    )
+    .load(connection)
+    .await
+    .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let page = Paged::from_rows(reviews, limit, |(last, _)| {
+        CURSOR_SIGNER.encode(REVIEW_SORT, &last.id)
+    });
+
+    Ok(Json(page.map(|(review, username)| ReviewResult {
+        review,
+        username,
+    })))
+}
+
+/// Rates and optionally reviews a book. Users have one review per book, so
+/// posting again replaces the earlier one.
+pub async fn set_review(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Path(book_id): Path<i32>,
+    Json(request): Json<ReviewRequest>,
+) -> Result<Json<Review>, (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::reviews::dsl::{
+        body as db_body, book_id as db_book_id, rating as db_rating, reviews as db_reviews,
+        updated as db_updated, user_id as db_user_id,
+    };
+
+    let token = TOKEN_ISSUER
+        .validate_auth(auth.token())
+        .map_err(|err| get_error(err, StatusCode::UNAUTHORIZED).to_tuple())?;
+
+    if !(1..=5).contains(&request.rating) {
+        return Err(get_error_from_string(
+            StatusCode::BAD_REQUEST,
+            "Ratings must be from 1 to 5".into(),
+        )
+        .to_tuple());
+    }
+
+    let body = request
+        .body
+        .map(|body| body.trim().to_string())
+        .filter(|body| !body.is_empty());
+
+    if body
+        .as_ref()
+        .is_some_and(|body| body.chars().count() > MAX_REVIEW_LENGTH)
+    {
+        return Err(get_error_from_string(
+            StatusCode::BAD_REQUEST,
+            format!(
+                "Reviews can't be longer than {} characters",
+                MAX_REVIEW_LENGTH
+            ),
+        )
+        .to_tuple());
+    }
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let book = find_book(book_id, connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?
+        .filter(|book| book.approved)
+        .ok_or_else(|| {
+            get_error_from_string(StatusCode::NOT_FOUND, format!("No book {} exists", book_id))
+                .to_tuple()
+        })?;
+
+    if book.author_id == token.user_id() {
+        return Err(get_error_from_string(
+            StatusCode::FORBIDDEN,
+            "Authors can't review their own books".into(),
+        )
+        .to_tuple());
+    }
+
+    let review = insert_into(db_reviews)
+        .values(NewReview {
+            book_id,
+            user_id: token.user_id(),
+            rating: request.rating,
+            body,
+        })
+        .on_conflict((db_book_id, db_user_id))
+        .do_update()
+        .set((
+            db_rating.eq(excluded(db_rating)),
+            db_body.eq(excluded(db_body)),
+            db_updated.eq(now),
+        ))
+        .returning(Review::as_returning())
+        .get_result(connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    Ok(Json(review))
+}
+
+pub async fn delete_review(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Path(book_id): Path<i32>,
+) -> Result<StatusCode, (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::reviews::dsl::{
+        book_id as db_book_id, reviews as db_reviews, user_id as db_user_id,
+    };
+
+    let token = TOKEN_ISSUER
+        .validate_auth(auth.token())
+        .map_err(|err| get_error(err, StatusCode::UNAUTHORIZED).to_tuple())?;
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let deleted = delete(db_reviews)
+        .filter(db_book_id.eq(book_id))
+        .filter(db_user_id.eq(token.user_id()))
+        .execute(connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    if deleted == 0 {
+        return Err(get_error_from_string(
+            StatusCode::NOT_FOUND,
+            format!("Book {} has not been reviewed", book_id),
+        )
+        .to_tuple());
+    }
+
+    Ok(StatusCode::OK)
+}
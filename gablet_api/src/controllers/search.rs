@@ -0,0 +1,162 @@
+use axum::{
+    extract::Query,
+    headers::{authorization::Bearer, Authorization},
+    http::StatusCode,
+    Json, TypedHeader,
+};
+use diesel::{
+    prelude::*,
+    sql_query,
+    sql_types::{Array, Float4, Int4, Int8, Nullable, Text, Varchar},
+};
+use diesel_async::RunQueryDsl;
+use gablet_shared_api::{
+    errors::{get_error_from_string, get_internal_error, ErrorResult},
+    pagination::{clamp_limit, Paged},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    models::books::{Book, BookStatus, ContentRating},
+    schema::sql_types::{BookStatus as BookStatusType, ContentRating as ContentRatingType},
+    utils::{
+        books::get_max_content_rating,
+        tags::{parse_tag_list, resolve_tags},
+    },
+    CURSOR_SIGNER, PG_POOL, TOKEN_ISSUER,
+};
+
+const DEFAULT_SEARCH_LIMIT: i64 = 20;
+const MAX_SEARCH_LIMIT: i64 = 50;
+
+/// Ranks books by full-text relevance, plus trigram similarity against the
+/// book and author names so that misspelled searches still find something.
+const SEARCH_BOOKS_SQL: &str = "
+SELECT * FROM (
+    SELECT books.id, books.name, books.description, books.approved, books.small_thumbnail,
+        books.big_thumbnail, books.author_id, books.lang, books.status, books.content_rating,
+        (ts_rank(books.search_vector, tsq)
+            + GREATEST(word_similarity($1, books.name), word_similarity($1, users.name)))::REAL AS rank
+    FROM books
+    INNER JOIN users ON users.id = books.author_id
+    CROSS JOIN websearch_to_tsquery('simple', $1) tsq
+    WHERE books.approved
+        AND (books.search_vector @@ tsq OR $1 <% books.name OR $1 <% users.name)
+        AND ($2::VARCHAR IS NULL OR books.lang = $2)
+        AND ($3::book_status IS NULL OR books.status = $3)
+        AND books.content_rating <= $4
+        AND ($8::INT[] IS NULL OR books.id IN (
+            SELECT book_id FROM book_tags
+            WHERE tag_id = ANY($8)
+            GROUP BY book_id
+            HAVING COUNT(*) = cardinality($8)
+        ))
+) results
+WHERE $5::REAL IS NULL OR (results.rank, results.id) < ($5, $6)
+ORDER BY results.rank DESC, results.id DESC
+LIMIT $7";
+
+#[derive(Deserialize)]
+pub struct SearchQuery {
+    pub q: String,
+    pub lang: Option<String>,
+    pub status: Option<BookStatus>,
+    pub max_rating: Option<ContentRating>,
+    /// A comma separated list of tags. Books must have all of them.
+    pub tags: Option<String>,
+    pub cursor: Option<String>,
+    pub limit: Option<i64>,
+}
+
+#[derive(QueryableByName, Serialize)]
+pub struct BookSearchResult {
+    #[diesel(embed)]
+    #[serde(flatten)]
+    pub book: Book,
+    #[diesel(sql_type = Float4)]
+    pub rank: f32,
+}
+
+/// The only order search results come in. Cursors hold the `(rank, id)` of
+/// the last result on a page.
+const SEARCH_SORT: &str = "rank";
+
+pub async fn search_books(
+    bearer: Option<TypedHeader<Authorization<Bearer>>>,
+    Query(query): Query<SearchQuery>,
+) -> Result<Json<Paged<BookSearchResult>>, (StatusCode, Json<ErrorResult>)> {
+    let token = bearer.and_then(|TypedHeader(auth)| TOKEN_ISSUER.validate_auth(auth.token()).ok());
+
+    let search = query.q.trim();
+
+    if search.is_empty() {
+        return Err(get_error_from_string(
+            StatusCode::BAD_REQUEST,
+            "A search query is required".into(),
+        )
+        .to_tuple());
+    }
+
+    let cursor: Option<(f32, i32)> = query
+        .cursor
+        .as_deref()
+        .map(|cursor| CURSOR_SIGNER.decode(SEARCH_SORT, cursor))
+        .transpose()
+        .map_err(|err| ErrorResult::from(err).to_tuple())?;
+
+    let limit = clamp_limit(query.limit, DEFAULT_SEARCH_LIMIT, MAX_SEARCH_LIMIT);
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let max_rating = get_max_content_rating(token.as_ref(), query.max_rating, connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let tag_names = query
+        .tags
+        .as_deref()
+        .map(parse_tag_list)
+        .filter(|tags| !tags.is_empty());
+
+    let tag_ids = match tag_names {
+        Some(tags) => {
+            let (tags, unknown) = resolve_tags(&tags, connection)
+                .await
+                .map_err(|err| get_internal_error(err).to_tuple())?;
+
+            // No book can have a tag that doesn't exist.
+            if !unknown.is_empty() {
+                return Ok(Json(Paged {
+                    items: vec![],
+                    next_cursor: None,
+                }));
+            }
+
+            Some(tags.into_iter().map(|tag| tag.id).collect::<Vec<i32>>())
+        }
+        None => None,
+    };
+
+    // Fetch one extra row to find out whether there is another page.
+    let books: Vec<BookSearchResult> = sql_query(SEARCH_BOOKS_SQL)
+        .bind::<Text, _>(search)
+        .bind::<Nullable<Varchar>, _>(query.lang)
+        .bind::<Nullable<BookStatusType>, _>(query.status)
+        .bind::<ContentRatingType, _>(max_rating)
+        .bind::<Nullable<Float4>, _>(cursor.map(|(rank, _)| rank))
+        .bind::<Nullable<Int4>, _>(cursor.map(|(_, id)| id))
+        .bind::<Int8, _>(limit + 1)
+        .bind::<Nullable<Array<Int4>>, _>(tag_ids)
+        .load(connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    Ok(Json(Paged::from_rows(books, limit, |last| {
+        CURSOR_SIGNER.encode(SEARCH_SORT, &(last.rank, last.book.id))
+    })))
+}
@@ -0,0 +1,407 @@
+use axum::{
+    extract::{Path, Query},
+    headers::{authorization::Bearer, Authorization},
+    http::StatusCode,
+    Json, TypedHeader,
+};
+use diesel::{delete, insert_into, prelude::*, result::Error as DbError};
+use diesel_async::{scoped_futures::ScopedFutureExt, AsyncConnection, RunQueryDsl};
+use gablet_shared_api::errors::{
+    get_error, get_error_from_string, get_internal_error, ErrorResult,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    models::{
+        tags::{NewBookTag, NewTag, NewTagAlias, Tag, TagAlias, TagKind},
+        users::UserLevel,
+    },
+    utils::{
+        auth::require_user_level,
+        books::{can_view_unapproved, find_book},
+        tags::{clean_tag_name, merge_tags, normalize_tag_name, resolve_tags},
+        tracking::send_tracked_book,
+    },
+    PG_POOL, TOKEN_ISSUER,
+};
+
+const MAX_BOOK_TAGS: usize = 20;
+
+#[derive(Deserialize)]
+pub struct TagQuery {
+    pub kind: Option<TagKind>,
+}
+
+#[derive(Deserialize)]
+pub struct CreateTagRequest {
+    pub name: String,
+    pub kind: TagKind,
+}
+
+#[derive(Deserialize)]
+pub struct TagAliasRequest {
+    pub alias: String,
+}
+
+#[derive(Deserialize)]
+pub struct MergeTagRequest {
+    pub into: i32,
+}
+
+#[derive(Deserialize)]
+pub struct BookTagsRequest {
+    pub tags: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct TagResult {
+    #[serde(flatten)]
+    pub tag: Tag,
+    pub aliases: Vec<String>,
+}
+
+/// Lists every canonical tag along with its aliases.
+pub async fn list_tags(
+    Query(query): Query<TagQuery>,
+) -> Result<Json<Vec<TagResult>>, (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::tags::dsl::{kind as db_kind, name as db_name, tags as db_tags};
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let mut tags_query = db_tags.into_boxed();
+
+    if let Some(kind) = query.kind {
+        tags_query = tags_query.filter(db_kind.eq(kind));
+    }
+
+    let tags: Vec<Tag> = tags_query
+        .order(db_name.asc())
+        .select(Tag::as_select())
+        .load(connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let aliases: Vec<TagAlias> = TagAlias::belonging_to(&tags)
+        .select(TagAlias::as_select())
+        .load(connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let results = aliases
+        .grouped_by(&tags)
+        .into_iter()
+        .zip(tags)
+        .map(|(aliases, tag)| TagResult {
+            tag,
+            aliases: aliases.into_iter().map(|alias| alias.alias).collect(),
+        })
+        .collect();
+
+    Ok(Json(results))
+}
+
+pub async fn create_tag(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Json(request): Json<CreateTagRequest>,
+) -> Result<(StatusCode, Json<Tag>), (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::tags::dsl::tags as db_tags;
+
+    let token = TOKEN_ISSUER
+        .validate_auth(auth.token())
+        .map_err(|err| get_error(err, StatusCode::UNAUTHORIZED).to_tuple())?;
+
+    require_user_level(&token, UserLevel::Mod).map_err(|err| err.to_tuple())?;
+
+    let name = clean_tag_name(&request.name);
+
+    if name.is_empty() || name.contains(',') {
+        return Err(get_error_from_string(
+            StatusCode::BAD_REQUEST,
+            "Tag names can't be empty or contain commas".into(),
+        )
+        .to_tuple());
+    }
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let (existing, _) = resolve_tags(std::slice::from_ref(&name), connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    if let Some(tag) = existing.into_iter().next() {
+        return Err(get_error_from_string(
+            StatusCode::CONFLICT,
+            format!(
+                "{} is already a name or alias of the tag {}",
+                name, tag.name
+            ),
+        )
+        .to_tuple());
+    }
+
+    let tag = insert_into(db_tags)
+        .values(NewTag {
+            name,
+            kind: request.kind,
+        })
+        .returning(Tag::as_returning())
+        .get_result(connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    Ok((StatusCode::CREATED, Json(tag)))
+}
+
+pub async fn add_tag_alias(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Path(tag_id): Path<i32>,
+    Json(request): Json<TagAliasRequest>,
+) -> Result<(StatusCode, Json<TagAlias>), (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::tag_aliases::dsl::tag_aliases as db_tag_aliases;
+    use crate::schema::tags::dsl::tags as db_tags;
+
+    let token = TOKEN_ISSUER
+        .validate_auth(auth.token())
+        .map_err(|err| get_error(err, StatusCode::UNAUTHORIZED).to_tuple())?;
+
+    require_user_level(&token, UserLevel::Mod).map_err(|err| err.to_tuple())?;
+
+    let alias = normalize_tag_name(&request.alias);
+
+    if alias.is_empty() || alias.contains(',') {
+        return Err(get_error_from_string(
+            StatusCode::BAD_REQUEST,
+            "Tag aliases can't be empty or contain commas".into(),
+        )
+        .to_tuple());
+    }
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    db_tags
+        .find(tag_id)
+        .select(Tag::as_select())
+        .first(connection)
+        .await
+        .optional()
+        .map_err(|err| get_internal_error(err).to_tuple())?
+        .ok_or_else(|| {
+            get_error_from_string(StatusCode::NOT_FOUND, format!("No tag {} exists", tag_id))
+                .to_tuple()
+        })?;
+
+    let (existing, _) = resolve_tags(std::slice::from_ref(&alias), connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    if let Some(tag) = existing.into_iter().next() {
+        return Err(get_error_from_string(
+            StatusCode::CONFLICT,
+            format!(
+                "{} is already a name or alias of the tag {}",
+                alias, tag.name
+            ),
+        )
+        .to_tuple());
+    }
+
+    let alias = insert_into(db_tag_aliases)
+        .values(NewTagAlias { tag_id, alias })
+        .returning(TagAlias::as_returning())
+        .get_result(connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    Ok((StatusCode::CREATED, Json(alias)))
+}
+
+/// Merges a tag into another one. The merged tag is removed and its name is
+/// kept as an alias, so books and searches using it end up on the new tag.
+pub async fn merge_tag(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Path(tag_id): Path<i32>,
+    Json(request): Json<MergeTagRequest>,
+) -> Result<Json<Tag>, (StatusCode, Json<ErrorResult>)> {
+    let token = TOKEN_ISSUER
+        .validate_auth(auth.token())
+        .map_err(|err| get_error(err, StatusCode::UNAUTHORIZED).to_tuple())?;
+
+    require_user_level(&token, UserLevel::Mod).map_err(|err| err.to_tuple())?;
+
+    if tag_id == request.into {
+        return Err(get_error_from_string(
+            StatusCode::BAD_REQUEST,
+            "Can't merge a tag into itself".into(),
+        )
+        .to_tuple());
+    }
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let (tag, book_ids) = merge_tags(tag_id, request.into, connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?
+        .ok_or_else(|| {
+            get_error_from_string(
+                StatusCode::NOT_FOUND,
+                format!("Tags {} and {} must both exist", tag_id, request.into),
+            )
+            .to_tuple()
+        })?;
+
+    for book_id in book_ids {
+        send_tracked_book(book_id, connection).await;
+    }
+
+    Ok(Json(tag))
+}
+
+pub async fn get_book_tags(
+    bearer: Option<TypedHeader<Authorization<Bearer>>>,
+    Path(book_id): Path<i32>,
+) -> Result<Json<Vec<Tag>>, (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::book_tags::dsl::{book_id as db_book_id, book_tags as db_book_tags};
+    use crate::schema::tags::dsl::{name as db_name, tags as db_tags};
+
+    let token = bearer.and_then(|TypedHeader(auth)| TOKEN_ISSUER.validate_auth(auth.token()).ok());
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    find_book(book_id, connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?
+        .filter(|book| book.approved || can_view_unapproved(book, token.as_ref()))
+        .ok_or_else(|| {
+            get_error_from_string(StatusCode::NOT_FOUND, format!("No book {} exists", book_id))
+                .to_tuple()
+        })?;
+
+    let tags = db_book_tags
+        .inner_join(db_tags)
+        .filter(db_book_id.eq(book_id))
+        .order(db_name.asc())
+        .select(Tag::as_select())
+        .load(connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    Ok(Json(tags))
+}
+
+/// Replaces the tags of a book. Tags can be given by name or by alias, but
+/// only existing tags can be used. Only the author of the book can tag it.
+pub async fn set_book_tags(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Path(book_id): Path<i32>,
+    Json(request): Json<BookTagsRequest>,
+) -> Result<Json<Vec<Tag>>, (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::book_tags::dsl::{book_id as db_book_id, book_tags as db_book_tags};
+
+    let token = TOKEN_ISSUER
+        .validate_auth(auth.token())
+        .map_err(|err| get_error(err, StatusCode::UNAUTHORIZED).to_tuple())?;
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let book = find_book(book_id, connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?
+        .ok_or_else(|| {
+            get_error_from_string(StatusCode::NOT_FOUND, format!("No book {} exists", book_id))
+                .to_tuple()
+        })?;
+
+    if book.author_id != token.user_id() {
+        return Err(get_error_from_string(
+            StatusCode::FORBIDDEN,
+            format!("Only the author of {} can change its tags", book.name),
+        )
+        .to_tuple());
+    }
+
+    let (mut tags, unknown) = resolve_tags(&request.tags, connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    if !unknown.is_empty() {
+        return Err(get_error_from_string(
+            StatusCode::BAD_REQUEST,
+            format!("Unknown tags: {}", unknown.join(", ")),
+        )
+        .to_tuple());
+    }
+
+    if tags.len() > MAX_BOOK_TAGS {
+        return Err(get_error_from_string(
+            StatusCode::BAD_REQUEST,
+            format!("A book can have at most {} tags", MAX_BOOK_TAGS),
+        )
+        .to_tuple());
+    }
+
+    let new_tags: Vec<NewBookTag> = tags
+        .iter()
+        .map(|tag| NewBookTag {
+            book_id,
+            tag_id: tag.id,
+        })
+        .collect();
+
+    connection
+        .transaction::<_, DbError, _>(|connection| {
+            async move {
+                delete(db_book_tags)
+                    .filter(db_book_id.eq(book_id))
+                    .execute(connection)
+                    .await?;
+
+                if !new_tags.is_empty() {
+                    insert_into(db_book_tags)
+                        .values(new_tags)
+                        .execute(connection)
+                        .await?;
+                }
+
+                Ok(())
+            }
+            .scope_boxed()
+        })
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    send_tracked_book(book_id, connection).await;
+
+    tags.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(Json(tags))
+}
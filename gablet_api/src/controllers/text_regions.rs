@@ -0,0 +1,765 @@
+use std::collections::HashMap;
+
+use axum::{
+    extract::{Path, Query},
+    headers::{authorization::Bearer, Authorization},
+    http::StatusCode,
+    Json, TypedHeader,
+};
+use diesel::{
+    delete, dsl::now, insert_into, pg::upsert::excluded, prelude::*, result::Error as DbError,
+    update,
+};
+use diesel_async::{
+    scoped_futures::ScopedFutureExt, AsyncConnection, AsyncPgConnection, RunQueryDsl,
+};
+use gablet_shared_api::errors::{
+    get_error, get_error_from_string, get_internal_error, ErrorResult,
+};
+use gablet_tokens::AuthToken;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    models::{
+        chapters::Chapter,
+        text_regions::{
+            NewTextRegion, NewTextRegionTranslation, NewTranslationRevision, TextRegion,
+            TextRegionChanges, TextRegionTranslation, TranslationRevision,
+        },
+    },
+    utils::{
+        books::{can_view_chapter, find_book},
+        diff::{diff_words, DiffChunk},
+        glossary::{find_glossary_matches, find_region_book_id, load_glossary, GlossaryMatch},
+        lang::validate_lang,
+    },
+    PG_POOL, TOKEN_ISSUER,
+};
+
+#[derive(Deserialize)]
+pub struct RegionQuery {
+    pub lang: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct TextRegionRequest {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    pub polygon: Option<Vec<f32>>,
+    #[serde(default)]
+    pub source_text: String,
+    pub source_lang: String,
+}
+
+#[derive(Deserialize)]
+pub struct TranslationRequest {
+    pub text: String,
+}
+
+#[derive(Deserialize)]
+pub struct DiffQuery {
+    pub from: i32,
+    pub to: Option<i32>,
+}
+
+#[derive(Serialize)]
+pub struct RevisionResult {
+    #[serde(flatten)]
+    pub revision: TranslationRevision,
+    pub author: String,
+}
+
+/// An edited region along with the glossary terms found in its source text.
+#[derive(Serialize)]
+pub struct RegionEditResult {
+    #[serde(flatten)]
+    pub region: TextRegion,
+    pub glossary_matches: Vec<GlossaryMatch>,
+}
+
+#[derive(Serialize)]
+pub struct TranslationEditResult {
+    #[serde(flatten)]
+    pub translation: TextRegionTranslation,
+    pub glossary_matches: Vec<GlossaryMatch>,
+}
+
+#[derive(Serialize)]
+pub struct TextRegionResult {
+    #[serde(flatten)]
+    pub region: TextRegion,
+    pub translations: Vec<TextRegionTranslation>,
+}
+
+/// Checks that a region lies on the page and that its polygon, if any, is a
+/// list of at least three `x, y` pairs that also lie on the page.
+fn validate_region(request: &TextRegionRequest) -> Result<(), ErrorResult> {
+    let on_page = |value: f32| (0.0..=1.0).contains(&value);
+
+    if !on_page(request.x)
+        || !on_page(request.y)
+        || request.width <= 0.0
+        || request.height <= 0.0
+        || !on_page(request.x + request.width)
+        || !on_page(request.y + request.height)
+    {
+        return Err(get_error_from_string(
+            StatusCode::BAD_REQUEST,
+            "Region bounds must be fractions of the page between 0 and 1".into(),
+        ));
+    }
+
+    if let Some(polygon) = &request.polygon {
+        if polygon.len() < 6 || polygon.len() % 2 != 0 {
+            return Err(get_error_from_string(
+                StatusCode::BAD_REQUEST,
+                "Region polygon must contain at least three x, y pairs".into(),
+            ));
+        }
+
+        if !polygon.iter().all(|value| on_page(*value)) {
+            return Err(get_error_from_string(
+                StatusCode::BAD_REQUEST,
+                "Region polygon points must be fractions of the page between 0 and 1".into(),
+            ));
+        }
+    }
+
+    validate_lang(&request.source_lang)
+}
+
+/// Finds the glossary terms of the region's book in its source text, either
+/// for one target language or for all of them.
+async fn find_region_glossary_matches(
+    region_id: i32,
+    source_text: &str,
+    lang: Option<&str>,
+    connection: &mut AsyncPgConnection,
+) -> Result<Vec<GlossaryMatch>, DbError> {
+    let Some(book_id) = find_region_book_id(region_id, connection).await? else {
+        return Ok(Vec::new());
+    };
+
+    let terms = load_glossary(book_id, lang, connection).await?;
+
+    Ok(find_glossary_matches(source_text, &terms))
+}
+
+/// Fails with not found unless the chapter can be read with `token`, so
+/// chapters that aren't out yet look the same as ones that don't exist.
+async fn check_chapter_visible(
+    chapter: Option<Chapter>,
+    token: Option<&AuthToken>,
+    not_found: String,
+    connection: &mut AsyncPgConnection,
+) -> Result<(), (StatusCode, Json<ErrorResult>)> {
+    let book = match &chapter {
+        Some(chapter) => find_book(chapter.book_id, connection)
+            .await
+            .map_err(|err| get_internal_error(err).to_tuple())?,
+        None => None,
+    };
+
+    match (&chapter, &book) {
+        (Some(chapter), Some(book)) if can_view_chapter(book, chapter, token) => Ok(()),
+        _ => Err(get_error_from_string(StatusCode::NOT_FOUND, not_found).to_tuple()),
+    }
+}
+
+/// Fails with not found unless the chapter of the page can be read.
+async fn check_page_visible(
+    page_id: i32,
+    token: Option<&AuthToken>,
+    connection: &mut AsyncPgConnection,
+) -> Result<(), (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::chapters::table as db_chapters;
+    use crate::schema::pages::dsl::pages as db_pages;
+
+    let chapter: Option<Chapter> = db_pages
+        .find(page_id)
+        .inner_join(db_chapters)
+        .select(Chapter::as_select())
+        .first(connection)
+        .await
+        .optional()
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    check_chapter_visible(
+        chapter,
+        token,
+        format!("No page {} exists", page_id),
+        connection,
+    )
+    .await
+}
+
+/// Fails with not found unless the chapter of the region can be read.
+async fn check_region_visible(
+    region_id: i32,
+    token: Option<&AuthToken>,
+    connection: &mut AsyncPgConnection,
+) -> Result<(), (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::chapters::table as db_chapters;
+    use crate::schema::pages::table as db_pages;
+    use crate::schema::text_regions::dsl::text_regions as db_text_regions;
+
+    let chapter: Option<Chapter> = db_text_regions
+        .find(region_id)
+        .inner_join(db_pages.inner_join(db_chapters))
+        .select(Chapter::as_select())
+        .first(connection)
+        .await
+        .optional()
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    check_chapter_visible(
+        chapter,
+        token,
+        format!("No text region {} exists", region_id),
+        connection,
+    )
+    .await
+}
+
+/// Lists the regions of a page. Pages of chapters that readers can't see yet
+/// are only shown to the people who can see the unapproved parts of the book.
+pub async fn get_page_regions(
+    bearer: Option<TypedHeader<Authorization<Bearer>>>,
+    Path(page_id): Path<i32>,
+    Query(query): Query<RegionQuery>,
+) -> Result<Json<Vec<TextRegionResult>>, (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::text_region_translations::dsl as translations_dsl;
+    use crate::schema::text_regions::dsl as regions_dsl;
+
+    let token = bearer.and_then(|TypedHeader(auth)| TOKEN_ISSUER.validate_auth(auth.token()).ok());
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    check_page_visible(page_id, token.as_ref(), connection).await?;
+
+    let regions: Vec<TextRegion> = regions_dsl::text_regions
+        .filter(regions_dsl::page_id.eq(page_id))
+        .order(regions_dsl::id.asc())
+        .select(TextRegion::as_select())
+        .load(connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let region_ids: Vec<i32> = regions.iter().map(|region| region.id).collect();
+
+    let mut translation_query = translations_dsl::text_region_translations
+        .filter(translations_dsl::region_id.eq_any(region_ids))
+        .into_boxed();
+
+    if let Some(lang) = query.lang {
+        translation_query = translation_query.filter(translations_dsl::lang.eq(lang));
+    }
+
+    let translations: Vec<TextRegionTranslation> = translation_query
+        .select(TextRegionTranslation::as_select())
+        .load(connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let mut translations_by_region: HashMap<i32, Vec<TextRegionTranslation>> = HashMap::new();
+    for translation in translations {
+        translations_by_region
+            .entry(translation.region_id)
+            .or_default()
+            .push(translation);
+    }
+
+    Ok(Json(
+        regions
+            .into_iter()
+            .map(|region| TextRegionResult {
+                translations: translations_by_region
+                    .remove(&region.id)
+                    .unwrap_or_default(),
+                region,
+            })
+            .collect(),
+    ))
+}
+
+/// Adds a region to a page. Glossary matches in the source text are returned
+/// for the `lang` query parameter, or for every language if it's left out.
+pub async fn create_region(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Path(page_id): Path<i32>,
+    Query(query): Query<RegionQuery>,
+    Json(request): Json<TextRegionRequest>,
+) -> Result<(StatusCode, Json<RegionEditResult>), (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::text_regions::dsl::text_regions as db_text_regions;
+
+    let token = TOKEN_ISSUER
+        .validate_auth(auth.token())
+        .map_err(|err| get_error(err, StatusCode::UNAUTHORIZED).to_tuple())?;
+
+    validate_region(&request).map_err(|err| err.to_tuple())?;
+
+    if let Some(lang) = &query.lang {
+        validate_lang(lang).map_err(|err| err.to_tuple())?;
+    }
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    check_page_visible(page_id, Some(&token), connection).await?;
+
+    let region = NewTextRegion {
+        page_id,
+        x: request.x,
+        y: request.y,
+        width: request.width,
+        height: request.height,
+        polygon: request.polygon,
+        source_text: request.source_text,
+        source_lang: request.source_lang,
+        created_by: token.user_id(),
+    };
+
+    let region = insert_into(db_text_regions)
+        .values(region)
+        .returning(TextRegion::as_returning())
+        .get_result(connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let glossary_matches = find_region_glossary_matches(
+        region.id,
+        &region.source_text,
+        query.lang.as_deref(),
+        connection,
+    )
+    .await
+    .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(RegionEditResult {
+            region,
+            glossary_matches,
+        }),
+    ))
+}
+
+/// Replaces a region. Only the user who created a region can change it, the
+/// same as deleting it. Glossary matches are returned the same way as for
+/// `create_region`.
+pub async fn update_region(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Path(region_id): Path<i32>,
+    Query(query): Query<RegionQuery>,
+    Json(request): Json<TextRegionRequest>,
+) -> Result<Json<RegionEditResult>, (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::text_regions::dsl::{
+        created_by as db_created_by, id as db_region_id, text_regions as db_text_regions,
+        updated as db_updated,
+    };
+
+    let token = TOKEN_ISSUER
+        .validate_auth(auth.token())
+        .map_err(|err| get_error(err, StatusCode::UNAUTHORIZED).to_tuple())?;
+
+    validate_region(&request).map_err(|err| err.to_tuple())?;
+
+    if let Some(lang) = &query.lang {
+        validate_lang(lang).map_err(|err| err.to_tuple())?;
+    }
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let changes = TextRegionChanges {
+        x: request.x,
+        y: request.y,
+        width: request.width,
+        height: request.height,
+        polygon: request.polygon,
+        source_text: request.source_text,
+        source_lang: request.source_lang,
+    };
+
+    let region = update(
+        db_text_regions
+            .filter(db_region_id.eq(region_id))
+            .filter(db_created_by.eq(token.user_id())),
+    )
+    .set((&changes, db_updated.eq(now)))
+    .returning(TextRegion::as_returning())
+    .get_result(connection)
+    .await
+    .optional()
+    .map_err(|err| get_internal_error(err).to_tuple())?
+    .ok_or_else(|| {
+        get_error_from_string(
+            StatusCode::NOT_FOUND,
+            format!(
+                "No text region {} created by {}",
+                region_id,
+                token.username()
+            ),
+        )
+        .to_tuple()
+    })?;
+
+    let glossary_matches = find_region_glossary_matches(
+        region.id,
+        &region.source_text,
+        query.lang.as_deref(),
+        connection,
+    )
+    .await
+    .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    Ok(Json(RegionEditResult {
+        region,
+        glossary_matches,
+    }))
+}
+
+pub async fn delete_region(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Path(region_id): Path<i32>,
+) -> Result<StatusCode, (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::text_regions::dsl::{
+        created_by as db_created_by, id as db_region_id, text_regions as db_text_regions,
+    };
+
+    let token = TOKEN_ISSUER
+        .validate_auth(auth.token())
+        .map_err(|err| get_error(err, StatusCode::UNAUTHORIZED).to_tuple())?;
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let deleted = delete(db_text_regions)
+        .filter(db_region_id.eq(region_id))
+        .filter(db_created_by.eq(token.user_id()))
+        .execute(connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    if deleted == 0 {
+        return Err(get_error_from_string(
+            StatusCode::NOT_FOUND,
+            format!(
+                "No text region {} created by {}",
+                region_id,
+                token.username()
+            ),
+        )
+        .to_tuple());
+    }
+
+    Ok(StatusCode::OK)
+}
+
+/// Saves the translation of a region and records it as a new revision. Saving
+/// the same text again doesn't add a revision.
+async fn save_translation(
+    translation: NewTextRegionTranslation,
+    connection: &mut AsyncPgConnection,
+) -> Result<TextRegionTranslation, DbError> {
+    use crate::schema::text_region_translations::dsl::{
+        lang as db_lang, region_id as db_region_id, text as db_text,
+        text_region_translations as db_translations, translator_id as db_translator_id,
+        updated as db_updated,
+    };
+    use crate::schema::translation_revisions::dsl::translation_revisions as db_revisions;
+
+    connection
+        .transaction(|connection| {
+            async move {
+                let previous: Option<String> = db_translations
+                    .filter(db_region_id.eq(translation.region_id))
+                    .filter(db_lang.eq(&translation.lang))
+                    .select(db_text)
+                    .for_update()
+                    .first(connection)
+                    .await
+                    .optional()?;
+
+                if previous.as_ref() == Some(&translation.text) {
+                    return db_translations
+                        .filter(db_region_id.eq(translation.region_id))
+                        .filter(db_lang.eq(&translation.lang))
+                        .select(TextRegionTranslation::as_select())
+                        .first(connection)
+                        .await;
+                }
+
+                let saved: TextRegionTranslation = insert_into(db_translations)
+                    .values(&translation)
+                    .on_conflict((db_region_id, db_lang))
+                    .do_update()
+                    .set((
+                        db_text.eq(excluded(db_text)),
+                        db_translator_id.eq(excluded(db_translator_id)),
+                        db_updated.eq(now),
+                    ))
+                    .returning(TextRegionTranslation::as_returning())
+                    .get_result(connection)
+                    .await?;
+
+                insert_into(db_revisions)
+                    .values(NewTranslationRevision {
+                        translation_id: saved.id,
+                        text: saved.text.clone(),
+                        author_id: saved.translator_id,
+                    })
+                    .execute(connection)
+                    .await?;
+
+                Ok(saved)
+            }
+            .scope_boxed()
+        })
+        .await
+}
+
+/// Finds the translation of a region into a language.
+async fn find_translation(
+    region_id: i32,
+    lang: &str,
+    connection: &mut AsyncPgConnection,
+) -> Result<TextRegionTranslation, (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::text_region_translations::dsl::{
+        lang as db_lang, region_id as db_region_id, text_region_translations as db_translations,
+    };
+
+    db_translations
+        .filter(db_region_id.eq(region_id))
+        .filter(db_lang.eq(lang))
+        .select(TextRegionTranslation::as_select())
+        .first(connection)
+        .await
+        .optional()
+        .map_err(|err| get_internal_error(err).to_tuple())?
+        .ok_or_else(|| {
+            get_error_from_string(
+                StatusCode::NOT_FOUND,
+                format!("Text region {} has no {} translation", region_id, lang),
+            )
+            .to_tuple()
+        })
+}
+
+/// Finds a revision of the given translation.
+async fn find_revision(
+    translation_id: i32,
+    revision_id: i32,
+    connection: &mut AsyncPgConnection,
+) -> Result<TranslationRevision, (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::translation_revisions::dsl::{
+        translation_id as db_translation_id, translation_revisions as db_revisions,
+    };
+
+    db_revisions
+        .find(revision_id)
+        .filter(db_translation_id.eq(translation_id))
+        .select(TranslationRevision::as_select())
+        .first(connection)
+        .await
+        .optional()
+        .map_err(|err| get_internal_error(err).to_tuple())?
+        .ok_or_else(|| {
+            get_error_from_string(
+                StatusCode::NOT_FOUND,
+                format!("No revision {} of this translation exists", revision_id),
+            )
+            .to_tuple()
+        })
+}
+
+/// Saves the translation of a region. The glossary terms for the language
+/// found in the region's source text are returned with it.
+pub async fn set_region_translation(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Path((region_id, lang)): Path<(i32, String)>,
+    Json(request): Json<TranslationRequest>,
+) -> Result<Json<TranslationEditResult>, (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::text_regions::dsl::{
+        id as db_text_region_id, source_text as db_source_text, text_regions as db_text_regions,
+    };
+
+    let token = TOKEN_ISSUER
+        .validate_auth(auth.token())
+        .map_err(|err| get_error(err, StatusCode::UNAUTHORIZED).to_tuple())?;
+
+    validate_lang(&lang).map_err(|err| err.to_tuple())?;
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    check_region_visible(region_id, Some(&token), connection).await?;
+
+    let source_text: String = db_text_regions
+        .filter(db_text_region_id.eq(region_id))
+        .select(db_source_text)
+        .first(connection)
+        .await
+        .optional()
+        .map_err(|err| get_internal_error(err).to_tuple())?
+        .ok_or_else(|| {
+            get_error_from_string(
+                StatusCode::NOT_FOUND,
+                format!("No text region {} exists", region_id),
+            )
+            .to_tuple()
+        })?;
+
+    let translation = NewTextRegionTranslation {
+        region_id,
+        lang,
+        text: request.text,
+        translator_id: token.user_id(),
+    };
+
+    let translation = save_translation(translation, connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let glossary_matches =
+        find_region_glossary_matches(region_id, &source_text, Some(&translation.lang), connection)
+            .await
+            .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    Ok(Json(TranslationEditResult {
+        translation,
+        glossary_matches,
+    }))
+}
+
+/// Lists the revisions of a translation, newest first. Translations in
+/// chapters that readers can't see yet are only shown to the people who can
+/// see the unapproved parts of the book.
+pub async fn list_translation_revisions(
+    bearer: Option<TypedHeader<Authorization<Bearer>>>,
+    Path((region_id, lang)): Path<(i32, String)>,
+) -> Result<Json<Vec<RevisionResult>>, (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::translation_revisions::dsl::{
+        id as db_id, translation_id as db_translation_id, translation_revisions as db_revisions,
+    };
+    use crate::schema::users::dsl::{username as db_username, users as db_users};
+
+    let token = bearer.and_then(|TypedHeader(auth)| TOKEN_ISSUER.validate_auth(auth.token()).ok());
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    check_region_visible(region_id, token.as_ref(), connection).await?;
+
+    let translation = find_translation(region_id, &lang, connection).await?;
+
+    let revisions = db_revisions
+        .inner_join(db_users)
+        .filter(db_translation_id.eq(translation.id))
+        .order(db_id.desc())
+        .select((TranslationRevision::as_select(), db_username))
+        .load::<(TranslationRevision, String)>(connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?
+        .into_iter()
+        .map(|(revision, author)| RevisionResult { revision, author })
+        .collect();
+
+    Ok(Json(revisions))
+}
+
+/// Shows a word level diff between two revisions of a translation. The diff
+/// is against the current text if `to` is left out. Only shown for chapters
+/// that can be read, the same as `list_translation_revisions`.
+pub async fn diff_translation_revisions(
+    bearer: Option<TypedHeader<Authorization<Bearer>>>,
+    Path((region_id, lang)): Path<(i32, String)>,
+    Query(query): Query<DiffQuery>,
+) -> Result<Json<Vec<DiffChunk>>, (StatusCode, Json<ErrorResult>)> {
+    let token = bearer.and_then(|TypedHeader(auth)| TOKEN_ISSUER.validate_auth(auth.token()).ok());
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    check_region_visible(region_id, token.as_ref(), connection).await?;
+
+    let translation = find_translation(region_id, &lang, connection).await?;
+
+    let from = find_revision(translation.id, query.from, connection).await?;
+
+    let to = match query.to {
+        Some(to) => find_revision(translation.id, to, connection).await?.text,
+        None => translation.text,
+    };
+
+    Ok(Json(diff_words(&from.text, &to)))
+}
+
+/// Restores the text of an earlier revision. The restored text is saved as a
+/// new revision, so the revisions after it stay in the history.
+pub async fn revert_translation(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Path((region_id, lang, revision_id)): Path<(i32, String, i32)>,
+) -> Result<Json<TextRegionTranslation>, (StatusCode, Json<ErrorResult>)> {
+    let token = TOKEN_ISSUER
+        .validate_auth(auth.token())
+        .map_err(|err| get_error(err, StatusCode::UNAUTHORIZED).to_tuple())?;
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    check_region_visible(region_id, Some(&token), connection).await?;
+
+    let translation = find_translation(region_id, &lang, connection).await?;
+
+    let revision = find_revision(translation.id, revision_id, connection).await?;
+
+    let translation = save_translation(
+        NewTextRegionTranslation {
+            region_id,
+            lang,
+            text: revision.text,
+            translator_id: token.user_id(),
+        },
+        connection,
+    )
+    .await
+    .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    Ok(Json(translation))
+}
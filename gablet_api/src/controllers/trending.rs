@@ -0,0 +1,88 @@
+use std::collections::HashMap;
+
+use axum::{
+    extract::Query,
+    headers::{authorization::Bearer, Authorization},
+    http::StatusCode,
+    Json, TypedHeader,
+};
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use gablet_shared_api::{
+    errors::{get_error_from_string, get_internal_error, ErrorResult},
+    trending::TrendingQuery,
+};
+use serde::Serialize;
+
+use crate::{
+    models::books::Book,
+    utils::{books::get_max_content_rating, tracking::fetch_trending},
+    PG_POOL, TOKEN_ISSUER,
+};
+
+#[derive(Serialize)]
+pub struct TrendingResult {
+    pub book: Book,
+    /// Roughly how many different visitors viewed the book recently, with
+    /// older views counting for less.
+    pub score: f64,
+}
+
+/// The books that are trending, most first, as scored by gablet_tracking.
+/// Books rated above what the current user allows are left out, so fewer
+/// than `limit` books can be returned.
+pub async fn get_trending(
+    bearer: Option<TypedHeader<Authorization<Bearer>>>,
+    Query(query): Query<TrendingQuery>,
+) -> Result<Json<Vec<TrendingResult>>, (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::books::dsl::{
+        approved as db_approved, books as db_books, content_rating as db_content_rating,
+        id as db_id,
+    };
+
+    let token = bearer.and_then(|TypedHeader(auth)| TOKEN_ISSUER.validate_auth(auth.token()).ok());
+
+    let trending = fetch_trending(&query).await.map_err(|err| {
+        tracing::error!("Failed to get trending books: {}", err);
+        get_error_from_string(
+            StatusCode::BAD_GATEWAY,
+            "Trending books aren't available right now".into(),
+        )
+        .to_tuple()
+    })?;
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let max_rating = get_max_content_rating(token.as_ref(), None, connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let mut books: HashMap<i32, Book> = db_books
+        .filter(db_id.eq_any(trending.iter().map(|book| book.book_id)))
+        .filter(db_approved.eq(true))
+        .filter(db_content_rating.le(max_rating))
+        .select(Book::as_select())
+        .load::<Book>(connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?
+        .into_iter()
+        .map(|book| (book.id, book))
+        .collect();
+
+    let results = trending
+        .into_iter()
+        .filter_map(|trending| {
+            books.remove(&trending.book_id).map(|book| TrendingResult {
+                book,
+                score: trending.score,
+            })
+        })
+        .collect();
+
+    Ok(Json(results))
+}
@@ -0,0 +1,809 @@
+use std::collections::BTreeMap;
+
+use axum::{
+    extract::{Path, Query},
+    headers::{authorization::Bearer, Authorization},
+    http::StatusCode,
+    Json, TypedHeader,
+};
+use chrono::{NaiveDateTime, Utc};
+use diesel::{delete, insert_into, prelude::*, result::Error as DbError};
+use diesel_async::{
+    scoped_futures::ScopedFutureExt, AsyncConnection, AsyncPgConnection, RunQueryDsl,
+};
+use gablet_shared_api::errors::{
+    get_error, get_error_from_string, get_internal_error, ErrorResult,
+};
+use gablet_tokens::AuthToken;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    models::{
+        books::Book,
+        chapters::Chapter,
+        workflows::{
+            ChapterWorkflow, NewChapterWorkflow, NewWorkflowEvent, WorkflowAction, WorkflowEvent,
+            WorkflowMember, WorkflowStage,
+        },
+    },
+    utils::{
+        books::find_book,
+        errors::map_insert_error,
+        lang::validate_lang,
+        workflows::{
+            apply_transition, load_workflow_actor, WorkflowActor, WorkflowError, WorkflowTransition,
+        },
+        tracking::send_tracked_book,
+    },
+    PG_POOL, TOKEN_ISSUER,
+};
+
+const DEFAULT_ACTIVITY_LIMIT: i64 = 50;
+const MAX_ACTIVITY_LIMIT: i64 = 200;
+const MAX_NOTE_LENGTH: usize = 1000;
+const MAX_WORKFLOW_MEMBERS: usize = 100;
+
+#[derive(Deserialize)]
+pub struct WorkflowListQuery {
+    pub stage: Option<WorkflowStage>,
+}
+
+#[derive(Deserialize)]
+pub struct ActivityQuery {
+    pub chapter_id: Option<i32>,
+    pub before: Option<i32>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Deserialize)]
+pub struct StartWorkflowRequest {
+    pub due: Option<NaiveDateTime>,
+}
+
+#[derive(Deserialize, Default)]
+pub struct TransitionRequest {
+    pub note: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ReturnRequest {
+    pub stage: WorkflowStage,
+    pub note: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct DeadlineRequest {
+    /// Leave out to remove the deadline.
+    pub due: Option<NaiveDateTime>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct WorkflowMemberEntry {
+    pub username: String,
+    pub stages: Vec<WorkflowStage>,
+}
+
+#[derive(Serialize)]
+pub struct WorkflowResult {
+    #[serde(flatten)]
+    pub workflow: ChapterWorkflow,
+    pub chapter_number: i32,
+    pub claimed_by_name: Option<String>,
+    pub overdue: bool,
+}
+
+#[derive(Serialize)]
+pub struct WorkflowEventResult {
+    #[serde(flatten)]
+    pub event: WorkflowEvent,
+    pub chapter_id: i32,
+    pub username: String,
+}
+
+fn workflow_error(err: WorkflowError) -> (StatusCode, Json<ErrorResult>) {
+    match err {
+        WorkflowError::Db(err) => get_internal_error(err).to_tuple(),
+        err @ (WorkflowError::NotMember(_)
+        | WorkflowError::ClaimedBySomeoneElse(_)
+        | WorkflowError::ManagerOnly) => get_error(err, StatusCode::FORBIDDEN).to_tuple(),
+        err => get_error(err, StatusCode::CONFLICT).to_tuple(),
+    }
+}
+
+fn clean_note(note: Option<String>) -> Result<Option<String>, (StatusCode, Json<ErrorResult>)> {
+    let note = note
+        .map(|note| note.trim().to_string())
+        .filter(|note| !note.is_empty());
+
+    if note
+        .as_ref()
+        .is_some_and(|note| note.chars().count() > MAX_NOTE_LENGTH)
+    {
+        return Err(get_error_from_string(
+            StatusCode::BAD_REQUEST,
+            format!("Notes can't be longer than {} characters", MAX_NOTE_LENGTH),
+        )
+        .to_tuple());
+    }
+
+    Ok(note)
+}
+
+fn validate_due(due: Option<NaiveDateTime>) -> Result<(), (StatusCode, Json<ErrorResult>)> {
+    if due.is_some_and(|due| due <= Utc::now().naive_utc()) {
+        return Err(get_error_from_string(
+            StatusCode::BAD_REQUEST,
+            "Deadlines must be in the future".into(),
+        )
+        .to_tuple());
+    }
+
+    Ok(())
+}
+
+fn is_overdue(workflow: &ChapterWorkflow) -> bool {
+    workflow.stage != WorkflowStage::Complete
+        && workflow.due.is_some_and(|due| due < Utc::now().naive_utc())
+}
+
+fn validate_token(
+    auth: &Authorization<Bearer>,
+) -> Result<AuthToken, (StatusCode, Json<ErrorResult>)> {
+    TOKEN_ISSUER
+        .validate_auth(auth.token())
+        .map_err(|err| get_error(err, StatusCode::UNAUTHORIZED).to_tuple())
+}
+
+fn book_not_found(book_id: i32) -> (StatusCode, Json<ErrorResult>) {
+    get_error_from_string(StatusCode::NOT_FOUND, format!("No book {} exists", book_id)).to_tuple()
+}
+
+/// Finds a book and works out what the owner of the token can do in its
+/// workflows for the language. Only the author and members of the workflow
+/// can see it.
+async fn find_workflow_book(
+    book_id: i32,
+    lang: &str,
+    token: &AuthToken,
+    connection: &mut AsyncPgConnection,
+) -> Result<(Book, WorkflowActor), (StatusCode, Json<ErrorResult>)> {
+    let book = find_book(book_id, connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?
+        .ok_or_else(|| book_not_found(book_id))?;
+
+    let actor = load_workflow_actor(book.author_id, book.id, lang, token.user_id(), connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    if !actor.is_member() {
+        return Err(get_error_from_string(
+            StatusCode::FORBIDDEN,
+            format!("You aren't a member of the {} team of {}", lang, book.name),
+        )
+        .to_tuple());
+    }
+
+    Ok((book, actor))
+}
+
+/// Finds a chapter whether or not it has been approved or published.
+async fn find_workflow_chapter(
+    chapter_id: i32,
+    connection: &mut AsyncPgConnection,
+) -> Result<Chapter, (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::chapters::dsl::chapters as db_chapters;
+
+    db_chapters
+        .find(chapter_id)
+        .select(Chapter::as_select())
+        .first(connection)
+        .await
+        .optional()
+        .map_err(|err| get_internal_error(err).to_tuple())?
+        .ok_or_else(|| {
+            get_error_from_string(
+                StatusCode::NOT_FOUND,
+                format!("No chapter {} exists", chapter_id),
+            )
+            .to_tuple()
+        })
+}
+
+async fn find_workflow(
+    chapter_id: i32,
+    lang: &str,
+    connection: &mut AsyncPgConnection,
+) -> Result<ChapterWorkflow, (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::chapter_workflows::dsl::{
+        chapter_id as db_chapter_id, chapter_workflows as db_workflows, lang as db_lang,
+    };
+
+    db_workflows
+        .filter(db_chapter_id.eq(chapter_id))
+        .filter(db_lang.eq(lang.to_string()))
+        .select(ChapterWorkflow::as_select())
+        .first(connection)
+        .await
+        .optional()
+        .map_err(|err| get_internal_error(err).to_tuple())?
+        .ok_or_else(|| {
+            get_error_from_string(
+                StatusCode::NOT_FOUND,
+                format!(
+                    "Chapter {} doesn't have a {} workflow yet",
+                    chapter_id, lang
+                ),
+            )
+            .to_tuple()
+        })
+}
+
+async fn to_workflow_result(
+    workflow: ChapterWorkflow,
+    chapter_number: i32,
+    connection: &mut AsyncPgConnection,
+) -> Result<WorkflowResult, (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::users::dsl::{username as db_username, users as db_users};
+
+    let claimed_by_name = match workflow.claimed_by {
+        Some(user_id) => db_users
+            .find(user_id)
+            .select(db_username)
+            .first(connection)
+            .await
+            .optional()
+            .map_err(|err| get_internal_error(err).to_tuple())?,
+        None => None,
+    };
+
+    Ok(WorkflowResult {
+        overdue: is_overdue(&workflow),
+        workflow,
+        chapter_number,
+        claimed_by_name,
+    })
+}
+
+/// Lists the workflows of a book's translation into a language in chapter
+/// order, optionally only those in one stage.
+pub async fn list_workflows(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Path((book_id, lang)): Path<(i32, String)>,
+    Query(query): Query<WorkflowListQuery>,
+) -> Result<Json<Vec<WorkflowResult>>, (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::chapter_workflows::dsl::{lang as db_lang, stage as db_stage};
+    use crate::schema::chapters::dsl::{
+        book_id as db_book_id, chapters as db_chapters, number as db_number,
+    };
+    use crate::schema::users::dsl::{username as db_username, users as db_users};
+
+    let token = validate_token(&auth)?;
+
+    validate_lang(&lang).map_err(|err| err.to_tuple())?;
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    find_workflow_book(book_id, &lang, &token, connection).await?;
+
+    let mut workflows_query = crate::schema::chapter_workflows::table
+        .inner_join(db_chapters)
+        .left_join(db_users)
+        .filter(db_book_id.eq(book_id))
+        .filter(db_lang.eq(&lang))
+        .into_boxed();
+
+    if let Some(stage) = query.stage {
+        workflows_query = workflows_query.filter(db_stage.eq(stage));
+    }
+
+    let workflows = workflows_query
+        .order(db_number.asc())
+        .select((
+            ChapterWorkflow::as_select(),
+            db_number,
+            db_username.nullable(),
+        ))
+        .load::<(ChapterWorkflow, i32, Option<String>)>(connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?
+        .into_iter()
+        .map(
+            |(workflow, chapter_number, claimed_by_name)| WorkflowResult {
+                overdue: is_overdue(&workflow),
+                workflow,
+                chapter_number,
+                claimed_by_name,
+            },
+        )
+        .collect();
+
+    Ok(Json(workflows))
+}
+
+/// Lists the activity of a book's translation into a language, newest first.
+pub async fn list_workflow_activity(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Path((book_id, lang)): Path<(i32, String)>,
+    Query(query): Query<ActivityQuery>,
+) -> Result<Json<Vec<WorkflowEventResult>>, (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::chapter_workflows::dsl::{
+        chapter_id as db_chapter_id, chapter_workflows as db_workflows, lang as db_lang,
+    };
+    use crate::schema::chapters::dsl::{book_id as db_book_id, chapters as db_chapters};
+    use crate::schema::users::dsl::{username as db_username, users as db_users};
+    use crate::schema::workflow_events::dsl::{id as db_id, workflow_events as db_events};
+
+    let token = validate_token(&auth)?;
+
+    validate_lang(&lang).map_err(|err| err.to_tuple())?;
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    find_workflow_book(book_id, &lang, &token, connection).await?;
+
+    let mut events_query = db_events
+        .inner_join(db_workflows.inner_join(db_chapters))
+        .inner_join(db_users)
+        .filter(db_book_id.eq(book_id))
+        .filter(db_lang.eq(&lang))
+        .into_boxed();
+
+    if let Some(chapter_id) = query.chapter_id {
+        events_query = events_query.filter(db_chapter_id.eq(chapter_id));
+    }
+
+    if let Some(before) = query.before {
+        events_query = events_query.filter(db_id.lt(before));
+    }
+
+    let events = events_query
+        .order(db_id.desc())
+        .limit(
+            query
+                .limit
+                .unwrap_or(DEFAULT_ACTIVITY_LIMIT)
+                .clamp(1, MAX_ACTIVITY_LIMIT),
+        )
+        .select((WorkflowEvent::as_select(), db_chapter_id, db_username))
+        .load::<(WorkflowEvent, i32, String)>(connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?
+        .into_iter()
+        .map(|(event, chapter_id, username)| WorkflowEventResult {
+            event,
+            chapter_id,
+            username,
+        })
+        .collect();
+
+    Ok(Json(events))
+}
+
+/// Lists the members of a book's translation team for a language and the
+/// stages they work on.
+pub async fn list_workflow_members(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Path((book_id, lang)): Path<(i32, String)>,
+) -> Result<Json<Vec<WorkflowMemberEntry>>, (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::users::dsl::{username as db_username, users as db_users};
+    use crate::schema::workflow_members::dsl::{
+        book_id as db_book_id, lang as db_lang, stage as db_stage, workflow_members as db_members,
+    };
+
+    let token = validate_token(&auth)?;
+
+    validate_lang(&lang).map_err(|err| err.to_tuple())?;
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    find_workflow_book(book_id, &lang, &token, connection).await?;
+
+    let rows = db_members
+        .inner_join(db_users)
+        .filter(db_book_id.eq(book_id))
+        .filter(db_lang.eq(&lang))
+        .order((db_username.asc(), db_stage.asc()))
+        .select((db_username, db_stage))
+        .load::<(String, WorkflowStage)>(connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let mut members: Vec<WorkflowMemberEntry> = Vec::new();
+
+    for (username, stage) in rows {
+        match members.last_mut() {
+            Some(member) if member.username == username => member.stages.push(stage),
+            _ => members.push(WorkflowMemberEntry {
+                username,
+                stages: vec![stage],
+            }),
+        }
+    }
+
+    Ok(Json(members))
+}
+
+/// Replaces the translation team of a book for a language. Only the author of
+/// the book can change its team. Claims held by people who are no longer on
+/// the team are left alone, but the author can release them.
+pub async fn set_workflow_members(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Path((book_id, lang)): Path<(i32, String)>,
+    Json(request): Json<Vec<WorkflowMemberEntry>>,
+) -> Result<Json<Vec<WorkflowMemberEntry>>, (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::users::dsl::{id as db_user_id, username as db_username, users as db_users};
+    use crate::schema::workflow_members::dsl::{
+        book_id as db_book_id, lang as db_lang, workflow_members as db_members,
+    };
+
+    let token = validate_token(&auth)?;
+
+    validate_lang(&lang).map_err(|err| err.to_tuple())?;
+
+    if request.len() > MAX_WORKFLOW_MEMBERS {
+        return Err(get_error_from_string(
+            StatusCode::BAD_REQUEST,
+            format!(
+                "Teams can't have more than {} members",
+                MAX_WORKFLOW_MEMBERS
+            ),
+        )
+        .to_tuple());
+    }
+
+    let mut entries: BTreeMap<String, Vec<WorkflowStage>> = BTreeMap::new();
+
+    for entry in request {
+        if entry.stages.contains(&WorkflowStage::Complete) {
+            return Err(get_error_from_string(
+                StatusCode::BAD_REQUEST,
+                "Nobody can work on the complete stage".into(),
+            )
+            .to_tuple());
+        }
+
+        let stages = entries.entry(entry.username).or_default();
+        stages.extend(entry.stages);
+        stages.sort();
+        stages.dedup();
+    }
+
+    entries.retain(|_, stages| !stages.is_empty());
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let book = find_book(book_id, connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?
+        .ok_or_else(|| book_not_found(book_id))?;
+
+    if book.author_id != token.user_id() {
+        return Err(workflow_error(WorkflowError::ManagerOnly));
+    }
+
+    let usernames: Vec<&String> = entries.keys().collect();
+
+    let users: BTreeMap<String, i32> = db_users
+        .filter(db_username.eq_any(usernames))
+        .select((db_username, db_user_id))
+        .load::<(String, i32)>(connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?
+        .into_iter()
+        .collect();
+
+    let mut members = Vec::new();
+
+    for (username, stages) in &entries {
+        let Some(&user_id) = users.get(username) else {
+            return Err(get_error_from_string(
+                StatusCode::BAD_REQUEST,
+                format!("No user {} exists", username),
+            )
+            .to_tuple());
+        };
+
+        members.extend(stages.iter().map(|&stage| WorkflowMember {
+            book_id,
+            lang: lang.clone(),
+            user_id,
+            stage,
+        }));
+    }
+
+    let lang_ref = &lang;
+
+    connection
+        .transaction::<_, DbError, _>(|connection| {
+            async move {
+                delete(db_members)
+                    .filter(db_book_id.eq(book_id))
+                    .filter(db_lang.eq(lang_ref))
+                    .execute(connection)
+                    .await?;
+
+                if !members.is_empty() {
+                    insert_into(db_members)
+                        .values(members)
+                        .execute(connection)
+                        .await?;
+                }
+
+                Ok(())
+            }
+            .scope_boxed()
+        })
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    send_tracked_book(book_id, connection).await;
+
+    Ok(Json(
+        entries
+            .into_iter()
+            .map(|(username, stages)| WorkflowMemberEntry { username, stages })
+            .collect(),
+    ))
+}
+
+/// Starts the workflow of a chapter's translation into a language at the
+/// translate stage. Only the author of the book can start workflows.
+pub async fn start_workflow(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Path((chapter_id, lang)): Path<(i32, String)>,
+    Json(request): Json<StartWorkflowRequest>,
+) -> Result<(StatusCode, Json<WorkflowResult>), (StatusCode, Json<ErrorResult>)> {
+    use crate::schema::chapter_workflows::dsl::chapter_workflows as db_workflows;
+    use crate::schema::workflow_events::dsl::workflow_events as db_events;
+
+    let token = validate_token(&auth)?;
+
+    validate_lang(&lang).map_err(|err| err.to_tuple())?;
+    validate_due(request.due)?;
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let chapter = find_workflow_chapter(chapter_id, connection).await?;
+    let (book, actor) = find_workflow_book(chapter.book_id, &lang, &token, connection).await?;
+
+    if !actor.manager {
+        return Err(workflow_error(WorkflowError::ManagerOnly));
+    }
+
+    if book.lang == lang {
+        return Err(get_error_from_string(
+            StatusCode::BAD_REQUEST,
+            format!("{} is already written in {}", book.name, lang),
+        )
+        .to_tuple());
+    }
+
+    let new_workflow = NewChapterWorkflow {
+        chapter_id,
+        lang: lang.clone(),
+        due: request.due,
+    };
+
+    let workflow = connection
+        .transaction::<_, DbError, _>(|connection| {
+            async move {
+                let workflow = insert_into(db_workflows)
+                    .values(new_workflow)
+                    .returning(ChapterWorkflow::as_returning())
+                    .get_result(connection)
+                    .await?;
+
+                insert_into(db_events)
+                    .values(NewWorkflowEvent {
+                        workflow_id: workflow.id,
+                        user_id: actor.user_id,
+                        action: WorkflowAction::Started,
+                        from_stage: workflow.stage,
+                        to_stage: workflow.stage,
+                        note: None,
+                    })
+                    .execute(connection)
+                    .await?;
+
+                Ok(workflow)
+            }
+            .scope_boxed()
+        })
+        .await
+        .map_err(|err| {
+            map_insert_error(
+                err,
+                format!("Chapter {} already has a {} workflow", chapter.number, lang),
+            )
+        })?;
+
+    let result = to_workflow_result(workflow, chapter.number, connection).await?;
+
+    Ok((StatusCode::CREATED, Json(result)))
+}
+
+/// Shows where a chapter's translation into a language is.
+pub async fn get_workflow(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Path((chapter_id, lang)): Path<(i32, String)>,
+) -> Result<Json<WorkflowResult>, (StatusCode, Json<ErrorResult>)> {
+    let token = validate_token(&auth)?;
+
+    validate_lang(&lang).map_err(|err| err.to_tuple())?;
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let chapter = find_workflow_chapter(chapter_id, connection).await?;
+    find_workflow_book(chapter.book_id, &lang, &token, connection).await?;
+
+    let workflow = find_workflow(chapter_id, &lang, connection).await?;
+    let result = to_workflow_result(workflow, chapter.number, connection).await?;
+
+    Ok(Json(result))
+}
+
+/// Validates a transition of a chapter's workflow and makes it.
+async fn transition_workflow(
+    auth: Authorization<Bearer>,
+    chapter_id: i32,
+    lang: String,
+    transition: WorkflowTransition,
+    due: Option<NaiveDateTime>,
+    note: Option<String>,
+) -> Result<Json<WorkflowResult>, (StatusCode, Json<ErrorResult>)> {
+    let token = validate_token(&auth)?;
+
+    validate_lang(&lang).map_err(|err| err.to_tuple())?;
+
+    let note = clean_note(note)?;
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let chapter = find_workflow_chapter(chapter_id, connection).await?;
+    let (_, actor) = find_workflow_book(chapter.book_id, &lang, &token, connection).await?;
+    let workflow = find_workflow(chapter_id, &lang, connection).await?;
+
+    let workflow = apply_transition(workflow.id, transition, &actor, due, note, connection)
+        .await
+        .map_err(workflow_error)?
+        .ok_or_else(|| {
+            get_error_from_string(
+                StatusCode::NOT_FOUND,
+                format!(
+                    "Chapter {} doesn't have a {} workflow yet",
+                    chapter_id, lang
+                ),
+            )
+            .to_tuple()
+        })?;
+
+    let result = to_workflow_result(workflow, chapter.number, connection).await?;
+
+    Ok(Json(result))
+}
+
+/// Claims the current stage of a chapter's workflow for the caller.
+pub async fn claim_workflow(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Path((chapter_id, lang)): Path<(i32, String)>,
+) -> Result<Json<WorkflowResult>, (StatusCode, Json<ErrorResult>)> {
+    transition_workflow(
+        auth,
+        chapter_id,
+        lang,
+        WorkflowTransition::Claim,
+        None,
+        None,
+    )
+    .await
+}
+
+/// Gives up a claim on the current stage so someone else can take it.
+pub async fn release_workflow(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Path((chapter_id, lang)): Path<(i32, String)>,
+    request: Option<Json<TransitionRequest>>,
+) -> Result<Json<WorkflowResult>, (StatusCode, Json<ErrorResult>)> {
+    let Json(request) = request.unwrap_or_default();
+
+    transition_workflow(
+        auth,
+        chapter_id,
+        lang,
+        WorkflowTransition::Release,
+        None,
+        request.note,
+    )
+    .await
+}
+
+/// Marks the current stage as done and moves the chapter on to the next one.
+pub async fn submit_workflow(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Path((chapter_id, lang)): Path<(i32, String)>,
+    request: Option<Json<TransitionRequest>>,
+) -> Result<Json<WorkflowResult>, (StatusCode, Json<ErrorResult>)> {
+    let Json(request) = request.unwrap_or_default();
+
+    transition_workflow(
+        auth,
+        chapter_id,
+        lang,
+        WorkflowTransition::Submit,
+        None,
+        request.note,
+    )
+    .await
+}
+
+/// Sends a chapter back to an earlier stage.
+pub async fn return_workflow(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Path((chapter_id, lang)): Path<(i32, String)>,
+    Json(request): Json<ReturnRequest>,
+) -> Result<Json<WorkflowResult>, (StatusCode, Json<ErrorResult>)> {
+    transition_workflow(
+        auth,
+        chapter_id,
+        lang,
+        WorkflowTransition::Return(request.stage),
+        None,
+        request.note,
+    )
+    .await
+}
+
+/// Sets or removes the deadline of the current stage.
+pub async fn set_workflow_deadline(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Path((chapter_id, lang)): Path<(i32, String)>,
+    Json(request): Json<DeadlineRequest>,
+) -> Result<Json<WorkflowResult>, (StatusCode, Json<ErrorResult>)> {
+    validate_due(request.due)?;
+
+    transition_workflow(
+        auth,
+        chapter_id,
+        lang,
+        WorkflowTransition::SetDeadline,
+        request.due,
+        None,
+    )
+    .await
+}
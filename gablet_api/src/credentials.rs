@@ -13,7 +13,10 @@ pub struct Postgres {
 #[derive(Debug, Clone, Deserialize)]
 pub struct AuthCredentials {
     pub access_secret: String,
-    pub refresh_secret: String
+    pub refresh_secret: String,
+    /// The key page cursors are signed with. The access secret is used if
+    /// this isn't set.
+    pub cursor_secret: Option<String>
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -24,11 +27,18 @@ pub struct Mail {
     pub port: u16
 }
 
+#[derive(Debug, Clone, Deserialize)]
+pub struct Tracking {
+    /// Where gablet_tracking can be reached, e.g. `http://127.0.0.1:3001`.
+    pub url: String
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct Credentials {
     pub postgres: Postgres,
     pub mail: Mail,
-    pub auth: AuthCredentials
+    pub auth: AuthCredentials,
+    pub tracking: Option<Tracking>
 }
 
 const CONFIG_FILE_PATH: &str = "./config/credentials.toml";
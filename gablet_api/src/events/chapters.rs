@@ -0,0 +1,83 @@
+use diesel::{
+    prelude::*,
+    sql_query,
+    sql_types::{Bool, Int4, Text, Varchar},
+};
+use diesel_async::RunQueryDsl;
+use std::error::Error;
+
+use crate::{
+    models::{
+        chapters::{Chapter, ChapterPublished},
+        notifications::NotificationKind,
+    },
+    schema::sql_types::NotificationKind as NotificationKindType,
+    utils::books::find_book,
+    PG_POOL,
+};
+
+/// Notifies every follower who reads the book in the published language.
+/// Followers who were already notified are skipped, so replayed events don't
+/// send the same notification twice.
+const NOTIFY_FOLLOWERS_SQL: &str = "
+INSERT INTO notifications (user_id, kind, message, book_id, chapter_id)
+SELECT book_follows.user_id, $1, $2, $3, $4
+FROM book_follows
+WHERE book_follows.book_id = $3
+    AND (book_follows.lang = $5 OR ($6 AND book_follows.lang IS NULL))
+    AND NOT EXISTS (
+        SELECT 1 FROM notifications
+        WHERE notifications.user_id = book_follows.user_id
+            AND notifications.chapter_id = $4
+            AND notifications.kind = $1
+    )";
+
+pub async fn notify_followers(event: &ChapterPublished) -> Result<(), Box<dyn Error>> {
+    use crate::schema::chapters::dsl::chapters as db_chapters;
+
+    let pool = PG_POOL.get().unwrap().clone();
+
+    let connection = &mut pool.get().await?;
+
+    let Some(book) = find_book(event.book_id, connection).await? else {
+        return Ok(());
+    };
+
+    let Some(chapter) = db_chapters
+        .find(event.chapter_id)
+        .select(Chapter::as_select())
+        .first(connection)
+        .await
+        .optional()?
+    else {
+        return Ok(());
+    };
+
+    let (kind, message, lang) = match &event.lang {
+        Some(lang) => (
+            NotificationKind::NewTranslation,
+            format!(
+                "Chapter {} of {} has been translated to {}",
+                chapter.number, book.name, lang
+            ),
+            lang.clone(),
+        ),
+        None => (
+            NotificationKind::NewChapter,
+            format!("Chapter {} of {} is out", chapter.number, book.name),
+            book.lang.clone(),
+        ),
+    };
+
+    sql_query(NOTIFY_FOLLOWERS_SQL)
+        .bind::<NotificationKindType, _>(kind)
+        .bind::<Text, _>(message)
+        .bind::<Int4, _>(book.id)
+        .bind::<Int4, _>(chapter.id)
+        .bind::<Varchar, _>(lang)
+        .bind::<Bool, _>(event.lang.is_none())
+        .execute(connection)
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,22 @@
+use gablet_shared_api::kafka::kafka_events::CHAPTER_PUBLISHED_EVENT;
+use std::error::Error;
+
+use crate::{events::chapters::notify_followers, models::chapters::ChapterPublished};
+
+pub async fn dispatch_kafka_event(key: String, value: String) -> Result<(), Box<dyn Error>> {
+    match key.as_str() {
+        CHAPTER_PUBLISHED_EVENT => forward_chapter_published(value).await,
+        _ => {
+            tracing::info!("Unknown kafka event {}", key.as_str());
+            Ok(())
+        }
+    }
+}
+
+async fn forward_chapter_published(value: String) -> Result<(), Box<dyn Error>> {
+    let event: ChapterPublished = serde_json::from_str(&value)?;
+
+    notify_followers(&event).await?;
+
+    Ok(())
+}
@@ -1,21 +1,66 @@
 #![feature(lazy_cell)]
 
-use std::{net::SocketAddr, sync::{LazyLock, OnceLock}};
+use std::{net::SocketAddr, sync::{LazyLock, Mutex, OnceLock}, time::Duration};
 
-use axum::{routing::{get, post}, Router, http::{header::{AUTHORIZATION, CONTENT_TYPE}, Method}};
+use axum::{extract::DefaultBodyLimit, routing::{get, post, put}, Router, http::{header::{AUTHORIZATION, CONTENT_TYPE}, Method}};
 use credentials::Credentials;
 use diesel_async::{pooled_connection::{bb8::Pool, AsyncDieselConnectionManager}, AsyncPgConnection};
+use gablet_shared_api::{cancellation_token::CancellationSource, kafka::kafka_thread::kafka_thread, pagination::CursorSigner};
 use gablet_tokens::TokenIssuer;
+use kafka::producer::Producer;
 use tower::ServiceBuilder;
 use tower_http::cors::CorsLayer;
 use tower_http::trace::TraceLayer;
 use tracing_subscriber::{prelude::__tracing_subscriber_SubscriberExt, util::SubscriberInitExt};
 
-use crate::controllers::profile::{current_user};
+use crate::controllers::{
+    archives::{export_book, export_chapter, import_chapter},
+    books::{
+        cancel_chapter_schedule, create_book, create_chapter, get_book, list_books,
+        list_chapters, publish_chapter_translation, resubmit_book, resubmit_chapter,
+        schedule_chapter, update_book,
+    },
+    comments::{create_comment, delete_comment, list_comments, update_comment, vote_comment},
+    follows::{follow_book, get_followed_feed, list_follows, unfollow_book},
+    glossary::{
+        create_glossary_term, delete_glossary_term, export_glossary, import_glossary,
+        list_glossary, update_glossary_term,
+    },
+    ledger::{create_earning, get_balance, get_book_shares, get_history, set_book_shares},
+    moderation::{approve_item, list_pending, reject_item},
+    notifications::{list_notifications, mark_notification_read},
+    reviews::{delete_review, list_reviews, set_review},
+    reading::{
+        continue_reading, create_bookmark, delete_bookmark, get_progress, list_bookmarks,
+        update_bookmark, update_progress,
+    },
+    profile::{
+        current_user, get_content_settings, get_user_profile, update_content_settings,
+        update_profile,
+    },
+    search::search_books,
+    tags::{add_tag_alias, create_tag, get_book_tags, list_tags, merge_tag, set_book_tags},
+    text_regions::{
+        create_region, delete_region, diff_translation_revisions, get_page_regions,
+        list_translation_revisions, revert_translation, set_region_translation, update_region,
+    },
+    trending::get_trending,
+    workflows::{
+        claim_workflow, get_workflow, list_workflow_activity, list_workflow_members,
+        list_workflows, release_workflow, return_workflow, set_workflow_deadline,
+        set_workflow_members, start_workflow, submit_workflow,
+    },
+};
+use crate::gablet_kafka::kafka_thread::dispatch_kafka_event;
+use crate::scheduler::release_scheduler;
+use crate::utils::{archives::MAX_ARCHIVE_SIZE, tracking::sync_tracked_books};
 
 pub mod controllers;
 pub mod credentials;
+pub mod events;
+pub mod gablet_kafka;
 pub mod models;
+pub mod scheduler;
 pub mod schema;
 pub mod utils;
 
@@ -43,6 +88,25 @@ pub static TOKEN_ISSUER: LazyLock<TokenIssuer> = LazyLock::new(|| {
     TokenIssuer::new(creds.auth.access_secret, creds.auth.refresh_secret)
 });
 
+pub static CURSOR_SIGNER: LazyLock<CursorSigner> = LazyLock::new(|| {
+    let creds = Credentials::new().unwrap();
+    CursorSigner::new(creds.auth.cursor_secret.unwrap_or(creds.auth.access_secret))
+});
+
+pub static EVENT_PRODUCER: LazyLock<Mutex<Producer>> = LazyLock::new(|| {
+    let creds = gablet_shared_api::credentials::Credentials::new("./config/credentials.toml")
+        .unwrap()
+        .kafka
+        .expect("Missing kafka credentials");
+    let producer = Producer::from_hosts(creds.hosts)
+        .with_ack_timeout(Duration::from_secs(2))
+        .with_required_acks(kafka::producer::RequiredAcks::One)
+        .create()
+        .expect("Failed to create kafka producer");
+
+    Mutex::new(producer)
+});
+
 pub async fn start() {
     let filter = tracing_subscriber::filter::Targets::new()
         .with_target("tower_http::trace::on_response", tracing::Level::TRACE)
@@ -62,13 +126,170 @@ pub async fn start() {
     let cors = CorsLayer::new()
         .allow_origin(tower_http::cors::Any)
         .allow_headers([AUTHORIZATION, CONTENT_TYPE])
-        .allow_methods([Method::GET, Method::POST]);
+        .allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE]);
 
     let pool = postgres_connection().await;
     PG_POOL.set(pool).expect("Failed to set postgres pool");
 
     let api_routes = Router::new()
-        .route("/api/profile", post(current_user));
+        .route("/api/profile", get(current_user).put(update_profile))
+        .route(
+            "/api/profile/settings",
+            get(get_content_settings).put(update_content_settings),
+        )
+        .route("/api/users/:username", get(get_user_profile))
+        .route("/api/books", get(list_books).post(create_book))
+        .route("/api/books/:book_id", get(get_book).put(update_book))
+        .route(
+            "/api/books/:book_id/chapters",
+            get(list_chapters).post(create_chapter),
+        )
+        .route(
+            "/api/books/:book_id/chapters/import",
+            post(import_chapter).layer(DefaultBodyLimit::max(MAX_ARCHIVE_SIZE)),
+        )
+        .route("/api/books/:book_id/export", get(export_book))
+        .route("/api/chapters/:chapter_id/export", get(export_chapter))
+        .route(
+            "/api/books/:book_id/follow",
+            put(follow_book).delete(unfollow_book),
+        )
+        .route("/api/books/:book_id/reviews", get(list_reviews))
+        .route(
+            "/api/books/:book_id/review",
+            put(set_review).delete(delete_review),
+        )
+        .route(
+            "/api/books/:book_id/progress",
+            get(get_progress).put(update_progress),
+        )
+        .route(
+            "/api/books/:book_id/bookmarks",
+            get(list_bookmarks).post(create_bookmark),
+        )
+        .route(
+            "/api/bookmarks/:bookmark_id",
+            put(update_bookmark).delete(delete_bookmark),
+        )
+        .route("/api/progress", get(continue_reading))
+        .route("/api/follows", get(list_follows))
+        .route("/api/follows/feed", get(get_followed_feed))
+        .route(
+            "/api/chapters/:chapter_id/translations/:lang",
+            post(publish_chapter_translation),
+        )
+        .route(
+            "/api/chapters/:chapter_id/schedule",
+            put(schedule_chapter).delete(cancel_chapter_schedule),
+        )
+        .route("/api/books/:book_id/resubmit", post(resubmit_book))
+        .route("/api/chapters/:chapter_id/resubmit", post(resubmit_chapter))
+        .route(
+            "/api/chapters/:chapter_id/comments",
+            get(list_comments).post(create_comment),
+        )
+        .route(
+            "/api/comments/:comment_id",
+            put(update_comment).delete(delete_comment),
+        )
+        .route("/api/comments/:comment_id/vote", put(vote_comment))
+        .route(
+            "/api/books/:book_id/glossary/:lang",
+            get(list_glossary).post(create_glossary_term),
+        )
+        .route(
+            "/api/books/:book_id/glossary/:lang/import",
+            post(import_glossary),
+        )
+        .route(
+            "/api/books/:book_id/glossary/:lang/export",
+            get(export_glossary),
+        )
+        .route(
+            "/api/glossary/:term_id",
+            put(update_glossary_term).delete(delete_glossary_term),
+        )
+        .route(
+            "/api/books/:book_id/tags",
+            get(get_book_tags).put(set_book_tags),
+        )
+        .route("/api/search/books", get(search_books))
+        .route("/api/trending", get(get_trending))
+        .route("/api/tags", get(list_tags).post(create_tag))
+        .route("/api/tags/:tag_id/aliases", post(add_tag_alias))
+        .route("/api/tags/:tag_id/merge", post(merge_tag))
+        .route("/api/moderation", get(list_pending))
+        .route("/api/moderation/:item_id/approve", post(approve_item))
+        .route("/api/moderation/:item_id/reject", post(reject_item))
+        .route("/api/notifications", get(list_notifications))
+        .route(
+            "/api/notifications/:notification_id/read",
+            post(mark_notification_read),
+        )
+        .route(
+            "/api/pages/:page_id/regions",
+            get(get_page_regions).post(create_region),
+        )
+        .route(
+            "/api/regions/:region_id",
+            put(update_region).delete(delete_region),
+        )
+        .route(
+            "/api/regions/:region_id/translations/:lang",
+            put(set_region_translation),
+        )
+        .route(
+            "/api/regions/:region_id/translations/:lang/revisions",
+            get(list_translation_revisions),
+        )
+        .route(
+            "/api/regions/:region_id/translations/:lang/diff",
+            get(diff_translation_revisions),
+        )
+        .route(
+            "/api/regions/:region_id/translations/:lang/revisions/:revision_id/revert",
+            post(revert_translation),
+        )
+        .route("/api/books/:book_id/workflows/:lang", get(list_workflows))
+        .route(
+            "/api/books/:book_id/workflows/:lang/members",
+            get(list_workflow_members).put(set_workflow_members),
+        )
+        .route(
+            "/api/books/:book_id/workflows/:lang/activity",
+            get(list_workflow_activity),
+        )
+        .route(
+            "/api/chapters/:chapter_id/workflows/:lang",
+            get(get_workflow).post(start_workflow),
+        )
+        .route(
+            "/api/chapters/:chapter_id/workflows/:lang/claim",
+            post(claim_workflow),
+        )
+        .route(
+            "/api/chapters/:chapter_id/workflows/:lang/release",
+            post(release_workflow),
+        )
+        .route(
+            "/api/chapters/:chapter_id/workflows/:lang/submit",
+            post(submit_workflow),
+        )
+        .route(
+            "/api/chapters/:chapter_id/workflows/:lang/return",
+            post(return_workflow),
+        )
+        .route(
+            "/api/chapters/:chapter_id/workflows/:lang/deadline",
+            put(set_workflow_deadline),
+        )
+        .route("/api/ledger/earnings", post(create_earning))
+        .route("/api/ledger/balance", get(get_balance))
+        .route("/api/ledger/history", get(get_history))
+        .route(
+            "/api/books/:book_id/shares",
+            get(get_book_shares).put(set_book_shares),
+        );
 
     let web_routes = Router::new()
         .route("/web/profile", post(current_user));
@@ -81,15 +302,55 @@ pub async fn start() {
         )
         .layer(TraceLayer::new_for_http());
 
+    let mut cts = CancellationSource::new();
+    let token = cts.token();
+
+    std::thread::spawn(move || kafka_thread(token, dispatch_kafka_event));
+    tokio::spawn(release_scheduler(cts.token()));
+
     let addr = SocketAddr::from(([127, 0, 0, 1], 3000));
     tracing::debug!("listening on {}", addr);
     axum::Server::bind(&addr)
         .serve(app.into_make_service_with_connect_info::<SocketAddr>())
         .await
         .unwrap();
+
+    cts.request_cancellation();
+}
+
+const USAGE: &str = "Usage: gablet_api [sync-tracking]";
+
+/// Sends every book to gablet_tracking, which keeps its own copy of who can
+/// see their analytics, e.g. `gablet_api sync-tracking` after setting it up.
+async fn sync_tracking() {
+    let pool = postgres_connection().await;
+    PG_POOL.set(pool).expect("Failed to set postgres pool");
+
+    let pool = PG_POOL.get().unwrap().clone();
+    let result = match pool.get().await {
+        Ok(mut connection) => sync_tracked_books(&mut connection).await,
+        Err(err) => Err(err.into()),
+    };
+
+    match result {
+        Ok(sent) => println!("Sent {} books to gablet_tracking", sent),
+        Err(err) => {
+            eprintln!("Failed to send books to gablet_tracking: {}", err);
+            std::process::exit(1);
+        }
+    }
 }
 
 #[tokio::main]
 async fn main() {
-    start().await;
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    match args.iter().map(String::as_str).collect::<Vec<_>>().as_slice() {
+        [] => start().await,
+        ["sync-tracking"] => sync_tracking().await,
+        _ => {
+            eprintln!("Unknown arguments\n{}", USAGE);
+            std::process::exit(2);
+        }
+    }
 }
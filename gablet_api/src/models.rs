@@ -1 +1,14 @@
-pub mod books;
\ No newline at end of file
+pub mod books;
+pub mod chapters;
+pub mod comments;
+pub mod follows;
+pub mod glossary;
+pub mod ledger;
+pub mod moderation;
+pub mod notifications;
+pub mod reading;
+pub mod reviews;
+pub mod tags;
+pub mod text_regions;
+pub mod users;
+pub mod workflows;
@@ -1,6 +1,32 @@
 use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
 
-#[derive(Queryable, Selectable)]
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize, diesel_derive_enum::DbEnum)]
+#[ExistingTypePath = "crate::schema::sql_types::BookStatus"]
+#[serde(rename_all = "snake_case")]
+pub enum BookStatus {
+    Ongoing,
+    Completed,
+    Hiatus,
+    Cancelled,
+}
+
+/// Content ratings, ordered from the most to the least restrictive audience.
+#[derive(
+    Debug, PartialEq, PartialOrd, Clone, Copy, Serialize, Deserialize, diesel_derive_enum::DbEnum,
+)]
+#[ExistingTypePath = "crate::schema::sql_types::ContentRating"]
+#[serde(rename_all = "snake_case")]
+pub enum ContentRating {
+    AllAges,
+    Teen,
+    Mature,
+    Explicit,
+}
+
+#[derive(
+    Queryable, QueryableByName, Selectable, Identifiable, Serialize, Deserialize, Debug, Clone,
+)]
 #[diesel(table_name = crate::schema::books)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 pub struct Book {
@@ -9,5 +35,31 @@ pub struct Book {
     pub description: Option<String>,
     pub approved: bool,
     pub small_thumbnail: Option<String>,
-    pub big_thumbnail: Option<String>
-}
\ No newline at end of file
+    pub big_thumbnail: Option<String>,
+    pub author_id: i32,
+    pub lang: String,
+    pub status: BookStatus,
+    pub content_rating: ContentRating,
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = crate::schema::books)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewBook {
+    pub name: String,
+    pub description: Option<String>,
+    pub author_id: i32,
+    pub lang: String,
+    pub content_rating: ContentRating,
+}
+
+/// The fields of a book its author can change. Missing fields are left as is.
+#[derive(AsChangeset, Deserialize, Debug, Clone)]
+#[diesel(table_name = crate::schema::books)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct BookChanges {
+    pub description: Option<String>,
+    pub lang: Option<String>,
+    pub status: Option<BookStatus>,
+    pub content_rating: Option<ContentRating>,
+}
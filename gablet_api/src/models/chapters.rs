@@ -0,0 +1,81 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Queryable, Selectable, Identifiable, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = crate::schema::chapters)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct Chapter {
+    pub id: i32,
+    pub book_id: i32,
+    pub number: i32,
+    pub title: Option<String>,
+    pub created: NaiveDateTime,
+    pub approved: bool,
+    pub published: Option<NaiveDateTime>,
+    /// When the chapter is scheduled to be published, if it hasn't been yet.
+    pub publish_at: Option<NaiveDateTime>,
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = crate::schema::chapters)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewChapter {
+    pub book_id: i32,
+    pub number: i32,
+    pub title: Option<String>,
+    pub publish_at: Option<NaiveDateTime>,
+}
+
+/// A translation of a chapter that has been released to readers.
+#[derive(Queryable, Selectable, Identifiable, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = crate::schema::chapter_translations)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct ChapterTranslation {
+    pub id: i32,
+    pub chapter_id: i32,
+    pub lang: String,
+    pub published_by: i32,
+    pub published: NaiveDateTime,
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = crate::schema::chapter_translations)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewChapterTranslation {
+    pub chapter_id: i32,
+    pub lang: String,
+    pub published_by: i32,
+}
+
+/// Sent on kafka when a chapter, or a translation of a chapter, is released.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ChapterPublished {
+    pub book_id: i32,
+    pub chapter_id: i32,
+    /// The language of the translation, or `None` for the original chapter.
+    pub lang: Option<String>,
+}
+
+#[derive(Queryable, Selectable, Identifiable, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = crate::schema::pages)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct Page {
+    pub id: i32,
+    pub chapter_id: i32,
+    pub number: i32,
+    pub image: String,
+    pub width: i32,
+    pub height: i32,
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = crate::schema::pages)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewPage {
+    pub chapter_id: i32,
+    pub number: i32,
+    pub image: String,
+    pub width: i32,
+    pub height: i32,
+}
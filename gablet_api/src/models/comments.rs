@@ -0,0 +1,44 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// A comment on a chapter. Replies point at the comment they answer and at the
+/// top level comment of their thread.
+#[derive(Queryable, Selectable, Identifiable, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = crate::schema::comments)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct Comment {
+    pub id: i32,
+    pub chapter_id: i32,
+    pub user_id: i32,
+    pub parent_id: Option<i32>,
+    pub root_id: Option<i32>,
+    pub body: String,
+    pub spoiler: bool,
+    pub score: i32,
+    pub deleted: bool,
+    pub deleted_by: Option<i32>,
+    pub created: NaiveDateTime,
+    pub edited: Option<NaiveDateTime>,
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = crate::schema::comments)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewComment {
+    pub chapter_id: i32,
+    pub user_id: i32,
+    pub parent_id: Option<i32>,
+    pub root_id: Option<i32>,
+    pub body: String,
+    pub spoiler: bool,
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = crate::schema::comment_votes)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewCommentVote {
+    pub comment_id: i32,
+    pub user_id: i32,
+    pub value: i16,
+}
@@ -0,0 +1,22 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Queryable, Selectable, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = crate::schema::book_follows)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct BookFollow {
+    pub user_id: i32,
+    pub book_id: i32,
+    pub lang: Option<String>,
+    pub created: NaiveDateTime,
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = crate::schema::book_follows)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewBookFollow {
+    pub user_id: i32,
+    pub book_id: i32,
+    pub lang: Option<String>,
+}
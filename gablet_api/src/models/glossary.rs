@@ -0,0 +1,63 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use strum::EnumString;
+
+#[derive(
+    Debug,
+    PartialEq,
+    Clone,
+    Copy,
+    Serialize,
+    Deserialize,
+    EnumString,
+    strum::Display,
+    diesel_derive_enum::DbEnum,
+)]
+#[ExistingTypePath = "crate::schema::sql_types::PartOfSpeech"]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum PartOfSpeech {
+    Noun,
+    ProperNoun,
+    Pronoun,
+    Verb,
+    Adjective,
+    Adverb,
+    Phrase,
+    Other,
+}
+
+/// A term in the glossary of a book, along with the translation everyone
+/// translating the book into `lang` should use for it.
+#[derive(Queryable, Selectable, Identifiable, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = crate::schema::glossary_terms)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct GlossaryTerm {
+    pub id: i32,
+    pub book_id: i32,
+    pub lang: String,
+    pub source_term: String,
+    #[serde(skip)]
+    pub source_key: String,
+    pub translation: String,
+    pub notes: Option<String>,
+    pub part_of_speech: Option<PartOfSpeech>,
+    pub updated_by: i32,
+    pub created: NaiveDateTime,
+    pub updated: NaiveDateTime,
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = crate::schema::glossary_terms)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewGlossaryTerm {
+    pub book_id: i32,
+    pub lang: String,
+    pub source_term: String,
+    pub source_key: String,
+    pub translation: String,
+    pub notes: Option<String>,
+    pub part_of_speech: Option<PartOfSpeech>,
+    pub updated_by: i32,
+}
@@ -0,0 +1,96 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize, diesel_derive_enum::DbEnum)]
+#[ExistingTypePath = "crate::schema::sql_types::LedgerAccountKind"]
+#[serde(rename_all = "snake_case")]
+pub enum LedgerAccountKind {
+    Revenue,
+    Platform,
+    Contributor,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize, diesel_derive_enum::DbEnum)]
+#[ExistingTypePath = "crate::schema::sql_types::EarningKind"]
+#[serde(rename_all = "snake_case")]
+pub enum EarningKind {
+    Tip,
+    Subscription,
+    AdRevenue,
+}
+
+#[derive(Queryable, Selectable, Identifiable, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = crate::schema::ledger_accounts)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct LedgerAccount {
+    pub id: i32,
+    pub kind: LedgerAccountKind,
+    pub user_id: Option<i32>,
+    pub created: NaiveDateTime,
+}
+
+#[derive(Queryable, Selectable, Identifiable, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = crate::schema::ledger_transactions)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct LedgerTransaction {
+    pub id: i32,
+    pub kind: EarningKind,
+    pub book_id: i32,
+    pub lang: Option<String>,
+    pub amount_cents: i64,
+    pub description: Option<String>,
+    pub created: NaiveDateTime,
+}
+
+#[derive(Insertable, Deserialize, Debug, Clone)]
+#[diesel(table_name = crate::schema::ledger_transactions)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewLedgerTransaction {
+    pub kind: EarningKind,
+    pub book_id: i32,
+    pub lang: Option<String>,
+    pub amount_cents: i64,
+    pub description: Option<String>,
+}
+
+#[derive(Queryable, Selectable, Identifiable, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = crate::schema::ledger_entries)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct LedgerEntry {
+    pub id: i32,
+    pub transaction_id: i32,
+    pub account_id: i32,
+    pub amount_cents: i64,
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = crate::schema::ledger_entries)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewLedgerEntry {
+    pub transaction_id: i32,
+    pub account_id: i32,
+    pub amount_cents: i64,
+}
+
+#[derive(Queryable, Selectable, Identifiable, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = crate::schema::share_agreements)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct ShareAgreement {
+    pub id: i32,
+    pub book_id: i32,
+    pub lang: Option<String>,
+    pub user_id: i32,
+    pub share_bps: i32,
+    pub created: NaiveDateTime,
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = crate::schema::share_agreements)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewShareAgreement {
+    pub book_id: i32,
+    pub lang: Option<String>,
+    pub user_id: i32,
+    pub share_bps: i32,
+}
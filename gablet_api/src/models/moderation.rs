@@ -0,0 +1,47 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize, diesel_derive_enum::DbEnum)]
+#[ExistingTypePath = "crate::schema::sql_types::ModerationKind"]
+#[serde(rename_all = "snake_case")]
+pub enum ModerationKind {
+    Book,
+    Chapter,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize, diesel_derive_enum::DbEnum)]
+#[ExistingTypePath = "crate::schema::sql_types::ModerationStatus"]
+#[serde(rename_all = "snake_case")]
+pub enum ModerationStatus {
+    Pending,
+    Approved,
+    Rejected,
+}
+
+/// A book or chapter waiting for (or having received) a moderator's review.
+#[derive(Queryable, Selectable, Identifiable, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = crate::schema::moderation_items)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct ModerationItem {
+    pub id: i32,
+    pub kind: ModerationKind,
+    pub book_id: i32,
+    pub chapter_id: Option<i32>,
+    pub submitted_by: i32,
+    pub status: ModerationStatus,
+    pub reason: Option<String>,
+    pub reviewed_by: Option<i32>,
+    pub created: NaiveDateTime,
+    pub reviewed: Option<NaiveDateTime>,
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = crate::schema::moderation_items)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewModerationItem {
+    pub kind: ModerationKind,
+    pub book_id: i32,
+    pub chapter_id: Option<i32>,
+    pub submitted_by: i32,
+}
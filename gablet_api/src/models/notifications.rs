@@ -0,0 +1,40 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize, diesel_derive_enum::DbEnum)]
+#[ExistingTypePath = "crate::schema::sql_types::NotificationKind"]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+    BookApproved,
+    BookRejected,
+    ChapterApproved,
+    ChapterRejected,
+    NewChapter,
+    NewTranslation,
+}
+
+#[derive(Queryable, Selectable, Identifiable, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = crate::schema::notifications)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct Notification {
+    pub id: i32,
+    pub user_id: i32,
+    pub kind: NotificationKind,
+    pub message: String,
+    pub book_id: Option<i32>,
+    pub chapter_id: Option<i32>,
+    pub read: bool,
+    pub created: NaiveDateTime,
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = crate::schema::notifications)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewNotification {
+    pub user_id: i32,
+    pub kind: NotificationKind,
+    pub message: String,
+    pub book_id: Option<i32>,
+    pub chapter_id: Option<i32>,
+}
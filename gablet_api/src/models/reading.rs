@@ -0,0 +1,69 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// The last page a user read in a book.
+#[derive(Queryable, Selectable, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = crate::schema::reading_progress)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct ReadingProgress {
+    pub user_id: i32,
+    pub book_id: i32,
+    pub chapter_id: i32,
+    pub page_number: i32,
+    pub lang: Option<String>,
+    pub updated: NaiveDateTime,
+}
+
+#[derive(Insertable, AsChangeset, Debug, Clone)]
+#[diesel(table_name = crate::schema::reading_progress)]
+#[diesel(treat_none_as_null = true)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewReadingProgress {
+    pub user_id: i32,
+    pub book_id: i32,
+    pub chapter_id: i32,
+    pub page_number: i32,
+    pub lang: Option<String>,
+    pub updated: NaiveDateTime,
+}
+
+#[derive(Queryable, Selectable, Identifiable, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = crate::schema::bookmarks)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct Bookmark {
+    pub id: i32,
+    pub user_id: i32,
+    pub book_id: i32,
+    pub chapter_id: i32,
+    pub page_number: i32,
+    pub lang: Option<String>,
+    pub name: String,
+    pub created: NaiveDateTime,
+    pub updated: NaiveDateTime,
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = crate::schema::bookmarks)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewBookmark {
+    pub user_id: i32,
+    pub book_id: i32,
+    pub chapter_id: i32,
+    pub page_number: i32,
+    pub lang: Option<String>,
+    pub name: String,
+    pub updated: NaiveDateTime,
+}
+
+#[derive(AsChangeset, Debug, Clone)]
+#[diesel(table_name = crate::schema::bookmarks)]
+#[diesel(treat_none_as_null = true)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct BookmarkChanges {
+    pub chapter_id: i32,
+    pub page_number: i32,
+    pub lang: Option<String>,
+    pub name: String,
+    pub updated: NaiveDateTime,
+}
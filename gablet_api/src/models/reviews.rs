@@ -0,0 +1,68 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Queryable, Selectable, Identifiable, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = crate::schema::reviews)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct Review {
+    pub id: i32,
+    pub book_id: i32,
+    pub user_id: i32,
+    pub rating: i16,
+    pub body: Option<String>,
+    pub created: NaiveDateTime,
+    pub updated: NaiveDateTime,
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = crate::schema::reviews)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewReview {
+    pub book_id: i32,
+    pub user_id: i32,
+    pub rating: i16,
+    pub body: Option<String>,
+}
+
+/// The running totals of the reviews of a book. They're kept up to date by a
+/// trigger on `reviews`.
+#[derive(Queryable, Selectable, Identifiable, Debug, Clone)]
+#[diesel(table_name = crate::schema::book_ratings)]
+#[diesel(primary_key(book_id))]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct BookRating {
+    pub book_id: i32,
+    pub rating_count: i32,
+    pub rating_sum: i32,
+    pub distribution: Vec<Option<i32>>,
+    pub score: f64,
+}
+
+/// The rating aggregates shown on a book.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RatingSummary {
+    pub count: i32,
+    /// The plain average rating, or `None` if nobody has rated the book.
+    pub mean: Option<f64>,
+    /// The number of 1 to 5 star ratings, in that order.
+    pub distribution: Vec<i32>,
+    /// The Bayesian average books are sorted by.
+    pub score: f64,
+}
+
+impl From<BookRating> for RatingSummary {
+    fn from(rating: BookRating) -> Self {
+        RatingSummary {
+            count: rating.rating_count,
+            mean: (rating.rating_count > 0)
+                .then(|| rating.rating_sum as f64 / rating.rating_count as f64),
+            distribution: rating
+                .distribution
+                .into_iter()
+                .map(|count| count.unwrap_or(0))
+                .collect(),
+            score: rating.score,
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize, diesel_derive_enum::DbEnum)]
+#[ExistingTypePath = "crate::schema::sql_types::TagKind"]
+#[serde(rename_all = "snake_case")]
+pub enum TagKind {
+    Genre,
+    Tag,
+}
+
+/// A canonical tag. Other spellings of the same tag are stored as aliases.
+#[derive(Queryable, Selectable, Identifiable, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = crate::schema::tags)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct Tag {
+    pub id: i32,
+    pub name: String,
+    pub kind: TagKind,
+    pub created: NaiveDateTime,
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = crate::schema::tags)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewTag {
+    pub name: String,
+    pub kind: TagKind,
+}
+
+#[derive(
+    Queryable, Selectable, Identifiable, Associations, Serialize, Deserialize, Debug, Clone,
+)]
+#[diesel(belongs_to(Tag))]
+#[diesel(table_name = crate::schema::tag_aliases)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct TagAlias {
+    pub id: i32,
+    pub tag_id: i32,
+    pub alias: String,
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = crate::schema::tag_aliases)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewTagAlias {
+    pub tag_id: i32,
+    pub alias: String,
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = crate::schema::book_tags)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewBookTag {
+    pub book_id: i32,
+    pub tag_id: i32,
+}
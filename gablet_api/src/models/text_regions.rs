@@ -0,0 +1,99 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// A speech bubble or caption on a page.
+///
+/// The bounding box and polygon are stored as fractions of the page size, so
+/// `(0, 0)` is the top left corner of the page and `(1, 1)` the bottom right.
+#[derive(Queryable, Selectable, Identifiable, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = crate::schema::text_regions)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct TextRegion {
+    pub id: i32,
+    pub page_id: i32,
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    pub polygon: Option<Vec<f32>>,
+    pub source_text: String,
+    pub source_lang: String,
+    pub created_by: i32,
+    pub created: NaiveDateTime,
+    pub updated: NaiveDateTime,
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = crate::schema::text_regions)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewTextRegion {
+    pub page_id: i32,
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    pub polygon: Option<Vec<f32>>,
+    pub source_text: String,
+    pub source_lang: String,
+    pub created_by: i32,
+}
+
+/// Replaces the editable fields of a region. A missing polygon clears it.
+#[derive(AsChangeset, Debug, Clone)]
+#[diesel(table_name = crate::schema::text_regions)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+#[diesel(treat_none_as_null = true)]
+pub struct TextRegionChanges {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    pub polygon: Option<Vec<f32>>,
+    pub source_text: String,
+    pub source_lang: String,
+}
+
+#[derive(Queryable, Selectable, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = crate::schema::text_region_translations)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct TextRegionTranslation {
+    pub id: i32,
+    pub region_id: i32,
+    pub lang: String,
+    pub text: String,
+    pub translator_id: i32,
+    pub updated: NaiveDateTime,
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = crate::schema::text_region_translations)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewTextRegionTranslation {
+    pub region_id: i32,
+    pub lang: String,
+    pub text: String,
+    pub translator_id: i32,
+}
+
+/// A saved version of a translation. The newest revision matches the current
+/// text of the translation.
+#[derive(Queryable, Selectable, Identifiable, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = crate::schema::translation_revisions)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct TranslationRevision {
+    pub id: i32,
+    pub translation_id: i32,
+    pub text: String,
+    pub author_id: i32,
+    pub created: NaiveDateTime,
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = crate::schema::translation_revisions)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewTranslationRevision {
+    pub translation_id: i32,
+    pub text: String,
+    pub author_id: i32,
+}
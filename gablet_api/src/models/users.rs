@@ -0,0 +1,56 @@
+use diesel::prelude::*;
+use strum::EnumString;
+
+#[derive(
+    Debug,
+    PartialEq,
+    PartialOrd,
+    Clone,
+    Copy,
+    EnumString,
+    strum::Display,
+    diesel_derive_enum::DbEnum,
+)]
+#[ExistingTypePath = "crate::schema::sql_types::UserLevel"]
+pub enum UserLevel {
+    #[strum(serialize = "user")]
+    User,
+
+    #[strum(serialize = "superuser")]
+    Superuser,
+
+    #[strum(serialize = "mod")]
+    Mod,
+
+    #[strum(serialize = "admin")]
+    Admin,
+}
+
+/// The parts of a profile that are kept outside of the `users` table.
+#[derive(Queryable, Selectable, Debug, Clone, Default)]
+#[diesel(table_name = crate::schema::user_profiles)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct UserProfile {
+    pub about: Option<String>,
+    pub avatar: Option<String>,
+    pub banner: Option<String>,
+    pub gender: Option<String>,
+    pub country: Option<String>,
+    pub links: Vec<Option<String>>,
+    pub langs: Vec<Option<String>>,
+}
+
+#[derive(Insertable, AsChangeset, Debug, Clone)]
+#[diesel(table_name = crate::schema::user_profiles)]
+#[diesel(treat_none_as_null = true)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewUserProfile {
+    pub user_id: i32,
+    pub about: Option<String>,
+    pub avatar: Option<String>,
+    pub banner: Option<String>,
+    pub gender: Option<String>,
+    pub country: Option<String>,
+    pub links: Vec<Option<String>>,
+    pub langs: Vec<Option<String>>,
+}
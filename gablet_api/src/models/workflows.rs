@@ -0,0 +1,117 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// The stages a chapter goes through while it's being translated, in order.
+#[derive(
+    Debug,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Clone,
+    Copy,
+    Serialize,
+    Deserialize,
+    strum::Display,
+    diesel_derive_enum::DbEnum,
+)]
+#[ExistingTypePath = "crate::schema::sql_types::WorkflowStage"]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum WorkflowStage {
+    Translate,
+    Proofread,
+    Typeset,
+    QualityCheck,
+    Complete,
+}
+
+impl WorkflowStage {
+    /// The stage that follows this one, or `None` once the work is complete.
+    pub fn next(self) -> Option<WorkflowStage> {
+        match self {
+            WorkflowStage::Translate => Some(WorkflowStage::Proofread),
+            WorkflowStage::Proofread => Some(WorkflowStage::Typeset),
+            WorkflowStage::Typeset => Some(WorkflowStage::QualityCheck),
+            WorkflowStage::QualityCheck => Some(WorkflowStage::Complete),
+            WorkflowStage::Complete => None,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize, diesel_derive_enum::DbEnum)]
+#[ExistingTypePath = "crate::schema::sql_types::WorkflowAction"]
+#[serde(rename_all = "snake_case")]
+pub enum WorkflowAction {
+    Started,
+    Claimed,
+    Released,
+    Submitted,
+    Returned,
+    DeadlineSet,
+}
+
+/// Where the translation of a chapter into a language is, and who is working
+/// on it.
+#[derive(Queryable, Selectable, Identifiable, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = crate::schema::chapter_workflows)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct ChapterWorkflow {
+    pub id: i32,
+    pub chapter_id: i32,
+    pub lang: String,
+    pub stage: WorkflowStage,
+    pub claimed_by: Option<i32>,
+    pub claimed: Option<NaiveDateTime>,
+    /// The deadline for the current stage.
+    pub due: Option<NaiveDateTime>,
+    pub created: NaiveDateTime,
+    pub updated: NaiveDateTime,
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = crate::schema::chapter_workflows)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewChapterWorkflow {
+    pub chapter_id: i32,
+    pub lang: String,
+    pub due: Option<NaiveDateTime>,
+}
+
+/// Someone who can claim a stage of a book's translation into a language.
+#[derive(Queryable, Selectable, Insertable, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = crate::schema::workflow_members)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct WorkflowMember {
+    pub book_id: i32,
+    pub lang: String,
+    pub user_id: i32,
+    pub stage: WorkflowStage,
+}
+
+#[derive(Queryable, Selectable, Identifiable, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = crate::schema::workflow_events)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct WorkflowEvent {
+    pub id: i32,
+    pub workflow_id: i32,
+    pub user_id: i32,
+    pub action: WorkflowAction,
+    pub from_stage: WorkflowStage,
+    pub to_stage: WorkflowStage,
+    pub note: Option<String>,
+    pub created: NaiveDateTime,
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = crate::schema::workflow_events)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewWorkflowEvent {
+    pub workflow_id: i32,
+    pub user_id: i32,
+    pub action: WorkflowAction,
+    pub from_stage: WorkflowStage,
+    pub to_stage: WorkflowStage,
+    pub note: Option<String>,
+}
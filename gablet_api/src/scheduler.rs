@@ -0,0 +1,88 @@
+use std::{error::Error, time::Duration};
+
+use diesel::{
+    prelude::*,
+    sql_query,
+    sql_types::{Int4, Int8},
+};
+use diesel_async::RunQueryDsl;
+use gablet_shared_api::{
+    cancellation_token::CancellationToken,
+    kafka::kafka_events::{BOOK_TOPIC, CHAPTER_PUBLISHED_EVENT},
+};
+
+use crate::{models::chapters::ChapterPublished, utils::kafka::send_event, PG_POOL};
+
+/// How often to look for chapters that are due to be released.
+const RELEASE_INTERVAL: Duration = Duration::from_secs(30);
+const RELEASE_BATCH_SIZE: i64 = 100;
+
+/// Publishes approved chapters whose release time has passed. Rows another
+/// instance is already releasing are skipped, and only unpublished chapters are
+/// updated, so each chapter is published and announced exactly once no matter
+/// how many instances are running.
+const RELEASE_DUE_CHAPTERS_SQL: &str = "
+WITH due AS (
+    SELECT id FROM chapters
+    WHERE published IS NULL
+        AND approved
+        AND publish_at <= CURRENT_TIMESTAMP
+    ORDER BY publish_at
+    LIMIT $1
+    FOR UPDATE SKIP LOCKED
+)
+UPDATE chapters SET published = CURRENT_TIMESTAMP
+FROM due
+WHERE chapters.id = due.id
+RETURNING chapters.id AS chapter_id, chapters.book_id";
+
+#[derive(QueryableByName)]
+struct ReleasedChapter {
+    #[diesel(sql_type = Int4)]
+    chapter_id: i32,
+    #[diesel(sql_type = Int4)]
+    book_id: i32,
+}
+
+/// Releases scheduled chapters until cancellation is requested.
+pub async fn release_scheduler(token: CancellationToken) {
+    while !token.is_cancellation_requested() {
+        if let Err(err) = release_due_chapters().await {
+            tracing::error!("Failed to release scheduled chapters: {}", err);
+        }
+
+        tokio::time::sleep(RELEASE_INTERVAL).await;
+    }
+}
+
+/// Publishes every chapter that is due and sends the same event as a chapter
+/// published by a moderator.
+async fn release_due_chapters() -> Result<(), Box<dyn Error>> {
+    let pool = PG_POOL.get().unwrap().clone();
+    let connection = &mut pool.get().await?;
+
+    loop {
+        let released: Vec<ReleasedChapter> = sql_query(RELEASE_DUE_CHAPTERS_SQL)
+            .bind::<Int8, _>(RELEASE_BATCH_SIZE)
+            .load(connection)
+            .await?;
+
+        for chapter in &released {
+            tracing::info!("Released scheduled chapter {}", chapter.chapter_id);
+
+            send_event(
+                BOOK_TOPIC,
+                CHAPTER_PUBLISHED_EVENT,
+                &ChapterPublished {
+                    book_id: chapter.book_id,
+                    chapter_id: chapter.chapter_id,
+                    lang: None,
+                },
+            );
+        }
+
+        if (released.len() as i64) < RELEASE_BATCH_SIZE {
+            return Ok(());
+        }
+    }
+}
@@ -1,12 +1,65 @@
 // @generated automatically by Diesel CLI.
 
 pub mod sql_types {
+    #[derive(diesel::query_builder::QueryId, diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "book_status"))]
+    pub struct BookStatus;
+
+    #[derive(diesel::query_builder::QueryId, diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "content_rating"))]
+    pub struct ContentRating;
+
+    #[derive(diesel::query_builder::QueryId, diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "earning_kind"))]
+    pub struct EarningKind;
+
+    #[derive(diesel::query_builder::QueryId, diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "ledger_account_kind"))]
+    pub struct LedgerAccountKind;
+
+    #[derive(diesel::query_builder::QueryId, diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "moderation_kind"))]
+    pub struct ModerationKind;
+
+    #[derive(diesel::query_builder::QueryId, diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "moderation_status"))]
+    pub struct ModerationStatus;
+
+    #[derive(diesel::query_builder::QueryId, diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "notification_kind"))]
+    pub struct NotificationKind;
+
+    #[derive(diesel::query_builder::QueryId, diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "part_of_speech"))]
+    pub struct PartOfSpeech;
+
+    #[derive(diesel::query_builder::QueryId, diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "tag_kind"))]
+    pub struct TagKind;
+
+    #[derive(diesel::query_builder::QueryId, diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "tsvector", schema = "pg_catalog"))]
+    pub struct Tsvector;
+
     #[derive(diesel::query_builder::QueryId, diesel::sql_types::SqlType)]
     #[diesel(postgres_type(name = "user_level"))]
     pub struct UserLevel;
+
+    #[derive(diesel::query_builder::QueryId, diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "workflow_action"))]
+    pub struct WorkflowAction;
+
+    #[derive(diesel::query_builder::QueryId, diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "workflow_stage"))]
+    pub struct WorkflowStage;
 }
 
 diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::BookStatus;
+    use super::sql_types::ContentRating;
+    use super::sql_types::Tsvector;
+
     books (id) {
         id -> Int4,
         #[max_length = 255]
@@ -17,12 +70,353 @@ diesel::table! {
         small_thumbnail -> Nullable<Varchar>,
         #[max_length = 255]
         big_thumbnail -> Nullable<Varchar>,
+        author_id -> Int4,
+        #[max_length = 10]
+        lang -> Varchar,
+        status -> BookStatus,
+        content_rating -> ContentRating,
+        search_vector -> Tsvector,
+    }
+}
+
+diesel::table! {
+    book_follows (user_id, book_id) {
+        user_id -> Int4,
+        book_id -> Int4,
+        #[max_length = 10]
+        lang -> Nullable<Varchar>,
+        created -> Timestamp,
+    }
+}
+
+diesel::table! {
+    book_ratings (book_id) {
+        book_id -> Int4,
+        rating_count -> Int4,
+        rating_sum -> Int4,
+        distribution -> Array<Nullable<Int4>>,
+        score -> Float8,
+    }
+}
+
+diesel::table! {
+    book_tags (book_id, tag_id) {
+        book_id -> Int4,
+        tag_id -> Int4,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::WorkflowStage;
+
+    chapter_workflows (id) {
+        id -> Int4,
+        chapter_id -> Int4,
+        #[max_length = 10]
+        lang -> Varchar,
+        stage -> WorkflowStage,
+        claimed_by -> Nullable<Int4>,
+        claimed -> Nullable<Timestamp>,
+        due -> Nullable<Timestamp>,
+        created -> Timestamp,
+        updated -> Timestamp,
+    }
+}
+
+diesel::table! {
+    chapters (id) {
+        id -> Int4,
+        book_id -> Int4,
+        number -> Int4,
+        #[max_length = 255]
+        title -> Nullable<Varchar>,
+        created -> Timestamp,
+        approved -> Bool,
+        published -> Nullable<Timestamp>,
+        publish_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    bookmarks (id) {
+        id -> Int4,
+        user_id -> Int4,
+        book_id -> Int4,
+        chapter_id -> Int4,
+        page_number -> Int4,
+        #[max_length = 10]
+        lang -> Nullable<Varchar>,
+        #[max_length = 100]
+        name -> Varchar,
+        created -> Timestamp,
+        updated -> Timestamp,
+    }
+}
+
+diesel::table! {
+    chapter_translations (id) {
+        id -> Int4,
+        chapter_id -> Int4,
+        #[max_length = 10]
+        lang -> Varchar,
+        published_by -> Int4,
+        published -> Timestamp,
+    }
+}
+
+diesel::table! {
+    comment_votes (comment_id, user_id) {
+        comment_id -> Int4,
+        user_id -> Int4,
+        value -> Int2,
+    }
+}
+
+diesel::table! {
+    comments (id) {
+        id -> Int4,
+        chapter_id -> Int4,
+        user_id -> Int4,
+        parent_id -> Nullable<Int4>,
+        root_id -> Nullable<Int4>,
+        body -> Text,
+        spoiler -> Bool,
+        score -> Int4,
+        deleted -> Bool,
+        deleted_by -> Nullable<Int4>,
+        created -> Timestamp,
+        edited -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::PartOfSpeech;
+
+    glossary_terms (id) {
+        id -> Int4,
+        book_id -> Int4,
+        #[max_length = 10]
+        lang -> Varchar,
+        #[max_length = 200]
+        source_term -> Varchar,
+        #[max_length = 200]
+        source_key -> Varchar,
+        #[max_length = 200]
+        translation -> Varchar,
+        notes -> Nullable<Text>,
+        part_of_speech -> Nullable<PartOfSpeech>,
+        updated_by -> Int4,
+        created -> Timestamp,
+        updated -> Timestamp,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::LedgerAccountKind;
+
+    ledger_accounts (id) {
+        id -> Int4,
+        kind -> LedgerAccountKind,
+        user_id -> Nullable<Int4>,
+        created -> Timestamp,
+    }
+}
+
+diesel::table! {
+    ledger_entries (id) {
+        id -> Int4,
+        transaction_id -> Int4,
+        account_id -> Int4,
+        amount_cents -> Int8,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::EarningKind;
+
+    ledger_transactions (id) {
+        id -> Int4,
+        kind -> EarningKind,
+        book_id -> Int4,
+        #[max_length = 10]
+        lang -> Nullable<Varchar>,
+        amount_cents -> Int8,
+        description -> Nullable<Text>,
+        created -> Timestamp,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::ModerationKind;
+    use super::sql_types::ModerationStatus;
+
+    moderation_items (id) {
+        id -> Int4,
+        kind -> ModerationKind,
+        book_id -> Int4,
+        chapter_id -> Nullable<Int4>,
+        submitted_by -> Int4,
+        status -> ModerationStatus,
+        reason -> Nullable<Text>,
+        reviewed_by -> Nullable<Int4>,
+        created -> Timestamp,
+        reviewed -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::NotificationKind;
+
+    notifications (id) {
+        id -> Int4,
+        user_id -> Int4,
+        kind -> NotificationKind,
+        message -> Text,
+        book_id -> Nullable<Int4>,
+        chapter_id -> Nullable<Int4>,
+        read -> Bool,
+        created -> Timestamp,
+    }
+}
+
+diesel::table! {
+    pages (id) {
+        id -> Int4,
+        chapter_id -> Int4,
+        number -> Int4,
+        #[max_length = 255]
+        image -> Varchar,
+        width -> Int4,
+        height -> Int4,
+    }
+}
+
+diesel::table! {
+    reading_progress (user_id, book_id) {
+        user_id -> Int4,
+        book_id -> Int4,
+        chapter_id -> Int4,
+        page_number -> Int4,
+        #[max_length = 10]
+        lang -> Nullable<Varchar>,
+        updated -> Timestamp,
+    }
+}
+
+diesel::table! {
+    reviews (id) {
+        id -> Int4,
+        book_id -> Int4,
+        user_id -> Int4,
+        rating -> Int2,
+        body -> Nullable<Text>,
+        created -> Timestamp,
+        updated -> Timestamp,
+    }
+}
+
+diesel::table! {
+    share_agreements (id) {
+        id -> Int4,
+        book_id -> Int4,
+        #[max_length = 10]
+        lang -> Nullable<Varchar>,
+        user_id -> Int4,
+        share_bps -> Int4,
+        created -> Timestamp,
+    }
+}
+
+diesel::table! {
+    tag_aliases (id) {
+        id -> Int4,
+        tag_id -> Int4,
+        #[max_length = 50]
+        alias -> Varchar,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::TagKind;
+
+    tags (id) {
+        id -> Int4,
+        #[max_length = 50]
+        name -> Varchar,
+        kind -> TagKind,
+        created -> Timestamp,
+    }
+}
+
+diesel::table! {
+    text_region_translations (id) {
+        id -> Int4,
+        region_id -> Int4,
+        #[max_length = 10]
+        lang -> Varchar,
+        text -> Text,
+        translator_id -> Int4,
+        updated -> Timestamp,
+    }
+}
+
+diesel::table! {
+    text_regions (id) {
+        id -> Int4,
+        page_id -> Int4,
+        x -> Float4,
+        y -> Float4,
+        width -> Float4,
+        height -> Float4,
+        polygon -> Nullable<Array<Float4>>,
+        source_text -> Text,
+        #[max_length = 10]
+        source_lang -> Varchar,
+        created_by -> Int4,
+        created -> Timestamp,
+        updated -> Timestamp,
+    }
+}
+
+diesel::table! {
+    translation_revisions (id) {
+        id -> Int4,
+        translation_id -> Int4,
+        text -> Text,
+        author_id -> Int4,
+        created -> Timestamp,
+    }
+}
+
+diesel::table! {
+    user_profiles (id) {
+        id -> Int4,
+        user_id -> Int4,
+        about -> Nullable<Text>,
+        #[max_length = 255]
+        avatar -> Nullable<Varchar>,
+        #[max_length = 255]
+        banner -> Nullable<Varchar>,
+        #[max_length = 30]
+        gender -> Nullable<Varchar>,
+        #[max_length = 50]
+        country -> Nullable<Varchar>,
+        links -> Array<Nullable<Text>>,
+        langs -> Array<Nullable<Text>>,
     }
 }
 
 diesel::table! {
     use diesel::sql_types::*;
     use super::sql_types::UserLevel;
+    use super::sql_types::ContentRating;
 
     users (id) {
         id -> Int4,
@@ -36,12 +430,117 @@ diesel::table! {
         name -> Varchar,
         verified -> Bool,
         level -> UserLevel,
+        max_content_rating -> ContentRating,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::WorkflowAction;
+    use super::sql_types::WorkflowStage;
+
+    workflow_events (id) {
+        id -> Int4,
+        workflow_id -> Int4,
+        user_id -> Int4,
+        action -> WorkflowAction,
+        from_stage -> WorkflowStage,
+        to_stage -> WorkflowStage,
+        note -> Nullable<Text>,
+        created -> Timestamp,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::WorkflowStage;
+
+    workflow_members (book_id, lang, user_id, stage) {
+        book_id -> Int4,
+        #[max_length = 10]
+        lang -> Varchar,
+        user_id -> Int4,
+        stage -> WorkflowStage,
     }
 }
 
-diesel::joinable!(books -> users (id));
+diesel::joinable!(book_follows -> books (book_id));
+diesel::joinable!(book_follows -> users (user_id));
+diesel::joinable!(book_ratings -> books (book_id));
+diesel::joinable!(book_tags -> books (book_id));
+diesel::joinable!(book_tags -> tags (tag_id));
+diesel::joinable!(bookmarks -> books (book_id));
+diesel::joinable!(bookmarks -> chapters (chapter_id));
+diesel::joinable!(bookmarks -> users (user_id));
+diesel::joinable!(books -> users (author_id));
+diesel::joinable!(chapter_workflows -> chapters (chapter_id));
+diesel::joinable!(chapter_workflows -> users (claimed_by));
+diesel::joinable!(chapter_translations -> chapters (chapter_id));
+diesel::joinable!(chapter_translations -> users (published_by));
+diesel::joinable!(chapters -> books (book_id));
+diesel::joinable!(comment_votes -> comments (comment_id));
+diesel::joinable!(comment_votes -> users (user_id));
+diesel::joinable!(comments -> chapters (chapter_id));
+diesel::joinable!(glossary_terms -> books (book_id));
+diesel::joinable!(glossary_terms -> users (updated_by));
+diesel::joinable!(ledger_accounts -> users (user_id));
+diesel::joinable!(ledger_entries -> ledger_accounts (account_id));
+diesel::joinable!(ledger_entries -> ledger_transactions (transaction_id));
+diesel::joinable!(ledger_transactions -> books (book_id));
+diesel::joinable!(moderation_items -> books (book_id));
+diesel::joinable!(moderation_items -> chapters (chapter_id));
+diesel::joinable!(notifications -> books (book_id));
+diesel::joinable!(notifications -> chapters (chapter_id));
+diesel::joinable!(notifications -> users (user_id));
+diesel::joinable!(pages -> chapters (chapter_id));
+diesel::joinable!(reading_progress -> books (book_id));
+diesel::joinable!(reading_progress -> chapters (chapter_id));
+diesel::joinable!(reading_progress -> users (user_id));
+diesel::joinable!(reviews -> books (book_id));
+diesel::joinable!(reviews -> users (user_id));
+diesel::joinable!(share_agreements -> books (book_id));
+diesel::joinable!(share_agreements -> users (user_id));
+diesel::joinable!(tag_aliases -> tags (tag_id));
+diesel::joinable!(text_region_translations -> text_regions (region_id));
+diesel::joinable!(text_region_translations -> users (translator_id));
+diesel::joinable!(text_regions -> pages (page_id));
+diesel::joinable!(text_regions -> users (created_by));
+diesel::joinable!(translation_revisions -> text_region_translations (translation_id));
+diesel::joinable!(translation_revisions -> users (author_id));
+diesel::joinable!(user_profiles -> users (user_id));
+diesel::joinable!(workflow_events -> chapter_workflows (workflow_id));
+diesel::joinable!(workflow_events -> users (user_id));
+diesel::joinable!(workflow_members -> books (book_id));
+diesel::joinable!(workflow_members -> users (user_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
+    book_follows,
+    book_ratings,
+    book_tags,
+    bookmarks,
     books,
+    chapter_translations,
+    chapter_workflows,
+    chapters,
+    comment_votes,
+    comments,
+    glossary_terms,
+    ledger_accounts,
+    ledger_entries,
+    ledger_transactions,
+    moderation_items,
+    notifications,
+    pages,
+    reading_progress,
+    reviews,
+    share_agreements,
+    tag_aliases,
+    tags,
+    text_region_translations,
+    text_regions,
+    translation_revisions,
+    user_profiles,
     users,
+    workflow_events,
+    workflow_members,
 );
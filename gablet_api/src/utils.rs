@@ -1 +1,18 @@
-pub mod password;
\ No newline at end of file
+pub mod archives;
+pub mod auth;
+pub mod books;
+pub mod diff;
+pub mod errors;
+pub mod glossary;
+pub mod images;
+pub mod kafka;
+pub mod lang;
+pub mod ledger;
+pub mod moderation;
+pub mod notifications;
+pub mod password;
+pub mod revenue_split;
+pub mod storage;
+pub mod tags;
+pub mod tracking;
+pub mod workflows;
@@ -0,0 +1,893 @@
+use std::{
+    cmp::Ordering,
+    error::Error,
+    fmt::Display,
+    io::{Cursor, Read, Write},
+    iter::Peekable,
+    str::Chars,
+};
+
+use chrono::Utc;
+use diesel::result::Error as DbError;
+use diesel::{insert_into, prelude::*};
+use diesel_async::{
+    scoped_futures::ScopedFutureExt, AsyncConnection, AsyncPgConnection, RunQueryDsl,
+};
+use quick_xml::{
+    events::{BytesDecl, BytesText, Event},
+    Writer,
+};
+use zip::{result::ZipError, write::FileOptions, CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::{
+    models::chapters::{Chapter, NewChapter, NewPage, Page},
+    utils::{
+        books::insert_chapter,
+        images::{read_image_info, ImageFormat, ImageInfo},
+        storage::{delete_page_images, page_image_key, save_page_image},
+    },
+};
+
+pub const MAX_ARCHIVE_SIZE: usize = 512 * 1024 * 1024;
+pub const MAX_ARCHIVE_PAGES: usize = 500;
+pub const MAX_PAGE_IMAGE_SIZE: u64 = 32 * 1024 * 1024;
+
+#[derive(Debug)]
+pub enum ArchiveError {
+    Zip(ZipError),
+    Xml(quick_xml::Error),
+    Io(std::io::Error),
+    Db(DbError),
+    NoPages,
+    TooManyPages,
+    TooLarge,
+    PageTooLarge(String),
+    InvalidImage(String),
+}
+
+impl Display for ArchiveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ArchiveError::Zip(err) => write!(f, "Invalid archive: {}", err),
+            ArchiveError::Xml(err) => write!(f, "Failed to write archive metadata: {}", err),
+            ArchiveError::Io(err) => write!(f, "Archive io error: {}", err),
+            ArchiveError::Db(err) => write!(f, "Archive database error: {}", err),
+            ArchiveError::NoPages => write!(f, "The archive doesn't contain any images"),
+            ArchiveError::TooManyPages => write!(
+                f,
+                "The archive contains more than {} images",
+                MAX_ARCHIVE_PAGES
+            ),
+            ArchiveError::TooLarge => write!(
+                f,
+                "The archive is larger than {} bytes once extracted",
+                MAX_ARCHIVE_SIZE
+            ),
+            ArchiveError::PageTooLarge(name) => {
+                write!(f, "{} is larger than {} bytes", name, MAX_PAGE_IMAGE_SIZE)
+            }
+            ArchiveError::InvalidImage(name) => {
+                write!(f, "{} isn't a valid PNG, JPEG, GIF or WebP image", name)
+            }
+        }
+    }
+}
+
+impl Error for ArchiveError {}
+
+impl From<ZipError> for ArchiveError {
+    fn from(err: ZipError) -> Self {
+        ArchiveError::Zip(err)
+    }
+}
+
+impl From<quick_xml::Error> for ArchiveError {
+    fn from(err: quick_xml::Error) -> Self {
+        ArchiveError::Xml(err)
+    }
+}
+
+impl From<std::io::Error> for ArchiveError {
+    fn from(err: std::io::Error) -> Self {
+        ArchiveError::Io(err)
+    }
+}
+
+impl From<DbError> for ArchiveError {
+    fn from(err: DbError) -> Self {
+        ArchiveError::Db(err)
+    }
+}
+
+fn take_number(chars: &mut Peekable<Chars>) -> String {
+    let mut digits = String::new();
+
+    while let Some(&char) = chars.peek() {
+        if !char.is_ascii_digit() {
+            break;
+        }
+        digits.push(char);
+        chars.next();
+    }
+
+    digits
+}
+
+/// Compares file names the way people expect them to be sorted, so that
+/// `page2.png` comes before `page10.png`. Runs of digits are compared by their
+/// value and everything else is compared without case.
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a_chars = a.chars().peekable();
+    let mut b_chars = b.chars().peekable();
+
+    loop {
+        match (a_chars.peek().copied(), b_chars.peek().copied()) {
+            (None, None) => return a.cmp(b),
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(a_char), Some(b_char)) if a_char.is_ascii_digit() && b_char.is_ascii_digit() => {
+                let a_number = take_number(&mut a_chars);
+                let b_number = take_number(&mut b_chars);
+                let a_value = a_number.trim_start_matches('0');
+                let b_value = b_number.trim_start_matches('0');
+
+                let ordering = a_value
+                    .len()
+                    .cmp(&b_value.len())
+                    .then_with(|| a_value.cmp(b_value));
+
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            (Some(a_char), Some(b_char)) => {
+                let ordering = a_char.to_lowercase().cmp(b_char.to_lowercase());
+
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+
+                a_chars.next();
+                b_chars.next();
+            }
+        }
+    }
+}
+
+/// A page image read from an archive.
+#[derive(Debug, Clone)]
+pub struct ArchivePage {
+    pub name: String,
+    pub info: ImageInfo,
+    pub data: Vec<u8>,
+}
+
+/// Files that archiving tools add, which aren't pages.
+fn is_hidden_entry(name: &str) -> bool {
+    name.split('/')
+        .any(|part| part.starts_with('.') || part == "__MACOSX")
+}
+
+/// Reads the pages of a CBZ or ZIP archive in natural file name order. Every
+/// file with an image extension has to be a valid image, and anything else in
+/// the archive, such as a ComicInfo.xml, is ignored.
+pub fn read_archive_pages(data: Vec<u8>) -> Result<Vec<ArchivePage>, ArchiveError> {
+    let mut archive = ZipArchive::new(Cursor::new(data))?;
+    let mut pages = Vec::new();
+    let mut total_size = 0;
+
+    for index in 0..archive.len() {
+        let mut file = archive.by_index(index)?;
+
+        if file.is_dir() {
+            continue;
+        }
+
+        let name = file.name().to_string();
+
+        let is_image = name
+            .rsplit_once('.')
+            .and_then(|(_, extension)| ImageFormat::from_extension(extension))
+            .is_some();
+
+        if !is_image || is_hidden_entry(&name) {
+            continue;
+        }
+
+        if pages.len() == MAX_ARCHIVE_PAGES {
+            return Err(ArchiveError::TooManyPages);
+        }
+
+        // The sizes in the archive can't be trusted, so reading stops as
+        // soon as a file goes over the limit.
+        let mut data = Vec::new();
+        (&mut file)
+            .take(MAX_PAGE_IMAGE_SIZE + 1)
+            .read_to_end(&mut data)?;
+
+        if data.len() as u64 > MAX_PAGE_IMAGE_SIZE {
+            return Err(ArchiveError::PageTooLarge(name));
+        }
+
+        total_size += data.len();
+        if total_size > MAX_ARCHIVE_SIZE {
+            return Err(ArchiveError::TooLarge);
+        }
+
+        let info =
+            read_image_info(&data).ok_or_else(|| ArchiveError::InvalidImage(name.clone()))?;
+
+        pages.push(ArchivePage { name, info, data });
+    }
+
+    if pages.is_empty() {
+        return Err(ArchiveError::NoPages);
+    }
+
+    pages.sort_by(|a, b| natural_cmp(&a.name, &b.name));
+
+    Ok(pages)
+}
+
+/// Creates a chapter from the pages of an archive and stores the page images.
+/// The chapter goes into the moderation queue like any other new chapter.
+pub async fn import_chapter_pages(
+    chapter: NewChapter,
+    author_id: i32,
+    pages: Vec<ArchivePage>,
+    connection: &mut AsyncPgConnection,
+) -> Result<(Chapter, Vec<Page>), ArchiveError> {
+    use crate::schema::pages::dsl::pages as db_pages;
+
+    let mut saved = Vec::new();
+
+    let result = connection
+        .transaction::<_, ArchiveError, _>(|connection| {
+            let saved = &mut saved;
+
+            async move {
+                let chapter = insert_chapter(chapter, author_id, connection).await?;
+
+                let mut new_pages = Vec::with_capacity(pages.len());
+
+                for (number, page) in (1..).zip(&pages) {
+                    let key = page_image_key(chapter.id, number, page.info.format);
+
+                    save_page_image(&key, &page.data).await?;
+                    saved.push(key.clone());
+
+                    new_pages.push(NewPage {
+                        chapter_id: chapter.id,
+                        number,
+                        image: key,
+                        width: page.info.width as i32,
+                        height: page.info.height as i32,
+                    });
+                }
+
+                let pages = insert_into(db_pages)
+                    .values(new_pages)
+                    .returning(Page::as_returning())
+                    .get_results(connection)
+                    .await?;
+
+                Ok((chapter, pages))
+            }
+            .scope_boxed()
+        })
+        .await;
+
+    if result.is_err() {
+        delete_page_images(&saved).await;
+    }
+
+    result
+}
+
+/// A region of a page covered by its translated text.
+#[derive(Debug, Clone)]
+pub struct PageOverlay {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    pub text: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct ExportPage {
+    pub info: ImageInfo,
+    pub image: Vec<u8>,
+    pub overlays: Vec<PageOverlay>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ExportChapter {
+    pub number: i32,
+    pub title: Option<String>,
+    pub pages: Vec<ExportPage>,
+}
+
+impl ExportChapter {
+    fn label(&self) -> String {
+        match &self.title {
+            Some(title) => format!("Chapter {}: {}", self.number, title),
+            None => format!("Chapter {}", self.number),
+        }
+    }
+}
+
+/// What an export is of. `chapter_id` is set when a single chapter is
+/// exported instead of the whole book.
+#[derive(Debug, Clone)]
+pub struct ExportMetadata {
+    pub book_id: i32,
+    pub chapter_id: Option<i32>,
+    pub title: String,
+    pub series: String,
+    pub author: String,
+    pub description: Option<String>,
+    pub lang: String,
+}
+
+/// Names the files of a page so they sort in reading order.
+fn page_file_name(chapter_index: usize, page_index: usize) -> String {
+    format!("{:04}-{:04}", chapter_index + 1, page_index + 1)
+}
+
+fn xml_writer() -> Writer<Cursor<Vec<u8>>> {
+    Writer::new_with_indent(Cursor::new(Vec::new()), b' ', 2)
+}
+
+fn finish_xml(writer: Writer<Cursor<Vec<u8>>>) -> Vec<u8> {
+    writer.into_inner().into_inner()
+}
+
+fn write_decl(writer: &mut Writer<Cursor<Vec<u8>>>) -> quick_xml::Result<()> {
+    writer.write_event(Event::Decl(BytesDecl::new("1.0", Some("utf-8"), None)))
+}
+
+fn write_text_element(
+    writer: &mut Writer<Cursor<Vec<u8>>>,
+    name: &str,
+    text: &str,
+) -> quick_xml::Result<()> {
+    writer
+        .create_element(name)
+        .write_text_content(BytesText::new(text))?;
+    Ok(())
+}
+
+fn comic_info(
+    metadata: &ExportMetadata,
+    chapters: &[ExportChapter],
+) -> Result<Vec<u8>, ArchiveError> {
+    let mut writer = xml_writer();
+    write_decl(&mut writer)?;
+
+    writer
+        .create_element("ComicInfo")
+        .with_attribute(("xmlns:xsd", "http://www.w3.org/2001/XMLSchema"))
+        .with_attribute(("xmlns:xsi", "http://www.w3.org/2001/XMLSchema-instance"))
+        .write_inner_content(|writer| {
+            write_text_element(writer, "Title", &metadata.title)?;
+            write_text_element(writer, "Series", &metadata.series)?;
+
+            if let [chapter] = chapters {
+                write_text_element(writer, "Number", &chapter.number.to_string())?;
+            }
+
+            if let Some(description) = &metadata.description {
+                write_text_element(writer, "Summary", description)?;
+            }
+
+            write_text_element(writer, "Writer", &metadata.author)?;
+            write_text_element(writer, "LanguageISO", &metadata.lang)?;
+
+            let page_count: usize = chapters.iter().map(|chapter| chapter.pages.len()).sum();
+            write_text_element(writer, "PageCount", &page_count.to_string())?;
+
+            writer
+                .create_element("Pages")
+                .write_inner_content(|writer| {
+                    let mut image = 0;
+
+                    for chapter in chapters {
+                        for (index, page) in chapter.pages.iter().enumerate() {
+                            let mut element = writer
+                                .create_element("Page")
+                                .with_attribute(("Image", image.to_string().as_str()))
+                                .with_attribute((
+                                    "ImageSize",
+                                    page.image.len().to_string().as_str(),
+                                ))
+                                .with_attribute((
+                                    "ImageWidth",
+                                    page.info.width.to_string().as_str(),
+                                ))
+                                .with_attribute((
+                                    "ImageHeight",
+                                    page.info.height.to_string().as_str(),
+                                ));
+
+                            if index == 0 && chapters.len() > 1 {
+                                element =
+                                    element.with_attribute(("Bookmark", chapter.label().as_str()));
+                            }
+
+                            element.write_empty()?;
+                            image += 1;
+                        }
+                    }
+
+                    Ok(())
+                })?;
+
+            Ok(())
+        })?;
+
+    Ok(finish_xml(writer))
+}
+
+/// Writes chapters as a CBZ archive with a ComicInfo.xml describing them.
+pub fn write_cbz(
+    metadata: &ExportMetadata,
+    chapters: &[ExportChapter],
+) -> Result<Vec<u8>, ArchiveError> {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let stored = FileOptions::default().compression_method(CompressionMethod::Stored);
+    let deflated = FileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    for (chapter_index, chapter) in chapters.iter().enumerate() {
+        for (page_index, page) in chapter.pages.iter().enumerate() {
+            zip.start_file(
+                format!(
+                    "{}.{}",
+                    page_file_name(chapter_index, page_index),
+                    page.info.format.extension()
+                ),
+                stored,
+            )?;
+            zip.write_all(&page.image)?;
+        }
+    }
+
+    zip.start_file("ComicInfo.xml", deflated)?;
+    zip.write_all(&comic_info(metadata, chapters)?)?;
+
+    Ok(zip.finish()?.into_inner())
+}
+
+const EPUB_STYLE: &str = "html, body {
+  margin: 0;
+  padding: 0;
+}
+
+.page {
+  position: relative;
+  overflow: hidden;
+}
+
+.page img {
+  position: absolute;
+  left: 0;
+  top: 0;
+  width: 100%;
+  height: 100%;
+}
+
+.overlay {
+  position: absolute;
+  display: flex;
+  align-items: center;
+  justify-content: center;
+  box-sizing: border-box;
+  padding: 0.1em;
+  background: #fff;
+  color: #000;
+  font-family: sans-serif;
+  line-height: 1.15;
+  text-align: center;
+  white-space: pre-wrap;
+  overflow: hidden;
+}
+";
+
+fn epub_container() -> Result<Vec<u8>, ArchiveError> {
+    let mut writer = xml_writer();
+    write_decl(&mut writer)?;
+
+    writer
+        .create_element("container")
+        .with_attribute(("version", "1.0"))
+        .with_attribute(("xmlns", "urn:oasis:names:tc:opendocument:xmlns:container"))
+        .write_inner_content(|writer| {
+            writer
+                .create_element("rootfiles")
+                .write_inner_content(|writer| {
+                    writer
+                        .create_element("rootfile")
+                        .with_attribute(("full-path", "OEBPS/content.opf"))
+                        .with_attribute(("media-type", "application/oebps-package+xml"))
+                        .write_empty()?;
+                    Ok(())
+                })?;
+            Ok(())
+        })?;
+
+    Ok(finish_xml(writer))
+}
+
+fn epub_package(
+    metadata: &ExportMetadata,
+    chapters: &[ExportChapter],
+) -> Result<Vec<u8>, ArchiveError> {
+    let identifier = match metadata.chapter_id {
+        Some(chapter_id) => format!(
+            "urn:gablet:book:{}:chapter:{}:{}",
+            metadata.book_id, chapter_id, metadata.lang
+        ),
+        None => format!("urn:gablet:book:{}:{}", metadata.book_id, metadata.lang),
+    };
+    let modified = Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string();
+
+    let mut writer = xml_writer();
+    write_decl(&mut writer)?;
+
+    writer
+        .create_element("package")
+        .with_attribute(("xmlns", "http://www.idpf.org/2007/opf"))
+        .with_attribute(("version", "3.0"))
+        .with_attribute(("unique-identifier", "book-id"))
+        .with_attribute(("xml:lang", metadata.lang.as_str()))
+        .write_inner_content(|writer| {
+            writer
+                .create_element("metadata")
+                .with_attribute(("xmlns:dc", "http://purl.org/dc/elements/1.1/"))
+                .write_inner_content(|writer| {
+                    writer
+                        .create_element("dc:identifier")
+                        .with_attribute(("id", "book-id"))
+                        .write_text_content(BytesText::new(&identifier))?;
+                    write_text_element(writer, "dc:title", &metadata.title)?;
+                    write_text_element(writer, "dc:language", &metadata.lang)?;
+                    write_text_element(writer, "dc:creator", &metadata.author)?;
+
+                    if let Some(description) = &metadata.description {
+                        write_text_element(writer, "dc:description", description)?;
+                    }
+
+                    for (property, value) in [
+                        ("dcterms:modified", modified.as_str()),
+                        ("rendition:layout", "pre-paginated"),
+                        ("rendition:orientation", "auto"),
+                        ("rendition:spread", "none"),
+                    ] {
+                        writer
+                            .create_element("meta")
+                            .with_attribute(("property", property))
+                            .write_text_content(BytesText::new(value))?;
+                    }
+
+                    Ok(())
+                })?;
+
+            writer
+                .create_element("manifest")
+                .write_inner_content(|writer| {
+                    writer
+                        .create_element("item")
+                        .with_attribute(("id", "nav"))
+                        .with_attribute(("href", "nav.xhtml"))
+                        .with_attribute(("media-type", "application/xhtml+xml"))
+                        .with_attribute(("properties", "nav"))
+                        .write_empty()?;
+                    writer
+                        .create_element("item")
+                        .with_attribute(("id", "style"))
+                        .with_attribute(("href", "style.css"))
+                        .with_attribute(("media-type", "text/css"))
+                        .write_empty()?;
+
+                    for (chapter_index, chapter) in chapters.iter().enumerate() {
+                        for (page_index, page) in chapter.pages.iter().enumerate() {
+                            let name = page_file_name(chapter_index, page_index);
+
+                            let mut image = writer
+                                .create_element("item")
+                                .with_attribute(("id", format!("image-{}", name).as_str()))
+                                .with_attribute((
+                                    "href",
+                                    format!("images/{}.{}", name, page.info.format.extension())
+                                        .as_str(),
+                                ))
+                                .with_attribute(("media-type", page.info.format.mime_type()));
+
+                            if chapter_index == 0 && page_index == 0 {
+                                image = image.with_attribute(("properties", "cover-image"));
+                            }
+
+                            image.write_empty()?;
+
+                            writer
+                                .create_element("item")
+                                .with_attribute(("id", format!("page-{}", name).as_str()))
+                                .with_attribute(("href", format!("pages/{}.xhtml", name).as_str()))
+                                .with_attribute(("media-type", "application/xhtml+xml"))
+                                .write_empty()?;
+                        }
+                    }
+
+                    Ok(())
+                })?;
+
+            writer
+                .create_element("spine")
+                .write_inner_content(|writer| {
+                    for (chapter_index, chapter) in chapters.iter().enumerate() {
+                        for page_index in 0..chapter.pages.len() {
+                            let name = page_file_name(chapter_index, page_index);
+
+                            writer
+                                .create_element("itemref")
+                                .with_attribute(("idref", format!("page-{}", name).as_str()))
+                                .write_empty()?;
+                        }
+                    }
+
+                    Ok(())
+                })?;
+
+            Ok(())
+        })?;
+
+    Ok(finish_xml(writer))
+}
+
+fn write_xhtml_start(writer: &mut Writer<Cursor<Vec<u8>>>) -> quick_xml::Result<()> {
+    write_decl(writer)?;
+    writer.write_event(Event::DocType(BytesText::from_escaped("html")))
+}
+
+fn epub_nav(
+    metadata: &ExportMetadata,
+    chapters: &[ExportChapter],
+) -> Result<Vec<u8>, ArchiveError> {
+    let mut writer = xml_writer();
+    write_xhtml_start(&mut writer)?;
+
+    writer
+        .create_element("html")
+        .with_attribute(("xmlns", "http://www.w3.org/1999/xhtml"))
+        .with_attribute(("xmlns:epub", "http://www.idpf.org/2007/ops"))
+        .with_attribute(("xml:lang", metadata.lang.as_str()))
+        .write_inner_content(|writer| {
+            writer
+                .create_element("head")
+                .write_inner_content(|writer| {
+                    write_text_element(writer, "title", &metadata.title)
+                })?;
+
+            writer
+                .create_element("body")
+                .write_inner_content(|writer| {
+                    writer
+                        .create_element("nav")
+                        .with_attribute(("epub:type", "toc"))
+                        .write_inner_content(|writer| {
+                            write_text_element(writer, "h1", &metadata.title)?;
+
+                            writer.create_element("ol").write_inner_content(|writer| {
+                                for (chapter_index, chapter) in chapters.iter().enumerate() {
+                                    writer.create_element("li").write_inner_content(|writer| {
+                                        writer
+                                            .create_element("a")
+                                            .with_attribute((
+                                                "href",
+                                                format!(
+                                                    "pages/{}.xhtml",
+                                                    page_file_name(chapter_index, 0)
+                                                )
+                                                .as_str(),
+                                            ))
+                                            .write_text_content(BytesText::new(&chapter.label()))?;
+                                        Ok(())
+                                    })?;
+                                }
+
+                                Ok(())
+                            })?;
+
+                            Ok(())
+                        })?;
+
+                    Ok(())
+                })?;
+
+            Ok(())
+        })?;
+
+    Ok(finish_xml(writer))
+}
+
+fn epub_page(
+    metadata: &ExportMetadata,
+    chapter: &ExportChapter,
+    page: &ExportPage,
+    name: &str,
+) -> Result<Vec<u8>, ArchiveError> {
+    let width = page.info.width;
+    let height = page.info.height;
+
+    let mut writer = xml_writer();
+    write_xhtml_start(&mut writer)?;
+
+    writer
+        .create_element("html")
+        .with_attribute(("xmlns", "http://www.w3.org/1999/xhtml"))
+        .with_attribute(("xml:lang", metadata.lang.as_str()))
+        .write_inner_content(|writer| {
+            writer.create_element("head").write_inner_content(|writer| {
+                write_text_element(writer, "title", &chapter.label())?;
+                writer
+                    .create_element("meta")
+                    .with_attribute(("name", "viewport"))
+                    .with_attribute((
+                        "content",
+                        format!("width={}, height={}", width, height).as_str(),
+                    ))
+                    .write_empty()?;
+                writer
+                    .create_element("link")
+                    .with_attribute(("rel", "stylesheet"))
+                    .with_attribute(("type", "text/css"))
+                    .with_attribute(("href", "../style.css"))
+                    .write_empty()?;
+                Ok(())
+            })?;
+
+            writer.create_element("body").write_inner_content(|writer| {
+                writer
+                    .create_element("div")
+                    .with_attribute(("class", "page"))
+                    .with_attribute((
+                        "style",
+                        format!("width: {}px; height: {}px;", width, height).as_str(),
+                    ))
+                    .write_inner_content(|writer| {
+                        writer
+                            .create_element("img")
+                            .with_attribute((
+                                "src",
+                                format!("../images/{}.{}", name, page.info.format.extension())
+                                    .as_str(),
+                            ))
+                            .with_attribute(("alt", ""))
+                            .write_empty()?;
+
+                        for overlay in &page.overlays {
+                            // A rough fit that keeps a few lines of text
+                            // inside the region.
+                            let region_height = overlay.height * height as f32;
+                            let font_size = (region_height / 4.0)
+                                .min(width as f32 * 0.04)
+                                .max(10.0);
+
+                            writer
+                                .create_element("div")
+                                .with_attribute(("class", "overlay"))
+                                .with_attribute((
+                                    "style",
+                                    format!(
+                                        "left: {:.3}%; top: {:.3}%; width: {:.3}%; height: {:.3}%; font-size: {:.1}px;",
+                                        overlay.x * 100.0,
+                                        overlay.y * 100.0,
+                                        overlay.width * 100.0,
+                                        overlay.height * 100.0,
+                                        font_size
+                                    )
+                                    .as_str(),
+                                ))
+                                .write_text_content(BytesText::new(&overlay.text))?;
+                        }
+
+                        Ok(())
+                    })?;
+
+                Ok(())
+            })?;
+
+            Ok(())
+        })?;
+
+    Ok(finish_xml(writer))
+}
+
+/// Writes chapters as a fixed layout EPUB with one spread-less page per
+/// image. Overlays are laid over the page as opaque text boxes, so readers see
+/// the translated text in place of the original.
+pub fn write_epub(
+    metadata: &ExportMetadata,
+    chapters: &[ExportChapter],
+) -> Result<Vec<u8>, ArchiveError> {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let stored = FileOptions::default().compression_method(CompressionMethod::Stored);
+    let deflated = FileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    // The mimetype has to come first and can't be compressed.
+    zip.start_file("mimetype", stored)?;
+    zip.write_all(b"application/epub+zip")?;
+
+    zip.start_file("META-INF/container.xml", deflated)?;
+    zip.write_all(&epub_container()?)?;
+
+    zip.start_file("OEBPS/content.opf", deflated)?;
+    zip.write_all(&epub_package(metadata, chapters)?)?;
+
+    zip.start_file("OEBPS/nav.xhtml", deflated)?;
+    zip.write_all(&epub_nav(metadata, chapters)?)?;
+
+    zip.start_file("OEBPS/style.css", deflated)?;
+    zip.write_all(EPUB_STYLE.as_bytes())?;
+
+    for (chapter_index, chapter) in chapters.iter().enumerate() {
+        for (page_index, page) in chapter.pages.iter().enumerate() {
+            let name = page_file_name(chapter_index, page_index);
+
+            zip.start_file(
+                format!("OEBPS/images/{}.{}", name, page.info.format.extension()),
+                stored,
+            )?;
+            zip.write_all(&page.image)?;
+
+            zip.start_file(format!("OEBPS/pages/{}.xhtml", name), deflated)?;
+            zip.write_all(&epub_page(metadata, chapter, page, &name)?)?;
+        }
+    }
+
+    Ok(zip.finish()?.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn natural_cmp_orders_numbers_by_value() {
+        let mut names = vec![
+            "page10.png",
+            "page2.png",
+            "Page1.png",
+            "page2b.png",
+            "page02a.png",
+            "cover.png",
+            "page100.png",
+        ];
+        names.sort_by(|a, b| natural_cmp(a, b));
+
+        assert_eq!(
+            names,
+            vec![
+                "cover.png",
+                "Page1.png",
+                "page2.png",
+                "page02a.png",
+                "page2b.png",
+                "page10.png",
+                "page100.png",
+            ]
+        );
+    }
+
+    #[test]
+    fn natural_cmp_breaks_ties() {
+        assert_eq!(natural_cmp("a", "a"), Ordering::Equal);
+        assert_eq!(natural_cmp("a", "ab"), Ordering::Less);
+        assert_eq!(natural_cmp("A", "a"), "A".cmp("a"));
+        assert_eq!(natural_cmp("page01", "page1"), "page01".cmp("page1"));
+        assert_eq!(
+            natural_cmp("page99999999999999999999", "page100000000000000000000"),
+            Ordering::Less
+        );
+    }
+}
@@ -0,0 +1,27 @@
+use axum::http::StatusCode;
+use gablet_shared_api::errors::{get_error, get_error_from_string, ErrorResult};
+use gablet_tokens::AuthToken;
+
+use crate::models::users::UserLevel;
+
+/// Returns the user level stored in an access token.
+pub fn get_user_level(token: &AuthToken) -> Result<UserLevel, ErrorResult> {
+    token
+        .role()
+        .parse()
+        .map_err(|err| get_error(err, StatusCode::UNAUTHORIZED))
+}
+
+/// Returns an error unless the owner of the token is at least the given level.
+pub fn require_user_level(token: &AuthToken, level: UserLevel) -> Result<UserLevel, ErrorResult> {
+    let user_level = get_user_level(token)?;
+
+    if user_level < level {
+        return Err(get_error_from_string(
+            StatusCode::FORBIDDEN,
+            format!("User {} must be at least {}", token.username(), level),
+        ));
+    }
+
+    Ok(user_level)
+}
@@ -0,0 +1,220 @@
+use diesel::result::Error as DbError;
+use diesel::{insert_into, prelude::*};
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use gablet_tokens::AuthToken;
+
+use crate::{
+    models::{
+        books::{Book, ContentRating},
+        chapters::{Chapter, NewChapter},
+        moderation::{ModerationKind, NewModerationItem},
+        users::UserLevel,
+    },
+    utils::{auth::get_user_level, moderation::submit_for_review},
+};
+
+/// The highest content rating shown to visitors who aren't logged in.
+pub const ANONYMOUS_MAX_RATING: ContentRating = ContentRating::AllAges;
+
+pub async fn find_book(
+    book_id: i32,
+    connection: &mut AsyncPgConnection,
+) -> Result<Option<Book>, DbError> {
+    use crate::schema::books::dsl::{books as db_books, id as db_id};
+
+    db_books
+        .filter(db_id.eq(book_id))
+        .select(Book::as_select())
+        .first(connection)
+        .await
+        .optional()
+}
+
+/// Finds a chapter that readers can see, meaning both the chapter and its book
+/// have been approved and the chapter has been published.
+pub async fn find_published_chapter(
+    chapter_id: i32,
+    connection: &mut AsyncPgConnection,
+) -> Result<Option<Chapter>, DbError> {
+    use crate::schema::books::dsl::approved as db_book_approved;
+    use crate::schema::chapters::dsl::{
+        approved as db_approved, chapters as db_chapters, published as db_published,
+    };
+
+    db_chapters
+        .find(chapter_id)
+        .inner_join(crate::schema::books::table)
+        .filter(db_approved.eq(true))
+        .filter(db_published.is_not_null())
+        .filter(db_book_approved.eq(true))
+        .select(Chapter::as_select())
+        .first(connection)
+        .await
+        .optional()
+}
+
+/// Adds a chapter to a book and submits it for review. This should be run
+/// inside a transaction so the chapter isn't left without a review.
+pub async fn insert_chapter(
+    chapter: NewChapter,
+    author_id: i32,
+    connection: &mut AsyncPgConnection,
+) -> Result<Chapter, DbError> {
+    use crate::schema::chapters::dsl::chapters as db_chapters;
+
+    let chapter: Chapter = insert_into(db_chapters)
+        .values(chapter)
+        .returning(Chapter::as_returning())
+        .get_result(connection)
+        .await?;
+
+    submit_for_review(
+        NewModerationItem {
+            kind: ModerationKind::Chapter,
+            book_id: chapter.book_id,
+            chapter_id: Some(chapter.id),
+            submitted_by: author_id,
+        },
+        connection,
+    )
+    .await?;
+
+    Ok(chapter)
+}
+
+/// Returns whether the owner of the token can see the parts of a book that
+/// haven't been approved yet, which is limited to the author and moderators.
+pub fn can_view_unapproved(book: &Book, token: Option<&AuthToken>) -> bool {
+    match token {
+        Some(token) => {
+            token.user_id() == book.author_id
+                || get_user_level(token)
+                    .map(|level| level >= UserLevel::Mod)
+                    .unwrap_or(false)
+        }
+        None => false,
+    }
+}
+
+/// Returns whether a chapter can be read. Readers only see released chapters
+/// of approved books, and the people who can see the unapproved parts of a
+/// book see all of its chapters.
+pub fn can_view_chapter(book: &Book, chapter: &Chapter, token: Option<&AuthToken>) -> bool {
+    (book.approved && chapter.approved && chapter.published.is_some())
+        || can_view_unapproved(book, token)
+}
+
+/// Returns the highest content rating a listing should show. Logged in users
+/// get the rating from their settings and anonymous visitors get
+/// `ANONYMOUS_MAX_RATING`. A requested rating can only lower the limit.
+pub async fn get_max_content_rating(
+    token: Option<&AuthToken>,
+    requested: Option<ContentRating>,
+    connection: &mut AsyncPgConnection,
+) -> Result<ContentRating, DbError> {
+    use crate::schema::users::dsl::{
+        max_content_rating as db_max_content_rating, users as db_users,
+    };
+
+    let allowed = match token {
+        Some(token) => db_users
+            .find(token.user_id())
+            .select(db_max_content_rating)
+            .first(connection)
+            .await
+            .optional()?
+            .unwrap_or(ANONYMOUS_MAX_RATING),
+        None => ANONYMOUS_MAX_RATING,
+    };
+
+    Ok(match requested {
+        Some(requested) if requested < allowed => requested,
+        _ => allowed,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+    use crate::models::books::BookStatus;
+
+    const AUTHOR: i32 = 1;
+    const READER: i32 = 2;
+
+    fn book(approved: bool) -> Book {
+        Book {
+            id: 1,
+            name: "Book".into(),
+            description: None,
+            approved,
+            small_thumbnail: None,
+            big_thumbnail: None,
+            author_id: AUTHOR,
+            lang: "en".into(),
+            status: BookStatus::Ongoing,
+            content_rating: ContentRating::AllAges,
+        }
+    }
+
+    fn chapter(approved: bool, released: bool) -> Chapter {
+        let now = Utc::now().naive_utc();
+
+        Chapter {
+            id: 1,
+            book_id: 1,
+            number: 1,
+            title: None,
+            created: now,
+            approved,
+            published: released.then_some(now),
+            publish_at: (!released).then_some(now),
+        }
+    }
+
+    fn token(user_id: i32, role: &str) -> AuthToken {
+        AuthToken::new("user", user_id, role, 60)
+    }
+
+    #[test]
+    fn readers_only_see_released_chapters_of_approved_books() {
+        let reader = Some(token(READER, "user"));
+        let reader = reader.as_ref();
+
+        assert!(can_view_chapter(&book(true), &chapter(true, true), None));
+        assert!(can_view_chapter(&book(true), &chapter(true, true), reader));
+        assert!(!can_view_chapter(&book(true), &chapter(true, false), None));
+        assert!(!can_view_chapter(
+            &book(true),
+            &chapter(true, false),
+            reader
+        ));
+        assert!(!can_view_chapter(
+            &book(true),
+            &chapter(false, true),
+            reader
+        ));
+        assert!(!can_view_chapter(
+            &book(false),
+            &chapter(true, true),
+            reader
+        ));
+    }
+
+    #[test]
+    fn authors_and_mods_see_unreleased_chapters() {
+        let unreleased = chapter(true, false);
+
+        assert!(can_view_chapter(
+            &book(true),
+            &unreleased,
+            Some(&token(AUTHOR, "user"))
+        ));
+        assert!(can_view_chapter(
+            &book(false),
+            &unreleased,
+            Some(&token(READER, "mod"))
+        ));
+    }
+}
@@ -0,0 +1,137 @@
+use serde::Serialize;
+
+/// Diffs with more token pairs than this aren't worth lining up, so the old
+/// text is shown as deleted and the new text as inserted.
+const MAX_DIFF_CELLS: usize = 4_000_000;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffOp {
+    Equal,
+    Insert,
+    Delete,
+}
+
+/// A run of text that was kept, added or removed.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct DiffChunk {
+    pub op: DiffOp,
+    pub text: String,
+}
+
+/// Splits text into words and the whitespace between them, so the tokens can
+/// be joined back into the original text.
+fn tokenize(text: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut start = 0;
+    let mut in_whitespace = None;
+
+    for (index, char) in text.char_indices() {
+        let whitespace = char.is_whitespace();
+
+        if in_whitespace.is_some_and(|previous| previous != whitespace) {
+            tokens.push(&text[start..index]);
+            start = index;
+        }
+
+        in_whitespace = Some(whitespace);
+    }
+
+    if start < text.len() {
+        tokens.push(&text[start..]);
+    }
+
+    tokens
+}
+
+fn push_chunk(chunks: &mut Vec<DiffChunk>, op: DiffOp, text: &str) {
+    match chunks.last_mut() {
+        Some(last) if last.op == op => last.text.push_str(text),
+        _ => chunks.push(DiffChunk {
+            op,
+            text: text.to_string(),
+        }),
+    }
+}
+
+/// Computes a word level diff between two texts using the longest common
+/// subsequence of their words.
+pub fn diff_words(old: &str, new: &str) -> Vec<DiffChunk> {
+    let old_tokens = tokenize(old);
+    let new_tokens = tokenize(new);
+
+    // Shared leading and trailing tokens don't need to go through the table.
+    let prefix = old_tokens
+        .iter()
+        .zip(&new_tokens)
+        .take_while(|(old, new)| old == new)
+        .count();
+    let suffix = old_tokens[prefix..]
+        .iter()
+        .rev()
+        .zip(new_tokens[prefix..].iter().rev())
+        .take_while(|(old, new)| old == new)
+        .count();
+
+    let old_middle = &old_tokens[prefix..old_tokens.len() - suffix];
+    let new_middle = &new_tokens[prefix..new_tokens.len() - suffix];
+
+    let mut chunks = Vec::new();
+
+    for token in &old_tokens[..prefix] {
+        push_chunk(&mut chunks, DiffOp::Equal, token);
+    }
+
+    if old_middle.len().saturating_mul(new_middle.len()) > MAX_DIFF_CELLS {
+        for token in old_middle {
+            push_chunk(&mut chunks, DiffOp::Delete, token);
+        }
+        for token in new_middle {
+            push_chunk(&mut chunks, DiffOp::Insert, token);
+        }
+    } else {
+        // lengths[i][j] is the length of the longest common subsequence of
+        // old_middle[i..] and new_middle[j..].
+        let width = new_middle.len() + 1;
+        let mut lengths = vec![0u32; (old_middle.len() + 1) * width];
+
+        for i in (0..old_middle.len()).rev() {
+            for j in (0..new_middle.len()).rev() {
+                lengths[i * width + j] = if old_middle[i] == new_middle[j] {
+                    lengths[(i + 1) * width + j + 1] + 1
+                } else {
+                    lengths[(i + 1) * width + j].max(lengths[i * width + j + 1])
+                };
+            }
+        }
+
+        let (mut i, mut j) = (0, 0);
+
+        while i < old_middle.len() && j < new_middle.len() {
+            if old_middle[i] == new_middle[j] {
+                push_chunk(&mut chunks, DiffOp::Equal, old_middle[i]);
+                i += 1;
+                j += 1;
+            } else if lengths[(i + 1) * width + j] >= lengths[i * width + j + 1] {
+                push_chunk(&mut chunks, DiffOp::Delete, old_middle[i]);
+                i += 1;
+            } else {
+                push_chunk(&mut chunks, DiffOp::Insert, new_middle[j]);
+                j += 1;
+            }
+        }
+
+        for token in &old_middle[i..] {
+            push_chunk(&mut chunks, DiffOp::Delete, token);
+        }
+        for token in &new_middle[j..] {
+            push_chunk(&mut chunks, DiffOp::Insert, token);
+        }
+    }
+
+    for token in &old_tokens[old_tokens.len() - suffix..] {
+        push_chunk(&mut chunks, DiffOp::Equal, token);
+    }
+
+    chunks
+}
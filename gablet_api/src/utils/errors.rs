@@ -0,0 +1,14 @@
+use axum::{http::StatusCode, Json};
+use diesel::result::{DatabaseErrorKind, Error as DbError};
+use gablet_shared_api::errors::{get_error_from_string, get_internal_error, ErrorResult};
+
+/// Turns a unique constraint violation into a conflict with the given message.
+/// Any other error is an internal error.
+pub fn map_insert_error(err: DbError, message: String) -> (StatusCode, Json<ErrorResult>) {
+    match err {
+        DbError::DatabaseError(DatabaseErrorKind::UniqueViolation, _) => {
+            get_error_from_string(StatusCode::CONFLICT, message).to_tuple()
+        }
+        err => get_internal_error(err).to_tuple(),
+    }
+}
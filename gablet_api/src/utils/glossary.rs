@@ -0,0 +1,313 @@
+use diesel::result::Error as DbError;
+use diesel::{dsl::exists, prelude::*};
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use serde::Serialize;
+
+use crate::models::glossary::GlossaryTerm;
+
+pub const MAX_TERM_LENGTH: usize = 200;
+
+/// A glossary term found in the source text of a region. `start` and `end`
+/// are character offsets into the text.
+#[derive(Serialize, Debug, Clone)]
+pub struct GlossaryMatch {
+    pub term: GlossaryTerm,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// The form source terms are compared in.
+pub fn glossary_key(term: &str) -> String {
+    term.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Scripts that don't put spaces between words, where a term can start or end
+/// anywhere.
+fn is_unspaced(char: char) -> bool {
+    matches!(char,
+        '\u{3040}'..='\u{30FF}' // Hiragana and Katakana
+        | '\u{3400}'..='\u{4DBF}' // CJK Extension A
+        | '\u{4E00}'..='\u{9FFF}' // CJK Unified Ideographs
+        | '\u{AC00}'..='\u{D7AF}' // Hangul
+        | '\u{FF66}'..='\u{FF9F}' // Half width Katakana
+    )
+}
+
+fn is_word_char(char: char) -> bool {
+    char.is_alphanumeric() && !is_unspaced(char)
+}
+
+fn chars_match(a: char, b: char) -> bool {
+    a == b || a.to_lowercase().eq(b.to_lowercase())
+}
+
+/// Finds every glossary term in a text, ignoring case. In scripts that use
+/// spaces, terms only match whole words. Matches are ordered by position, with
+/// longer terms first when two start at the same place.
+pub fn find_glossary_matches(text: &str, terms: &[GlossaryTerm]) -> Vec<GlossaryMatch> {
+    let text: Vec<char> = text.chars().collect();
+    let mut matches = Vec::new();
+
+    for term in terms {
+        let term_chars: Vec<char> = term.source_term.chars().collect();
+
+        let (Some(first), Some(last)) = (
+            term.source_term.chars().next(),
+            term.source_term.chars().last(),
+        ) else {
+            continue;
+        };
+
+        if term_chars.len() > text.len() {
+            continue;
+        }
+
+        for start in 0..=text.len() - term_chars.len() {
+            let end = start + term_chars.len();
+
+            if !text[start..end]
+                .iter()
+                .zip(&term_chars)
+                .all(|(a, b)| chars_match(*a, *b))
+            {
+                continue;
+            }
+
+            let starts_word = start == 0 || !is_word_char(first) || !is_word_char(text[start - 1]);
+            let ends_word = end == text.len() || !is_word_char(last) || !is_word_char(text[end]);
+
+            if starts_word && ends_word {
+                matches.push(GlossaryMatch {
+                    term: term.clone(),
+                    start,
+                    end,
+                });
+            }
+        }
+    }
+
+    matches.sort_by(|a, b| a.start.cmp(&b.start).then(b.end.cmp(&a.end)));
+    matches
+}
+
+/// Loads the glossary of a book. Terms for every language are returned if no
+/// language is given.
+pub async fn load_glossary(
+    book_id: i32,
+    lang: Option<&str>,
+    connection: &mut AsyncPgConnection,
+) -> Result<Vec<GlossaryTerm>, DbError> {
+    use crate::schema::glossary_terms::dsl::{
+        book_id as db_book_id, glossary_terms as db_glossary_terms, lang as db_lang,
+        source_key as db_source_key,
+    };
+
+    let mut query = db_glossary_terms
+        .filter(db_book_id.eq(book_id))
+        .into_boxed();
+
+    if let Some(lang) = lang {
+        query = query.filter(db_lang.eq(lang.to_string()));
+    }
+
+    query
+        .order((db_lang.asc(), db_source_key.asc()))
+        .select(GlossaryTerm::as_select())
+        .load(connection)
+        .await
+}
+
+/// Finds the book a text region belongs to.
+pub async fn find_region_book_id(
+    region_id: i32,
+    connection: &mut AsyncPgConnection,
+) -> Result<Option<i32>, DbError> {
+    use crate::schema::chapters::dsl::{book_id as db_book_id, chapters as db_chapters};
+    use crate::schema::pages::dsl::pages as db_pages;
+    use crate::schema::text_regions::dsl::text_regions as db_regions;
+
+    db_regions
+        .find(region_id)
+        .inner_join(db_pages.inner_join(db_chapters))
+        .select(db_book_id)
+        .first(connection)
+        .await
+        .optional()
+}
+
+/// Returns whether a user can change the glossary of a book for a language,
+/// which is limited to the author and people translating the book into that
+/// language.
+pub async fn can_edit_glossary(
+    book_author_id: i32,
+    book_id: i32,
+    lang: &str,
+    user_id: i32,
+    connection: &mut AsyncPgConnection,
+) -> Result<bool, DbError> {
+    use crate::schema::chapters::dsl::{book_id as db_book_id, chapters as db_chapters};
+    use crate::schema::pages::dsl::pages as db_pages;
+    use crate::schema::text_region_translations::dsl::{
+        lang as db_lang, text_region_translations as db_translations,
+        translator_id as db_translator_id,
+    };
+    use crate::schema::text_regions::dsl::text_regions as db_regions;
+
+    if book_author_id == user_id {
+        return Ok(true);
+    }
+
+    diesel::select(exists(
+        db_translations
+            .inner_join(db_regions.inner_join(db_pages.inner_join(db_chapters)))
+            .filter(db_book_id.eq(book_id))
+            .filter(db_lang.eq(lang.to_string()))
+            .filter(db_translator_id.eq(user_id)),
+    ))
+    .get_result(connection)
+    .await
+}
+
+/// A row of CSV or TSV text and the line of the text it starts on.
+pub struct DelimitedRow {
+    pub line: usize,
+    pub fields: Vec<String>,
+}
+
+/// Splits CSV or TSV text into rows of fields. Fields can be quoted with `"`,
+/// and quotes inside a quoted field are written twice. Reading stops with an
+/// error as soon as there are more than `max_rows` rows.
+pub fn parse_delimited(
+    text: &str,
+    delimiter: char,
+    max_rows: usize,
+) -> Result<Vec<DelimitedRow>, String> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut chars = text.trim_start_matches('\u{FEFF}').chars().peekable();
+    let mut line = 1;
+    let mut row_line = 1;
+    let mut in_quotes = false;
+    let mut quoted = false;
+
+    let too_many_rows = || format!("At most {} rows can be read at once", max_rows);
+
+    while let Some(char) = chars.next() {
+        if in_quotes {
+            match char {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => in_quotes = false,
+                '\n' => {
+                    line += 1;
+                    field.push(char);
+                }
+                _ => field.push(char),
+            }
+            continue;
+        }
+
+        match char {
+            '"' if field.is_empty() && !quoted => {
+                in_quotes = true;
+                quoted = true;
+            }
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                row.push(std::mem::take(&mut field));
+                rows.push(DelimitedRow {
+                    line: row_line,
+                    fields: std::mem::take(&mut row),
+                });
+                quoted = false;
+                line += 1;
+                row_line = line;
+
+                if rows.len() > max_rows {
+                    return Err(too_many_rows());
+                }
+            }
+            _ if char == delimiter => {
+                row.push(std::mem::take(&mut field));
+                quoted = false;
+            }
+            _ if quoted => {
+                return Err(format!(
+                    "Unexpected text after a quoted field on line {}",
+                    line
+                ));
+            }
+            _ => field.push(char),
+        }
+    }
+
+    if in_quotes {
+        return Err(format!("Unclosed quote on line {}", line));
+    }
+
+    if !field.is_empty() || quoted || !row.is_empty() {
+        row.push(field);
+        rows.push(DelimitedRow {
+            line: row_line,
+            fields: row,
+        });
+
+        if rows.len() > max_rows {
+            return Err(too_many_rows());
+        }
+    }
+
+    Ok(rows)
+}
+
+/// Joins rows of fields into CSV or TSV text, quoting fields that need it.
+pub fn write_delimited(rows: &[Vec<String>], delimiter: char) -> String {
+    let mut text = String::new();
+
+    for row in rows {
+        for (index, field) in row.iter().enumerate() {
+            if index > 0 {
+                text.push(delimiter);
+            }
+
+            if field.contains(['"', '\n', '\r', delimiter]) {
+                text.push('"');
+                text.push_str(&field.replace('"', "\"\""));
+                text.push('"');
+            } else {
+                text.push_str(field);
+            }
+        }
+
+        text.push_str("\r\n");
+    }
+
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rows_keep_the_line_they_start_on() {
+        let rows = parse_delimited("a,b\n\"multi\nline\",c\nd,e\n", ',', 10).unwrap();
+
+        let lines: Vec<usize> = rows.iter().map(|row| row.line).collect();
+        assert_eq!(lines, vec![1, 2, 4]);
+        assert_eq!(rows[1].fields, vec!["multi\nline", "c"]);
+    }
+
+    #[test]
+    fn too_many_rows_are_rejected() {
+        assert_eq!(parse_delimited("a\nb\n", ',', 2).unwrap().len(), 2);
+        assert!(parse_delimited("a\nb\nc", ',', 2).is_err());
+        assert!(parse_delimited("a\nb\nc\nd\n", ',', 2).is_err());
+    }
+}
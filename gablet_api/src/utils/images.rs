@@ -0,0 +1,324 @@
+/// The largest width or height a page image can have.
+pub const MAX_IMAGE_DIMENSION: u32 = 20_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Png,
+    Jpeg,
+    Gif,
+    Webp,
+}
+
+impl ImageFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Jpeg => "jpg",
+            ImageFormat::Gif => "gif",
+            ImageFormat::Webp => "webp",
+        }
+    }
+
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            ImageFormat::Png => "image/png",
+            ImageFormat::Jpeg => "image/jpeg",
+            ImageFormat::Gif => "image/gif",
+            ImageFormat::Webp => "image/webp",
+        }
+    }
+
+    pub fn from_extension(extension: &str) -> Option<ImageFormat> {
+        match extension.to_ascii_lowercase().as_str() {
+            "png" => Some(ImageFormat::Png),
+            "jpg" | "jpeg" => Some(ImageFormat::Jpeg),
+            "gif" => Some(ImageFormat::Gif),
+            "webp" => Some(ImageFormat::Webp),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageInfo {
+    pub format: ImageFormat,
+    pub width: u32,
+    pub height: u32,
+}
+
+fn u16_be(data: &[u8], at: usize) -> Option<u32> {
+    let bytes = data.get(at..at + 2)?;
+    Some(u16::from_be_bytes([bytes[0], bytes[1]]) as u32)
+}
+
+fn u16_le(data: &[u8], at: usize) -> Option<u32> {
+    let bytes = data.get(at..at + 2)?;
+    Some(u16::from_le_bytes([bytes[0], bytes[1]]) as u32)
+}
+
+fn u24_le(data: &[u8], at: usize) -> Option<u32> {
+    let bytes = data.get(at..at + 3)?;
+    Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], 0]))
+}
+
+fn u32_be(data: &[u8], at: usize) -> Option<u32> {
+    let bytes = data.get(at..at + 4)?;
+    Some(u32::from_be_bytes(bytes.try_into().ok()?))
+}
+
+fn u32_le(data: &[u8], at: usize) -> Option<u32> {
+    let bytes = data.get(at..at + 4)?;
+    Some(u32::from_le_bytes(bytes.try_into().ok()?))
+}
+
+fn read_png(data: &[u8]) -> Option<(u32, u32)> {
+    if data.get(12..16)? != b"IHDR" || data.get(data.len().checked_sub(8)?..)?[..4] != *b"IEND" {
+        return None;
+    }
+
+    Some((u32_be(data, 16)?, u32_be(data, 20)?))
+}
+
+fn read_gif(data: &[u8]) -> Option<(u32, u32)> {
+    if data.last() != Some(&0x3B) {
+        return None;
+    }
+
+    Some((u16_le(data, 6)?, u16_le(data, 8)?))
+}
+
+fn read_webp(data: &[u8]) -> Option<(u32, u32)> {
+    // The RIFF size covers everything after the first eight bytes.
+    if u32_le(data, 4)? as usize + 8 > data.len() {
+        return None;
+    }
+
+    match data.get(12..16)? {
+        b"VP8 " if data.get(23..26)? == [0x9D, 0x01, 0x2A] => {
+            Some((u16_le(data, 26)? & 0x3FFF, u16_le(data, 28)? & 0x3FFF))
+        }
+        b"VP8L" if *data.get(20)? == 0x2F => {
+            let bits = u32_le(data, 21)?;
+            Some((1 + (bits & 0x3FFF), 1 + ((bits >> 14) & 0x3FFF)))
+        }
+        b"VP8X" => Some((1 + u24_le(data, 24)?, 1 + u24_le(data, 27)?)),
+        _ => None,
+    }
+}
+
+fn read_jpeg(data: &[u8]) -> Option<(u32, u32)> {
+    let end = data.iter().rposition(|&byte| byte != 0)?;
+    if end < 1 || data[end - 1..=end] != [0xFF, 0xD9] {
+        return None;
+    }
+
+    let mut index = 2;
+
+    loop {
+        if *data.get(index)? != 0xFF {
+            return None;
+        }
+
+        while *data.get(index)? == 0xFF {
+            index += 1;
+        }
+
+        let marker = *data.get(index)?;
+        index += 1;
+
+        match marker {
+            // Markers without a length.
+            0x01 | 0xD0..=0xD7 => continue,
+            // The image data started before a frame header was found.
+            0xD9 | 0xDA => return None,
+            // Start of frame markers, apart from DHT, JPG and DAC.
+            0xC0..=0xCF if !matches!(marker, 0xC4 | 0xC8 | 0xCC) => {
+                return Some((u16_be(data, index + 5)?, u16_be(data, index + 3)?));
+            }
+            _ => index += u16_be(data, index)? as usize,
+        }
+    }
+}
+
+/// Works out the format and size of an image from its headers. Returns
+/// `None` if the data isn't a complete PNG, JPEG, GIF or WebP image, or if its
+/// size is out of bounds.
+///
+/// This only reads enough of the file to find its size and checks that it
+/// isn't cut short. It doesn't decode the pixels.
+pub fn read_image_info(data: &[u8]) -> Option<ImageInfo> {
+    let (format, (width, height)) = if data.starts_with(b"\x89PNG\r\n\x1A\n") {
+        (ImageFormat::Png, read_png(data)?)
+    } else if data.starts_with(&[0xFF, 0xD8]) {
+        (ImageFormat::Jpeg, read_jpeg(data)?)
+    } else if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+        (ImageFormat::Gif, read_gif(data)?)
+    } else if data.starts_with(b"RIFF") && data.get(8..12) == Some(b"WEBP") {
+        (ImageFormat::Webp, read_webp(data)?)
+    } else {
+        return None;
+    };
+
+    let in_bounds = |value: u32| (1..=MAX_IMAGE_DIMENSION).contains(&value);
+
+    if !in_bounds(width) || !in_bounds(height) {
+        return None;
+    }
+
+    Some(ImageInfo {
+        format,
+        width,
+        height,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut data = b"\x89PNG\r\n\x1A\n".to_vec();
+        data.extend([0, 0, 0, 13]);
+        data.extend(b"IHDR");
+        data.extend(width.to_be_bytes());
+        data.extend(height.to_be_bytes());
+        data.extend([8, 6, 0, 0, 0]);
+        data.extend([0; 4]);
+        data.extend([0, 0, 0, 0]);
+        data.extend(b"IEND");
+        data.extend([0xAE, 0x42, 0x60, 0x82]);
+        data
+    }
+
+    fn gif(width: u16, height: u16) -> Vec<u8> {
+        let mut data = b"GIF89a".to_vec();
+        data.extend(width.to_le_bytes());
+        data.extend(height.to_le_bytes());
+        data.extend([0, 0, 0, 0x3B]);
+        data
+    }
+
+    fn jpeg(width: u16, height: u16) -> Vec<u8> {
+        let mut data = vec![0xFF, 0xD8];
+        // An APP0 segment before the frame header, which has to be skipped.
+        data.extend([0xFF, 0xE0, 0, 16]);
+        data.extend(b"JFIF\0");
+        data.extend([1, 1, 0, 0, 1, 0, 1, 0, 0]);
+        data.extend([0xFF, 0xC0, 0, 11, 8]);
+        data.extend(height.to_be_bytes());
+        data.extend(width.to_be_bytes());
+        data.extend([1, 1, 0x11, 0]);
+        data.extend([0xFF, 0xD9]);
+        data
+    }
+
+    fn webp(chunk: &[u8; 4], header: &[u8]) -> Vec<u8> {
+        let mut data = b"RIFF".to_vec();
+        data.extend(((header.len() + 12) as u32).to_le_bytes());
+        data.extend(b"WEBP");
+        data.extend(chunk);
+        data.extend((header.len() as u32).to_le_bytes());
+        data.extend(header);
+        data
+    }
+
+    fn webp_lossless(width: u32, height: u32) -> Vec<u8> {
+        let bits = (width - 1) | ((height - 1) << 14);
+        let mut header = vec![0x2F];
+        header.extend(bits.to_le_bytes());
+        webp(b"VP8L", &header)
+    }
+
+    fn webp_extended(width: u32, height: u32) -> Vec<u8> {
+        let mut header = vec![0; 4];
+        header.extend(&(width - 1).to_le_bytes()[..3]);
+        header.extend(&(height - 1).to_le_bytes()[..3]);
+        webp(b"VP8X", &header)
+    }
+
+    fn info(format: ImageFormat, width: u32, height: u32) -> Option<ImageInfo> {
+        Some(ImageInfo {
+            format,
+            width,
+            height,
+        })
+    }
+
+    #[test]
+    fn reads_sizes() {
+        assert_eq!(
+            read_image_info(&png(800, 1200)),
+            info(ImageFormat::Png, 800, 1200)
+        );
+        assert_eq!(
+            read_image_info(&gif(320, 240)),
+            info(ImageFormat::Gif, 320, 240)
+        );
+        assert_eq!(
+            read_image_info(&jpeg(1024, 768)),
+            info(ImageFormat::Jpeg, 1024, 768)
+        );
+        assert_eq!(
+            read_image_info(&webp_lossless(640, 480)),
+            info(ImageFormat::Webp, 640, 480)
+        );
+        assert_eq!(
+            read_image_info(&webp_extended(1600, 900)),
+            info(ImageFormat::Webp, 1600, 900)
+        );
+    }
+
+    #[test]
+    fn rejects_truncated_images() {
+        for image in [
+            png(800, 1200),
+            gif(320, 240),
+            jpeg(1024, 768),
+            webp_lossless(640, 480),
+            webp_extended(1600, 900),
+        ] {
+            for length in 0..image.len() {
+                assert_eq!(
+                    read_image_info(&image[..length]),
+                    None,
+                    "{:?}",
+                    &image[..length]
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_headers() {
+        let mut png_without_ihdr = png(800, 1200);
+        png_without_ihdr[12..16].copy_from_slice(b"IHDX");
+        assert_eq!(read_image_info(&png_without_ihdr), None);
+
+        let mut jpeg_without_marker = jpeg(1024, 768);
+        jpeg_without_marker[2] = 0x00;
+        assert_eq!(read_image_info(&jpeg_without_marker), None);
+
+        // Image data before any frame header.
+        let mut jpeg_without_frame = vec![0xFF, 0xD8, 0xFF, 0xDA, 0, 2];
+        jpeg_without_frame.extend([0xFF, 0xD9]);
+        assert_eq!(read_image_info(&jpeg_without_frame), None);
+
+        let mut webp_bad_signature = webp_lossless(640, 480);
+        webp_bad_signature[20] = 0x00;
+        assert_eq!(read_image_info(&webp_bad_signature), None);
+
+        assert_eq!(read_image_info(&webp(b"ABCD", &[0; 10])), None);
+        assert_eq!(read_image_info(b"BM not an image"), None);
+    }
+
+    #[test]
+    fn rejects_sizes_out_of_bounds() {
+        assert_eq!(read_image_info(&png(0, 100)), None);
+        assert_eq!(read_image_info(&gif(100, 0)), None);
+        assert_eq!(read_image_info(&png(MAX_IMAGE_DIMENSION + 1, 100)), None);
+        assert_eq!(
+            read_image_info(&png(MAX_IMAGE_DIMENSION, 100)),
+            info(ImageFormat::Png, MAX_IMAGE_DIMENSION, 100)
+        );
+    }
+}
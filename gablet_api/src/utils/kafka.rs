@@ -0,0 +1,43 @@
+use kafka::producer::Record;
+use serde::Serialize;
+
+use crate::EVENT_PRODUCER;
+
+fn send_record(topic: &'static str, key: &'static str, value: String) -> Result<(), String> {
+    match EVENT_PRODUCER.lock() {
+        Ok(mut producer) => producer
+            .send(&Record::from_key_value(topic, key, value))
+            .map_err(|err| err.to_string()),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+/// Sends an event to kafka in the background, so requests don't have to wait
+/// on the broker. Failures are logged since nothing is waiting on the result.
+pub fn send_event<T: Serialize>(topic: &'static str, key: &'static str, event: &T) {
+    let value = match serde_json::to_string(event) {
+        Ok(value) => value,
+        Err(err) => {
+            tracing::error!("Failed to serialize kafka event {}: {}", key, err);
+            return;
+        }
+    };
+
+    tokio::task::spawn_blocking(move || {
+        if let Err(err) = send_record(topic, key, value) {
+            tracing::error!("Failed to send kafka event {}: {}", key, err);
+        }
+    });
+}
+
+/// Sends an event to kafka and waits for the broker to take it, for commands
+/// that exit as soon as they're done.
+pub fn send_event_now<T: Serialize>(
+    topic: &'static str,
+    key: &'static str,
+    event: &T,
+) -> Result<(), String> {
+    let value = serde_json::to_string(event).map_err(|err| err.to_string())?;
+
+    send_record(topic, key, value)
+}
@@ -0,0 +1,59 @@
+use axum::http::StatusCode;
+use gablet_shared_api::errors::{get_error_from_string, ErrorResult};
+
+/// The longest language code the database can store.
+pub const MAX_LANG_LENGTH: usize = 10;
+
+/// Whether a language code looks like `en`, `pt-BR` or `zh-Hant`: a two or
+/// three letter language followed by subtags of letters and digits.
+fn is_lang_code(lang: &str) -> bool {
+    let mut subtags = lang.split('-');
+
+    let language = subtags.next().unwrap_or_default();
+
+    (2..=3).contains(&language.len())
+        && language.chars().all(|c| c.is_ascii_alphabetic())
+        && subtags.all(|subtag| {
+            (2..=8).contains(&subtag.len()) && subtag.chars().all(|c| c.is_ascii_alphanumeric())
+        })
+}
+
+pub fn validate_lang(lang: &str) -> Result<(), ErrorResult> {
+    if lang.len() > MAX_LANG_LENGTH || !is_lang_code(lang) {
+        return Err(get_error_from_string(
+            StatusCode::BAD_REQUEST,
+            format!("Invalid language code {}", lang),
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_language_codes() {
+        for lang in ["en", "ja", "fil", "pt-BR", "zh-Hant", "es-419"] {
+            assert!(validate_lang(lang).is_ok(), "{}", lang);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_codes() {
+        for lang in [
+            "",
+            "e",
+            "english",
+            "en_US",
+            "en-",
+            "-en",
+            "en-x",
+            "zh-Hant-TWN",
+            "日本",
+        ] {
+            assert!(validate_lang(lang).is_err(), "{}", lang);
+        }
+    }
+}
@@ -0,0 +1,216 @@
+use std::{error::Error, fmt::Display};
+
+use diesel::result::Error as DbError;
+use diesel::{insert_into, prelude::*};
+use diesel_async::{
+    scoped_futures::ScopedFutureExt, AsyncConnection, AsyncPgConnection, RunQueryDsl,
+};
+
+use crate::{
+    models::ledger::{
+        LedgerAccountKind, LedgerEntry, LedgerTransaction, NewLedgerEntry, NewLedgerTransaction,
+        ShareAgreement,
+    },
+    utils::revenue_split::{split_earning, SplitError},
+};
+
+#[derive(Debug)]
+pub enum LedgerError {
+    Db(DbError),
+    Split(SplitError),
+}
+
+impl Display for LedgerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LedgerError::Db(err) => write!(f, "Ledger database error: {}", err),
+            LedgerError::Split(err) => write!(f, "Ledger split error: {}", err),
+        }
+    }
+}
+
+impl Error for LedgerError {}
+
+impl From<DbError> for LedgerError {
+    fn from(err: DbError) -> Self {
+        LedgerError::Db(err)
+    }
+}
+
+impl From<SplitError> for LedgerError {
+    fn from(err: SplitError) -> Self {
+        LedgerError::Split(err)
+    }
+}
+
+/// Returns the id of the shared revenue or platform account.
+pub async fn get_system_account(
+    kind: LedgerAccountKind,
+    connection: &mut AsyncPgConnection,
+) -> Result<i32, DbError> {
+    use crate::schema::ledger_accounts::dsl::{
+        id as db_id, kind as db_kind, ledger_accounts as db_accounts, user_id as db_user_id,
+    };
+
+    db_accounts
+        .filter(db_kind.eq(kind))
+        .filter(db_user_id.is_null())
+        .select(db_id)
+        .first(connection)
+        .await
+}
+
+/// Returns the id of a user's contributor account, if they have one yet.
+pub async fn find_contributor_account(
+    user_id: i32,
+    connection: &mut AsyncPgConnection,
+) -> Result<Option<i32>, DbError> {
+    use crate::schema::ledger_accounts::dsl::{
+        id as db_id, ledger_accounts as db_accounts, user_id as db_user_id,
+    };
+
+    db_accounts
+        .filter(db_user_id.eq(user_id))
+        .select(db_id)
+        .first(connection)
+        .await
+        .optional()
+}
+
+/// Returns the id of a user's contributor account, creating it if needed.
+pub async fn get_contributor_account(
+    user_id: i32,
+    connection: &mut AsyncPgConnection,
+) -> Result<i32, DbError> {
+    use crate::schema::ledger_accounts::dsl::{
+        id as db_id, kind as db_kind, ledger_accounts as db_accounts, user_id as db_user_id,
+    };
+
+    insert_into(db_accounts)
+        .values((
+            db_kind.eq(LedgerAccountKind::Contributor),
+            db_user_id.eq(user_id),
+        ))
+        .on_conflict(db_user_id)
+        .do_nothing()
+        .execute(connection)
+        .await?;
+
+    db_accounts
+        .filter(db_user_id.eq(user_id))
+        .select(db_id)
+        .first(connection)
+        .await
+}
+
+/// Returns the share agreements for a book, or for one translation of it.
+pub async fn get_share_agreements(
+    book_id: i32,
+    lang: Option<&str>,
+    connection: &mut AsyncPgConnection,
+) -> Result<Vec<ShareAgreement>, DbError> {
+    use crate::schema::share_agreements::dsl::{
+        book_id as db_book_id, id as db_id, lang as db_lang, share_agreements as db_agreements,
+    };
+
+    let mut query = db_agreements.filter(db_book_id.eq(book_id)).into_boxed();
+
+    query = match lang {
+        Some(lang) => query.filter(db_lang.eq(lang.to_owned())),
+        None => query.filter(db_lang.is_null()),
+    };
+
+    query
+        .order(db_id.asc())
+        .select(ShareAgreement::as_select())
+        .load(connection)
+        .await
+}
+
+/// Records an earning and credits it to the contributors of the book (and of
+/// the translation, if the earning has a language) in a single transaction.
+pub async fn record_earning(
+    earning: NewLedgerTransaction,
+    connection: &mut AsyncPgConnection,
+) -> Result<(LedgerTransaction, Vec<LedgerEntry>), LedgerError> {
+    use crate::schema::ledger_entries::dsl::ledger_entries as db_entries;
+    use crate::schema::ledger_transactions::dsl::ledger_transactions as db_transactions;
+
+    connection
+        .transaction(|connection| {
+            async move {
+                let book_shares = get_share_agreements(earning.book_id, None, connection).await?;
+                let translation_shares = match &earning.lang {
+                    Some(lang) => {
+                        get_share_agreements(earning.book_id, Some(lang), connection).await?
+                    }
+                    None => Vec::new(),
+                };
+
+                let bps = |agreements: &[ShareAgreement]| -> Vec<u32> {
+                    agreements
+                        .iter()
+                        .map(|agreement| agreement.share_bps as u32)
+                        .collect()
+                };
+
+                let split = split_earning(
+                    earning.amount_cents,
+                    &bps(&translation_shares),
+                    &bps(&book_shares),
+                )?;
+
+                let transaction: LedgerTransaction = insert_into(db_transactions)
+                    .values(&earning)
+                    .returning(LedgerTransaction::as_returning())
+                    .get_result(connection)
+                    .await?;
+
+                let revenue_account =
+                    get_system_account(LedgerAccountKind::Revenue, connection).await?;
+                let platform_account =
+                    get_system_account(LedgerAccountKind::Platform, connection).await?;
+
+                let mut entries = vec![NewLedgerEntry {
+                    transaction_id: transaction.id,
+                    account_id: revenue_account,
+                    amount_cents: -transaction.amount_cents,
+                }];
+
+                let credits = translation_shares
+                    .iter()
+                    .zip(split.translation)
+                    .chain(book_shares.iter().zip(split.book));
+
+                for (agreement, amount_cents) in credits {
+                    if amount_cents == 0 {
+                        continue;
+                    }
+
+                    entries.push(NewLedgerEntry {
+                        transaction_id: transaction.id,
+                        account_id: get_contributor_account(agreement.user_id, connection).await?,
+                        amount_cents,
+                    });
+                }
+
+                if split.platform != 0 {
+                    entries.push(NewLedgerEntry {
+                        transaction_id: transaction.id,
+                        account_id: platform_account,
+                        amount_cents: split.platform,
+                    });
+                }
+
+                let entries: Vec<LedgerEntry> = insert_into(db_entries)
+                    .values(entries)
+                    .returning(LedgerEntry::as_returning())
+                    .get_results(connection)
+                    .await?;
+
+                Ok((transaction, entries))
+            }
+            .scope_boxed()
+        })
+        .await
+}
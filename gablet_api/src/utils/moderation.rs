@@ -0,0 +1,202 @@
+use chrono::NaiveDateTime;
+use diesel::result::Error as DbError;
+use diesel::{dsl::now, insert_into, prelude::*, update};
+use diesel_async::{
+    scoped_futures::ScopedFutureExt, AsyncConnection, AsyncPgConnection, RunQueryDsl,
+};
+
+use crate::{
+    models::{
+        moderation::{ModerationItem, ModerationKind, ModerationStatus, NewModerationItem},
+        notifications::{NewNotification, NotificationKind},
+    },
+    utils::{books::find_book, notifications::send_notification},
+};
+
+/// Adds a book, or a chapter of a book, to the moderation queue.
+pub async fn submit_for_review(
+    item: NewModerationItem,
+    connection: &mut AsyncPgConnection,
+) -> Result<ModerationItem, DbError> {
+    use crate::schema::moderation_items::dsl::moderation_items as db_items;
+
+    insert_into(db_items)
+        .values(item)
+        .returning(ModerationItem::as_returning())
+        .get_result(connection)
+        .await
+}
+
+/// Sends a rejected book or chapter back to the moderation queue. Its latest
+/// item goes back to pending and the earlier review is cleared. Chapters are
+/// resubmitted by passing their id, and books by leaving it out.
+///
+/// Returns `None` if the latest review of the book or chapter wasn't a
+/// rejection.
+pub async fn resubmit_item(
+    book_id: i32,
+    chapter_id: Option<i32>,
+    submitted_by: i32,
+    connection: &mut AsyncPgConnection,
+) -> Result<Option<ModerationItem>, DbError> {
+    use crate::schema::moderation_items::dsl::{
+        book_id as db_book_id, chapter_id as db_chapter_id, created as db_created, id as db_id,
+        kind as db_kind, moderation_items as db_items, reason as db_reason,
+        reviewed as db_reviewed, reviewed_by as db_reviewed_by, status as db_status,
+        submitted_by as db_submitted_by,
+    };
+
+    let mut latest_query = db_items.filter(db_book_id.eq(book_id)).into_boxed();
+
+    latest_query = match chapter_id {
+        Some(chapter_id) => latest_query
+            .filter(db_kind.eq(ModerationKind::Chapter))
+            .filter(db_chapter_id.eq(chapter_id)),
+        None => latest_query.filter(db_kind.eq(ModerationKind::Book)),
+    };
+
+    let latest: Option<i32> = latest_query
+        .order(db_id.desc())
+        .select(db_id)
+        .first(connection)
+        .await
+        .optional()?;
+
+    let Some(latest) = latest else {
+        return Ok(None);
+    };
+
+    // Checking the status in the update keeps two resubmissions from both
+    // going through.
+    update(db_items.find(latest))
+        .filter(db_status.eq(ModerationStatus::Rejected))
+        .set((
+            db_status.eq(ModerationStatus::Pending),
+            db_reason.eq(None::<String>),
+            db_reviewed_by.eq(None::<i32>),
+            db_reviewed.eq(None::<NaiveDateTime>),
+            db_submitted_by.eq(submitted_by),
+            db_created.eq(now),
+        ))
+        .returning(ModerationItem::as_returning())
+        .get_result(connection)
+        .await
+        .optional()
+}
+
+/// Approves or rejects a pending item, updates the visibility of the book or
+/// chapter and notifies the author of the book. Approved chapters are published
+/// straight away unless they're scheduled for later.
+///
+/// Returns `None` if there is no pending item with the given id.
+pub async fn review_item(
+    item_id: i32,
+    reviewer_id: i32,
+    status: ModerationStatus,
+    reason: Option<String>,
+    connection: &mut AsyncPgConnection,
+) -> Result<Option<ModerationItem>, DbError> {
+    use crate::schema::books::dsl::{approved as db_book_approved, books as db_books};
+    use crate::schema::chapters::dsl::{
+        approved as db_chapter_approved, chapters as db_chapters, publish_at as db_publish_at,
+        published as db_published,
+    };
+    use crate::schema::moderation_items::dsl::{
+        id as db_id, moderation_items as db_items, reason as db_reason, reviewed as db_reviewed,
+        reviewed_by as db_reviewed_by, status as db_status,
+    };
+
+    connection
+        .transaction(|connection| {
+            async move {
+                let item: Option<ModerationItem> = update(db_items)
+                    .filter(db_id.eq(item_id))
+                    .filter(db_status.eq(ModerationStatus::Pending))
+                    .set((
+                        db_status.eq(status),
+                        db_reason.eq(&reason),
+                        db_reviewed_by.eq(reviewer_id),
+                        db_reviewed.eq(now),
+                    ))
+                    .returning(ModerationItem::as_returning())
+                    .get_result(connection)
+                    .await
+                    .optional()?;
+
+                let Some(item) = item else {
+                    return Ok(None);
+                };
+
+                let approved = status == ModerationStatus::Approved;
+
+                match (item.kind, item.chapter_id) {
+                    (ModerationKind::Chapter, Some(chapter_id)) if approved => {
+                        update(db_chapters.find(chapter_id))
+                            .set(db_chapter_approved.eq(true))
+                            .execute(connection)
+                            .await?;
+
+                        // Scheduled chapters are left for the release
+                        // scheduler unless their time has already come.
+                        update(db_chapters.find(chapter_id))
+                            .filter(db_published.is_null())
+                            .filter(db_publish_at.is_null().or(db_publish_at.le(now)))
+                            .set(db_published.eq(now))
+                            .execute(connection)
+                            .await?;
+                    }
+                    (ModerationKind::Chapter, Some(chapter_id)) => {
+                        update(db_chapters.find(chapter_id))
+                            .set(db_chapter_approved.eq(false))
+                            .execute(connection)
+                            .await?;
+                    }
+                    _ => {
+                        update(db_books.find(item.book_id))
+                            .set(db_book_approved.eq(approved))
+                            .execute(connection)
+                            .await?;
+                    }
+                }
+
+                let book = find_book(item.book_id, connection)
+                    .await?
+                    .ok_or(DbError::NotFound)?;
+
+                let (kind, subject) = match (item.kind, approved) {
+                    (ModerationKind::Book, true) => (NotificationKind::BookApproved, "Your book"),
+                    (ModerationKind::Book, false) => (NotificationKind::BookRejected, "Your book"),
+                    (ModerationKind::Chapter, true) => {
+                        (NotificationKind::ChapterApproved, "A chapter of your book")
+                    }
+                    (ModerationKind::Chapter, false) => {
+                        (NotificationKind::ChapterRejected, "A chapter of your book")
+                    }
+                };
+
+                let message = match &reason {
+                    Some(reason) if !approved => {
+                        format!("{} {} was rejected: {}", subject, book.name, reason)
+                    }
+                    _ if !approved => format!("{} {} was rejected", subject, book.name),
+                    _ => format!("{} {} was approved", subject, book.name),
+                };
+
+                send_notification(
+                    NewNotification {
+                        user_id: book.author_id,
+                        kind,
+                        message,
+                        book_id: Some(book.id),
+                        chapter_id: item.chapter_id,
+                    },
+                    connection,
+                )
+                .await?;
+
+                Ok(Some(item))
+            }
+            .scope_boxed()
+        })
+        .await
+}
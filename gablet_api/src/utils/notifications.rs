@@ -0,0 +1,19 @@
+use diesel::insert_into;
+use diesel::result::Error as DbError;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+
+use crate::models::notifications::NewNotification;
+
+pub async fn send_notification(
+    notification: NewNotification,
+    connection: &mut AsyncPgConnection,
+) -> Result<(), DbError> {
+    use crate::schema::notifications::dsl::notifications as db_notifications;
+
+    insert_into(db_notifications)
+        .values(notification)
+        .execute(connection)
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,182 @@
+use std::{error::Error, fmt::Display};
+
+/// The number of basis points in a whole amount.
+pub const TOTAL_BPS: u32 = 10_000;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum SplitError {
+    NegativeAmount(i64),
+    SharesExceedTotal(u64),
+}
+
+impl Display for SplitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SplitError::NegativeAmount(amount) => {
+                write!(f, "Cannot split a negative amount of {} cents", amount)
+            }
+            SplitError::SharesExceedTotal(bps) => write!(
+                f,
+                "Shares add up to {} basis points, which is more than {}",
+                bps, TOTAL_BPS
+            ),
+        }
+    }
+}
+
+impl Error for SplitError {}
+
+/// The result of splitting an earning between the translators of a book, the
+/// contributors of the book itself and the platform.
+#[derive(Debug, PartialEq, Eq)]
+pub struct EarningSplit {
+    pub translation: Vec<i64>,
+    pub book: Vec<i64>,
+    pub platform: i64,
+}
+
+/// Splits `amount_cents` between shares given in basis points.
+///
+/// Returns one amount per share followed by the amount that wasn't allocated
+/// to any share. Cents lost to rounding are handed out using the largest
+/// remainder method, so the amounts always add up to `amount_cents` and each
+/// one is less than a cent away from its exact proportional value.
+pub fn split_cents(amount_cents: i64, shares_bps: &[u32]) -> Result<Vec<i64>, SplitError> {
+    if amount_cents < 0 {
+        return Err(SplitError::NegativeAmount(amount_cents));
+    }
+
+    let allocated: u64 = shares_bps.iter().map(|bps| *bps as u64).sum();
+    if allocated > TOTAL_BPS as u64 {
+        return Err(SplitError::SharesExceedTotal(allocated));
+    }
+
+    let mut weights: Vec<i128> = shares_bps.iter().map(|bps| *bps as i128).collect();
+    weights.push(TOTAL_BPS as i128 - allocated as i128);
+
+    let amount = amount_cents as i128;
+    let total = TOTAL_BPS as i128;
+
+    let mut parts: Vec<i64> = Vec::with_capacity(weights.len());
+    let mut remainders: Vec<(i128, usize)> = Vec::with_capacity(weights.len());
+
+    for (index, weight) in weights.iter().enumerate() {
+        let exact = amount * weight;
+        parts.push((exact / total) as i64);
+        remainders.push((exact % total, index));
+    }
+
+    let leftover = amount_cents - parts.iter().sum::<i64>();
+
+    // Ties go to the earlier share so the result doesn't depend on sort stability.
+    remainders.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+
+    for (_, index) in remainders.iter().take(leftover as usize) {
+        parts[*index] += 1;
+    }
+
+    Ok(parts)
+}
+
+/// Splits an earning for a book, or for a translation of a book.
+///
+/// Translators are paid their share of the full amount first. Whatever they
+/// don't take is split between the contributors of the book, and anything
+/// left after that goes to the platform.
+pub fn split_earning(
+    amount_cents: i64,
+    translation_bps: &[u32],
+    book_bps: &[u32],
+) -> Result<EarningSplit, SplitError> {
+    let mut translation = split_cents(amount_cents, translation_bps)?;
+    let book_amount = translation.pop().unwrap_or_default();
+
+    let mut book = split_cents(book_amount, book_bps)?;
+    let platform = book.pop().unwrap_or_default();
+
+    Ok(EarningSplit {
+        translation,
+        book,
+        platform,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn shares() -> impl Strategy<Value = Vec<u32>> {
+        prop::collection::vec(1u32..=TOTAL_BPS, 0..8)
+            .prop_filter("shares must not exceed the total", |shares| {
+                shares.iter().map(|bps| *bps as u64).sum::<u64>() <= TOTAL_BPS as u64
+            })
+    }
+
+    #[test]
+    fn splits_evenly_divisible_amounts_exactly() {
+        assert_eq!(split_cents(1000, &[5000, 2500]), Ok(vec![500, 250, 250]));
+    }
+
+    #[test]
+    fn hands_out_rounding_cents_by_largest_remainder() {
+        assert_eq!(
+            split_cents(100, &[3333, 3333, 3334]),
+            Ok(vec![33, 33, 34, 0])
+        );
+        assert_eq!(split_cents(1, &[5000, 5000]), Ok(vec![1, 0, 0]));
+    }
+
+    #[test]
+    fn rejects_invalid_input() {
+        assert_eq!(split_cents(-1, &[]), Err(SplitError::NegativeAmount(-1)));
+        assert_eq!(
+            split_cents(100, &[6000, 5000]),
+            Err(SplitError::SharesExceedTotal(11000))
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn split_preserves_total(amount in 0i64..=i64::MAX / 2, shares in shares()) {
+            let parts = split_cents(amount, &shares).unwrap();
+            prop_assert_eq!(parts.len(), shares.len() + 1);
+            prop_assert_eq!(parts.iter().map(|part| *part as i128).sum::<i128>(), amount as i128);
+        }
+
+        #[test]
+        fn split_is_within_a_cent_of_exact(amount in 0i64..=i64::MAX / 2, shares in shares()) {
+            let parts = split_cents(amount, &shares).unwrap();
+            let allocated: u32 = shares.iter().sum();
+            let weights = shares.iter().copied().chain(std::iter::once(TOTAL_BPS - allocated));
+
+            for (part, weight) in parts.iter().zip(weights) {
+                prop_assert!(*part >= 0);
+                let exact = amount as i128 * weight as i128;
+                let diff = (*part as i128 * TOTAL_BPS as i128 - exact).abs();
+                prop_assert!(diff < TOTAL_BPS as i128);
+            }
+        }
+
+        #[test]
+        fn earning_split_preserves_total(
+            amount in 0i64..=i64::MAX / 2,
+            translation in shares(),
+            book in shares(),
+        ) {
+            let split = split_earning(amount, &translation, &book).unwrap();
+            prop_assert_eq!(split.translation.len(), translation.len());
+            prop_assert_eq!(split.book.len(), book.len());
+
+            let total = split.translation.iter().chain(split.book.iter()).map(|part| *part as i128).sum::<i128>()
+                + split.platform as i128;
+            prop_assert_eq!(total, amount as i128);
+        }
+
+        #[test]
+        fn over_allocated_shares_are_rejected(amount in 0i64..1_000_000, extra in 1u32..TOTAL_BPS) {
+            let result = split_cents(amount, &[TOTAL_BPS, extra]);
+            prop_assert_eq!(result, Err(SplitError::SharesExceedTotal(TOTAL_BPS as u64 + extra as u64)));
+        }
+    }
+}
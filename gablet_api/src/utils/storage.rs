@@ -0,0 +1,56 @@
+use std::{
+    io::{Error, ErrorKind},
+    path::{Component, Path, PathBuf},
+};
+
+use crate::utils::images::ImageFormat;
+
+/// Where page images are kept. The `image` of a page is its path relative to
+/// this directory.
+pub const PAGE_STORAGE_DIR: &str = "./storage/pages";
+
+/// The path a page image is stored under.
+pub fn page_image_key(chapter_id: i32, number: i32, format: ImageFormat) -> String {
+    format!("{}/{:04}.{}", chapter_id, number, format.extension())
+}
+
+/// Resolves a key to a file in the storage directory, refusing keys that
+/// would point outside of it.
+fn page_image_path(key: &str) -> Result<PathBuf, Error> {
+    let relative = Path::new(key);
+
+    if !relative
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("Invalid page image path {}", key),
+        ));
+    }
+
+    Ok(Path::new(PAGE_STORAGE_DIR).join(relative))
+}
+
+pub async fn save_page_image(key: &str, data: &[u8]) -> Result<(), Error> {
+    let path = page_image_path(key)?;
+
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+
+    tokio::fs::write(path, data).await
+}
+
+pub async fn load_page_image(key: &str) -> Result<Vec<u8>, Error> {
+    tokio::fs::read(page_image_path(key)?).await
+}
+
+/// Removes page images, ignoring any that are already gone.
+pub async fn delete_page_images(keys: &[String]) {
+    for key in keys {
+        if let Ok(path) = page_image_path(key) {
+            let _ = tokio::fs::remove_file(path).await;
+        }
+    }
+}
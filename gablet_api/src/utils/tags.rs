@@ -0,0 +1,169 @@
+use diesel::result::Error as DbError;
+use diesel::{delete, insert_into, prelude::*, sql_types::Varchar, update};
+use diesel_async::{
+    scoped_futures::ScopedFutureExt, AsyncConnection, AsyncPgConnection, RunQueryDsl,
+};
+
+use crate::models::tags::{NewBookTag, NewTagAlias, Tag};
+
+sql_function!(fn lower(x: Varchar) -> Varchar);
+
+/// Trims a tag name and collapses any runs of whitespace inside it.
+pub fn clean_tag_name(name: &str) -> String {
+    name.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// The form tag names and aliases are compared in.
+pub fn normalize_tag_name(name: &str) -> String {
+    clean_tag_name(name).to_lowercase()
+}
+
+/// Splits a comma separated list of tags from a query string.
+pub fn parse_tag_list(tags: &str) -> Vec<String> {
+    tags.split(',')
+        .map(clean_tag_name)
+        .filter(|tag| !tag.is_empty())
+        .collect()
+}
+
+/// Finds the canonical tags for a list of tag names or aliases.
+///
+/// Returns the tags that were found, without duplicates, along with the names
+/// that didn't match any tag.
+pub async fn resolve_tags(
+    names: &[String],
+    connection: &mut AsyncPgConnection,
+) -> Result<(Vec<Tag>, Vec<String>), DbError> {
+    use crate::schema::tag_aliases::dsl::{alias as db_alias, tag_aliases as db_tag_aliases};
+    use crate::schema::tags::dsl::{name as db_name, tags as db_tags};
+
+    let normalized: Vec<String> = names.iter().map(|name| normalize_tag_name(name)).collect();
+
+    let by_name: Vec<Tag> = db_tags
+        .filter(lower(db_name).eq_any(&normalized))
+        .select(Tag::as_select())
+        .load(connection)
+        .await?;
+
+    let by_alias: Vec<(String, Tag)> = db_tag_aliases
+        .inner_join(db_tags)
+        .filter(db_alias.eq_any(&normalized))
+        .select((db_alias, Tag::as_select()))
+        .load(connection)
+        .await?;
+
+    let mut found: Vec<Tag> = Vec::new();
+    let mut unknown: Vec<String> = Vec::new();
+
+    for (name, normalized_name) in names.iter().zip(&normalized) {
+        let tag = by_name
+            .iter()
+            .find(|tag| tag.name.to_lowercase() == *normalized_name)
+            .or_else(|| {
+                by_alias
+                    .iter()
+                    .find(|(alias, _)| alias == normalized_name)
+                    .map(|(_, tag)| tag)
+            });
+
+        match tag {
+            Some(tag) if !found.iter().any(|other| other.id == tag.id) => found.push(tag.clone()),
+            Some(_) => {}
+            None => unknown.push(name.clone()),
+        }
+    }
+
+    Ok((found, unknown))
+}
+
+/// Merges one tag into another. Books tagged with the old tag get the new one
+/// instead, and the old name and aliases become aliases of the new tag.
+///
+/// Returns the tag they were merged into and the books that were moved to it,
+/// or `None` if either tag doesn't exist.
+pub async fn merge_tags(
+    from_id: i32,
+    into_id: i32,
+    connection: &mut AsyncPgConnection,
+) -> Result<Option<(Tag, Vec<i32>)>, DbError> {
+    use crate::schema::book_tags::dsl::{
+        book_id as db_book_id, book_tags as db_book_tags, tag_id as db_book_tag_id,
+    };
+    use crate::schema::tag_aliases::dsl::{
+        tag_aliases as db_tag_aliases, tag_id as db_alias_tag_id,
+    };
+    use crate::schema::tags::dsl::tags as db_tags;
+
+    connection
+        .transaction(|connection| {
+            async move {
+                let from: Option<Tag> = db_tags
+                    .find(from_id)
+                    .select(Tag::as_select())
+                    .first(connection)
+                    .await
+                    .optional()?;
+
+                let into: Option<Tag> = db_tags
+                    .find(into_id)
+                    .select(Tag::as_select())
+                    .first(connection)
+                    .await
+                    .optional()?;
+
+                let (Some(from), Some(into)) = (from, into) else {
+                    return Ok(None);
+                };
+
+                let book_ids: Vec<i32> = db_book_tags
+                    .filter(db_book_tag_id.eq(from.id))
+                    .select(db_book_id)
+                    .load(connection)
+                    .await?;
+
+                // Deleting first keeps the search vectors of the books in step
+                // with the final set of tags.
+                delete(db_book_tags)
+                    .filter(db_book_tag_id.eq(from.id))
+                    .execute(connection)
+                    .await?;
+
+                if !book_ids.is_empty() {
+                    insert_into(db_book_tags)
+                        .values(
+                            book_ids
+                                .iter()
+                                .map(|&book_id| NewBookTag {
+                                    book_id,
+                                    tag_id: into.id,
+                                })
+                                .collect::<Vec<_>>(),
+                        )
+                        .on_conflict_do_nothing()
+                        .execute(connection)
+                        .await?;
+                }
+
+                update(db_tag_aliases)
+                    .filter(db_alias_tag_id.eq(from.id))
+                    .set(db_alias_tag_id.eq(into.id))
+                    .execute(connection)
+                    .await?;
+
+                delete(db_tags.find(from.id)).execute(connection).await?;
+
+                insert_into(db_tag_aliases)
+                    .values(NewTagAlias {
+                        tag_id: into.id,
+                        alias: normalize_tag_name(&from.name),
+                    })
+                    .on_conflict_do_nothing()
+                    .execute(connection)
+                    .await?;
+
+                Ok(Some((into, book_ids)))
+            }
+            .scope_boxed()
+        })
+        .await
+}
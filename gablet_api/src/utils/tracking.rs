@@ -0,0 +1,208 @@
+use std::{error::Error, sync::LazyLock, time::Duration};
+
+use diesel::{dsl::now, prelude::*, result::Error as DbError, select};
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use gablet_shared_api::{
+    kafka::kafka_events::{BOOK_TOPIC, TRACKED_BOOK_EVENT},
+    tracked_books::TrackedBook,
+    trending::{TrendingBook, TrendingQuery},
+};
+use hyper::{client::HttpConnector, Client, Uri};
+
+use crate::{
+    credentials::Credentials,
+    models::tags::TagKind,
+    utils::kafka::{send_event, send_event_now},
+};
+
+/// Where gablet_tracking listens if it isn't configured.
+const DEFAULT_TRACKING_URL: &str = "http://127.0.0.1:3001";
+
+/// How long to wait on gablet_tracking before giving up.
+const TRACKING_TIMEOUT: Duration = Duration::from_secs(5);
+
+static TRACKING_URL: LazyLock<String> = LazyLock::new(|| {
+    Credentials::new()
+        .unwrap()
+        .tracking
+        .map_or_else(|| DEFAULT_TRACKING_URL.to_string(), |tracking| tracking.url)
+});
+
+static HTTP_CLIENT: LazyLock<Client<HttpConnector>> = LazyLock::new(Client::new);
+
+/// Reads what gablet_tracking needs to know about a book, or `None` if there's
+/// no such book.
+pub async fn load_tracked_book(
+    book_id: i32,
+    connection: &mut AsyncPgConnection,
+) -> Result<Option<TrackedBook>, DbError> {
+    use crate::schema::book_tags::dsl::{book_id as db_tag_book_id, book_tags as db_book_tags};
+    use crate::schema::books::dsl::{
+        approved as db_approved, author_id as db_author_id, books as db_books, lang as db_lang,
+    };
+    use crate::schema::chapter_translations::dsl::{
+        chapter_translations as db_translations, lang as db_translation_lang,
+    };
+    use crate::schema::chapters::dsl::{
+        book_id as db_chapter_book_id, chapters as db_chapters, id as db_chapter_id,
+    };
+    use crate::schema::share_agreements::dsl::{
+        book_id as db_share_book_id, share_agreements as db_shares, user_id as db_share_user_id,
+    };
+    use crate::schema::tags::dsl::{kind as db_kind, name as db_name, tags as db_tags};
+    use crate::schema::workflow_members::dsl::{
+        book_id as db_member_book_id, user_id as db_member_user_id,
+        workflow_members as db_members,
+    };
+
+    // Read first, so a copy of the book that's read later is always newer.
+    let updated = select(now).get_result(connection).await?;
+
+    let Some((author_id, approved, lang)) = db_books
+        .find(book_id)
+        .select((db_author_id, db_approved, db_lang))
+        .first::<(i32, bool, String)>(connection)
+        .await
+        .optional()?
+    else {
+        return Ok(None);
+    };
+
+    let genres = db_book_tags
+        .inner_join(db_tags)
+        .filter(db_tag_book_id.eq(book_id))
+        .filter(db_kind.eq(TagKind::Genre))
+        .select(db_name)
+        .order(db_name)
+        .load(connection)
+        .await?;
+
+    let mut members: Vec<i32> = db_shares
+        .filter(db_share_book_id.eq(book_id))
+        .select(db_share_user_id)
+        .load(connection)
+        .await?;
+
+    members.extend(
+        db_members
+            .filter(db_member_book_id.eq(book_id))
+            .select(db_member_user_id)
+            .load::<i32>(connection)
+            .await?,
+    );
+
+    members.sort_unstable();
+    members.dedup();
+
+    let chapter_ids = db_chapters
+        .filter(db_chapter_book_id.eq(book_id))
+        .select(db_chapter_id)
+        .order(db_chapter_id)
+        .load(connection)
+        .await?;
+
+    let translations = db_translations
+        .inner_join(db_chapters)
+        .filter(db_chapter_book_id.eq(book_id))
+        .select(db_translation_lang)
+        .distinct()
+        .order(db_translation_lang)
+        .load(connection)
+        .await?;
+
+    Ok(Some(TrackedBook {
+        book_id,
+        author_id,
+        approved,
+        lang,
+        translations,
+        genres,
+        members,
+        chapter_ids,
+        updated,
+    }))
+}
+
+/// Sends gablet_tracking a book after anything it keeps about the book
+/// changes: who can see its analytics, its chapters, and the approval,
+/// languages and genres trending books are filtered by. Failures are logged
+/// since the change has already been saved.
+pub async fn send_tracked_book(book_id: i32, connection: &mut AsyncPgConnection) {
+    match load_tracked_book(book_id, connection).await {
+        Ok(Some(book)) => send_event(BOOK_TOPIC, TRACKED_BOOK_EVENT, &book),
+        Ok(None) => (),
+        Err(err) => tracing::error!("Failed to read book {} for tracking: {}", book_id, err),
+    }
+}
+
+/// Sends gablet_tracking every book, for when it's first set up or its
+/// database has been rebuilt. Each event is sent before moving on, so this is
+/// done once it returns. Returns how many books were sent.
+pub async fn sync_tracked_books(connection: &mut AsyncPgConnection) -> Result<usize, Box<dyn Error>> {
+    use crate::schema::books::dsl::{books as db_books, id as db_id};
+
+    let book_ids: Vec<i32> = db_books
+        .select(db_id)
+        .order(db_id)
+        .load(connection)
+        .await?;
+
+    let mut sent = 0;
+
+    for book_id in book_ids {
+        if let Some(book) = load_tracked_book(book_id, connection).await? {
+            send_event_now(BOOK_TOPIC, TRACKED_BOOK_EVENT, &book)?;
+            sent += 1;
+        }
+    }
+
+    Ok(sent)
+}
+
+fn trending_uri(base: &str, query: &TrendingQuery) -> Result<Uri, Box<dyn Error>> {
+    let uri = format!(
+        "{}/trending?{}",
+        base.trim_end_matches('/'),
+        serde_urlencoded::to_string(query)?
+    );
+
+    Ok(uri.parse()?)
+}
+
+/// Asks gablet_tracking for the books that are trending.
+pub async fn fetch_trending(query: &TrendingQuery) -> Result<Vec<TrendingBook>, Box<dyn Error>> {
+    let uri = trending_uri(&TRACKING_URL, query)?;
+
+    let response = tokio::time::timeout(TRACKING_TIMEOUT, HTTP_CLIENT.get(uri)).await??;
+
+    if !response.status().is_success() {
+        return Err(format!("gablet_tracking responded with {}", response.status()).into());
+    }
+
+    let body = tokio::time::timeout(TRACKING_TIMEOUT, hyper::body::to_bytes(response.into_body()))
+        .await??;
+
+    Ok(serde_json::from_slice(&body)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trending_uri_leaves_out_missing_filters() {
+        let uri = trending_uri("http://127.0.0.1:3001/", &TrendingQuery::default()).unwrap();
+        assert_eq!(uri.to_string(), "http://127.0.0.1:3001/trending?");
+
+        let query = TrendingQuery {
+            lang: Some("en".into()),
+            genre: Some("Slice of Life".into()),
+            limit: Some(10),
+        };
+        let uri = trending_uri("http://127.0.0.1:3001", &query).unwrap();
+        assert_eq!(
+            uri.to_string(),
+            "http://127.0.0.1:3001/trending?lang=en&genre=Slice+of+Life&limit=10"
+        );
+    }
+}
@@ -0,0 +1,508 @@
+use std::{error::Error, fmt::Display};
+
+use chrono::{NaiveDateTime, Utc};
+use diesel::result::Error as DbError;
+use diesel::{insert_into, prelude::*, update};
+use diesel_async::{
+    scoped_futures::ScopedFutureExt, AsyncConnection, AsyncPgConnection, RunQueryDsl,
+};
+
+use crate::models::workflows::{ChapterWorkflow, NewWorkflowEvent, WorkflowAction, WorkflowStage};
+
+/// A change someone wants to make to the workflow of a chapter.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WorkflowTransition {
+    /// Start working on the current stage.
+    Claim,
+    /// Stop working on the current stage without finishing it.
+    Release,
+    /// Finish the current stage and move on to the next one.
+    Submit,
+    /// Send the chapter back to an earlier stage, usually to fix something.
+    Return(WorkflowStage),
+    /// Change the deadline of the current stage.
+    SetDeadline,
+}
+
+impl WorkflowTransition {
+    fn action(self) -> WorkflowAction {
+        match self {
+            WorkflowTransition::Claim => WorkflowAction::Claimed,
+            WorkflowTransition::Release => WorkflowAction::Released,
+            WorkflowTransition::Submit => WorkflowAction::Submitted,
+            WorkflowTransition::Return(_) => WorkflowAction::Returned,
+            WorkflowTransition::SetDeadline => WorkflowAction::DeadlineSet,
+        }
+    }
+}
+
+/// The person making a transition and the stages they can work on.
+#[derive(Debug, Clone)]
+pub struct WorkflowActor {
+    pub user_id: i32,
+    /// Whether the person manages the book's workflows, which lets them work
+    /// on every stage and step in for other members.
+    pub manager: bool,
+    pub stages: Vec<WorkflowStage>,
+}
+
+impl WorkflowActor {
+    pub fn can_work_on(&self, stage: WorkflowStage) -> bool {
+        self.manager || self.stages.contains(&stage)
+    }
+
+    pub fn is_member(&self) -> bool {
+        self.manager || !self.stages.is_empty()
+    }
+}
+
+#[derive(Debug)]
+pub enum WorkflowError {
+    Complete,
+    NotMember(WorkflowStage),
+    AlreadyClaimed(WorkflowStage),
+    NotClaimed(WorkflowStage),
+    ClaimedBySomeoneElse(WorkflowStage),
+    ManagerOnly,
+    InvalidReturn {
+        from: WorkflowStage,
+        to: WorkflowStage,
+    },
+    Db(DbError),
+}
+
+impl Display for WorkflowError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WorkflowError::Complete => write!(f, "The workflow is already complete"),
+            WorkflowError::NotMember(stage) => {
+                write!(f, "You aren't a member of the {} stage", stage)
+            }
+            WorkflowError::AlreadyClaimed(stage) => {
+                write!(f, "The {} stage has already been claimed", stage)
+            }
+            WorkflowError::NotClaimed(stage) => {
+                write!(f, "The {} stage hasn't been claimed", stage)
+            }
+            WorkflowError::ClaimedBySomeoneElse(stage) => {
+                write!(f, "The {} stage has been claimed by someone else", stage)
+            }
+            WorkflowError::ManagerOnly => {
+                write!(f, "Only the author of the book can do that")
+            }
+            WorkflowError::InvalidReturn { from, to } => write!(
+                f,
+                "Cannot return from the {} stage to the {} stage",
+                from, to
+            ),
+            WorkflowError::Db(err) => write!(f, "Workflow database error: {}", err),
+        }
+    }
+}
+
+impl Error for WorkflowError {}
+
+impl From<DbError> for WorkflowError {
+    fn from(err: DbError) -> Self {
+        WorkflowError::Db(err)
+    }
+}
+
+/// Checks whether a transition is allowed and returns the stage the workflow
+/// ends up in.
+///
+/// Only the member who claimed a stage can submit it. Returning a chapter to
+/// an earlier stage is left to whoever claimed the current stage, and managers
+/// can release claims, set deadlines and reopen complete workflows.
+pub fn check_transition(
+    workflow: &ChapterWorkflow,
+    transition: WorkflowTransition,
+    actor: &WorkflowActor,
+) -> Result<WorkflowStage, WorkflowError> {
+    let stage = workflow.stage;
+    let claimed_by_actor = workflow.claimed_by == Some(actor.user_id);
+
+    match transition {
+        WorkflowTransition::Claim => {
+            if stage == WorkflowStage::Complete {
+                return Err(WorkflowError::Complete);
+            }
+            if workflow.claimed_by.is_some() {
+                return Err(WorkflowError::AlreadyClaimed(stage));
+            }
+            if !actor.can_work_on(stage) {
+                return Err(WorkflowError::NotMember(stage));
+            }
+
+            Ok(stage)
+        }
+        WorkflowTransition::Release => {
+            if workflow.claimed_by.is_none() {
+                return Err(WorkflowError::NotClaimed(stage));
+            }
+            if !claimed_by_actor && !actor.manager {
+                return Err(WorkflowError::ClaimedBySomeoneElse(stage));
+            }
+
+            Ok(stage)
+        }
+        WorkflowTransition::Submit => {
+            let Some(next) = stage.next() else {
+                return Err(WorkflowError::Complete);
+            };
+            if workflow.claimed_by.is_none() {
+                return Err(WorkflowError::NotClaimed(stage));
+            }
+            if !claimed_by_actor {
+                return Err(WorkflowError::ClaimedBySomeoneElse(stage));
+            }
+
+            Ok(next)
+        }
+        WorkflowTransition::Return(to) => {
+            if to >= stage || to == WorkflowStage::Complete {
+                return Err(WorkflowError::InvalidReturn { from: stage, to });
+            }
+            if !actor.manager {
+                if stage == WorkflowStage::Complete {
+                    return Err(WorkflowError::ManagerOnly);
+                }
+                if workflow.claimed_by.is_none() {
+                    return Err(WorkflowError::NotClaimed(stage));
+                }
+                if !claimed_by_actor {
+                    return Err(WorkflowError::ClaimedBySomeoneElse(stage));
+                }
+            }
+
+            Ok(to)
+        }
+        WorkflowTransition::SetDeadline => {
+            if !actor.manager {
+                return Err(WorkflowError::ManagerOnly);
+            }
+            if stage == WorkflowStage::Complete {
+                return Err(WorkflowError::Complete);
+            }
+
+            Ok(stage)
+        }
+    }
+}
+
+/// Makes a transition and records it in the activity of the workflow. `due`
+/// is only used when setting a deadline.
+///
+/// Returns `None` if there is no workflow with the given id.
+pub async fn apply_transition(
+    workflow_id: i32,
+    transition: WorkflowTransition,
+    actor: &WorkflowActor,
+    due: Option<NaiveDateTime>,
+    note: Option<String>,
+    connection: &mut AsyncPgConnection,
+) -> Result<Option<ChapterWorkflow>, WorkflowError> {
+    use crate::schema::chapter_workflows::dsl::{
+        chapter_workflows as db_workflows, claimed as db_claimed, claimed_by as db_claimed_by,
+        due as db_due, stage as db_stage, updated as db_updated,
+    };
+    use crate::schema::workflow_events::dsl::workflow_events as db_events;
+
+    connection
+        .transaction(|connection| {
+            async move {
+                let workflow: Option<ChapterWorkflow> = db_workflows
+                    .find(workflow_id)
+                    .select(ChapterWorkflow::as_select())
+                    .for_update()
+                    .first(connection)
+                    .await
+                    .optional()?;
+
+                let Some(workflow) = workflow else {
+                    return Ok(None);
+                };
+
+                let stage = check_transition(&workflow, transition, actor)?;
+                let now = Utc::now().naive_utc();
+
+                let (claimed_by, claimed, due) = match transition {
+                    WorkflowTransition::Claim => (Some(actor.user_id), Some(now), workflow.due),
+                    WorkflowTransition::Release => (None, None, workflow.due),
+                    WorkflowTransition::Submit | WorkflowTransition::Return(_) => {
+                        (None, None, None)
+                    }
+                    WorkflowTransition::SetDeadline => (workflow.claimed_by, workflow.claimed, due),
+                };
+
+                let updated = update(db_workflows.find(workflow_id))
+                    .set((
+                        db_stage.eq(stage),
+                        db_claimed_by.eq(claimed_by),
+                        db_claimed.eq(claimed),
+                        db_due.eq(due),
+                        db_updated.eq(now),
+                    ))
+                    .returning(ChapterWorkflow::as_returning())
+                    .get_result(connection)
+                    .await?;
+
+                insert_into(db_events)
+                    .values(NewWorkflowEvent {
+                        workflow_id,
+                        user_id: actor.user_id,
+                        action: transition.action(),
+                        from_stage: workflow.stage,
+                        to_stage: stage,
+                        note,
+                    })
+                    .execute(connection)
+                    .await?;
+
+                Ok(Some(updated))
+            }
+            .scope_boxed()
+        })
+        .await
+}
+
+/// Finds the stages a user can work on in a book's translation into a
+/// language.
+pub async fn load_workflow_actor(
+    book_author_id: i32,
+    book_id: i32,
+    lang: &str,
+    user_id: i32,
+    connection: &mut AsyncPgConnection,
+) -> Result<WorkflowActor, DbError> {
+    use crate::schema::workflow_members::dsl::{
+        book_id as db_book_id, lang as db_lang, stage as db_stage, user_id as db_user_id,
+        workflow_members as db_members,
+    };
+
+    let stages = db_members
+        .filter(db_book_id.eq(book_id))
+        .filter(db_lang.eq(lang.to_string()))
+        .filter(db_user_id.eq(user_id))
+        .order(db_stage.asc())
+        .select(db_stage)
+        .load(connection)
+        .await?;
+
+    Ok(WorkflowActor {
+        user_id,
+        manager: book_author_id == user_id,
+        stages,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use WorkflowStage::*;
+    use WorkflowTransition::*;
+
+    const CLAIMER: i32 = 1;
+    const MEMBER: i32 = 2;
+    const MANAGER: i32 = 3;
+    const OUTSIDER: i32 = 4;
+
+    fn workflow(stage: WorkflowStage, claimed_by: Option<i32>) -> ChapterWorkflow {
+        let now = Utc::now().naive_utc();
+
+        ChapterWorkflow {
+            id: 1,
+            chapter_id: 1,
+            lang: "en".into(),
+            stage,
+            claimed_by,
+            claimed: claimed_by.map(|_| now),
+            due: None,
+            created: now,
+            updated: now,
+        }
+    }
+
+    fn actor(user_id: i32) -> WorkflowActor {
+        WorkflowActor {
+            user_id,
+            manager: user_id == MANAGER,
+            stages: match user_id {
+                CLAIMER | MEMBER => vec![Translate, Proofread, Typeset, QualityCheck],
+                _ => vec![],
+            },
+        }
+    }
+
+    fn check(
+        stage: WorkflowStage,
+        claimed_by: Option<i32>,
+        transition: WorkflowTransition,
+        user_id: i32,
+    ) -> Result<WorkflowStage, WorkflowError> {
+        check_transition(&workflow(stage, claimed_by), transition, &actor(user_id))
+    }
+
+    #[test]
+    fn legal_transitions() {
+        let cases = [
+            (Translate, None, Claim, CLAIMER, Translate),
+            (QualityCheck, None, Claim, MANAGER, QualityCheck),
+            (Proofread, Some(CLAIMER), Release, CLAIMER, Proofread),
+            (Proofread, Some(CLAIMER), Release, MANAGER, Proofread),
+            (Translate, Some(CLAIMER), Submit, CLAIMER, Proofread),
+            (Proofread, Some(CLAIMER), Submit, CLAIMER, Typeset),
+            (Typeset, Some(CLAIMER), Submit, CLAIMER, QualityCheck),
+            (QualityCheck, Some(CLAIMER), Submit, CLAIMER, Complete),
+            (
+                Typeset,
+                Some(CLAIMER),
+                Return(Translate),
+                CLAIMER,
+                Translate,
+            ),
+            (
+                Typeset,
+                Some(CLAIMER),
+                Return(Proofread),
+                MANAGER,
+                Proofread,
+            ),
+            (Typeset, None, Return(Proofread), MANAGER, Proofread),
+            (Complete, None, Return(QualityCheck), MANAGER, QualityCheck),
+            (Typeset, Some(CLAIMER), SetDeadline, MANAGER, Typeset),
+        ];
+
+        for (stage, claimed_by, transition, user_id, expected) in cases {
+            let result = check(stage, claimed_by, transition, user_id);
+
+            assert!(
+                matches!(result, Ok(next) if next == expected),
+                "{:?} from {:?} by {}: {:?}",
+                transition,
+                stage,
+                user_id,
+                result
+            );
+        }
+    }
+
+    #[test]
+    fn illegal_transitions() {
+        let cases = [
+            (Complete, None, Claim, MANAGER, WorkflowError::Complete),
+            (
+                Translate,
+                Some(CLAIMER),
+                Claim,
+                MEMBER,
+                WorkflowError::AlreadyClaimed(Translate),
+            ),
+            (
+                Translate,
+                None,
+                Claim,
+                OUTSIDER,
+                WorkflowError::NotMember(Translate),
+            ),
+            (
+                Translate,
+                None,
+                Release,
+                CLAIMER,
+                WorkflowError::NotClaimed(Translate),
+            ),
+            (
+                Translate,
+                Some(CLAIMER),
+                Release,
+                MEMBER,
+                WorkflowError::ClaimedBySomeoneElse(Translate),
+            ),
+            (Complete, None, Submit, MANAGER, WorkflowError::Complete),
+            (
+                Typeset,
+                None,
+                Submit,
+                CLAIMER,
+                WorkflowError::NotClaimed(Typeset),
+            ),
+            (
+                Typeset,
+                Some(CLAIMER),
+                Submit,
+                MANAGER,
+                WorkflowError::ClaimedBySomeoneElse(Typeset),
+            ),
+            (
+                Proofread,
+                Some(CLAIMER),
+                Return(Typeset),
+                CLAIMER,
+                WorkflowError::InvalidReturn {
+                    from: Proofread,
+                    to: Typeset,
+                },
+            ),
+            (
+                Proofread,
+                Some(CLAIMER),
+                Return(Proofread),
+                MANAGER,
+                WorkflowError::InvalidReturn {
+                    from: Proofread,
+                    to: Proofread,
+                },
+            ),
+            (
+                Complete,
+                None,
+                Return(Translate),
+                CLAIMER,
+                WorkflowError::ManagerOnly,
+            ),
+            (
+                Typeset,
+                None,
+                Return(Translate),
+                CLAIMER,
+                WorkflowError::NotClaimed(Typeset),
+            ),
+            (
+                Typeset,
+                Some(CLAIMER),
+                Return(Translate),
+                MEMBER,
+                WorkflowError::ClaimedBySomeoneElse(Typeset),
+            ),
+            (
+                Typeset,
+                Some(CLAIMER),
+                SetDeadline,
+                CLAIMER,
+                WorkflowError::ManagerOnly,
+            ),
+            (
+                Complete,
+                None,
+                SetDeadline,
+                MANAGER,
+                WorkflowError::Complete,
+            ),
+        ];
+
+        for (stage, claimed_by, transition, user_id, expected) in cases {
+            let result = check(stage, claimed_by, transition, user_id);
+
+            assert_eq!(
+                format!("{:?}", result),
+                format!("{:?}", Err::<WorkflowStage, _>(expected)),
+                "{:?} from {:?} by {}",
+                transition,
+                stage,
+                user_id
+            );
+        }
+    }
+}
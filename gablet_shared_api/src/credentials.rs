@@ -43,13 +43,52 @@ pub struct Kafka {
     pub group: String
 }
 
+/// How much of a visitor's ip is stored with their views.
+#[derive(Debug, Clone, Copy, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum IpPrivacy {
+    /// The whole ip.
+    #[default]
+    Full,
+    /// Only the network, i.e. the first 24 bits of IPv4 and 48 bits of IPv6
+    /// addresses.
+    Truncate,
+    /// A hash of the ip with a salt that changes every day.
+    Hash
+}
+
+/// Settings for gablet_tracking. Anything left out uses its default.
+#[derive(Debug, Clone, Deserialize, Default)]
+pub struct Tracking {
+    /// How long repeat profile views from the same viewer are ignored for.
+    pub profile_view_window_minutes: Option<i64>,
+    /// How many views one ip can send per minute before it's treated as a bot.
+    pub max_views_per_minute: Option<u32>,
+    #[serde(default)]
+    pub ip_privacy: IpPrivacy,
+    /// How many days raw views are kept once they've been rolled up. They're
+    /// kept forever if this isn't set.
+    pub raw_view_retention_days: Option<u64>,
+    /// How many views the consumer collects before writing them.
+    pub batch_size: Option<usize>,
+    /// How long the consumer waits for more views before writing the ones it
+    /// has, in milliseconds.
+    pub batch_interval_ms: Option<u64>,
+    /// How many hours it takes a book's trending score to halve.
+    pub trending_half_life_hours: Option<f64>,
+    /// How long a visitor has to wait before their views of a book count
+    /// towards its trending score again.
+    pub trending_visitor_window_minutes: Option<i64>
+}
+
 #[derive(Debug, Clone, Deserialize, Default)]
 pub struct Credentials {
     pub postgres: Option<Postgres>,
     pub auth: Option<AuthCredentials>,
     pub mail: Option<Mail>,
     pub kafka: Option<Kafka>,
-    pub logs: Option<Logging>
+    pub logs: Option<Logging>,
+    pub tracking: Option<Tracking>
 }
 
 impl Credentials {
@@ -2,4 +2,11 @@ pub const CLEAR_KAFKA_FAILS: &str = "CLEAR_KAFKA_FAILS";
 pub const STOP_KAFKA_THREAD: &str = "STOP_KAFKA_THREAD";
 pub const TRACKING_TOPIC: &str = "metrics";
 pub const TRACKING_WEB_EVENT: &str = "tracking_web_events";
-pub const LOG_TOPIC: &str = "logs";
\ No newline at end of file
+pub const TRACKING_BOOK_EVENT: &str = "tracking_book_events";
+pub const TRACKING_USER_EVENT: &str = "tracking_user_events";
+pub const TRACKING_FILTERED_EVENT: &str = "tracking_filtered_events";
+pub const LOG_TOPIC: &str = "logs";
+pub const DEAD_LETTER_TOPIC: &str = "dead_letters";
+pub const BOOK_TOPIC: &str = "books";
+pub const CHAPTER_PUBLISHED_EVENT: &str = "chapter_published";
+pub const TRACKED_BOOK_EVENT: &str = "tracked_book";
\ No newline at end of file
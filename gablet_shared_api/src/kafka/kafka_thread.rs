@@ -7,13 +7,20 @@ use std::{
         Arc,
     },
     thread::sleep,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use dashmap::DashMap;
-use kafka::consumer::{Consumer, FetchOffset, GroupOffsetStorage};
+use kafka::{
+    consumer::{Consumer, FetchOffset, GroupOffsetStorage},
+    producer::{Producer, Record, RequiredAcks},
+};
+use tokio::runtime::Handle;
 
-use crate::{cancellation_token::CancellationToken, credentials::Credentials};
+use crate::{
+    cancellation_token::CancellationToken, credentials::Credentials,
+    kafka::kafka_events::{DEAD_LETTER_TOPIC, STOP_KAFKA_THREAD},
+};
 
 #[derive(Debug)]
 pub struct KafkaPollError {
@@ -62,20 +69,7 @@ pub fn kafka_thread<Fut>(
     }
 }
 
-fn kafka_worker<Fut>(cancellation_token: CancellationToken, handle_event: fn(String, String) -> Fut) -> Result<bool, KafkaPollError>
-where
-    Fut: Future<Output = Result<(), Box<dyn Error>>> + Send + 'static,
-{
-    let fails: Arc<DashMap<String, AtomicI32>> = Arc::new(DashMap::new());
-
-    let pool = tokio::runtime::Builder::new_multi_thread()
-        .enable_all()
-        .build()
-        .map_err(|err| KafkaPollError {
-            started: false,
-            inner: Some(Box::new(err)),
-        })?;
-
+fn create_consumer() -> Result<Consumer, KafkaPollError> {
     let creds = Credentials::new("./config/credentials.toml")
         .map_err(|err| KafkaPollError {
             started: false,
@@ -96,10 +90,27 @@ where
         consumer_builder = consumer_builder.with_topic(topic);
     }
 
-    let mut con = consumer_builder.create().map_err(|err| KafkaPollError {
+    consumer_builder.create().map_err(|err| KafkaPollError {
         started: false,
         inner: Some(Box::new(err)),
-    })?;
+    })
+}
+
+fn kafka_worker<Fut>(cancellation_token: CancellationToken, handle_event: fn(String, String) -> Fut) -> Result<bool, KafkaPollError>
+where
+    Fut: Future<Output = Result<(), Box<dyn Error>>> + Send + 'static,
+{
+    let fails: Arc<DashMap<String, AtomicI32>> = Arc::new(DashMap::new());
+
+    let pool = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .map_err(|err| KafkaPollError {
+            started: false,
+            inner: Some(Box::new(err)),
+        })?;
+
+    let mut con = create_consumer()?;
 
     loop {
         if cancellation_token.is_cancellation_requested() {
@@ -203,3 +214,303 @@ async fn dispatch_kafka_event<Fut>(
         });
     }
 }
+
+/// Events read from kafka that are written all at once.
+pub trait EventBatch {
+    /// Adds an event to the batch. Events that can't be read should be logged
+    /// and skipped instead of failing the batch, since they would fail again
+    /// every time they're read.
+    fn add(&mut self, key: &str, value: &str);
+
+    /// How many events are waiting to be written.
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Writes every event in the batch and empties it. If this fails, the
+    /// batch should be left as it was so it can be tried again, and nothing
+    /// should have been written.
+    fn flush(&mut self) -> impl Future<Output = Result<(), Box<dyn Error>>>;
+
+    /// Whether an error from `flush` means the database rejected one of the
+    /// events, so writing the same batch again would fail the same way, as
+    /// opposed to the database being unreachable for a while.
+    fn is_rejected(err: &(dyn Error + 'static)) -> bool;
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct BatchOptions {
+    /// A batch is written as soon as it has this many events.
+    pub max_events: usize,
+    /// A batch is written once its oldest event has waited this long.
+    pub max_wait: Duration,
+}
+
+/// How long to wait before writing a batch again or reconnecting, so a
+/// database that's down isn't hammered. The wait doubles after each failure.
+const BATCH_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// The longest wait between attempts to write a batch.
+const MAX_BATCH_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// Like `kafka_thread`, but collects events into batches. Offsets are only
+/// committed once the events before them have been written, so events aren't
+/// lost if the process stops or the database is down. Events the database
+/// rejects are sent to `DEAD_LETTER_TOPIC` instead. Events can be written
+/// twice if committing fails after a batch was written.
+///
+/// Batches are written on the runtime of `handle`, which should be the one
+/// the batch's connection pool was created on, since its connections can't
+/// be used from another runtime.
+pub fn batched_kafka_thread<B: EventBatch>(
+    cancellation_token: CancellationToken,
+    handle: Handle,
+    make_batch: fn() -> B,
+    options: BatchOptions,
+) {
+    loop {
+        if cancellation_token.is_cancellation_requested() {
+            tracing::info!("Kafka thread cancelled");
+            return;
+        }
+        tracing::info!("Batched kafka thread starting...");
+        let result = batched_kafka_worker(cancellation_token.clone(), &handle, make_batch, options);
+        match result {
+            Err(err) => {
+                tracing::error!("Batched kafka worker failed: {}\n\nRestarting...", err);
+                sleep(BATCH_RETRY_DELAY);
+            }
+            Ok(_) => return,
+        }
+    }
+}
+
+/// The events read since the last flush, kept as they were read as well as in
+/// a batch, so they can be written one at a time if the batch is rejected.
+struct PendingEvents<B: EventBatch> {
+    make_batch: fn() -> B,
+    batch: B,
+    events: Vec<(String, String)>,
+}
+
+impl<B: EventBatch> PendingEvents<B> {
+    fn new(make_batch: fn() -> B) -> Self {
+        PendingEvents {
+            make_batch,
+            batch: make_batch(),
+            events: Vec::new(),
+        }
+    }
+
+    fn add(&mut self, key: &str, value: &str) {
+        self.batch.add(key, value);
+        self.events.push((key.to_owned(), value.to_owned()));
+    }
+}
+
+fn batched_kafka_worker<B: EventBatch>(
+    cancellation_token: CancellationToken,
+    handle: &Handle,
+    make_batch: fn() -> B,
+    options: BatchOptions,
+) -> Result<bool, KafkaPollError> {
+    let mut con = create_consumer()?;
+    let mut pending = PendingEvents::new(make_batch);
+
+    // When the oldest event in the batch was read.
+    let mut oldest: Option<Instant> = None;
+
+    loop {
+        if cancellation_token.is_cancellation_requested() {
+            flush_batch(&cancellation_token, handle, &mut pending, &mut con)?;
+            return Ok(false);
+        }
+
+        let mss = con.poll().map_err(|err| KafkaPollError {
+            started: true,
+            inner: Some(Box::new(err)),
+        })?;
+
+        for ms in mss.iter() {
+            for m in ms.messages() {
+                let key = std::str::from_utf8(m.key).map_err(|err| KafkaPollError {
+                    started: true,
+                    inner: Some(Box::new(err)),
+                })?;
+
+                let value = std::str::from_utf8(m.value).map_err(|err| KafkaPollError {
+                    started: true,
+                    inner: Some(Box::new(err)),
+                })?;
+
+                match key {
+                    STOP_KAFKA_THREAD => {
+                        tracing::info!("Received kafka event to stop the kafka thread");
+                        // Everything up to and including the stop event has
+                        // been handled, so none of it is read again.
+                        con.consume_message(ms.topic(), ms.partition(), m.offset)
+                            .map_err(|err| KafkaPollError {
+                                started: true,
+                                inner: Some(Box::new(err)),
+                            })?;
+                        flush_batch(&cancellation_token, handle, &mut pending, &mut con)?;
+                        return Ok(true);
+                    }
+                    _ => {
+                        tracing::trace!(
+                            "Batching kafka event from topic {} with key {} and value {}",
+                            ms.topic(),
+                            key,
+                            value
+                        );
+                        pending.add(key, value);
+                        oldest.get_or_insert_with(Instant::now);
+                    }
+                }
+            }
+
+            con.consume_messageset(ms).map_err(|err| KafkaPollError {
+                started: true,
+                inner: Some(Box::new(err)),
+            })?;
+        }
+
+        let waited = oldest.map(|oldest| oldest.elapsed());
+
+        if pending.events.len() >= options.max_events
+            || waited.is_some_and(|waited| waited >= options.max_wait)
+        {
+            flush_batch(&cancellation_token, handle, &mut pending, &mut con)?;
+            oldest = None;
+        } else if mss.is_empty() {
+            let remaining = waited.map_or(options.max_wait, |waited| options.max_wait - waited);
+            sleep(remaining.min(Duration::from_secs(1)));
+        }
+    }
+}
+
+/// Sleeps for `delay`, waking up early if cancellation is requested.
+fn sleep_unless_cancelled(cancellation_token: &CancellationToken, delay: Duration) {
+    let until = Instant::now() + delay;
+
+    while !cancellation_token.is_cancellation_requested() {
+        let now = Instant::now();
+        if now >= until {
+            return;
+        }
+        sleep((until - now).min(Duration::from_secs(1)));
+    }
+}
+
+/// Writes a batch, trying again with a growing delay for as long as the
+/// database can't be reached. Returns the error if the database rejected the
+/// batch instead, and fails without writing it if cancellation is requested.
+fn write_batch<B: EventBatch>(
+    cancellation_token: &CancellationToken,
+    handle: &Handle,
+    batch: &mut B,
+) -> Result<Option<Box<dyn Error>>, KafkaPollError> {
+    let mut delay = BATCH_RETRY_DELAY;
+
+    loop {
+        let err = match handle.block_on(batch.flush()) {
+            Ok(()) => return Ok(None),
+            Err(err) if B::is_rejected(err.as_ref()) => return Ok(Some(err)),
+            Err(err) => err,
+        };
+
+        if cancellation_token.is_cancellation_requested() {
+            return Err(KafkaPollError {
+                started: true,
+                inner: Some(err),
+            });
+        }
+
+        tracing::warn!(
+            "Failed to write {} kafka events, trying again in {:?}: {}",
+            batch.len(),
+            delay,
+            err
+        );
+
+        sleep_unless_cancelled(cancellation_token, delay);
+        delay = (delay * 2).min(MAX_BATCH_RETRY_DELAY);
+    }
+}
+
+/// Sends an event the database rejected to `DEAD_LETTER_TOPIC`, so it can be
+/// looked at and replayed once whatever rejected it is fixed.
+fn dead_letter(key: &str, value: &str, err: &dyn Error) -> Result<(), KafkaPollError> {
+    tracing::error!("Sending kafka event {} with value {} to the dead letters: {}", key, value, err);
+
+    let creds = Credentials::new("./config/credentials.toml")
+        .map_err(|err| KafkaPollError {
+            started: true,
+            inner: Some(Box::new(err)),
+        })?
+        .kafka
+        .ok_or_else(|| KafkaPollError {
+            started: true,
+            inner: None,
+        })?;
+
+    let mut producer = Producer::from_hosts(creds.hosts)
+        .with_ack_timeout(Duration::from_secs(2))
+        .with_required_acks(RequiredAcks::One)
+        .create()
+        .map_err(|err| KafkaPollError {
+            started: true,
+            inner: Some(Box::new(err)),
+        })?;
+
+    producer
+        .send(&Record::from_key_value(DEAD_LETTER_TOPIC, key, value))
+        .map_err(|err| KafkaPollError {
+            started: true,
+            inner: Some(Box::new(err)),
+        })
+}
+
+/// Writes the pending events, then commits the offsets of every event read so
+/// far. Offsets are never committed for events that weren't written. If the
+/// database rejects the batch, its events are written one at a time, and only
+/// the ones that are rejected on their own are dead lettered.
+fn flush_batch<B: EventBatch>(
+    cancellation_token: &CancellationToken,
+    handle: &Handle,
+    pending: &mut PendingEvents<B>,
+    con: &mut Consumer,
+) -> Result<(), KafkaPollError> {
+    if !pending.events.is_empty() {
+        let len = pending.events.len();
+
+        match write_batch(cancellation_token, handle, &mut pending.batch)? {
+            None => tracing::debug!("Wrote a batch of {} kafka events", len),
+            Some(err) => {
+                tracing::warn!(
+                    "A batch of {} kafka events was rejected, writing them one at a time: {}",
+                    len,
+                    err
+                );
+
+                for (key, value) in &pending.events {
+                    let mut single = (pending.make_batch)();
+                    single.add(key, value);
+
+                    if let Some(err) = write_batch(cancellation_token, handle, &mut single)? {
+                        dead_letter(key, value, err.as_ref())?;
+                    }
+                }
+            }
+        }
+
+        *pending = PendingEvents::new(pending.make_batch);
+    }
+
+    con.commit_consumed().map_err(|err| KafkaPollError {
+        started: true,
+        inner: Some(Box::new(err)),
+    })
+}
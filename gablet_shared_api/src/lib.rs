@@ -2,4 +2,7 @@ pub mod errors;
 pub mod auth;
 pub mod credentials;
 pub mod cancellation_token;
-pub mod kafka;
\ No newline at end of file
+pub mod kafka;
+pub mod pagination;
+pub mod trending;
+pub mod tracked_books;
\ No newline at end of file
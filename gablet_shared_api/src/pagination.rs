@@ -0,0 +1,447 @@
+use std::{error::Error, fmt::Display};
+
+use axum::http::StatusCode;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use diesel::query_dsl::methods::LimitDsl;
+use hmac::{Hmac, Mac};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sha2::Sha256;
+
+use crate::errors::{get_error, get_error_from_string, ErrorResult};
+
+pub const DEFAULT_PAGE_LIMIT: i64 = 25;
+pub const MAX_PAGE_LIMIT: i64 = 100;
+
+/// The query parameters shared by every paged list.
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct PageQuery {
+    pub cursor: Option<String>,
+    pub limit: Option<i64>,
+    pub sort: Option<String>,
+}
+
+impl PageQuery {
+    /// The requested limit, clamped to between 1 and `max`.
+    pub fn limit(&self, default: i64, max: i64) -> i64 {
+        clamp_limit(self.limit, default, max)
+    }
+}
+
+/// Clamps a requested page size to between 1 and `max`, using `default` if
+/// none was requested.
+pub fn clamp_limit(requested: Option<i64>, default: i64, max: i64) -> i64 {
+    requested.unwrap_or(default).clamp(1, max)
+}
+
+/// A page of results along with the cursor to pass back for the next page,
+/// which is `None` on the last page.
+#[derive(Serialize, Debug, Clone)]
+pub struct Paged<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>,
+}
+
+impl<T> Paged<T> {
+    /// Builds a page from rows fetched with `limit + 1`, as done by
+    /// `fetch_page`. The extra row only shows that there is another page and
+    /// is dropped, and the cursor is made from the last row that's kept.
+    pub fn from_rows<F>(mut rows: Vec<T>, limit: i64, cursor: F) -> Self
+    where
+        F: FnOnce(&T) -> String,
+    {
+        let limit = limit.max(0) as usize;
+        let has_more = rows.len() > limit;
+
+        rows.truncate(limit);
+
+        let next_cursor = if has_more {
+            rows.last().map(cursor)
+        } else {
+            None
+        };
+
+        Paged {
+            items: rows,
+            next_cursor,
+        }
+    }
+
+    pub fn map<U, F>(self, f: F) -> Paged<U>
+    where
+        F: FnMut(T) -> U,
+    {
+        Paged {
+            items: self.items.into_iter().map(f).collect(),
+            next_cursor: self.next_cursor,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SortDirection {
+    Asc,
+    Desc,
+}
+
+/// The fields a list can be sorted by. Anything not in the list is rejected,
+/// so clients can't sort by columns that aren't indexed.
+///
+/// ```ignore
+/// const BOOK_SORTS: SortWhitelist<BookSort> = SortWhitelist {
+///     fields: &[("newest", BookSort::Newest), ("rating", BookSort::Rating)],
+///     default: BookSort::Newest,
+/// };
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct SortWhitelist<T: Copy + 'static> {
+    pub fields: &'static [(&'static str, T)],
+    pub default: T,
+}
+
+impl<T: Copy + 'static> SortWhitelist<T> {
+    /// Looks up a requested sort, falling back to the default if there's
+    /// none.
+    pub fn parse(&self, requested: Option<&str>) -> Result<T, ErrorResult> {
+        let Some(requested) = requested else {
+            return Ok(self.default);
+        };
+
+        self.fields
+            .iter()
+            .find(|(name, _)| *name == requested)
+            .map(|(_, sort)| *sort)
+            .ok_or_else(|| {
+                let names: Vec<&str> = self.fields.iter().map(|(name, _)| *name).collect();
+
+                get_error_from_string(
+                    StatusCode::BAD_REQUEST,
+                    format!(
+                        "Cannot sort by {}, expected one of {}",
+                        requested,
+                        names.join(", ")
+                    ),
+                )
+            })
+    }
+
+    /// The name of a sort, used to tie cursors to the sort they came from.
+    pub fn name(&self, sort: T) -> &'static str
+    where
+        T: PartialEq,
+    {
+        self.fields
+            .iter()
+            .find(|(_, field)| *field == sort)
+            .map(|(name, _)| *name)
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CursorError {
+    Malformed,
+    BadSignature,
+    WrongSort,
+}
+
+impl Display for CursorError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CursorError::Malformed => write!(f, "Invalid cursor"),
+            CursorError::BadSignature => write!(f, "Invalid cursor signature"),
+            CursorError::WrongSort => write!(f, "The cursor is for a different sort"),
+        }
+    }
+}
+
+impl Error for CursorError {}
+
+impl From<CursorError> for ErrorResult {
+    fn from(err: CursorError) -> Self {
+        get_error(err, StatusCode::BAD_REQUEST)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct CursorPayload<T> {
+    #[serde(rename = "s")]
+    sort: String,
+    #[serde(rename = "p")]
+    position: T,
+}
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Signs cursors so clients can pass them back without being able to change
+/// them. A cursor is the position of the last row of a page, encoded as
+/// base64 JSON and followed by its signature. It's tied to the sort it was
+/// made for, so it can't be used to page through a list sorted another way.
+///
+/// Cursors hide nothing from someone who decodes them. They only prove that
+/// the server made them.
+#[derive(Clone)]
+pub struct CursorSigner {
+    key: Vec<u8>,
+}
+
+impl CursorSigner {
+    pub fn new(secret: impl AsRef<[u8]>) -> Self {
+        CursorSigner {
+            key: secret.as_ref().to_vec(),
+        }
+    }
+
+    fn mac(&self) -> HmacSha256 {
+        HmacSha256::new_from_slice(&self.key).expect("HMAC accepts keys of any length")
+    }
+
+    pub fn encode<T: Serialize>(&self, sort: &str, position: &T) -> String {
+        let payload = serde_json::to_vec(&CursorPayload {
+            sort: sort.to_string(),
+            position,
+        })
+        .expect("Cursor positions serialize to JSON");
+
+        let mut mac = self.mac();
+        mac.update(&payload);
+        let signature = mac.finalize().into_bytes();
+
+        format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(payload),
+            URL_SAFE_NO_PAD.encode(signature)
+        )
+    }
+
+    pub fn decode<T: DeserializeOwned>(&self, sort: &str, cursor: &str) -> Result<T, CursorError> {
+        let (payload, signature) = cursor.split_once('.').ok_or(CursorError::Malformed)?;
+
+        let payload = URL_SAFE_NO_PAD
+            .decode(payload)
+            .map_err(|_| CursorError::Malformed)?;
+        let signature = URL_SAFE_NO_PAD
+            .decode(signature)
+            .map_err(|_| CursorError::Malformed)?;
+
+        let mut mac = self.mac();
+        mac.update(&payload);
+        mac.verify_slice(&signature)
+            .map_err(|_| CursorError::BadSignature)?;
+
+        let payload: CursorPayload<T> =
+            serde_json::from_slice(&payload).map_err(|_| CursorError::Malformed)?;
+
+        if payload.sort != sort {
+            return Err(CursorError::WrongSort);
+        }
+
+        Ok(payload.position)
+    }
+
+    /// Decodes the cursor of a page query, if it has one.
+    pub fn decode_query<T: DeserializeOwned>(
+        &self,
+        sort: &str,
+        query: &PageQuery,
+    ) -> Result<Option<T>, ErrorResult> {
+        query
+            .cursor
+            .as_deref()
+            .map(|cursor| self.decode(sort, cursor))
+            .transpose()
+            .map_err(ErrorResult::from)
+    }
+}
+
+/// Fetches one row more than the page holds, so `Paged::from_rows` can tell
+/// whether there's another page.
+pub fn fetch_page<Q: LimitDsl>(query: Q, limit: i64) -> Q::Output {
+    query.limit(limit + 1)
+}
+
+/// Sorts a boxed Diesel query by a column with an id as a tie breaker, and
+/// skips every row up to and including `after` when it's given. `after` is an
+/// `Option<(value, id)>` taken from a cursor. Rows need a unique id for
+/// keyset paging to never skip or repeat a row.
+///
+/// This is a macro rather than a function because the trait bounds Diesel
+/// needs to build these expressions generically are longer than the query.
+///
+/// ```ignore
+/// let mut query = db_books.into_boxed();
+/// query = keyset_page!(query, db_score, db_id, SortDirection::Desc, after);
+/// let books = fetch_page(query, limit).load(connection).await?;
+/// ```
+#[macro_export]
+macro_rules! keyset_page {
+    ($query:expr, $column:expr, $id_column:expr, $direction:expr, $after:expr) => {{
+        use diesel::{BoolExpressionMethods as _, ExpressionMethods as _, QueryDsl as _};
+
+        let query = $query;
+        let after = $after;
+
+        match $direction {
+            $crate::pagination::SortDirection::Desc => {
+                let query = match after {
+                    Some((after_value, after_id)) => query.filter(
+                        $column
+                            .lt(after_value.clone())
+                            .or($column.eq(after_value).and($id_column.lt(after_id))),
+                    ),
+                    None => query,
+                };
+
+                query.order(($column.desc(), $id_column.desc()))
+            }
+            $crate::pagination::SortDirection::Asc => {
+                let query = match after {
+                    Some((after_value, after_id)) => query.filter(
+                        $column
+                            .gt(after_value.clone())
+                            .or($column.eq(after_value).and($id_column.gt(after_id))),
+                    ),
+                    None => query,
+                };
+
+                query.order(($column.asc(), $id_column.asc()))
+            }
+        }
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum TestSort {
+        Newest,
+        Score,
+    }
+
+    const TEST_SORTS: SortWhitelist<TestSort> = SortWhitelist {
+        fields: &[("newest", TestSort::Newest), ("score", TestSort::Score)],
+        default: TestSort::Newest,
+    };
+
+    #[test]
+    fn cursor_round_trip() {
+        let signer = CursorSigner::new("secret");
+        let cursor = signer.encode("score", &(12, 34));
+
+        assert_eq!(signer.decode::<(i32, i32)>("score", &cursor), Ok((12, 34)));
+    }
+
+    #[test]
+    fn tampered_payload_is_rejected() {
+        let signer = CursorSigner::new("secret");
+        let cursor = signer.encode("score", &(12, 34));
+        let (_, signature) = cursor.split_once('.').unwrap();
+
+        let forged = signer.encode("score", &(99, 34));
+        let (payload, _) = forged.split_once('.').unwrap();
+
+        assert_eq!(
+            signer.decode::<(i32, i32)>("score", &format!("{}.{}", payload, signature)),
+            Err(CursorError::BadSignature)
+        );
+    }
+
+    #[test]
+    fn unsigned_payload_is_rejected() {
+        let signer = CursorSigner::new("secret");
+        let payload = URL_SAFE_NO_PAD.encode(r#"{"s":"score","p":[99,34]}"#);
+        let signature = URL_SAFE_NO_PAD.encode([0u8; 32]);
+
+        assert_eq!(
+            signer.decode::<(i32, i32)>("score", &format!("{}.{}", payload, signature)),
+            Err(CursorError::BadSignature)
+        );
+    }
+
+    #[test]
+    fn other_key_is_rejected() {
+        let cursor = CursorSigner::new("secret").encode("newest", &5);
+
+        assert_eq!(
+            CursorSigner::new("other").decode::<i32>("newest", &cursor),
+            Err(CursorError::BadSignature)
+        );
+    }
+
+    #[test]
+    fn wrong_sort_is_rejected() {
+        let signer = CursorSigner::new("secret");
+        let cursor = signer.encode("newest", &5);
+
+        assert_eq!(
+            signer.decode::<i32>("score", &cursor),
+            Err(CursorError::WrongSort)
+        );
+    }
+
+    #[test]
+    fn malformed_cursor_is_rejected() {
+        let signer = CursorSigner::new("secret");
+
+        assert_eq!(
+            signer.decode::<i32>("newest", "5"),
+            Err(CursorError::Malformed)
+        );
+        assert_eq!(
+            signer.decode::<i32>("newest", "not base64!.abc"),
+            Err(CursorError::Malformed)
+        );
+
+        // A signed position of the wrong type.
+        let cursor = signer.encode("newest", &"five");
+        assert_eq!(
+            signer.decode::<i32>("newest", &cursor),
+            Err(CursorError::Malformed)
+        );
+    }
+
+    #[test]
+    fn decode_query_without_cursor() {
+        let signer = CursorSigner::new("secret");
+
+        assert_eq!(
+            signer
+                .decode_query::<i32>("newest", &PageQuery::default())
+                .ok(),
+            Some(None)
+        );
+    }
+
+    #[test]
+    fn sort_whitelist() {
+        assert_eq!(TEST_SORTS.parse(None).ok(), Some(TestSort::Newest));
+        assert_eq!(TEST_SORTS.parse(Some("score")).ok(), Some(TestSort::Score));
+        assert!(TEST_SORTS.parse(Some("password")).is_err());
+        assert_eq!(TEST_SORTS.name(TestSort::Score), "score");
+    }
+
+    #[test]
+    fn from_rows_with_another_page() {
+        let page = Paged::from_rows(vec![1, 2, 3], 2, |last| last.to_string());
+
+        assert_eq!(page.items, vec![1, 2]);
+        assert_eq!(page.next_cursor.as_deref(), Some("2"));
+    }
+
+    #[test]
+    fn from_rows_on_last_page() {
+        let page = Paged::from_rows(vec![1, 2], 2, |last| last.to_string());
+
+        assert_eq!(page.items, vec![1, 2]);
+        assert_eq!(page.next_cursor, None);
+    }
+
+    #[test]
+    fn clamps_limits() {
+        assert_eq!(clamp_limit(None, 25, 100), 25);
+        assert_eq!(clamp_limit(Some(0), 25, 100), 1);
+        assert_eq!(clamp_limit(Some(1000), 25, 100), 100);
+    }
+}
@@ -0,0 +1,29 @@
+use chrono::NaiveDateTime;
+use serde::{Serialize, Deserialize};
+
+/// What gablet_tracking needs to know about a book, since it keeps its own
+/// database. gablet_api sends the whole book every time it changes, so an
+/// event that arrives late can be ignored instead of being merged.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TrackedBook {
+    pub book_id: i32,
+    pub author_id: i32,
+    /// Only approved books can trend.
+    pub approved: bool,
+    pub lang: String,
+    /// The languages the book's chapters have been translated into, so the
+    /// book trends in those languages as well as its own. Missing from events
+    /// sent before translations were tracked.
+    #[serde(default)]
+    pub translations: Vec<String>,
+    /// The names of the book's genre tags, which trending books can be
+    /// filtered by.
+    pub genres: Vec<String>,
+    /// Translators with a revenue share in the book or a place in one of its
+    /// workflows. They can see the book's analytics along with the author.
+    pub members: Vec<i32>,
+    pub chapter_ids: Vec<i32>,
+    /// When the book was read. Older events than the one already saved are
+    /// ignored.
+    pub updated: NaiveDateTime
+}
@@ -0,0 +1,22 @@
+use diesel::{sql_types::{Double, Int4}, QueryableByName};
+use serde::{Serialize, Deserialize};
+
+/// The query of gablet_tracking's `/trending` endpoint.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct TrendingQuery {
+    /// Only books in this language.
+    pub lang: Option<String>,
+    /// Only books with a genre of this name, ignoring case.
+    pub genre: Option<String>,
+    pub limit: Option<i64>
+}
+
+/// A book from the `/trending` endpoint. Scores are roughly how many different
+/// visitors viewed the book recently, with older views counting for less.
+#[derive(Serialize, Deserialize, Debug, Clone, QueryableByName)]
+pub struct TrendingBook {
+    #[diesel(sql_type = Int4)]
+    pub book_id: i32,
+    #[diesel(sql_type = Double)]
+    pub score: f64
+}
\ No newline at end of file
@@ -0,0 +1,10 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE rollups;
+
+DROP INDEX user_views_dt_idx;
+DROP INDEX book_views_dt_idx;
+DROP INDEX web_views_dt_idx;
+
+DROP INDEX daily_user_views_user_date_idx;
+DROP INDEX daily_book_views_book_chapter_date_idx;
+DROP INDEX daily_web_views_href_date_idx;
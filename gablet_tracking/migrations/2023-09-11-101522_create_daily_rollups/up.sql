@@ -0,0 +1,16 @@
+-- Your SQL goes here
+CREATE UNIQUE INDEX daily_web_views_href_date_idx ON daily_web_views(href, date);
+CREATE UNIQUE INDEX daily_book_views_book_chapter_date_idx ON daily_book_views(book_id, chapter_id, date);
+CREATE UNIQUE INDEX daily_user_views_user_date_idx ON daily_user_views(user_id, date);
+
+CREATE INDEX web_views_dt_idx ON web_views(dt);
+CREATE INDEX book_views_dt_idx ON book_views(dt);
+CREATE INDEX user_views_dt_idx ON user_views(dt);
+
+-- The first day each daily table hasn't finished counting. Every day before it
+-- has been rolled up from the raw views.
+CREATE TABLE rollups(
+    name VARCHAR(50) PRIMARY KEY,
+    rolled_up_to DATE NOT NULL,
+    updated TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX user_views_user_viewer_dt_idx;
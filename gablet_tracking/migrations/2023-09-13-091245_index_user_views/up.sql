@@ -0,0 +1,2 @@
+-- Your SQL goes here
+CREATE INDEX user_views_user_viewer_dt_idx ON user_views(user_id, viewer_id, dt);
@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX daily_book_views_chapter_date_idx;
+DROP INDEX book_views_chapter_dt_idx;
+DROP INDEX book_views_book_dt_idx;
+
+ALTER TABLE user_views DROP COLUMN browser;
+ALTER TABLE book_views DROP COLUMN browser;
@@ -0,0 +1,7 @@
+-- Your SQL goes here
+ALTER TABLE book_views ADD COLUMN browser VARCHAR(50) NOT NULL DEFAULT 'Other';
+ALTER TABLE user_views ADD COLUMN browser VARCHAR(50) NOT NULL DEFAULT 'Other';
+
+CREATE INDEX book_views_book_dt_idx ON book_views(book_id, dt);
+CREATE INDEX book_views_chapter_dt_idx ON book_views(chapter_id, dt);
+CREATE INDEX daily_book_views_chapter_date_idx ON daily_book_views(chapter_id, date);
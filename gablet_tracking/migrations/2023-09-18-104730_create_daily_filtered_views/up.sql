@@ -0,0 +1,10 @@
+-- Your SQL goes here
+CREATE TABLE daily_filtered_views(
+    id SERIAL PRIMARY KEY,
+    count INT NOT NULL,
+    kind VARCHAR(10) NOT NULL,
+    target_id INT NOT NULL DEFAULT -1,
+    reason VARCHAR(20) NOT NULL,
+    date DATE NOT NULL DEFAULT CURRENT_DATE,
+    UNIQUE (kind, target_id, reason, date)
+);
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE rollups DROP COLUMN purged_before;
+
+DROP TABLE ip_salts;
@@ -0,0 +1,11 @@
+-- Your SQL goes here
+-- The salt ips are hashed with on each day. Salts are deleted once their day
+-- is over, so the hashes can't be matched back to ips afterwards.
+CREATE TABLE ip_salts(
+    day DATE PRIMARY KEY,
+    salt BYTEA NOT NULL
+);
+
+-- Raw views before this day have been deleted, so the day can't be counted
+-- again from them.
+ALTER TABLE rollups ADD COLUMN purged_before DATE;
@@ -0,0 +1,14 @@
+-- Your SQL goes here
+-- A HyperLogLog sketch of the readers of each book and chapter on each day.
+-- Sketches are merged to estimate the readers of a whole book or a range of
+-- days, so they're kept for days whose raw views have been deleted.
+CREATE TABLE daily_book_readers(
+    id SERIAL PRIMARY KEY,
+    book_id INT NOT NULL,
+    chapter_id INT NOT NULL DEFAULT -1,
+    date DATE NOT NULL DEFAULT CURRENT_DATE,
+    sketch BYTEA NOT NULL,
+    UNIQUE (book_id, chapter_id, date)
+);
+
+CREATE INDEX daily_book_readers_chapter_date_idx ON daily_book_readers(chapter_id, date);
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE trending_visitors;
+DROP TABLE trending_books;
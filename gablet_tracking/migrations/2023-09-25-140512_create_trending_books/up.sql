@@ -0,0 +1,19 @@
+-- Your SQL goes here
+-- How popular each book is right now. Scores decay exponentially from when
+-- they were last updated, so they're decayed to the current time when read.
+CREATE TABLE trending_books(
+    book_id INT PRIMARY KEY,
+    score DOUBLE PRECISION NOT NULL,
+    updated TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- When each visitor last counted towards the score of a book. Visitors only
+-- count once per window, so one person can't push a book up on their own.
+CREATE TABLE trending_visitors(
+    book_id INT NOT NULL,
+    visitor VARCHAR(64) NOT NULL,
+    counted TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (book_id, visitor)
+);
+
+CREATE INDEX trending_visitors_counted_idx ON trending_visitors(counted);
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE daily_user_breakdowns;
+DROP TABLE daily_book_breakdowns;
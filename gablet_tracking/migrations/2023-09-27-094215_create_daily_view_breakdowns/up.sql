@@ -0,0 +1,40 @@
+-- Your SQL goes here
+-- How many views of each book, chapter and profile came from each device, OS
+-- and browser on each day. These are rolled up with the daily counts, so the
+-- breakdowns are kept once the raw views have been deleted.
+CREATE TABLE daily_book_breakdowns(
+    id SERIAL PRIMARY KEY,
+    book_id INT NOT NULL,
+    chapter_id INT NOT NULL,
+    date DATE NOT NULL,
+    field VARCHAR(10) NOT NULL,
+    value VARCHAR(50) NOT NULL,
+    count INT NOT NULL,
+    UNIQUE (book_id, chapter_id, date, field, value)
+);
+
+CREATE INDEX daily_book_breakdowns_chapter_date_idx ON daily_book_breakdowns(chapter_id, date);
+
+CREATE TABLE daily_user_breakdowns(
+    id SERIAL PRIMARY KEY,
+    user_id INT NOT NULL,
+    date DATE NOT NULL,
+    field VARCHAR(10) NOT NULL,
+    value VARCHAR(50) NOT NULL,
+    count INT NOT NULL,
+    UNIQUE (user_id, date, field, value)
+);
+
+-- Days that have already been rolled up aren't counted again, so their
+-- breakdowns are filled in from whatever raw views are left.
+INSERT INTO daily_book_breakdowns (book_id, chapter_id, date, field, value, count)
+SELECT book_id, chapter_id, dt::date, f.field, f.value, COUNT(*)::int FROM book_views
+CROSS JOIN LATERAL (VALUES ('device', device), ('os', os), ('browser', browser)) AS f(field, value)
+WHERE dt < (SELECT rolled_up_to FROM rollups WHERE name = 'daily_book_views')
+GROUP BY book_id, chapter_id, dt::date, f.field, f.value;
+
+INSERT INTO daily_user_breakdowns (user_id, date, field, value, count)
+SELECT user_id, dt::date, f.field, f.value, COUNT(*)::int FROM user_views
+CROSS JOIN LATERAL (VALUES ('device', device), ('os', os), ('browser', browser)) AS f(field, value)
+WHERE dt < (SELECT rolled_up_to FROM rollups WHERE name = 'daily_user_views')
+GROUP BY user_id, dt::date, f.field, f.value;
@@ -0,0 +1,10 @@
+-- Your SQL goes here
+-- The ids of the filtered views that have been counted, so views that are read
+-- from kafka again aren't counted twice. They're only needed until the views
+-- can't be read again, and are deleted after a few days.
+CREATE TABLE filtered_view_events(
+    event_id BIGINT PRIMARY KEY,
+    received TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX filtered_view_events_received_idx ON filtered_view_events(received);
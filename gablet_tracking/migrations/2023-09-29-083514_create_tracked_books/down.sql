@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE tracked_chapters;
+DROP TABLE tracked_book_members;
+DROP TABLE tracked_books;
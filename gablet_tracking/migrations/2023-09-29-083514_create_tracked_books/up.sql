@@ -0,0 +1,22 @@
+-- Your SQL goes here
+-- The books from gablet_api, which keeps them in its own database. They're
+-- kept up to date from the tracked_book events it sends whenever a book's
+-- author, translators or chapters change.
+CREATE TABLE tracked_books(
+    book_id INT PRIMARY KEY,
+    author_id INT NOT NULL,
+    updated TIMESTAMP NOT NULL
+);
+
+CREATE TABLE tracked_book_members(
+    book_id INT NOT NULL REFERENCES tracked_books(book_id) ON DELETE CASCADE,
+    user_id INT NOT NULL,
+    PRIMARY KEY (book_id, user_id)
+);
+
+CREATE TABLE tracked_chapters(
+    chapter_id INT PRIMARY KEY,
+    book_id INT NOT NULL REFERENCES tracked_books(book_id) ON DELETE CASCADE
+);
+
+CREATE INDEX tracked_chapters_book_id_idx ON tracked_chapters(book_id);
@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE tracked_books
+    DROP COLUMN approved,
+    DROP COLUMN lang,
+    DROP COLUMN genres;
@@ -0,0 +1,7 @@
+-- Your SQL goes here
+-- What trending books are filtered by. Books are hidden from trending until
+-- gablet_api sends them again with these filled in.
+ALTER TABLE tracked_books
+    ADD COLUMN approved BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN lang VARCHAR(10) NOT NULL DEFAULT '',
+    ADD COLUMN genres TEXT[] NOT NULL DEFAULT '{}';
@@ -0,0 +1,4 @@
+-- Your SQL goes here
+-- The languages a book has been translated into, so it trends in them too.
+-- Books only trend in their own language until gablet_api sends them again.
+ALTER TABLE tracked_books ADD COLUMN translations TEXT[] NOT NULL DEFAULT '{}';
@@ -0,0 +1,338 @@
+use std::{
+    net::IpAddr,
+    sync::{LazyLock, Mutex},
+    time::{Duration, Instant},
+};
+
+use axum::http::{
+    header::{ACCEPT, ACCEPT_LANGUAGE},
+    HeaderMap,
+};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use strum::{Display, EnumString};
+
+use crate::TRACKING_CONFIG;
+
+/// How many views an ip can send per minute if it isn't configured. A reader
+/// flipping through pages sends one view per chapter, so anything near this is
+/// a script.
+const DEFAULT_MAX_VIEWS_PER_MINUTE: u32 = 60;
+
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+
+/// Names of crawlers, scripts and monitors that don't follow the usual
+/// naming. A user agent is split into products like `Googlebot/2.1`, and
+/// these are matched against a product's name or any word in it, lowercased.
+const BOT_USER_AGENTS: &[&str] = &[
+    "slurp",
+    "slackbot",
+    "bytespider",
+    "scrapy",
+    "headlesschrome",
+    "phantomjs",
+    "lighthouse",
+    "facebookexternalhit",
+    "embedly",
+    "curl",
+    "wget",
+    "httpie",
+    "python-requests",
+    "python-urllib",
+    "aiohttp",
+    "go-http-client",
+    "java",
+    "okhttp",
+    "apache-httpclient",
+    "libwww-perl",
+    "node-fetch",
+    "axios",
+    "postmanruntime",
+    "pingdom",
+    "statuscake",
+    "site24x7",
+];
+
+/// Words that mark a product as a bot on their own, e.g. in
+/// `spider-feedback@bytedance.com`.
+const BOT_WORDS: &[&str] = &["bot", "robot", "crawler", "spider"];
+
+/// Endings of product names that mark a versioned product as a bot, e.g.
+/// `bingbot/2.0` or `DuckDuckBot-Https/1.1`. A version is needed so phones
+/// like the Cubot, which send `CUBOT X30`, aren't counted as bots.
+const BOT_SUFFIXES: &[&str] = &["bot", "crawler", "spider"];
+
+/// Whether a lowercased user agent names a known bot.
+fn is_bot_user_agent(user_agent: &str) -> bool {
+    user_agent
+        .split(|c: char| c.is_whitespace() || matches!(c, '(' | ')' | ';' | ','))
+        .filter(|product| !product.is_empty())
+        .any(|product| {
+            let (name, version) = match product.split_once('/') {
+                Some((name, version)) => (name, Some(version)),
+                None => (product, None),
+            };
+
+            let mut words = name.split(['-', '_', '.', '@', '+', ':']);
+
+            BOT_USER_AGENTS.contains(&name)
+                || words.any(|word| {
+                    BOT_USER_AGENTS.contains(&word)
+                        || BOT_WORDS.contains(&word)
+                        || (version.is_some()
+                            && BOT_SUFFIXES.iter().any(|suffix| word.ends_with(suffix)))
+                })
+        })
+}
+
+/// Why a view was counted as a bot's.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Display, EnumString)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum BotReason {
+    /// The user agent parser recognised a crawler.
+    Crawler,
+    /// The user agent is on the list of known bots.
+    KnownBot,
+    /// Headers every browser sends are missing or don't look like a
+    /// browser's.
+    SuspiciousHeaders,
+    /// The ip sent more views than a person could.
+    RateLimited,
+}
+
+/// Views per ip in the current window. Ips are forgotten once their window
+/// has passed, so this only holds the ips seen in the last minute or so.
+///
+/// This is kept in memory, so each instance of the service counts only the
+/// views it receives. With several instances behind a load balancer an ip can
+/// send up to the limit to each of them.
+struct RateTracker {
+    windows: DashMap<IpAddr, (Instant, u32)>,
+    last_prune: Mutex<Instant>,
+}
+
+impl RateTracker {
+    /// Counts a view from an ip, returning how many it has sent in its
+    /// current window.
+    fn hit(&self, ip: IpAddr) -> u32 {
+        let now = Instant::now();
+
+        self.prune(now);
+
+        let mut window = self.windows.entry(ip).or_insert((now, 0));
+
+        if now.duration_since(window.0) >= RATE_WINDOW {
+            *window = (now, 0);
+        }
+
+        window.1 += 1;
+        window.1
+    }
+
+    fn prune(&self, now: Instant) {
+        let Ok(mut last_prune) = self.last_prune.try_lock() else {
+            return;
+        };
+
+        if now.duration_since(*last_prune) < RATE_WINDOW {
+            return;
+        }
+
+        *last_prune = now;
+        self.windows
+            .retain(|_, (start, _)| now.duration_since(*start) < RATE_WINDOW);
+    }
+}
+
+impl Default for RateTracker {
+    fn default() -> Self {
+        RateTracker {
+            windows: DashMap::new(),
+            last_prune: Mutex::new(Instant::now()),
+        }
+    }
+}
+
+static RATE_TRACKER: LazyLock<RateTracker> = LazyLock::new(RateTracker::default);
+
+/// Works out whether a view came from a bot. The cheap checks of the request
+/// itself come first, and every view that passes them counts towards the
+/// rate of its ip.
+///
+/// `device` is the device family from the user agent parser, which is
+/// "Spider" for the crawlers it knows.
+pub fn classify_request(
+    user_agent: &str,
+    device: &str,
+    headers: &HeaderMap,
+    ip: IpAddr,
+) -> Option<BotReason> {
+    let max_views = TRACKING_CONFIG
+        .max_views_per_minute
+        .unwrap_or(DEFAULT_MAX_VIEWS_PER_MINUTE);
+
+    classify(user_agent, device, headers, ip, &RATE_TRACKER, max_views)
+}
+
+fn classify(
+    user_agent: &str,
+    device: &str,
+    headers: &HeaderMap,
+    ip: IpAddr,
+    rates: &RateTracker,
+    max_views: u32,
+) -> Option<BotReason> {
+    if device == "Spider" {
+        return Some(BotReason::Crawler);
+    }
+
+    let user_agent = user_agent.to_ascii_lowercase();
+
+    if is_bot_user_agent(&user_agent) {
+        return Some(BotReason::KnownBot);
+    }
+
+    if !user_agent.starts_with("mozilla/")
+        || !headers.contains_key(ACCEPT)
+        || !headers.contains_key(ACCEPT_LANGUAGE)
+    {
+        return Some(BotReason::SuspiciousHeaders);
+    }
+
+    if rates.hit(ip) > max_views {
+        return Some(BotReason::RateLimited);
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use axum::http::HeaderValue;
+
+    const MAX_VIEWS: u32 = 5;
+
+    fn browser_headers() -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(ACCEPT, HeaderValue::from_static("*/*"));
+        headers.insert(ACCEPT_LANGUAGE, HeaderValue::from_static("en-US,en;q=0.9"));
+        headers
+    }
+
+    fn check(user_agent: &str, headers: &HeaderMap, ip: [u8; 4]) -> Option<BotReason> {
+        classify(
+            user_agent,
+            "Other",
+            headers,
+            IpAddr::from(ip),
+            &RateTracker::default(),
+            MAX_VIEWS,
+        )
+    }
+
+    #[test]
+    fn browsers_are_not_bots() {
+        let user_agents = [
+            "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/117.0.0.0 Safari/537.36",
+            "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/16.6 Safari/605.1.15",
+            "Mozilla/5.0 (X11; Linux x86_64; rv:109.0) Gecko/20100101 Firefox/118.0",
+            "Mozilla/5.0 (iPhone; CPU iPhone OS 16_6 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/16.6 Mobile/15E148 Safari/604.1",
+            "Mozilla/5.0 (Linux; Android 10; CUBOT X30) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/116.0.0.0 Mobile Safari/537.36",
+            "Mozilla/5.0 (Linux; Android 11; CUBOT_NOTE_20 Build/RP1A.200720.011) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/117.0.0.0 Mobile Safari/537.36",
+            "Mozilla/5.0 (Linux; Android 13; SM-S911B) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/117.0.0.0 Mobile Safari/537.36",
+        ];
+
+        for user_agent in user_agents {
+            assert_eq!(
+                check(user_agent, &browser_headers(), [10, 0, 0, 1]),
+                None,
+                "{}",
+                user_agent
+            );
+        }
+    }
+
+    #[test]
+    fn known_bots_are_caught() {
+        let user_agents = [
+            "Mozilla/5.0 (compatible; Googlebot/2.1; +http://www.google.com/bot.html)",
+            "Mozilla/5.0 (compatible; bingbot/2.0; +http://www.bing.com/bingbot.htm)",
+            "Mozilla/5.0 (compatible; AhrefsBot/7.0; +http://ahrefs.com/robot/)",
+            "DuckDuckBot-Https/1.1; (+https://duckduckgo.com/duckduckbot)",
+            "Mozilla/5.0 (compatible; Yahoo! Slurp; http://help.yahoo.com/help/us/ysearch/slurp)",
+            "Slackbot-LinkExpanding 1.0 (+https://api.slack.com/robots)",
+            "Mozilla/5.0 (Linux; Android 5.0) AppleWebKit/537.36 (KHTML, like Gecko) Mobile Safari/537.36 (compatible; Bytespider; spider-feedback@bytedance.com)",
+            "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) HeadlessChrome/117.0.0.0 Safari/537.36",
+            "facebookexternalhit/1.1 (+http://www.facebook.com/externalhit_uatext.php)",
+            "curl/8.1.2",
+            "python-requests/2.31.0",
+            "Mozilla/5.0 (compatible; UptimeRobot/2.0; http://www.uptimerobot.com/)",
+        ];
+
+        for user_agent in user_agents {
+            assert_eq!(
+                check(user_agent, &browser_headers(), [10, 0, 0, 1]),
+                Some(BotReason::KnownBot),
+                "{}",
+                user_agent
+            );
+        }
+    }
+
+    #[test]
+    fn parsed_crawlers_are_caught() {
+        let reason = classify(
+            "Mozilla/5.0",
+            "Spider",
+            &browser_headers(),
+            IpAddr::from([10, 0, 0, 1]),
+            &RateTracker::default(),
+            MAX_VIEWS,
+        );
+
+        assert_eq!(reason, Some(BotReason::Crawler));
+    }
+
+    #[test]
+    fn missing_browser_headers_are_suspicious() {
+        let firefox = "Mozilla/5.0 (X11; Linux x86_64; rv:109.0) Gecko/20100101 Firefox/118.0";
+
+        assert_eq!(
+            check(firefox, &HeaderMap::new(), [10, 0, 0, 1]),
+            Some(BotReason::SuspiciousHeaders)
+        );
+        assert_eq!(
+            check("SomeApp 1.0", &browser_headers(), [10, 0, 0, 1]),
+            Some(BotReason::SuspiciousHeaders)
+        );
+    }
+
+    #[test]
+    fn views_over_the_rate_limit_are_caught() {
+        let firefox = "Mozilla/5.0 (X11; Linux x86_64; rv:109.0) Gecko/20100101 Firefox/118.0";
+        let rates = RateTracker::default();
+        let headers = browser_headers();
+        let classify_from = |ip: [u8; 4]| {
+            classify(
+                firefox,
+                "Other",
+                &headers,
+                IpAddr::from(ip),
+                &rates,
+                MAX_VIEWS,
+            )
+        };
+
+        for _ in 0..MAX_VIEWS {
+            assert_eq!(classify_from([10, 0, 0, 1]), None);
+        }
+
+        assert_eq!(classify_from([10, 0, 0, 1]), Some(BotReason::RateLimited));
+
+        // Other ips have their own limit.
+        assert_eq!(classify_from([10, 0, 0, 2]), None);
+    }
+}
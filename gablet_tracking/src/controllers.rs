@@ -1 +1,3 @@
-pub mod metrics;
\ No newline at end of file
+pub mod analytics;
+pub mod metrics;
+pub mod trending;
\ No newline at end of file
@@ -0,0 +1,382 @@
+use std::collections::BTreeMap;
+
+use axum::{extract::Query, http::StatusCode, Json};
+use chrono::{Datelike, Days, NaiveDate, Utc};
+use diesel::{
+    prelude::*,
+    sql_query,
+    sql_types::{BigInt, Date, Int4, Text},
+};
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use gablet_shared_api::errors::{get_error_from_string, get_internal_error, ErrorResult};
+
+use crate::{
+    extractors::AnalyticsTarget,
+    hll::HyperLogLog,
+    models::{
+        analytics::{
+            AnalyticsQuery, AnalyticsReport, Bucket, ReaderBucket, ReaderEstimate, ViewBreakdown,
+            ViewBucket,
+        },
+        tracking::FilteredKind,
+    },
+    rollups::{rolled_up_to, Rollup},
+    PG_POOL,
+};
+
+const DEFAULT_RANGE_DAYS: u64 = 30;
+const MAX_RANGE_DAYS: i64 = 366;
+const MAX_BREAKDOWN_ROWS: i64 = 20;
+
+impl AnalyticsTarget {
+    fn id(self) -> i32 {
+        match self {
+            AnalyticsTarget::Book(id)
+            | AnalyticsTarget::Chapter(id)
+            | AnalyticsTarget::Profile(id) => id,
+        }
+    }
+
+    fn rollup(self) -> Rollup {
+        match self {
+            AnalyticsTarget::Book(_) | AnalyticsTarget::Chapter(_) => Rollup::Book,
+            AnalyticsTarget::Profile(_) => Rollup::User,
+        }
+    }
+
+    /// The daily device, OS and browser counts of the target's views.
+    fn breakdown_table(self) -> &'static str {
+        match self {
+            AnalyticsTarget::Book(_) | AnalyticsTarget::Chapter(_) => "daily_book_breakdowns",
+            AnalyticsTarget::Profile(_) => "daily_user_breakdowns",
+        }
+    }
+
+    fn raw_table(self) -> &'static str {
+        match self {
+            AnalyticsTarget::Book(_) | AnalyticsTarget::Chapter(_) => "book_views",
+            AnalyticsTarget::Profile(_) => "user_views",
+        }
+    }
+
+    fn filtered_kind(self) -> FilteredKind {
+        match self {
+            AnalyticsTarget::Book(_) => FilteredKind::Book,
+            AnalyticsTarget::Chapter(_) => FilteredKind::Chapter,
+            AnalyticsTarget::Profile(_) => FilteredKind::Profile,
+        }
+    }
+
+    /// The column the target's id is in, which is the same in the raw and
+    /// daily tables.
+    fn id_column(self) -> &'static str {
+        match self {
+            AnalyticsTarget::Book(_) => "book_id",
+            AnalyticsTarget::Chapter(_) => "chapter_id",
+            AnalyticsTarget::Profile(_) => "user_id",
+        }
+    }
+}
+
+/// The first day of the bucket a day is in, the same as `date_trunc` in
+/// postgres.
+fn bucket_start(bucket: Bucket, day: NaiveDate) -> NaiveDate {
+    match bucket {
+        Bucket::Day => day,
+        Bucket::Week => day - Days::new(day.weekday().num_days_from_monday() as u64),
+        Bucket::Month => day.with_day(1).unwrap_or(day),
+    }
+}
+
+/// The start of every bucket from `from` to `to`, inclusive, so buckets
+/// without any views or readers are still reported.
+fn bucket_starts(bucket: Bucket, from: NaiveDate, to: NaiveDate) -> Vec<NaiveDate> {
+    let mut starts: Vec<NaiveDate> = from
+        .iter_days()
+        .take_while(|day| *day <= to)
+        .map(|day| bucket_start(bucket, day))
+        .collect();
+
+    starts.dedup();
+    starts
+}
+
+impl From<&HyperLogLog> for ReaderEstimate {
+    fn from(sketch: &HyperLogLog) -> Self {
+        let estimate = sketch.estimate();
+        let margin = estimate * 2.0 * HyperLogLog::STANDARD_ERROR;
+
+        ReaderEstimate {
+            estimate: estimate.round() as u64,
+            low: (estimate - margin).max(0.0).floor() as u64,
+            high: (estimate + margin).ceil() as u64,
+        }
+    }
+}
+
+/// Loads the daily reader sketches of a book or chapter. Profiles don't have
+/// any.
+async fn load_reader_sketches(
+    target: AnalyticsTarget,
+    from: NaiveDate,
+    end: NaiveDate,
+    connection: &mut AsyncPgConnection,
+) -> Result<Vec<(NaiveDate, Vec<u8>)>, diesel::result::Error> {
+    use crate::schema::daily_book_readers::dsl::*;
+
+    let in_range = daily_book_readers
+        .filter(date.ge(from))
+        .filter(date.lt(end))
+        .select((date, sketch));
+
+    match target {
+        AnalyticsTarget::Book(book) => in_range.filter(book_id.eq(book)).load(connection).await,
+        AnalyticsTarget::Chapter(chapter) => {
+            in_range
+                .filter(chapter_id.eq(chapter))
+                .load(connection)
+                .await
+        }
+        AnalyticsTarget::Profile(_) => Ok(Vec::new()),
+    }
+}
+
+/// Counts the views of a target per bucket. Days that have been rolled up
+/// (from `$3` up to `$4`) are read from the daily table, and the rest (from
+/// `$4` up to `$5`) are counted from the raw views.
+fn bucket_sql(target: AnalyticsTarget) -> String {
+    format!(
+        "
+SELECT date_trunc($2, day)::date AS start, SUM(views)::bigint AS views FROM (
+    SELECT date AS day, count::bigint AS views FROM {daily}
+    WHERE {column} = $1 AND date >= $3 AND date < $4
+    UNION ALL
+    SELECT dt::date AS day, COUNT(*) AS views FROM {raw}
+    WHERE {column} = $1 AND dt >= $4 AND dt < $5
+    GROUP BY dt::date
+) counted
+GROUP BY 1
+ORDER BY 1",
+        daily = target.rollup().name(),
+        raw = target.raw_table(),
+        column = target.id_column(),
+    )
+}
+
+/// The most common values of `field` among a target's views. Like the
+/// buckets, days that have been rolled up (from `$2` up to `$3`) are read from
+/// the daily breakdowns and the rest (from `$3` up to `$4`) from the raw views.
+fn breakdown_sql(target: AnalyticsTarget, field: &str) -> String {
+    format!(
+        "
+SELECT name, SUM(views)::bigint AS views FROM (
+    SELECT value AS name, count::bigint AS views FROM {daily}
+    WHERE {column} = $1 AND field = '{field}' AND date >= $2 AND date < $3
+    UNION ALL
+    SELECT {field} AS name, COUNT(*) AS views FROM {raw}
+    WHERE {column} = $1 AND dt >= $3 AND dt < $4
+    GROUP BY 1
+) counted
+GROUP BY 1
+ORDER BY 2 DESC, 1
+LIMIT $5",
+        field = field,
+        daily = target.breakdown_table(),
+        raw = target.raw_table(),
+        column = target.id_column(),
+    )
+}
+
+/// How many views of a target were left out because they came from bots, by
+/// reason.
+const FILTERED_SQL: &str = "
+SELECT reason AS name, SUM(count)::bigint AS views FROM daily_filtered_views
+WHERE kind = $1 AND target_id = $2 AND date >= $3 AND date < $4
+GROUP BY 1
+ORDER BY 2 DESC, 1";
+
+async fn load_breakdown(
+    target: AnalyticsTarget,
+    field: &str,
+    from: NaiveDate,
+    cutoff: NaiveDate,
+    end: NaiveDate,
+    connection: &mut AsyncPgConnection,
+) -> Result<Vec<ViewBreakdown>, diesel::result::Error> {
+    sql_query(breakdown_sql(target, field))
+        .bind::<Int4, _>(target.id())
+        .bind::<Date, _>(from)
+        .bind::<Date, _>(cutoff)
+        .bind::<Date, _>(end)
+        .bind::<BigInt, _>(MAX_BREAKDOWN_ROWS)
+        .load(connection)
+        .await
+}
+
+pub async fn get_analytics(
+    target: AnalyticsTarget,
+    Query(query): Query<AnalyticsQuery>,
+) -> Result<Json<AnalyticsReport>, (StatusCode, Json<ErrorResult>)> {
+    let to = query.to.unwrap_or_else(|| Utc::now().date_naive());
+    let from = query
+        .from
+        .unwrap_or_else(|| to - Days::new(DEFAULT_RANGE_DAYS - 1));
+
+    if from > to {
+        return Err(get_error_from_string(
+            StatusCode::BAD_REQUEST,
+            format!("The range starts on {} after it ends on {}", from, to),
+        )
+        .to_tuple());
+    }
+
+    if (to - from).num_days() >= MAX_RANGE_DAYS {
+        return Err(get_error_from_string(
+            StatusCode::BAD_REQUEST,
+            format!("The range can be at most {} days long", MAX_RANGE_DAYS),
+        )
+        .to_tuple());
+    }
+
+    let end = to.succ_opt().ok_or_else(|| {
+        get_error_from_string(StatusCode::BAD_REQUEST, "Invalid end date".to_string()).to_tuple()
+    })?;
+
+    let pool = PG_POOL.get().unwrap().clone();
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let cutoff = rolled_up_to(target.rollup(), connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?
+        .unwrap_or(from)
+        .clamp(from, end);
+
+    let counted: Vec<ViewBucket> = sql_query(bucket_sql(target))
+        .bind::<Int4, _>(target.id())
+        .bind::<Text, _>(query.bucket.to_string())
+        .bind::<Date, _>(from)
+        .bind::<Date, _>(cutoff)
+        .bind::<Date, _>(end)
+        .load(connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let views: BTreeMap<NaiveDate, i64> = counted
+        .into_iter()
+        .map(|bucket| (bucket.start, bucket.views))
+        .collect();
+    let starts = bucket_starts(query.bucket, from, to);
+
+    let buckets: Vec<ViewBucket> = starts
+        .iter()
+        .map(|start| ViewBucket {
+            start: *start,
+            views: views.get(start).copied().unwrap_or_default(),
+        })
+        .collect();
+
+    let devices = load_breakdown(target, "device", from, cutoff, end, connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+    let os = load_breakdown(target, "os", from, cutoff, end, connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+    let browsers = load_breakdown(target, "browser", from, cutoff, end, connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let filtered: Vec<ViewBreakdown> = sql_query(FILTERED_SQL)
+        .bind::<Text, _>(target.filtered_kind().to_string())
+        .bind::<Int4, _>(target.id())
+        .bind::<Date, _>(from)
+        .bind::<Date, _>(end)
+        .load(connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let sketches = load_reader_sketches(target, from, end, connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let mut total_readers = HyperLogLog::default();
+    let mut bucket_readers: BTreeMap<NaiveDate, HyperLogLog> = BTreeMap::new();
+
+    for (day, bytes) in sketches {
+        let Some(sketch) = HyperLogLog::from_bytes(&bytes) else {
+            tracing::warn!("Skipping invalid reader sketch from {}", day);
+            continue;
+        };
+
+        total_readers.merge(&sketch);
+        bucket_readers
+            .entry(bucket_start(query.bucket, day))
+            .or_default()
+            .merge(&sketch);
+    }
+
+    let (readers, reader_buckets) = match target {
+        AnalyticsTarget::Profile(_) => (None, Vec::new()),
+        _ => {
+            let empty = HyperLogLog::default();
+            let reader_buckets = starts
+                .iter()
+                .map(|start| ReaderBucket {
+                    start: *start,
+                    readers: ReaderEstimate::from(bucket_readers.get(start).unwrap_or(&empty)),
+                })
+                .collect();
+
+            (Some(ReaderEstimate::from(&total_readers)), reader_buckets)
+        }
+    };
+
+    Ok(Json(AnalyticsReport {
+        from,
+        to,
+        bucket: query.bucket,
+        total: buckets.iter().map(|bucket| bucket.views).sum(),
+        buckets,
+        devices,
+        os,
+        browsers,
+        filtered,
+        readers,
+        reader_buckets,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(text: &str) -> NaiveDate {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn every_bucket_in_the_range_is_listed() {
+        assert_eq!(
+            bucket_starts(Bucket::Day, day("2023-09-29"), day("2023-10-02")),
+            vec![
+                day("2023-09-29"),
+                day("2023-09-30"),
+                day("2023-10-01"),
+                day("2023-10-02")
+            ]
+        );
+
+        // 2023-09-20 is a Wednesday, so its week starts on the Monday before.
+        assert_eq!(
+            bucket_starts(Bucket::Week, day("2023-09-20"), day("2023-10-02")),
+            vec![day("2023-09-18"), day("2023-09-25"), day("2023-10-02")]
+        );
+
+        assert_eq!(
+            bucket_starts(Bucket::Month, day("2023-08-15"), day("2023-10-01")),
+            vec![day("2023-08-01"), day("2023-09-01"), day("2023-10-01")]
+        );
+    }
+}
@@ -6,15 +6,25 @@ use axum::{
     http::{HeaderMap, StatusCode},
     Json, TypedHeader,
 };
+use chrono::Utc;
 use gablet_shared_api::{
-    errors::{get_internal_error, ErrorResult},
-    kafka::kafka_events::{TRACKING_TOPIC, TRACKING_WEB_EVENT},
+    errors::{get_internal_dyn_error, get_internal_error, ErrorResult},
+    kafka::kafka_events::{
+        TRACKING_BOOK_EVENT, TRACKING_FILTERED_EVENT, TRACKING_TOPIC, TRACKING_USER_EVENT,
+        TRACKING_WEB_EVENT,
+    },
 };
 use ipnetwork::IpNetwork;
 use kafka::producer::Record;
+use serde::Serialize;
 
 use crate::{
-    models::tracking::{NewWebView, UserInfo},
+    bots::classify_request,
+    models::tracking::{
+        BookViewInfo, FilteredKind, FilteredView, NewBookView, NewUserView, NewWebView,
+        ProfileViewInfo, UserInfo,
+    },
+    privacy::anonymize_ip,
     TOKEN_ISSUER, TRACKING_PRODUCER,
 };
 
@@ -32,20 +42,15 @@ pub async fn track_web_view(
     ConnectInfo(addr): ConnectInfo<SocketAddr>,
     user_info: Option<Json<UserInfo>>,
 ) -> Result<StatusCode, (StatusCode, Json<ErrorResult>)> {
-    let ua: fast_uaparser::UserAgent = user_agent
-        .as_str()
-        .parse()
-        .map_err(|err| get_internal_error(err).to_tuple())?;
-    let device: fast_uaparser::Device = user_agent
-        .as_str()
-        .parse()
-        .map_err(|err| get_internal_error(err).to_tuple())?;
-    let os: fast_uaparser::OperatingSystem = user_agent
-        .as_str()
-        .parse()
-        .map_err(|err| get_internal_error(err).to_tuple())?;
-    let ip = IpNetwork::new(addr.ip(), if addr.is_ipv4() { 32u8 } else { 128u8 })
-        .map_err(|err| get_internal_error(err).to_tuple())?;
+    let client = parse_client(&user_agent, addr)
+        .await
+        .map_err(|err| err.to_tuple())?;
+
+    let targets = vec![(FilteredKind::Web, -1)];
+
+    if filter_bot(&user_agent, &client, &headers, addr, targets).map_err(|err| err.to_tuple())? {
+        return Ok(StatusCode::OK);
+    }
 
     let domain = match headers.get("referer") {
         Some(referer) => referer
@@ -69,25 +74,161 @@ pub async fn track_web_view(
 
     let view = NewWebView {
         user_id,
+        browser: client.browser,
+        os: client.os,
+        device: client.device,
+        ip: client.ip,
+        href: host.hostname().to_string(),
+        domain: domain.to_owned(),
+    };
+
+    send_tracking_event(TRACKING_WEB_EVENT, &view).map_err(|err| err.to_tuple())?;
+
+    Ok(StatusCode::OK)
+}
+
+pub async fn track_book_view(
+    TypedHeader(user_agent): TypedHeader<UserAgent>,
+    bearer: Option<TypedHeader<Authorization<Bearer>>>,
+    headers: HeaderMap,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    Json(book_info): Json<BookViewInfo>,
+) -> Result<StatusCode, (StatusCode, Json<ErrorResult>)> {
+    let client = parse_client(&user_agent, addr)
+        .await
+        .map_err(|err| err.to_tuple())?;
+
+    let mut targets = vec![(FilteredKind::Book, book_info.book_id)];
+    targets.extend(book_info.chapter_id.map(|id| (FilteredKind::Chapter, id)));
+
+    if filter_bot(&user_agent, &client, &headers, addr, targets).map_err(|err| err.to_tuple())? {
+        return Ok(StatusCode::OK);
+    }
+
+    let user_id = bearer.and_then(|TypedHeader(Authorization(bearer))| {
+        TOKEN_ISSUER
+            .validate_auth(bearer.token())
+            .ok()
+            .map(|auth| auth.user_id())
+    });
+
+    let view = NewBookView {
+        book_id: book_info.book_id,
+        chapter_id: book_info.chapter_id,
+        user_id,
+        browser: client.browser,
+        os: client.os,
+        device: client.device,
+        ip: client.ip,
+    };
+
+    send_tracking_event(TRACKING_BOOK_EVENT, &view).map_err(|err| err.to_tuple())?;
+
+    Ok(StatusCode::OK)
+}
+
+pub async fn track_profile_view(
+    TypedHeader(user_agent): TypedHeader<UserAgent>,
+    bearer: Option<TypedHeader<Authorization<Bearer>>>,
+    headers: HeaderMap,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    Json(profile_info): Json<ProfileViewInfo>,
+) -> Result<StatusCode, (StatusCode, Json<ErrorResult>)> {
+    let client = parse_client(&user_agent, addr)
+        .await
+        .map_err(|err| err.to_tuple())?;
+
+    let targets = vec![(FilteredKind::Profile, profile_info.user_id)];
+
+    if filter_bot(&user_agent, &client, &headers, addr, targets).map_err(|err| err.to_tuple())? {
+        return Ok(StatusCode::OK);
+    }
+
+    let viewer_id = bearer.and_then(|TypedHeader(Authorization(bearer))| {
+        TOKEN_ISSUER
+            .validate_auth(bearer.token())
+            .ok()
+            .map(|auth| auth.user_id())
+    });
+
+    // People looking at their own profile aren't counted.
+    if viewer_id == Some(profile_info.user_id) {
+        return Ok(StatusCode::OK);
+    }
+
+    let view = NewUserView {
+        viewer_id,
+        user_id: profile_info.user_id,
+        browser: client.browser,
+        os: client.os,
+        device: client.device,
+        ip: client.ip,
+    };
+
+    send_tracking_event(TRACKING_USER_EVENT, &view).map_err(|err| err.to_tuple())?;
+
+    Ok(StatusCode::OK)
+}
+
+/// What can be told about a client from its user agent and address.
+struct ClientInfo {
+    browser: String,
+    os: String,
+    device: String,
+    ip: IpNetwork,
+}
+
+async fn parse_client(user_agent: &UserAgent, addr: SocketAddr) -> Result<ClientInfo, ErrorResult> {
+    let ua: fast_uaparser::UserAgent = user_agent.as_str().parse().map_err(get_internal_error)?;
+    let device: fast_uaparser::Device = user_agent.as_str().parse().map_err(get_internal_error)?;
+    let os: fast_uaparser::OperatingSystem =
+        user_agent.as_str().parse().map_err(get_internal_error)?;
+    let ip = anonymize_ip(addr.ip())
+        .await
+        .map_err(get_internal_dyn_error)?;
+
+    Ok(ClientInfo {
         browser: ua.family,
         os: os.family,
         device: device.family,
         ip,
-        href: host.hostname().to_string(),
-        domain: domain.to_owned(),
+    })
+}
+
+/// Checks whether a view came from a bot, and if it did counts it as filtered
+/// instead. Returns whether the view was filtered.
+fn filter_bot(
+    user_agent: &UserAgent,
+    client: &ClientInfo,
+    headers: &HeaderMap,
+    addr: SocketAddr,
+    targets: Vec<(FilteredKind, i32)>,
+) -> Result<bool, ErrorResult> {
+    let Some(reason) = classify_request(user_agent.as_str(), &client.device, headers, addr.ip())
+    else {
+        return Ok(false);
+    };
+
+    let view = FilteredView {
+        id: rand::random(),
+        dt: Utc::now().naive_utc(),
+        reason,
+        targets,
     };
 
-    let val = serde_json::to_string(&view).map_err(|err| get_internal_error(err).to_tuple())?;
+    send_tracking_event(TRACKING_FILTERED_EVENT, &view)?;
+
+    Ok(true)
+}
+
+fn send_tracking_event<T: Serialize>(key: &str, event: &T) -> Result<(), ErrorResult> {
+    let val = serde_json::to_string(event).map_err(get_internal_error)?;
 
     TRACKING_PRODUCER
         .lock()
-        .map_err(|err| get_internal_error(err).to_tuple())?
-        .send(&Record::from_key_value(
-            TRACKING_TOPIC,
-            TRACKING_WEB_EVENT,
-            val,
-        ))
-        .map_err(|err| get_internal_error(err).to_tuple())?;
+        .map_err(get_internal_error)?
+        .send(&Record::from_key_value(TRACKING_TOPIC, key, val))
+        .map_err(get_internal_error)?;
 
-    Ok(StatusCode::OK)
+    Ok(())
 }
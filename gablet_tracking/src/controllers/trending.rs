@@ -0,0 +1,168 @@
+use axum::{extract::Query, http::StatusCode, Json};
+use diesel::{
+    sql_query,
+    sql_types::{BigInt, Double, Nullable, Varchar},
+};
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use gablet_shared_api::{
+    errors::{get_internal_error, ErrorResult},
+    pagination::clamp_limit,
+    trending::{TrendingBook, TrendingQuery},
+};
+
+use crate::{trending::half_life_hours, PG_POOL};
+
+const DEFAULT_TRENDING_LIMIT: i64 = 20;
+const MAX_TRENDING_LIMIT: i64 = 100;
+
+/// The highest scoring approved books, with their scores decayed to now by a
+/// half life of `$1` hours. Languages and genres come from the copies of the
+/// books gablet_api sends in `tracked_book` events, and books trend in the
+/// languages they've been translated into as well as their own.
+const TRENDING_SQL: &str = "
+SELECT trending_books.book_id,
+    trending_books.score
+        * power(0.5, extract(epoch FROM CURRENT_TIMESTAMP - trending_books.updated) / 3600 / $1)
+        AS score
+FROM trending_books
+JOIN tracked_books USING (book_id)
+WHERE tracked_books.approved
+    AND ($2 IS NULL OR tracked_books.lang = $2 OR $2 = ANY(tracked_books.translations))
+    AND ($3 IS NULL OR EXISTS (
+        SELECT 1 FROM unnest(tracked_books.genres) AS genre
+        WHERE lower(genre) = lower($3)
+    ))
+ORDER BY score DESC, trending_books.book_id
+LIMIT $4";
+
+async fn load_trending(
+    query: TrendingQuery,
+    half_life_hours: f64,
+    limit: i64,
+    connection: &mut AsyncPgConnection,
+) -> Result<Vec<TrendingBook>, diesel::result::Error> {
+    sql_query(TRENDING_SQL)
+        .bind::<Double, _>(half_life_hours)
+        .bind::<Nullable<Varchar>, _>(query.lang)
+        .bind::<Nullable<Varchar>, _>(query.genre)
+        .bind::<BigInt, _>(limit)
+        .load(connection)
+        .await
+}
+
+pub async fn get_trending(
+    Query(query): Query<TrendingQuery>,
+) -> Result<Json<Vec<TrendingBook>>, (StatusCode, Json<ErrorResult>)> {
+    let limit = clamp_limit(query.limit, DEFAULT_TRENDING_LIMIT, MAX_TRENDING_LIMIT);
+
+    let pool = PG_POOL.get().unwrap().clone();
+    let connection = &mut pool
+        .get()
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    let books = load_trending(query, half_life_hours(), limit, connection)
+        .await
+        .map_err(|err| get_internal_error(err).to_tuple())?;
+
+    Ok(Json(books))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_db::{add_book, add_translations, clear, test_connection};
+
+    fn query(lang: Option<&str>, genre: Option<&str>) -> TrendingQuery {
+        TrendingQuery {
+            lang: lang.map(str::to_string),
+            genre: genre.map(str::to_string),
+            limit: None,
+        }
+    }
+
+    fn book_ids(books: &[TrendingBook]) -> Vec<i32> {
+        books.iter().map(|book| book.book_id).collect()
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TRACKING_TEST_DATABASE_URL"]
+    async fn scores_halve_every_half_life() {
+        let mut connection = test_connection().await;
+        clear(&mut connection).await;
+
+        add_book(1, true, "en", &[], 8.0, 12.0, &mut connection).await;
+        add_book(2, true, "en", &[], 3.0, 0.0, &mut connection).await;
+        add_book(3, true, "en", &[], 1.0, 3.0, &mut connection).await;
+
+        let books = load_trending(query(None, None), 6.0, 10, &mut connection)
+            .await
+            .unwrap();
+
+        // Two half lives take 8 down to 2, which is now behind 3.
+        assert_eq!(book_ids(&books), [2, 1, 3]);
+        assert!((books[0].score - 3.0).abs() < 1e-9);
+        assert!((books[1].score - 2.0).abs() < 1e-9);
+        assert!((books[2].score - 0.5f64.sqrt()).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TRACKING_TEST_DATABASE_URL"]
+    async fn only_approved_books_in_the_language_and_genre_trend() {
+        let mut connection = test_connection().await;
+        clear(&mut connection).await;
+
+        add_book(1, true, "en", &["Fantasy", "Action"], 5.0, 0.0, &mut connection).await;
+        add_book(2, false, "en", &["Fantasy"], 4.0, 0.0, &mut connection).await;
+        add_book(3, true, "ja", &["Fantasy"], 3.0, 0.0, &mut connection).await;
+        add_book(4, true, "en", &["Romance"], 2.0, 0.0, &mut connection).await;
+
+        let all = load_trending(query(None, None), 6.0, 10, &mut connection).await.unwrap();
+        assert_eq!(book_ids(&all), [1, 3, 4]);
+
+        let english = load_trending(query(Some("en"), None), 6.0, 10, &mut connection)
+            .await
+            .unwrap();
+        assert_eq!(book_ids(&english), [1, 4]);
+
+        let fantasy = load_trending(query(None, Some("fantasy")), 6.0, 10, &mut connection)
+            .await
+            .unwrap();
+        assert_eq!(book_ids(&fantasy), [1, 3]);
+
+        let both = load_trending(query(Some("en"), Some("FANTASY")), 6.0, 10, &mut connection)
+            .await
+            .unwrap();
+        assert_eq!(book_ids(&both), [1]);
+
+        let limited = load_trending(query(None, None), 6.0, 2, &mut connection).await.unwrap();
+        assert_eq!(book_ids(&limited), [1, 3]);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TRACKING_TEST_DATABASE_URL"]
+    async fn books_trend_in_the_languages_they_are_translated_into() {
+        let mut connection = test_connection().await;
+        clear(&mut connection).await;
+
+        add_book(1, true, "ja", &[], 5.0, 0.0, &mut connection).await;
+        add_book(2, true, "ja", &[], 4.0, 0.0, &mut connection).await;
+        add_book(3, true, "en", &[], 3.0, 0.0, &mut connection).await;
+        add_translations(1, &["en", "fr"], &mut connection).await;
+
+        let english = load_trending(query(Some("en"), None), 6.0, 10, &mut connection)
+            .await
+            .unwrap();
+        assert_eq!(book_ids(&english), [1, 3]);
+
+        let french = load_trending(query(Some("fr"), None), 6.0, 10, &mut connection)
+            .await
+            .unwrap();
+        assert_eq!(book_ids(&french), [1]);
+
+        let japanese = load_trending(query(Some("ja"), None), 6.0, 10, &mut connection)
+            .await
+            .unwrap();
+        assert_eq!(book_ids(&japanese), [1, 2]);
+    }
+}
@@ -1,19 +1,414 @@
-use crate::{models::tracking::NewWebView, PG_POOL};
-use diesel::insert_into;
-use diesel_async::RunQueryDsl;
-use std::error::Error;
+use std::collections::BTreeMap;
 
-pub async fn save_web_view(view: &NewWebView) -> Result<(), Box<dyn Error>> {
+use crate::{hll::HyperLogLog, models::tracking::{FilteredView, NewBookView, NewUserView, NewWebView}, trending::{half_life_hours, visitor_window_minutes}, TRACKING_CONFIG};
+use chrono::NaiveDate;
+use diesel::{insert_into, prelude::*, select, sql_query, sql_types::{Array, Bool, Double, Inet, Int4, Int8, Nullable, Text, Timestamp, Varchar}, upsert::excluded};
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use gablet_shared_api::tracked_books::TrackedBook;
+
+/// The most rows written by one insert. Postgres takes at most 65535
+/// parameters per statement, and a web view has 7.
+const MAX_ROWS_PER_INSERT: usize = 5000;
+
+pub async fn save_web_views(views: &[NewWebView], connection: &mut AsyncPgConnection) -> Result<(), diesel::result::Error> {
     use crate::schema::web_views;
 
-    let pool = PG_POOL.get().unwrap().clone();
+    for chunk in views.chunks(MAX_ROWS_PER_INSERT) {
+        insert_into(web_views::table)
+            .values(chunk)
+            .execute(connection)
+            .await?;
+    }
+
+    Ok(())
+}
+
+pub async fn save_book_views(views: &[NewBookView], connection: &mut AsyncPgConnection) -> Result<(), diesel::result::Error> {
+    use crate::schema::book_views;
+
+    for chunk in views.chunks(MAX_ROWS_PER_INSERT) {
+        insert_into(book_views::table)
+            .values(chunk)
+            .execute(connection)
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Takes the locks of the reader sketches of every book in `$1`, in order.
+const LOCK_BOOK_READERS_SQL: &str = "
+SELECT pg_advisory_xact_lock(hashtext('daily_book_readers'), book_id) FROM (
+    SELECT DISTINCT book_id FROM unnest($1::int4[]) AS book_id
+    ORDER BY book_id
+) locked";
+
+/// Who read a book. Readers who aren't logged in are told apart by the ip
+/// that's stored for them, so with hashed ips they count as a new reader each
+/// day, and with truncated ips everyone on the same network counts as one.
+fn reader_key(view: &NewBookView) -> String {
+    match view.user_id {
+        Some(user) => format!("user:{}", user),
+        None => format!("ip:{}", view.ip),
+    }
+}
 
-    let connection = &mut pool.get().await?;
+/// Adds the readers of a batch of book views to today's sketches. Adding the
+/// same reader again doesn't change a sketch, so a batch that's read again
+/// after a failure doesn't count anyone twice.
+pub async fn save_book_readers(views: &[NewBookView], connection: &mut AsyncPgConnection) -> Result<(), diesel::result::Error> {
+    use crate::schema::daily_book_readers::dsl::*;
 
-    insert_into(web_views::table)
-        .values(view)
+    if views.is_empty() {
+        return Ok(());
+    }
+
+    let today: NaiveDate = select(diesel::dsl::date(diesel::dsl::now)).first(connection).await?;
+
+    let mut sketches: BTreeMap<(i32, i32), HyperLogLog> = BTreeMap::new();
+
+    for view in views {
+        sketches
+            .entry((view.book_id, view.chapter_id.unwrap_or(-1)))
+            .or_default()
+            .insert(reader_key(view).as_bytes());
+    }
+
+    let book_ids: Vec<i32> = sketches.keys().map(|(book, _)| *book).collect();
+
+    // The sketches are merged here rather than in the database, so two
+    // batches with readers of the same book take turns.
+    sql_query(LOCK_BOOK_READERS_SQL)
+        .bind::<Array<Int4>, _>(&book_ids)
         .execute(connection)
         .await?;
 
+    let saved: Vec<(i32, i32, Vec<u8>)> = daily_book_readers
+        .filter(date.eq(today))
+        .filter(book_id.eq_any(&book_ids))
+        .select((book_id, chapter_id, sketch))
+        .load(connection)
+        .await?;
+
+    for (book, chapter, bytes) in saved {
+        if let (Some(current), Some(saved)) = (sketches.get_mut(&(book, chapter)), HyperLogLog::from_bytes(&bytes)) {
+            current.merge(&saved);
+        }
+    }
+
+    let rows: Vec<_> = sketches
+        .iter()
+        .map(|((book, chapter), readers)| {
+            (
+                book_id.eq(*book),
+                chapter_id.eq(*chapter),
+                date.eq(today),
+                sketch.eq(readers.to_bytes()),
+            )
+        })
+        .collect();
+
+    for chunk in rows.chunks(MAX_ROWS_PER_INSERT) {
+        insert_into(daily_book_readers)
+            .values(chunk)
+            .on_conflict((book_id, chapter_id, date))
+            .do_update()
+            .set(sketch.eq(excluded(sketch)))
+            .execute(connection)
+            .await?;
+    }
+
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Adds the views of a batch to the trending scores of their books. A visitor
+/// only counts towards a book once per window, which `trending_visitors`
+/// keeps track of, and a score decays by half every `$4` hours before the new
+/// views are added.
+const UPDATE_TRENDING_SQL: &str = "
+WITH counted AS (
+    INSERT INTO trending_visitors (book_id, visitor, counted)
+    SELECT DISTINCT v.book_id, v.visitor, CURRENT_TIMESTAMP::timestamp
+    FROM unnest($1::int4[], $2::varchar[]) AS v(book_id, visitor)
+    ON CONFLICT (book_id, visitor) DO UPDATE SET counted = EXCLUDED.counted
+    WHERE trending_visitors.counted <= EXCLUDED.counted - $3 * INTERVAL '1 minute'
+    RETURNING book_id
+)
+INSERT INTO trending_books (book_id, score, updated)
+SELECT book_id, COUNT(*), CURRENT_TIMESTAMP FROM counted
+GROUP BY book_id
+ON CONFLICT (book_id) DO UPDATE SET
+    score = trending_books.score
+        * power(0.5, extract(epoch FROM EXCLUDED.updated - trending_books.updated) / 3600 / $4)
+        + EXCLUDED.score,
+    updated = EXCLUDED.updated";
+
+pub async fn save_trending_views(views: &[NewBookView], connection: &mut AsyncPgConnection) -> Result<(), diesel::result::Error> {
+    update_trending(views, visitor_window_minutes(), half_life_hours(), connection).await
+}
+
+async fn update_trending(views: &[NewBookView], window_minutes: i32, half_life_hours: f64, connection: &mut AsyncPgConnection) -> Result<(), diesel::result::Error> {
+    if views.is_empty() {
+        return Ok(());
+    }
+
+    sql_query(UPDATE_TRENDING_SQL)
+        .bind::<Array<Int4>, _>(views.iter().map(|view| view.book_id).collect::<Vec<_>>())
+        .bind::<Array<Varchar>, _>(views.iter().map(reader_key).collect::<Vec<_>>())
+        .bind::<Int4, _>(window_minutes)
+        .bind::<Double, _>(half_life_hours)
+        .execute(connection)
+        .await?;
+
+    Ok(())
+}
+
+/// How long repeat profile views are ignored for if it isn't configured.
+const DEFAULT_PROFILE_VIEW_WINDOW_MINUTES: i64 = 30;
+
+/// Takes the locks of every profile in `$1`, in order so two batches can't
+/// wait on each other.
+const LOCK_USER_VIEWS_SQL: &str = "
+SELECT pg_advisory_xact_lock(hashtext('user_views'), user_id) FROM (
+    SELECT DISTINCT user_id FROM unnest($1::int4[]) AS user_id
+    ORDER BY user_id
+) locked";
+
+/// Records profile views unless it's someone looking at their own profile, or
+/// the same viewer already looked at it within the configured window.
+/// Visitors who aren't logged in are told apart by their ip.
+const INSERT_USER_VIEWS_SQL: &str = "
+INSERT INTO user_views (viewer_id, user_id, browser, os, device, ip)
+SELECT COALESCE(v.viewer_id, -1), v.user_id, v.browser, v.os, v.device, v.ip
+FROM unnest($1::int4[], $2::int4[], $3::varchar[], $4::varchar[], $5::varchar[], $6::inet[])
+    AS v(viewer_id, user_id, browser, os, device, ip)
+WHERE COALESCE(v.viewer_id, -1) <> v.user_id
+    AND NOT EXISTS (
+        SELECT 1 FROM user_views
+        WHERE user_views.user_id = v.user_id
+            AND user_views.viewer_id = COALESCE(v.viewer_id, -1)
+            AND (v.viewer_id IS NOT NULL OR user_views.ip = v.ip)
+            AND user_views.dt > CURRENT_TIMESTAMP - $7 * INTERVAL '1 minute'
+    )";
+
+/// Records a batch of profile views. The views are compared with the ones
+/// already saved, so this should be called with views that are already
+/// unique within the batch.
+pub async fn save_user_views(views: &[NewUserView], connection: &mut AsyncPgConnection) -> Result<(), diesel::result::Error> {
+    if views.is_empty() {
+        return Ok(());
+    }
+
+    let window = TRACKING_CONFIG
+        .profile_view_window_minutes
+        .unwrap_or(DEFAULT_PROFILE_VIEW_WINDOW_MINUTES)
+        .clamp(0, i32::MAX as i64) as i32;
+
+    let user_ids: Vec<i32> = views.iter().map(|view| view.user_id).collect();
+
+    // Views of the same profile are recorded one batch at a time, so two
+    // copies of a view can't both find that there's no earlier one. The
+    // caller is expected to be in a transaction, which holds the locks.
+    sql_query(LOCK_USER_VIEWS_SQL)
+        .bind::<Array<Int4>, _>(&user_ids)
+        .execute(connection)
+        .await?;
+
+    sql_query(INSERT_USER_VIEWS_SQL)
+        .bind::<Array<Nullable<Int4>>, _>(views.iter().map(|view| view.viewer_id).collect::<Vec<_>>())
+        .bind::<Array<Int4>, _>(&user_ids)
+        .bind::<Array<Varchar>, _>(views.iter().map(|view| view.browser.as_str()).collect::<Vec<_>>())
+        .bind::<Array<Varchar>, _>(views.iter().map(|view| view.os.as_str()).collect::<Vec<_>>())
+        .bind::<Array<Varchar>, _>(views.iter().map(|view| view.device.as_str()).collect::<Vec<_>>())
+        .bind::<Array<Inet>, _>(views.iter().map(|view| view.ip).collect::<Vec<_>>())
+        .bind::<Int4, _>(window)
+        .execute(connection)
+        .await?;
+
+    Ok(())
+}
+
+/// Counts views that were left out because they came from bots on the day
+/// they happened. The ids of the views are saved in `filtered_view_events`,
+/// and only views that weren't already there are counted, so a batch that's
+/// read again after a failure doesn't count anything twice.
+const INSERT_FILTERED_VIEWS_SQL: &str = "
+WITH new_events AS (
+    INSERT INTO filtered_view_events (event_id)
+    SELECT DISTINCT event_id FROM unnest($1::int8[]) AS event_id
+    ON CONFLICT DO NOTHING
+    RETURNING event_id
+)
+INSERT INTO daily_filtered_views (kind, target_id, reason, date, count)
+SELECT v.kind, v.target_id, v.reason, v.dt::date, COUNT(*)::int
+FROM unnest($1::int8[], $2::varchar[], $3::int4[], $4::varchar[], $5::timestamp[])
+    AS v(event_id, kind, target_id, reason, dt)
+JOIN new_events USING (event_id)
+GROUP BY 1, 2, 3, 4
+ON CONFLICT (kind, target_id, reason, date) DO UPDATE
+SET count = daily_filtered_views.count + EXCLUDED.count";
+
+pub async fn save_filtered_views(views: &[FilteredView], connection: &mut AsyncPgConnection) -> Result<(), diesel::result::Error> {
+    // One row per target, since a view is counted against each of them.
+    let targets: Vec<_> = views
+        .iter()
+        .flat_map(|view| view.targets.iter().map(move |(kind, target)| (view, kind, *target)))
+        .collect();
+
+    if targets.is_empty() {
+        return Ok(());
+    }
+
+    sql_query(INSERT_FILTERED_VIEWS_SQL)
+        .bind::<Array<Int8>, _>(targets.iter().map(|(view, _, _)| view.id).collect::<Vec<_>>())
+        .bind::<Array<Varchar>, _>(targets.iter().map(|(_, kind, _)| kind.to_string()).collect::<Vec<_>>())
+        .bind::<Array<Int4>, _>(targets.iter().map(|(_, _, target)| *target).collect::<Vec<_>>())
+        .bind::<Array<Varchar>, _>(targets.iter().map(|(view, _, _)| view.reason.to_string()).collect::<Vec<_>>())
+        .bind::<Array<Timestamp>, _>(targets.iter().map(|(view, _, _)| view.dt).collect::<Vec<_>>())
+        .execute(connection)
+        .await?;
+
+    Ok(())
+}
+/// Saves a book unless a newer version of it is already there. The same
+/// version is saved again, so a batch that's read again after a failure
+/// still writes its members and chapters.
+const UPSERT_TRACKED_BOOK_SQL: &str = "
+INSERT INTO tracked_books (book_id, author_id, approved, lang, translations, genres, updated)
+VALUES ($1, $2, $3, $4, $5, $6, $7)
+ON CONFLICT (book_id) DO UPDATE
+SET author_id = EXCLUDED.author_id,
+    approved = EXCLUDED.approved,
+    lang = EXCLUDED.lang,
+    translations = EXCLUDED.translations,
+    genres = EXCLUDED.genres,
+    updated = EXCLUDED.updated
+WHERE tracked_books.updated <= EXCLUDED.updated";
+
+const DELETE_TRACKED_MEMBERS_SQL: &str = "DELETE FROM tracked_book_members WHERE book_id = $1";
+
+const INSERT_TRACKED_MEMBERS_SQL: &str = "
+INSERT INTO tracked_book_members (book_id, user_id)
+SELECT DISTINCT $1, user_id FROM unnest($2::int4[]) AS user_id";
+
+const DELETE_TRACKED_CHAPTERS_SQL: &str = "DELETE FROM tracked_chapters WHERE book_id = $1";
+
+const INSERT_TRACKED_CHAPTERS_SQL: &str = "
+INSERT INTO tracked_chapters (chapter_id, book_id)
+SELECT DISTINCT chapter_id, $1 FROM unnest($2::int4[]) AS chapter_id
+ON CONFLICT (chapter_id) DO UPDATE SET book_id = EXCLUDED.book_id";
+
+/// Replaces the books gablet_api sent, along with their members and chapters.
+pub async fn save_tracked_books(books: &[TrackedBook], connection: &mut AsyncPgConnection) -> Result<(), diesel::result::Error> {
+    for book in books {
+        let saved = sql_query(UPSERT_TRACKED_BOOK_SQL)
+            .bind::<Int4, _>(book.book_id)
+            .bind::<Int4, _>(book.author_id)
+            .bind::<Bool, _>(book.approved)
+            .bind::<Varchar, _>(&book.lang)
+            .bind::<Array<Text>, _>(&book.translations)
+            .bind::<Array<Text>, _>(&book.genres)
+            .bind::<Timestamp, _>(book.updated)
+            .execute(connection)
+            .await?;
+
+        if saved == 0 {
+            continue;
+        }
+
+        sql_query(DELETE_TRACKED_MEMBERS_SQL)
+            .bind::<Int4, _>(book.book_id)
+            .execute(connection)
+            .await?;
+
+        sql_query(INSERT_TRACKED_MEMBERS_SQL)
+            .bind::<Int4, _>(book.book_id)
+            .bind::<Array<Int4>, _>(&book.members)
+            .execute(connection)
+            .await?;
+
+        sql_query(DELETE_TRACKED_CHAPTERS_SQL)
+            .bind::<Int4, _>(book.book_id)
+            .execute(connection)
+            .await?;
+
+        sql_query(INSERT_TRACKED_CHAPTERS_SQL)
+            .bind::<Int4, _>(book.book_id)
+            .bind::<Array<Int4>, _>(&book.chapter_ids)
+            .execute(connection)
+            .await?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_db::{clear, test_connection, view};
+
+    async fn score(book_id: i32, connection: &mut AsyncPgConnection) -> Option<f64> {
+        use crate::schema::trending_books;
+
+        trending_books::table.find(book_id).select(trending_books::score).first(connection).await.optional().unwrap()
+    }
+
+    /// Moves when every visitor was last counted back by some minutes.
+    async fn rewind_visitors(minutes: i32, connection: &mut AsyncPgConnection) {
+        sql_query("UPDATE trending_visitors SET counted = counted - $1 * INTERVAL '1 minute'")
+            .bind::<Int4, _>(minutes)
+            .execute(connection)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TRACKING_TEST_DATABASE_URL"]
+    async fn visitors_count_once_per_window() {
+        let mut connection = test_connection().await;
+        clear(&mut connection).await;
+
+        update_trending(&[view(1, 5), view(1, 5), view(1, 6), view(2, 5)], 60, 6.0, &mut connection).await.unwrap();
+        assert_eq!(score(1, &mut connection).await, Some(2.0));
+        assert_eq!(score(2, &mut connection).await, Some(1.0));
+
+        rewind_visitors(59, &mut connection).await;
+        update_trending(&[view(1, 5), view(1, 6)], 60, 6.0, &mut connection).await.unwrap();
+        assert_eq!(score(1, &mut connection).await, Some(2.0));
+
+        rewind_visitors(1, &mut connection).await;
+        update_trending(&[view(1, 5), view(1, 7)], 60, 6.0, &mut connection).await.unwrap();
+        assert_eq!(score(1, &mut connection).await, Some(4.0));
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TRACKING_TEST_DATABASE_URL"]
+    async fn visitors_inside_their_window_add_nothing() {
+        let mut connection = test_connection().await;
+        clear(&mut connection).await;
+
+        update_trending(&[view(1, 5)], 60, 6.0, &mut connection).await.unwrap();
+        sql_query("DELETE FROM trending_books").execute(&mut connection).await.unwrap();
+
+        update_trending(&[view(1, 5)], 60, 6.0, &mut connection).await.unwrap();
+        assert_eq!(score(1, &mut connection).await, None);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TRACKING_TEST_DATABASE_URL"]
+    async fn scores_decay_before_new_views_are_added() {
+        let mut connection = test_connection().await;
+        clear(&mut connection).await;
+
+        sql_query("INSERT INTO trending_books (book_id, score, updated) VALUES (1, 8, CURRENT_TIMESTAMP - INTERVAL '12 hours')")
+            .execute(&mut connection)
+            .await
+            .unwrap();
+
+        update_trending(&[view(1, 5)], 60, 6.0, &mut connection).await.unwrap();
+
+        // Two half lives take 8 down to 2, then the new visitor adds 1.
+        let decayed = score(1, &mut connection).await.unwrap();
+        assert!((decayed - 3.0).abs() < 1e-9, "{}", decayed);
+    }
+}
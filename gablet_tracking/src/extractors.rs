@@ -0,0 +1,131 @@
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Path},
+    headers::{authorization::Bearer, Authorization},
+    http::{request::Parts, StatusCode},
+    Json, TypedHeader,
+};
+use diesel::{
+    sql_query,
+    sql_types::{Bool, Int4},
+    QueryableByName,
+};
+use diesel_async::RunQueryDsl;
+use gablet_shared_api::errors::{
+    get_error, get_error_from_string, get_internal_error, ErrorResult,
+};
+use gablet_tokens::AuthToken;
+
+use crate::{PG_POOL, TOKEN_ISSUER};
+
+/// The token of the user making a request. Requests without a valid access
+/// token are rejected.
+pub struct AuthUser(pub AuthToken);
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for AuthUser {
+    type Rejection = (StatusCode, Json<ErrorResult>);
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let TypedHeader(Authorization(bearer)) =
+            TypedHeader::<Authorization<Bearer>>::from_request_parts(parts, state)
+                .await
+                .map_err(|err| get_error(err, StatusCode::UNAUTHORIZED).to_tuple())?;
+
+        TOKEN_ISSUER
+            .validate_auth(bearer.token())
+            .map(AuthUser)
+            .map_err(|err| get_error(err, StatusCode::UNAUTHORIZED).to_tuple())
+    }
+}
+
+/// The views of a book, chapter or profile, taken from an
+/// `/analytics/:kind/:id` path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnalyticsTarget {
+    Book(i32),
+    Chapter(i32),
+    Profile(i32),
+}
+
+/// Whether a user can see the views of a book. Authors can see their books,
+/// and translators can see the books they have a revenue share in or are
+/// working on. gablet_api keeps books in its own database, so this uses the
+/// copies it sends in `tracked_book` events.
+const BOOK_OWNER_SQL: &str = "
+SELECT EXISTS (SELECT 1 FROM tracked_books WHERE book_id = $1 AND author_id = $2)
+    OR EXISTS (SELECT 1 FROM tracked_book_members WHERE book_id = $1 AND user_id = $2)
+    AS owned";
+
+/// The same as `BOOK_OWNER_SQL` for the book a chapter belongs to.
+const CHAPTER_OWNER_SQL: &str = "
+WITH chapter AS (SELECT book_id FROM tracked_chapters WHERE chapter_id = $1)
+SELECT EXISTS (
+        SELECT 1 FROM tracked_books JOIN chapter USING (book_id)
+        WHERE tracked_books.author_id = $2
+    ) OR EXISTS (
+        SELECT 1 FROM tracked_book_members JOIN chapter USING (book_id)
+        WHERE tracked_book_members.user_id = $2
+    ) AS owned";
+
+#[derive(QueryableByName)]
+struct Ownership {
+    #[diesel(sql_type = Bool)]
+    owned: bool,
+}
+
+/// A target the user making the request owns. Anyone else is turned away,
+/// including when the target doesn't exist, so ids can't be probed.
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for AnalyticsTarget {
+    type Rejection = (StatusCode, Json<ErrorResult>);
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let AuthUser(auth) = AuthUser::from_request_parts(parts, state).await?;
+
+        let Path((kind, id)) = Path::<(String, i32)>::from_request_parts(parts, state)
+            .await
+            .map_err(|err| get_error(err, StatusCode::BAD_REQUEST).to_tuple())?;
+
+        let (target, owner_sql) = match kind.as_str() {
+            "books" => (AnalyticsTarget::Book(id), BOOK_OWNER_SQL),
+            "chapters" => (AnalyticsTarget::Chapter(id), CHAPTER_OWNER_SQL),
+            "profiles" if id == auth.user_id() => return Ok(AnalyticsTarget::Profile(id)),
+            "profiles" => return Err(not_owner()),
+            _ => {
+                return Err(get_error_from_string(
+                    StatusCode::NOT_FOUND,
+                    format!("Unknown analytics kind {}", kind),
+                )
+                .to_tuple())
+            }
+        };
+
+        let pool = PG_POOL.get().unwrap().clone();
+        let connection = &mut pool
+            .get()
+            .await
+            .map_err(|err| get_internal_error(err).to_tuple())?;
+
+        let ownership: Ownership = sql_query(owner_sql)
+            .bind::<Int4, _>(id)
+            .bind::<Int4, _>(auth.user_id())
+            .get_result(connection)
+            .await
+            .map_err(|err| get_internal_error(err).to_tuple())?;
+
+        if !ownership.owned {
+            return Err(not_owner());
+        }
+
+        Ok(target)
+    }
+}
+
+fn not_owner() -> (StatusCode, Json<ErrorResult>) {
+    get_error_from_string(
+        StatusCode::FORBIDDEN,
+        "You can only see the views of your own books and profile".to_string(),
+    )
+    .to_tuple()
+}
@@ -1,23 +1,167 @@
-use std::error::Error;
-use gablet_shared_api::kafka::kafka_events::TRACKING_WEB_EVENT;
-
-use crate::{models::tracking::NewWebView, events::tracking::save_web_view};
-
-pub async fn dispatch_kafka_event(key: String, value: String) -> Result<(), Box<dyn Error>> {
-    match key.as_str() {
-        "test" => {tracing::debug!("Received test value {}", value); Ok(()) },
-        TRACKING_WEB_EVENT => forward_track_web_view(value).await ,
-        _ => {
-            tracing::info!("Unknown kafka event {}", key.as_str());
-            Ok(())
+use std::{collections::HashSet, error::Error, time::Duration};
+use diesel::result::DatabaseErrorKind;
+use diesel_async::{scoped_futures::ScopedFutureExt, AsyncConnection};
+use gablet_shared_api::{kafka::{kafka_events::{CHAPTER_PUBLISHED_EVENT, TRACKED_BOOK_EVENT, TRACKING_BOOK_EVENT, TRACKING_FILTERED_EVENT, TRACKING_USER_EVENT, TRACKING_WEB_EVENT}, kafka_thread::{BatchOptions, EventBatch}}, tracked_books::TrackedBook};
+use ipnetwork::IpNetwork;
+use serde::de::DeserializeOwned;
+
+use crate::{events::tracking::{save_book_readers, save_book_views, save_filtered_views, save_tracked_books, save_trending_views, save_user_views, save_web_views}, models::tracking::{FilteredView, NewBookView, NewUserView, NewWebView}, PG_POOL, TRACKING_CONFIG};
+
+/// How many views are collected before they're written if it isn't configured.
+const DEFAULT_BATCH_SIZE: usize = 1000;
+
+/// How long views wait to be written if it isn't configured.
+const DEFAULT_BATCH_INTERVAL_MS: u64 = 1000;
+
+pub fn batch_options() -> BatchOptions {
+    BatchOptions {
+        max_events: TRACKING_CONFIG.batch_size.unwrap_or(DEFAULT_BATCH_SIZE).max(1),
+        max_wait: Duration::from_millis(TRACKING_CONFIG.batch_interval_ms.unwrap_or(DEFAULT_BATCH_INTERVAL_MS))
+    }
+}
+
+/// The views read from kafka since the last write, by table.
+#[derive(Default)]
+pub struct TrackingBatch {
+    web_views: Vec<NewWebView>,
+    book_views: Vec<NewBookView>,
+    user_views: Vec<NewUserView>,
+    /// Profile views already in the batch, so repeats within the batch are
+    /// dropped before they reach the database.
+    user_view_keys: HashSet<(i32, Option<i32>, Option<IpNetwork>)>,
+    filtered_views: Vec<FilteredView>,
+    tracked_books: Vec<TrackedBook>,
+    len: usize
+}
+
+/// Cuts a string down to the length of the column it's saved in, so one long
+/// value can't fail the whole batch.
+fn truncate(value: &mut String, max_chars: usize) {
+    if let Some((index, _)) = value.char_indices().nth(max_chars) {
+        value.truncate(index);
+    }
+}
+
+fn parse<T: DeserializeOwned>(key: &str, value: &str) -> Option<T> {
+    match serde_json::from_str(value) {
+        Ok(parsed) => Some(parsed),
+        Err(err) => {
+            tracing::error!("Skipping invalid {} event {}: {}", key, value, err);
+            None
+        }
+    }
+}
+
+impl TrackingBatch {
+    fn add_web_view(&mut self, mut view: NewWebView) {
+        truncate(&mut view.browser, 50);
+        truncate(&mut view.os, 50);
+        truncate(&mut view.device, 50);
+        truncate(&mut view.domain, 100);
+        self.web_views.push(view);
+    }
+
+    fn add_book_view(&mut self, mut view: NewBookView) {
+        truncate(&mut view.browser, 50);
+        truncate(&mut view.os, 50);
+        truncate(&mut view.device, 50);
+        self.book_views.push(view);
+    }
+
+    fn add_user_view(&mut self, mut view: NewUserView) {
+        // Anonymous visitors are told apart by their ip, the same way as in
+        // the database.
+        let key = (view.user_id, view.viewer_id, view.viewer_id.is_none().then_some(view.ip));
+
+        if view.viewer_id == Some(view.user_id) || !self.user_view_keys.insert(key) {
+            return;
         }
+
+        truncate(&mut view.browser, 50);
+        truncate(&mut view.os, 50);
+        truncate(&mut view.device, 50);
+        self.user_views.push(view);
+    }
+
+    fn add_filtered_view(&mut self, view: FilteredView) {
+        self.filtered_views.push(view);
     }
 }
 
-async fn forward_track_web_view(value: String) -> Result<(), Box<dyn Error>> {
-    let view: NewWebView = serde_json::from_str(&value)?;
+impl EventBatch for TrackingBatch {
+    fn add(&mut self, key: &str, value: &str) {
+        self.len += 1;
+
+        match key {
+            "test" => tracing::debug!("Received test value {}", value),
+            TRACKING_WEB_EVENT => if let Some(view) = parse(key, value) { self.add_web_view(view) },
+            TRACKING_BOOK_EVENT => if let Some(view) = parse(key, value) { self.add_book_view(view) },
+            TRACKING_USER_EVENT => if let Some(view) = parse(key, value) { self.add_user_view(view) },
+            TRACKING_FILTERED_EVENT => if let Some(view) = parse(key, value) { self.add_filtered_view(view) },
+            TRACKED_BOOK_EVENT => if let Some(book) = parse(key, value) { self.tracked_books.push(book) },
+            // Shares the books topic, but only gablet_api needs it.
+            CHAPTER_PUBLISHED_EVENT => (),
+            _ => tracing::info!("Unknown kafka event {}", key)
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Writes the tracked copies of books, then every view table in one
+    /// transaction, so a failed batch leaves no views behind when it's
+    /// written again. The tracked copies are only replaced by newer ones, so
+    /// writing them again is harmless.
+    async fn flush(&mut self) -> Result<(), Box<dyn Error>> {
+        let batch = &*self;
 
-    save_web_view(&view).await?;
+        let pool = PG_POOL.get().unwrap().clone();
 
-    Ok(())
+        let mut connection = pool.get().await?;
+
+        if !batch.tracked_books.is_empty() {
+            connection
+                .transaction::<_, diesel::result::Error, _>(|connection| {
+                    async move { save_tracked_books(&batch.tracked_books, connection).await }.scope_boxed()
+                })
+                .await?;
+        }
+
+        connection
+            .transaction::<_, diesel::result::Error, _>(|connection| {
+                async move {
+                    save_web_views(&batch.web_views, connection).await?;
+                    save_book_views(&batch.book_views, connection).await?;
+                    save_book_readers(&batch.book_views, connection).await?;
+                    save_trending_views(&batch.book_views, connection).await?;
+                    save_user_views(&batch.user_views, connection).await?;
+                    save_filtered_views(&batch.filtered_views, connection).await?;
+
+                    Ok(())
+                }
+                .scope_boxed()
+            })
+            .await?;
+
+        drop(connection);
+        *self = Self::default();
+
+        Ok(())
+    }
+
+    /// Errors from postgres about the rows themselves, as opposed to losing
+    /// the connection or a pool that can't connect.
+    fn is_rejected(err: &(dyn Error + 'static)) -> bool {
+        match err.downcast_ref::<diesel::result::Error>() {
+            Some(diesel::result::Error::DatabaseError(kind, _)) => !matches!(
+                kind,
+                DatabaseErrorKind::ClosedConnection
+                    | DatabaseErrorKind::UnableToSendCommand
+                    | DatabaseErrorKind::SerializationFailure
+                    | DatabaseErrorKind::ReadOnlyTransaction
+            ),
+            _ => false
+        }
+    }
 }
\ No newline at end of file
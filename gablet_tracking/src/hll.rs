@@ -0,0 +1,171 @@
+use sha2::{Digest, Sha256};
+
+/// How many bits of a hash pick the register it goes in. Each sketch has
+/// 2^12 one byte registers, for an error of about 1.6%.
+const PRECISION: u32 = 12;
+const REGISTERS: usize = 1 << PRECISION;
+
+/// A HyperLogLog sketch, which estimates how many different values were
+/// added to it in a fixed amount of space. Sketches of the same kind of value
+/// can be merged to estimate how many different values were added to any of
+/// them, e.g. the readers of a week from the readers of each day.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HyperLogLog {
+    registers: Vec<u8>,
+}
+
+impl Default for HyperLogLog {
+    fn default() -> Self {
+        HyperLogLog {
+            registers: vec![0; REGISTERS],
+        }
+    }
+}
+
+impl HyperLogLog {
+    /// The standard error of an estimate, relative to the estimate.
+    pub const STANDARD_ERROR: f64 = 1.04 / (1u64 << (PRECISION / 2)) as f64;
+
+    /// Reads a sketch saved with `to_bytes`, if it's the right size.
+    pub fn from_bytes(bytes: &[u8]) -> Option<HyperLogLog> {
+        if bytes.len() != REGISTERS {
+            return None;
+        }
+
+        Some(HyperLogLog {
+            registers: bytes.to_vec(),
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.registers.clone()
+    }
+
+    pub fn insert(&mut self, value: &[u8]) {
+        let digest = Sha256::digest(value);
+        let hash = u64::from_be_bytes(digest[..8].try_into().unwrap());
+
+        let index = (hash >> (64 - PRECISION)) as usize;
+        let rest = hash << PRECISION;
+        let rank = (rest.leading_zeros() + 1).min(64 - PRECISION + 1) as u8;
+
+        self.registers[index] = self.registers[index].max(rank);
+    }
+
+    /// Adds every value in another sketch to this one.
+    pub fn merge(&mut self, other: &HyperLogLog) {
+        for (register, other) in self.registers.iter_mut().zip(&other.registers) {
+            *register = (*register).max(*other);
+        }
+    }
+
+    /// Estimates how many different values have been added.
+    pub fn estimate(&self) -> f64 {
+        let m = REGISTERS as f64;
+        let alpha = 0.7213 / (1.0 + 1.079 / m);
+
+        let sum: f64 = self
+            .registers
+            .iter()
+            .map(|register| 2f64.powi(-(*register as i32)))
+            .sum();
+        let raw = alpha * m * m / sum;
+
+        // Small counts leave most registers empty, and counting the empty
+        // ones is more accurate than the raw estimate.
+        let zeros = self
+            .registers
+            .iter()
+            .filter(|register| **register == 0)
+            .count();
+
+        if raw <= 2.5 * m && zeros > 0 {
+            m * (m / zeros as f64).ln()
+        } else {
+            raw
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sketch_of(values: std::ops::Range<u64>) -> HyperLogLog {
+        let mut sketch = HyperLogLog::default();
+
+        for value in values {
+            sketch.insert(format!("user:{}", value).as_bytes());
+        }
+
+        sketch
+    }
+
+    #[test]
+    fn estimates_are_within_the_error_bound() {
+        for cardinality in [1_000, 10_000, 50_000, 250_000] {
+            let estimate = sketch_of(0..cardinality).estimate();
+            let error = (estimate - cardinality as f64).abs() / cardinality as f64;
+
+            // Three standard errors, which an estimate is outside of well
+            // under 1% of the time.
+            assert!(
+                error <= 3.0 * HyperLogLog::STANDARD_ERROR,
+                "{} estimated as {}",
+                cardinality,
+                estimate
+            );
+        }
+    }
+
+    #[test]
+    fn small_counts_use_linear_counting() {
+        assert_eq!(HyperLogLog::default().estimate(), 0.0);
+
+        for cardinality in [1, 10, 100] {
+            let estimate = sketch_of(0..cardinality).estimate();
+
+            assert!(
+                (estimate - cardinality as f64).abs() <= 1.0 + cardinality as f64 * 0.02,
+                "{} estimated as {}",
+                cardinality,
+                estimate
+            );
+        }
+    }
+
+    #[test]
+    fn repeated_values_are_counted_once() {
+        let mut sketch = sketch_of(0..500);
+        let before = sketch.clone();
+
+        sketch.merge(&sketch_of(0..500));
+        for value in 0..500 {
+            sketch.insert(format!("user:{}", value).as_bytes());
+        }
+
+        assert_eq!(sketch, before);
+    }
+
+    #[test]
+    fn merge_equals_union() {
+        let mut merged = sketch_of(0..60_000);
+        merged.merge(&sketch_of(40_000..100_000));
+
+        assert_eq!(merged, sketch_of(0..100_000));
+    }
+
+    #[test]
+    fn bytes_round_trip() {
+        let sketch = sketch_of(0..1_000);
+
+        assert_eq!(HyperLogLog::from_bytes(&sketch.to_bytes()), Some(sketch));
+    }
+
+    #[test]
+    fn from_bytes_rejects_the_wrong_length() {
+        assert_eq!(HyperLogLog::from_bytes(&[]), None);
+        assert_eq!(HyperLogLog::from_bytes(&[0; REGISTERS - 1]), None);
+        assert_eq!(HyperLogLog::from_bytes(&[0; REGISTERS + 1]), None);
+    }
+}
@@ -11,16 +11,18 @@ use axum::{
         header::{AUTHORIZATION, CONTENT_TYPE},
         Method,
     },
-    routing::get,
+    routing::{get, post},
     Router,
 };
+use chrono::NaiveDate;
 use diesel_async::{
     pooled_connection::{bb8::Pool, AsyncDieselConnectionManager},
     AsyncPgConnection,
 };
 use gablet_shared_api::{
-    cancellation_token::CancellationSource, credentials::Credentials,
-    kafka::kafka_thread::kafka_thread,
+    cancellation_token::CancellationSource,
+    credentials::{Credentials, Tracking},
+    kafka::kafka_thread::batched_kafka_thread,
 };
 use gablet_tokens::TokenIssuer;
 use kafka::producer::Producer;
@@ -29,15 +31,31 @@ use tower_http::{cors::CorsLayer, trace::TraceLayer};
 use tracing_subscriber::{prelude::__tracing_subscriber_SubscriberExt, util::SubscriberInitExt};
 
 use crate::{
-    controllers::metrics::{metrics_test, track_web_view},
-    gablet_kafka::kafka_thread::dispatch_kafka_event,
+    controllers::{
+        analytics::get_analytics,
+        metrics::{metrics_test, track_book_view, track_profile_view, track_web_view},
+        trending::get_trending,
+    },
+    gablet_kafka::kafka_thread::{batch_options, TrackingBatch},
+    retention::retention_scheduler,
+    rollups::{backfill_rollups, rollup_scheduler},
+    trending::trending_scheduler,
 };
 
+mod bots;
 mod controllers;
 mod events;
+mod extractors;
 mod gablet_kafka;
+mod hll;
 mod models;
+mod privacy;
+mod retention;
+mod rollups;
 mod schema;
+#[cfg(test)]
+mod test_db;
+mod trending;
 
 fn get_postgres_connection() -> String {
     let creds = Credentials::new("./config/credentials.toml")
@@ -66,6 +84,13 @@ pub static TOKEN_ISSUER: LazyLock<TokenIssuer> = LazyLock::new(|| {
     TokenIssuer::new(creds.access_secret, creds.refresh_secret)
 });
 
+pub static TRACKING_CONFIG: LazyLock<Tracking> = LazyLock::new(|| {
+    Credentials::new("./config/credentials.toml")
+        .unwrap()
+        .tracking
+        .unwrap_or_default()
+});
+
 pub static TRACKING_PRODUCER: LazyLock<Mutex<kafka::producer::Producer>> = LazyLock::new(|| {
     let creds = Credentials::new("./config/credentials.toml")
         .unwrap()
@@ -80,7 +105,7 @@ pub static TRACKING_PRODUCER: LazyLock<Mutex<kafka::producer::Producer>> = LazyL
     Mutex::new(producer)
 });
 
-pub async fn start() {
+fn init_tracing() {
     let filter = tracing_subscriber::filter::Targets::new()
         .with_target("tower_http::trace::on_response", tracing::Level::TRACE)
         .with_target("tower_http::trace::on_request", tracing::Level::TRACE)
@@ -95,6 +120,10 @@ pub async fn start() {
         .with(layer)
         .with(filter)
         .init();
+}
+
+pub async fn start() {
+    init_tracing();
 
     // Initialize CORS layer
     let cors = CorsLayer::new()
@@ -108,6 +137,10 @@ pub async fn start() {
     let app = Router::new()
         .route("/", get(metrics_test))
         .route("/tracking", get(track_web_view))
+        .route("/tracking/book", post(track_book_view))
+        .route("/tracking/profile", post(track_profile_view))
+        .route("/analytics/:kind/:id", get(get_analytics))
+        .route("/trending", get(get_trending))
         .layer(
             ServiceBuilder::new()
                 .layer(TraceLayer::new_for_http())
@@ -119,7 +152,12 @@ pub async fn start() {
 
     let (tx, rx) = tokio::sync::oneshot::channel::<()>();
 
-    std::thread::spawn(move || kafka_thread(token, dispatch_kafka_event));
+    let options = batch_options();
+    let handle = tokio::runtime::Handle::current();
+    std::thread::spawn(move || batched_kafka_thread(token, handle, TrackingBatch::default, options));
+    tokio::spawn(rollup_scheduler(cts.token()));
+    tokio::spawn(retention_scheduler(cts.token()));
+    tokio::spawn(trending_scheduler(cts.token()));
 
     let addr = SocketAddr::from(([127, 0, 0, 1], 3001));
     tracing::debug!("listening on {}", addr);
@@ -134,7 +172,52 @@ pub async fn start() {
     cts.request_cancellation();
 }
 
+const USAGE: &str = "Usage: gablet_tracking [backfill <from> <to>]";
+
+/// Prints an error along with how to run the program, and exits.
+fn exit_with_usage(message: &str) -> ! {
+    eprintln!("{}\n{}", message, USAGE);
+    std::process::exit(2);
+}
+
+fn parse_date(date: &str) -> NaiveDate {
+    match NaiveDate::parse_from_str(date, "%Y-%m-%d") {
+        Ok(date) => date,
+        Err(_) => exit_with_usage(&format!("Invalid date {}, expected YYYY-MM-DD", date)),
+    }
+}
+
+/// Recounts the daily view tables for a range of days, e.g.
+/// `gablet_tracking backfill 2023-08-01 2023-08-31`.
+async fn backfill(from: &str, to: &str) {
+    let from = parse_date(from);
+    let to = parse_date(to);
+
+    if from > to {
+        exit_with_usage(&format!(
+            "The start date {} is after the end date {}",
+            from, to
+        ));
+    }
+
+    init_tracing();
+
+    let pool = postgres_connection().await;
+    PG_POOL.set(pool).expect("Failed to set postgres pool");
+
+    if let Err(err) = backfill_rollups(from, to).await {
+        tracing::error!("Failed to backfill daily views: {}", err);
+        std::process::exit(1);
+    }
+}
+
 #[tokio::main]
 async fn main() {
-    start().await;
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    match args.iter().map(String::as_str).collect::<Vec<_>>().as_slice() {
+        [] => start().await,
+        ["backfill", from, to] => backfill(from, to).await,
+        _ => exit_with_usage("Unknown arguments"),
+    }
 }
@@ -1 +1,2 @@
+pub mod analytics;
 pub mod tracking;
\ No newline at end of file
@@ -0,0 +1,78 @@
+use chrono::NaiveDate;
+use diesel::{
+    sql_types::{BigInt, Date, Varchar},
+    QueryableByName,
+};
+use serde::{Deserialize, Serialize};
+use strum::{Display, EnumString};
+
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default, Display, EnumString,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum Bucket {
+    #[default]
+    Day,
+    Week,
+    Month,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct AnalyticsQuery {
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    #[serde(default)]
+    pub bucket: Bucket,
+}
+
+#[derive(Serialize, Debug, Clone, QueryableByName)]
+pub struct ViewBucket {
+    #[diesel(sql_type = Date)]
+    pub start: NaiveDate,
+    #[diesel(sql_type = BigInt)]
+    pub views: i64,
+}
+
+#[derive(Serialize, Debug, Clone, QueryableByName)]
+pub struct ViewBreakdown {
+    #[diesel(sql_type = Varchar)]
+    pub name: String,
+    #[diesel(sql_type = BigInt)]
+    pub views: i64,
+}
+
+/// An estimate of how many different readers there were. The real number is
+/// between `low` and `high` about 95% of the time.
+#[derive(Serialize, Debug, Clone, Copy)]
+pub struct ReaderEstimate {
+    pub estimate: u64,
+    pub low: u64,
+    pub high: u64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ReaderBucket {
+    pub start: NaiveDate,
+    pub readers: ReaderEstimate,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct AnalyticsReport {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub bucket: Bucket,
+    pub total: i64,
+    pub buckets: Vec<ViewBucket>,
+    pub devices: Vec<ViewBreakdown>,
+    pub os: Vec<ViewBreakdown>,
+    pub browsers: Vec<ViewBreakdown>,
+    /// Views that were left out because they came from bots, by reason.
+    /// These aren't part of any of the other counts.
+    pub filtered: Vec<ViewBreakdown>,
+    /// How many different people read a book or chapter over the whole
+    /// range. Profiles don't have readers, so this is only set for books and
+    /// chapters.
+    pub readers: Option<ReaderEstimate>,
+    pub reader_buckets: Vec<ReaderBucket>,
+}
@@ -1,5 +1,9 @@
+use chrono::{NaiveDateTime, Utc};
 use diesel::Insertable;
 use serde::{Serialize, Deserialize};
+use strum::{Display, EnumString};
+
+use crate::bots::BotReason;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct UserInfo {
@@ -29,4 +33,70 @@ pub struct WebView {
     ip: ipnetwork::IpNetwork,
     href: String,
     domain: String
+}
+
+/// The book or chapter a reader opened, sent by the reader page.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BookViewInfo {
+    pub book_id: i32,
+    pub chapter_id: Option<i32>
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Insertable)]
+#[diesel(table_name = crate::schema::book_views)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewBookView {
+    pub book_id: i32,
+    pub chapter_id: Option<i32>,
+    pub user_id: Option<i32>,
+    pub browser: String,
+    pub os: String,
+    pub device: String,
+    pub ip: ipnetwork::IpNetwork
+}
+
+/// The profile a visitor opened.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ProfileViewInfo {
+    pub user_id: i32
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct NewUserView {
+    pub viewer_id: Option<i32>,
+    pub user_id: i32,
+    pub browser: String,
+    pub os: String,
+    pub device: String,
+    pub ip: ipnetwork::IpNetwork
+}
+
+/// What kind of view a filtered view would have counted as.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Display, EnumString)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum FilteredKind {
+    Web,
+    Book,
+    Chapter,
+    Profile
+}
+
+/// A view that was left out because it came from a bot. It's counted against
+/// everything it would have been a view of, e.g. both a book and a chapter.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FilteredView {
+    /// A random id, so a view that's read from kafka again isn't counted
+    /// twice. Views sent before there were ids get a new one each time.
+    #[serde(default = "rand::random")]
+    pub id: i64,
+    /// When the view happened, which is the day it's counted on.
+    #[serde(default = "now")]
+    pub dt: NaiveDateTime,
+    pub reason: BotReason,
+    pub targets: Vec<(FilteredKind, i32)>
+}
+
+fn now() -> NaiveDateTime {
+    Utc::now().naive_utc()
 }
\ No newline at end of file
@@ -0,0 +1,95 @@
+use std::{
+    error::Error,
+    net::{IpAddr, Ipv6Addr},
+    sync::Mutex,
+};
+
+use chrono::{NaiveDate, Utc};
+use diesel::{insert_into, prelude::*};
+use diesel_async::RunQueryDsl;
+use gablet_shared_api::credentials::IpPrivacy;
+use hmac::{Hmac, Mac};
+use ipnetwork::IpNetwork;
+use sha2::Sha256;
+
+use crate::{PG_POOL, TRACKING_CONFIG};
+
+const IPV4_NETWORK_BITS: u8 = 24;
+const IPV6_NETWORK_BITS: u8 = 48;
+
+/// The salt for the current day, so it's only read from the database once a
+/// day.
+static DAILY_SALT: Mutex<Option<(NaiveDate, Vec<u8>)>> = Mutex::new(None);
+
+/// Gets the salt for a day, making one if no instance has yet. Every instance
+/// uses the same salt, so a visitor has the same hash all day whichever
+/// instance their views go through.
+async fn daily_salt(today: NaiveDate) -> Result<Vec<u8>, Box<dyn Error>> {
+    use crate::schema::ip_salts::dsl::*;
+
+    if let Some((cached_day, cached_salt)) = DAILY_SALT.lock().unwrap().as_ref() {
+        if *cached_day == today {
+            return Ok(cached_salt.clone());
+        }
+    }
+
+    let pool = PG_POOL.get().unwrap().clone();
+    let connection = &mut pool.get().await?;
+
+    let new_salt: [u8; 32] = rand::random();
+
+    insert_into(ip_salts)
+        .values((day.eq(today), salt.eq(new_salt.to_vec())))
+        .on_conflict_do_nothing()
+        .execute(connection)
+        .await?;
+
+    let today_salt: Vec<u8> = ip_salts
+        .filter(day.eq(today))
+        .select(salt)
+        .first(connection)
+        .await?;
+
+    *DAILY_SALT.lock().unwrap() = Some((today, today_salt.clone()));
+
+    Ok(today_salt)
+}
+
+/// Zeroes everything but the network part of an ip.
+fn truncate_ip(ip: IpAddr) -> Result<IpNetwork, Box<dyn Error>> {
+    let network = match ip {
+        IpAddr::V4(ip) => IpNetwork::new(IpAddr::V4(ip), IPV4_NETWORK_BITS)?,
+        IpAddr::V6(ip) => IpNetwork::new(IpAddr::V6(ip), IPV6_NETWORK_BITS)?,
+    };
+
+    Ok(IpNetwork::new(network.network(), network.prefix())?)
+}
+
+/// Hashes an ip into an IPv6 address in the private fd00::/8 range, so it can
+/// still be stored as an inet and compared with other views from the same day.
+fn hash_ip(ip: IpAddr, salt: &[u8]) -> Result<IpNetwork, Box<dyn Error>> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(salt)?;
+
+    match ip {
+        IpAddr::V4(ip) => mac.update(&ip.to_ipv6_mapped().octets()),
+        IpAddr::V6(ip) => mac.update(&ip.octets()),
+    }
+
+    let mut octets: [u8; 16] = mac.finalize().into_bytes()[..16].try_into()?;
+    octets[0] = 0xfd;
+
+    Ok(IpNetwork::new(IpAddr::V6(Ipv6Addr::from(octets)), 128)?)
+}
+
+/// Turns the ip a view came from into what's stored for it, depending on the
+/// configured privacy mode.
+pub async fn anonymize_ip(ip: IpAddr) -> Result<IpNetwork, Box<dyn Error>> {
+    match TRACKING_CONFIG.ip_privacy {
+        IpPrivacy::Full => Ok(IpNetwork::new(ip, if ip.is_ipv4() { 32u8 } else { 128u8 })?),
+        IpPrivacy::Truncate => truncate_ip(ip),
+        IpPrivacy::Hash => {
+            let salt = daily_salt(Utc::now().date_naive()).await?;
+            hash_ip(ip, &salt)
+        }
+    }
+}
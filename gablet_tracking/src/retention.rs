@@ -0,0 +1,126 @@
+use std::{error::Error, time::Duration};
+
+use chrono::{Days, NaiveDate, Utc};
+use diesel::{
+    delete,
+    dsl::{date, now},
+    prelude::*,
+    select, sql_query,
+    sql_types::{BigInt, Date},
+};
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use gablet_shared_api::cancellation_token::CancellationToken;
+
+use crate::{
+    rollups::{mark_purged, rolled_up_to, Rollup},
+    PG_POOL, TRACKING_CONFIG,
+};
+
+/// How often old raw views and salts are deleted.
+const RETENTION_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// How many raw views are deleted at once, so deleting a backlog doesn't hold
+/// locks on the table for long.
+const RETENTION_BATCH_SIZE: i64 = 10_000;
+
+/// How many days the ids of counted filtered views are kept. This only needs
+/// to be longer than kafka could take to deliver a view again.
+const FILTERED_EVENT_RETENTION_DAYS: i64 = 7;
+
+fn purge_sql(rollup: Rollup) -> String {
+    format!(
+        "DELETE FROM {raw} WHERE id IN (SELECT id FROM {raw} WHERE dt < $1 LIMIT $2)",
+        raw = rollup.raw_table()
+    )
+}
+
+/// Deletes the raw views older than the retention period. Views are only
+/// deleted once their day has been rolled up, so their counts and their
+/// device, OS and browser breakdowns are kept in the daily tables.
+async fn purge_raw_views(
+    rollup: Rollup,
+    retention_days: u64,
+    connection: &mut AsyncPgConnection,
+) -> Result<(), diesel::result::Error> {
+    let Some(rolled_up) = rolled_up_to(rollup, connection).await? else {
+        return Ok(());
+    };
+
+    let today: NaiveDate = select(date(now)).first(connection).await?;
+    let cutoff = rolled_up.min(today - Days::new(retention_days));
+
+    // This is recorded first, so a backfill won't recount a day that's only
+    // been partly deleted.
+    mark_purged(rollup, cutoff, connection).await?;
+
+    loop {
+        let deleted = sql_query(purge_sql(rollup))
+            .bind::<Date, _>(cutoff)
+            .bind::<BigInt, _>(RETENTION_BATCH_SIZE)
+            .execute(connection)
+            .await?;
+
+        if deleted > 0 {
+            tracing::info!("Deleted {} raw views from {}", deleted, rollup.raw_table());
+        }
+
+        if (deleted as i64) < RETENTION_BATCH_SIZE {
+            return Ok(());
+        }
+    }
+}
+
+/// Deletes the ids of counted filtered views once they're old enough that
+/// kafka won't deliver them again.
+async fn purge_filtered_view_events(
+    connection: &mut AsyncPgConnection,
+) -> Result<(), diesel::result::Error> {
+    use crate::schema::filtered_view_events::dsl::*;
+
+    let cutoff = Utc::now().naive_utc() - chrono::Duration::days(FILTERED_EVENT_RETENTION_DAYS);
+
+    delete(filtered_view_events.filter(received.lt(cutoff)))
+        .execute(connection)
+        .await?;
+
+    Ok(())
+}
+
+/// Deletes the salts of days that are over, so the ips hashed with them
+/// can't be worked out.
+async fn purge_ip_salts(connection: &mut AsyncPgConnection) -> Result<(), diesel::result::Error> {
+    use crate::schema::ip_salts::dsl::*;
+
+    delete(ip_salts.filter(day.lt(Utc::now().date_naive())))
+        .execute(connection)
+        .await?;
+
+    Ok(())
+}
+
+async fn run_retention() -> Result<(), Box<dyn Error>> {
+    let pool = PG_POOL.get().unwrap().clone();
+    let connection = &mut pool.get().await?;
+
+    purge_ip_salts(connection).await?;
+    purge_filtered_view_events(connection).await?;
+
+    if let Some(retention_days) = TRACKING_CONFIG.raw_view_retention_days {
+        for rollup in Rollup::ALL {
+            purge_raw_views(rollup, retention_days, connection).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Deletes old tracking data until cancellation is requested.
+pub async fn retention_scheduler(token: CancellationToken) {
+    while !token.is_cancellation_requested() {
+        if let Err(err) = run_retention().await {
+            tracing::error!("Failed to delete old tracking data: {}", err);
+        }
+
+        tokio::time::sleep(RETENTION_INTERVAL).await;
+    }
+}
@@ -0,0 +1,379 @@
+use std::{error::Error, time::Duration};
+
+use chrono::{Days, NaiveDate};
+use diesel::{
+    dsl::{date, now},
+    insert_into,
+    prelude::*,
+    select, sql_query,
+    sql_types::Date,
+    update,
+};
+use diesel_async::{
+    scoped_futures::ScopedFutureExt, AsyncConnection, AsyncPgConnection, RunQueryDsl,
+};
+use gablet_shared_api::cancellation_token::CancellationToken;
+
+use crate::PG_POOL;
+
+/// How often the daily tables are brought up to date.
+const ROLLUP_INTERVAL: Duration = Duration::from_secs(300);
+
+/// How many days back a view can still arrive, e.g. while the consumer
+/// catches up after an outage. These days are counted again on every run.
+const LATE_VIEW_DAYS: u64 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rollup {
+    Web,
+    Book,
+    User,
+}
+
+impl Rollup {
+    pub const ALL: [Rollup; 3] = [Rollup::Web, Rollup::Book, Rollup::User];
+
+    /// The daily table, which is also the name its progress is kept under.
+    pub fn name(self) -> &'static str {
+        match self {
+            Rollup::Web => "daily_web_views",
+            Rollup::Book => "daily_book_views",
+            Rollup::User => "daily_user_views",
+        }
+    }
+
+    /// The table of raw views that are counted.
+    pub fn raw_table(self) -> &'static str {
+        match self {
+            Rollup::Web => "web_views",
+            Rollup::Book => "book_views",
+            Rollup::User => "user_views",
+        }
+    }
+
+    /// Deletes the counts of the days from `$1` up to but not including `$2`,
+    /// so groups that no longer have any views don't keep their old counts.
+    fn delete_sql(self) -> &'static str {
+        match self {
+            Rollup::Web => "DELETE FROM daily_web_views WHERE date >= $1 AND date < $2",
+            Rollup::Book => "DELETE FROM daily_book_views WHERE date >= $1 AND date < $2",
+            Rollup::User => "DELETE FROM daily_user_views WHERE date >= $1 AND date < $2",
+        }
+    }
+
+    /// Recounts the views on the days from `$1` up to but not including `$2`.
+    /// Counts replace the ones already there, so a day can be counted any
+    /// number of times.
+    fn upsert_sql(self) -> &'static str {
+        match self {
+            Rollup::Web => {
+                "
+INSERT INTO daily_web_views (href, date, count)
+SELECT href, dt::date, COUNT(*)::int FROM web_views
+WHERE dt >= $1 AND dt < $2
+GROUP BY href, dt::date
+ON CONFLICT (href, date) DO UPDATE SET count = EXCLUDED.count"
+            }
+            Rollup::Book => {
+                "
+INSERT INTO daily_book_views (book_id, chapter_id, date, count)
+SELECT book_id, chapter_id, dt::date, COUNT(*)::int FROM book_views
+WHERE dt >= $1 AND dt < $2
+GROUP BY book_id, chapter_id, dt::date
+ON CONFLICT (book_id, chapter_id, date) DO UPDATE SET count = EXCLUDED.count"
+            }
+            Rollup::User => {
+                "
+INSERT INTO daily_user_views (user_id, date, count)
+SELECT user_id, dt::date, COUNT(*)::int FROM user_views
+WHERE dt >= $1 AND dt < $2
+GROUP BY user_id, dt::date
+ON CONFLICT (user_id, date) DO UPDATE SET count = EXCLUDED.count"
+            }
+        }
+    }
+
+    /// Deletes the device, OS and browser counts of the days from `$1` up to
+    /// but not including `$2`. Web views aren't broken down.
+    fn delete_breakdowns_sql(self) -> Option<&'static str> {
+        match self {
+            Rollup::Web => None,
+            Rollup::Book => {
+                Some("DELETE FROM daily_book_breakdowns WHERE date >= $1 AND date < $2")
+            }
+            Rollup::User => {
+                Some("DELETE FROM daily_user_breakdowns WHERE date >= $1 AND date < $2")
+            }
+        }
+    }
+
+    /// Counts the devices, OSes and browsers of the views on the days from
+    /// `$1` up to but not including `$2`, so they're kept once the raw views
+    /// have been deleted.
+    fn breakdown_sql(self) -> Option<&'static str> {
+        match self {
+            Rollup::Web => None,
+            Rollup::Book => Some(
+                "
+INSERT INTO daily_book_breakdowns (book_id, chapter_id, date, field, value, count)
+SELECT book_id, chapter_id, dt::date, f.field, f.value, COUNT(*)::int FROM book_views
+CROSS JOIN LATERAL (VALUES ('device', device), ('os', os), ('browser', browser)) AS f(field, value)
+WHERE dt >= $1 AND dt < $2
+GROUP BY book_id, chapter_id, dt::date, f.field, f.value
+ON CONFLICT (book_id, chapter_id, date, field, value) DO UPDATE SET count = EXCLUDED.count",
+            ),
+            Rollup::User => Some(
+                "
+INSERT INTO daily_user_breakdowns (user_id, date, field, value, count)
+SELECT user_id, dt::date, f.field, f.value, COUNT(*)::int FROM user_views
+CROSS JOIN LATERAL (VALUES ('device', device), ('os', os), ('browser', browser)) AS f(field, value)
+WHERE dt >= $1 AND dt < $2
+GROUP BY user_id, dt::date, f.field, f.value
+ON CONFLICT (user_id, date, field, value) DO UPDATE SET count = EXCLUDED.count",
+            ),
+        }
+    }
+
+    /// The day of the oldest raw view, if there are any.
+    async fn first_day(
+        self,
+        connection: &mut AsyncPgConnection,
+    ) -> Result<Option<NaiveDate>, diesel::result::Error> {
+        let first: Option<chrono::NaiveDateTime> = match self {
+            Rollup::Web => {
+                use crate::schema::web_views::dsl::*;
+                web_views
+                    .select(dt)
+                    .order(dt)
+                    .first(connection)
+                    .await
+                    .optional()?
+            }
+            Rollup::Book => {
+                use crate::schema::book_views::dsl::*;
+                book_views
+                    .select(dt)
+                    .order(dt)
+                    .first(connection)
+                    .await
+                    .optional()?
+            }
+            Rollup::User => {
+                use crate::schema::user_views::dsl::*;
+                user_views
+                    .select(dt)
+                    .order(dt)
+                    .first(connection)
+                    .await
+                    .optional()?
+            }
+        };
+
+        Ok(first.map(|first| first.date()))
+    }
+}
+
+/// The first day that hasn't been rolled up yet. The daily counts of every
+/// day before it are complete.
+pub async fn rolled_up_to(
+    rollup: Rollup,
+    connection: &mut AsyncPgConnection,
+) -> Result<Option<NaiveDate>, diesel::result::Error> {
+    use crate::schema::rollups::dsl::*;
+
+    rollups
+        .filter(name.eq(rollup.name()))
+        .select(rolled_up_to)
+        .first(connection)
+        .await
+        .optional()
+}
+
+/// Counts the views of every day from `from` to `to`, inclusive, replacing
+/// their old counts. This should be called in a transaction so the days are
+/// never seen without their counts.
+async fn rollup_days(
+    rollup: Rollup,
+    from: NaiveDate,
+    to: NaiveDate,
+    connection: &mut AsyncPgConnection,
+) -> Result<usize, diesel::result::Error> {
+    let Some(end) = to.succ_opt() else {
+        return Ok(0);
+    };
+
+    let statements = [
+        Some(rollup.delete_sql()),
+        rollup.delete_breakdowns_sql(),
+        rollup.breakdown_sql(),
+    ];
+
+    for statement in statements.into_iter().flatten() {
+        sql_query(statement)
+            .bind::<Date, _>(from)
+            .bind::<Date, _>(end)
+            .execute(connection)
+            .await?;
+    }
+
+    sql_query(rollup.upsert_sql())
+        .bind::<Date, _>(from)
+        .bind::<Date, _>(end)
+        .execute(connection)
+        .await
+}
+
+/// Counts every day that hasn't been rolled up yet, one day at a time so a
+/// long gap doesn't have to be counted all at once. Each day is counted in a
+/// transaction that holds the rollup's progress row, so instances running at
+/// the same time take turns instead of counting the same day together.
+async fn run_rollup(
+    rollup: Rollup,
+    connection: &mut AsyncPgConnection,
+) -> Result<(), diesel::result::Error> {
+    use crate::schema::rollups::dsl::*;
+
+    let today: NaiveDate = select(date(now)).first(connection).await?;
+
+    let start = match self::rolled_up_to(rollup, connection).await? {
+        Some(progress) => progress,
+        None => match rollup.first_day(connection).await? {
+            Some(first) => first,
+            None => return Ok(()),
+        },
+    };
+
+    let settled = today - Days::new(LATE_VIEW_DAYS);
+
+    for day in start.iter_days().take_while(|day| *day <= today) {
+        connection
+            .transaction::<_, diesel::result::Error, _>(|connection| {
+                async move {
+                    insert_into(rollups)
+                        .values((name.eq(rollup.name()), rolled_up_to.eq(day)))
+                        .on_conflict_do_nothing()
+                        .execute(connection)
+                        .await?;
+
+                    let current: NaiveDate = rollups
+                        .filter(name.eq(rollup.name()))
+                        .select(rolled_up_to)
+                        .for_update()
+                        .first(connection)
+                        .await?;
+
+                    // Another instance finished this day while we waited.
+                    if current > day {
+                        return Ok(());
+                    }
+
+                    rollup_days(rollup, day, day, connection).await?;
+
+                    if day < settled {
+                        update(rollups.filter(name.eq(rollup.name())))
+                            .set((rolled_up_to.eq(day + Days::new(1)), updated.eq(now)))
+                            .execute(connection)
+                            .await?;
+                    }
+
+                    Ok(())
+                }
+                .scope_boxed()
+            })
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Brings every daily table up to date.
+async fn run_rollups() -> Result<(), Box<dyn Error>> {
+    let pool = PG_POOL.get().unwrap().clone();
+    let connection = &mut pool.get().await?;
+
+    for rollup in Rollup::ALL {
+        run_rollup(rollup, connection).await?;
+    }
+
+    Ok(())
+}
+
+/// Keeps the daily tables up to date until cancellation is requested.
+pub async fn rollup_scheduler(token: CancellationToken) {
+    while !token.is_cancellation_requested() {
+        if let Err(err) = run_rollups().await {
+            tracing::error!("Failed to roll up daily views: {}", err);
+        }
+
+        tokio::time::sleep(ROLLUP_INTERVAL).await;
+    }
+}
+
+/// The first day that still has all of its raw views. Days before it can't be
+/// counted again without losing views.
+pub async fn purged_before(
+    rollup: Rollup,
+    connection: &mut AsyncPgConnection,
+) -> Result<Option<NaiveDate>, diesel::result::Error> {
+    use crate::schema::rollups::dsl::*;
+
+    Ok(rollups
+        .filter(name.eq(rollup.name()))
+        .select(purged_before)
+        .first::<Option<NaiveDate>>(connection)
+        .await
+        .optional()?
+        .flatten())
+}
+
+/// Records that the raw views before a day are being deleted. The day only
+/// ever moves forward.
+pub async fn mark_purged(
+    rollup: Rollup,
+    before: NaiveDate,
+    connection: &mut AsyncPgConnection,
+) -> Result<(), diesel::result::Error> {
+    use crate::schema::rollups::dsl::*;
+
+    update(rollups.filter(name.eq(rollup.name())))
+        .filter(purged_before.is_null().or(purged_before.lt(before)))
+        .set(purged_before.eq(before))
+        .execute(connection)
+        .await?;
+
+    Ok(())
+}
+
+/// Recounts every daily table for the days from `from` to `to`, inclusive.
+/// This doesn't move the progress of the scheduled rollups, so it can be used
+/// to fix up days that were counted before their views were imported. Days
+/// whose raw views have been deleted by the retention job are skipped.
+pub async fn backfill_rollups(from: NaiveDate, to: NaiveDate) -> Result<(), Box<dyn Error>> {
+    let pool = PG_POOL.get().unwrap().clone();
+    let connection = &mut pool.get().await?;
+
+    for rollup in Rollup::ALL {
+        let purged = self::purged_before(rollup, connection).await?;
+
+        if let Some(purged) = purged.filter(|purged| from < *purged) {
+            tracing::warn!(
+                "Skipping {} before {}, its raw views have been deleted",
+                rollup.name(),
+                purged
+            );
+        }
+
+        let from = purged.map_or(from, |purged| from.max(purged));
+
+        for day in from.iter_days().take_while(|day| *day <= to) {
+            let rows = connection
+                .transaction::<_, diesel::result::Error, _>(|connection| {
+                    rollup_days(rollup, day, day, connection).scope_boxed()
+                })
+                .await?;
+            tracing::info!("Backfilled {} rows of {} for {}", rows, rollup.name(), day);
+        }
+    }
+
+    Ok(())
+}
@@ -12,6 +12,32 @@ diesel::table! {
         device -> Varchar,
         ip -> Inet,
         dt -> Timestamp,
+        #[max_length = 50]
+        browser -> Varchar,
+    }
+}
+
+diesel::table! {
+    daily_book_breakdowns (id) {
+        id -> Int4,
+        book_id -> Int4,
+        chapter_id -> Int4,
+        date -> Date,
+        #[max_length = 10]
+        field -> Varchar,
+        #[max_length = 50]
+        value -> Varchar,
+        count -> Int4,
+    }
+}
+
+diesel::table! {
+    daily_book_readers (id) {
+        id -> Int4,
+        book_id -> Int4,
+        chapter_id -> Int4,
+        date -> Date,
+        sketch -> Bytea,
     }
 }
 
@@ -25,6 +51,32 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    daily_filtered_views (id) {
+        id -> Int4,
+        count -> Int4,
+        #[max_length = 10]
+        kind -> Varchar,
+        target_id -> Int4,
+        #[max_length = 20]
+        reason -> Varchar,
+        date -> Date,
+    }
+}
+
+diesel::table! {
+    daily_user_breakdowns (id) {
+        id -> Int4,
+        user_id -> Int4,
+        date -> Date,
+        #[max_length = 10]
+        field -> Varchar,
+        #[max_length = 50]
+        value -> Varchar,
+        count -> Int4,
+    }
+}
+
 diesel::table! {
     daily_user_views (id) {
         id -> Int4,
@@ -43,6 +95,74 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    filtered_view_events (event_id) {
+        event_id -> Int8,
+        received -> Timestamp,
+    }
+}
+
+diesel::table! {
+    ip_salts (day) {
+        day -> Date,
+        salt -> Bytea,
+    }
+}
+
+diesel::table! {
+    rollups (name) {
+        #[max_length = 50]
+        name -> Varchar,
+        rolled_up_to -> Date,
+        updated -> Timestamp,
+        purged_before -> Nullable<Date>,
+    }
+}
+
+diesel::table! {
+    tracked_book_members (book_id, user_id) {
+        book_id -> Int4,
+        user_id -> Int4,
+    }
+}
+
+diesel::table! {
+    tracked_books (book_id) {
+        book_id -> Int4,
+        author_id -> Int4,
+        updated -> Timestamp,
+        approved -> Bool,
+        #[max_length = 10]
+        lang -> Varchar,
+        genres -> Array<Nullable<Text>>,
+        translations -> Array<Nullable<Text>>,
+    }
+}
+
+diesel::table! {
+    tracked_chapters (chapter_id) {
+        chapter_id -> Int4,
+        book_id -> Int4,
+    }
+}
+
+diesel::table! {
+    trending_books (book_id) {
+        book_id -> Int4,
+        score -> Float8,
+        updated -> Timestamp,
+    }
+}
+
+diesel::table! {
+    trending_visitors (book_id, visitor) {
+        book_id -> Int4,
+        #[max_length = 64]
+        visitor -> Varchar,
+        counted -> Timestamp,
+    }
+}
+
 diesel::table! {
     user_views (id) {
         id -> Int4,
@@ -54,6 +174,8 @@ diesel::table! {
         device -> Varchar,
         ip -> Inet,
         dt -> Timestamp,
+        #[max_length = 50]
+        browser -> Varchar,
     }
 }
 
@@ -75,11 +197,26 @@ diesel::table! {
     }
 }
 
+diesel::joinable!(tracked_book_members -> tracked_books (book_id));
+diesel::joinable!(tracked_chapters -> tracked_books (book_id));
+
 diesel::allow_tables_to_appear_in_same_query!(
     book_views,
+    daily_book_breakdowns,
+    daily_book_readers,
     daily_book_views,
+    daily_filtered_views,
+    daily_user_breakdowns,
     daily_user_views,
     daily_web_views,
+    filtered_view_events,
+    ip_salts,
+    rollups,
+    tracked_book_members,
+    tracked_books,
+    tracked_chapters,
+    trending_books,
+    trending_visitors,
     user_views,
     web_views,
 );
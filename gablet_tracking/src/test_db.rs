@@ -0,0 +1,102 @@
+//! Fixtures for tests of the queries that only Postgres can run. These tests
+//! are ignored by default. Run them with `cargo test -- --ignored` and
+//! `TRACKING_TEST_DATABASE_URL` pointing at a database with the migrations
+//! run.
+
+use diesel::{
+    sql_query,
+    sql_types::{Array, Bool, Double, Int4, Text, Varchar},
+};
+use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl};
+
+use crate::models::tracking::NewBookView;
+
+/// A connection to the database in `TRACKING_TEST_DATABASE_URL`. Everything
+/// happens in a transaction that's never committed, and `CURRENT_TIMESTAMP`
+/// stays the same until the test ends.
+///
+/// Panics if the variable isn't set, so ignored tests that are asked for
+/// can't pass without running.
+pub async fn test_connection() -> AsyncPgConnection {
+    let url = std::env::var("TRACKING_TEST_DATABASE_URL")
+        .expect("TRACKING_TEST_DATABASE_URL must be set to run the database tests");
+
+    let mut connection = AsyncPgConnection::establish(&url)
+        .await
+        .expect("Failed to connect to the test database");
+
+    connection
+        .begin_test_transaction()
+        .await
+        .expect("Failed to start a test transaction");
+
+    connection
+}
+
+/// Empties the tables trending is worked out from.
+pub async fn clear(connection: &mut AsyncPgConnection) {
+    for table in ["trending_books", "trending_visitors", "tracked_books"] {
+        sql_query(format!("DELETE FROM {}", table))
+            .execute(connection)
+            .await
+            .unwrap();
+    }
+}
+
+/// Adds a tracked copy of a book, with a trending score that was last
+/// updated `hours_ago`.
+pub async fn add_book(
+    book_id: i32,
+    approved: bool,
+    lang: &str,
+    genres: &[&str],
+    score: f64,
+    hours_ago: f64,
+    connection: &mut AsyncPgConnection,
+) {
+    sql_query(
+        "INSERT INTO tracked_books (book_id, author_id, approved, lang, genres, updated)
+        VALUES ($1, 1, $2, $3, $4, CURRENT_TIMESTAMP)",
+    )
+    .bind::<Int4, _>(book_id)
+    .bind::<Bool, _>(approved)
+    .bind::<Varchar, _>(lang)
+    .bind::<Array<Text>, _>(genres)
+    .execute(connection)
+    .await
+    .unwrap();
+
+    sql_query(
+        "INSERT INTO trending_books (book_id, score, updated)
+        VALUES ($1, $2, CURRENT_TIMESTAMP - $3 * INTERVAL '1 hour')",
+    )
+    .bind::<Int4, _>(book_id)
+    .bind::<Double, _>(score)
+    .bind::<Double, _>(hours_ago)
+    .execute(connection)
+    .await
+    .unwrap();
+}
+
+/// Records the languages a tracked book has been translated into.
+pub async fn add_translations(book_id: i32, langs: &[&str], connection: &mut AsyncPgConnection) {
+    sql_query("UPDATE tracked_books SET translations = $2 WHERE book_id = $1")
+        .bind::<Int4, _>(book_id)
+        .bind::<Array<Text>, _>(langs)
+        .execute(connection)
+        .await
+        .unwrap();
+}
+
+/// A view of a book by a logged in user.
+pub fn view(book_id: i32, user_id: i32) -> NewBookView {
+    NewBookView {
+        book_id,
+        chapter_id: None,
+        user_id: Some(user_id),
+        browser: "Firefox".to_string(),
+        os: "Linux".to_string(),
+        device: "Other".to_string(),
+        ip: "127.0.0.1".parse().unwrap(),
+    }
+}
@@ -0,0 +1,84 @@
+use std::{error::Error, time::Duration};
+
+use diesel::{
+    sql_query,
+    sql_types::{Double, Int4},
+};
+use diesel_async::RunQueryDsl;
+use gablet_shared_api::cancellation_token::CancellationToken;
+
+use crate::{PG_POOL, TRACKING_CONFIG};
+
+/// How often scores that have decayed away and visitors whose window has
+/// passed are deleted.
+const TRENDING_PRUNE_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// How many hours it takes a score to halve if it isn't configured.
+const DEFAULT_HALF_LIFE_HOURS: f64 = 6.0;
+
+/// How long a visitor's views of a book count once if it isn't configured.
+const DEFAULT_VISITOR_WINDOW_MINUTES: i64 = 60;
+
+/// Scores are deleted once they decay below this. One visitor decays to it
+/// after about 7 half lives.
+const MIN_SCORE: f64 = 0.01;
+
+pub fn half_life_hours() -> f64 {
+    TRACKING_CONFIG
+        .trending_half_life_hours
+        .filter(|hours| *hours > 0.0)
+        .unwrap_or(DEFAULT_HALF_LIFE_HOURS)
+}
+
+pub fn visitor_window_minutes() -> i32 {
+    TRACKING_CONFIG
+        .trending_visitor_window_minutes
+        .unwrap_or(DEFAULT_VISITOR_WINDOW_MINUTES)
+        .clamp(0, i32::MAX as i64) as i32
+}
+
+/// Deletes the scores that have decayed below `$2`, with a half life of `$1`
+/// hours.
+const PRUNE_SCORES_SQL: &str = "
+DELETE FROM trending_books
+WHERE score * power(0.5, extract(epoch FROM CURRENT_TIMESTAMP - updated) / 3600 / $1) < $2";
+
+/// Deletes the visitors who would count again anyway.
+const PRUNE_VISITORS_SQL: &str = "
+DELETE FROM trending_visitors
+WHERE counted <= CURRENT_TIMESTAMP - $1 * INTERVAL '1 minute'";
+
+async fn prune_trending() -> Result<(), Box<dyn Error>> {
+    let pool = PG_POOL.get().unwrap().clone();
+    let connection = &mut pool.get().await?;
+
+    let scores = sql_query(PRUNE_SCORES_SQL)
+        .bind::<Double, _>(half_life_hours())
+        .bind::<Double, _>(MIN_SCORE)
+        .execute(connection)
+        .await?;
+
+    let visitors = sql_query(PRUNE_VISITORS_SQL)
+        .bind::<Int4, _>(visitor_window_minutes())
+        .execute(connection)
+        .await?;
+
+    tracing::debug!(
+        "Deleted {} trending scores and {} trending visitors",
+        scores,
+        visitors
+    );
+
+    Ok(())
+}
+
+/// Keeps the trending tables small until cancellation is requested.
+pub async fn trending_scheduler(token: CancellationToken) {
+    while !token.is_cancellation_requested() {
+        if let Err(err) = prune_trending().await {
+            tracing::error!("Failed to prune trending books: {}", err);
+        }
+
+        tokio::time::sleep(TRENDING_PRUNE_INTERVAL).await;
+    }
+}